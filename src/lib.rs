@@ -1,4 +1,6 @@
 //! More optimistic file system utilities.
 
+pub mod organize;
 pub mod rename;
+pub mod rules;
 pub mod walk_dir;
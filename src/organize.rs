@@ -0,0 +1,49 @@
+//! Utilities for planning the reorganization of directory trees.
+//!
+//! Each organizer walks a tree, derives a destination for every file in it,
+//! and plans the moves as a [`RenameQueue`], which can be inspected before
+//! it is executed, and reverted afterwards. Directories emptied by the
+//! moves are left in place.
+//!
+//! [`RenameQueue`]: crate::rename::RenameQueue
+
+pub use self::anonymize::{Anonymize, Pseudonyms, anonymize};
+pub use self::date::{ByDate, by_date};
+pub use self::dedup::{Dedup, Duplicates, dedup};
+pub use self::episode::{Episode, Episodes, Number, episodes};
+pub use self::error::Error;
+pub use self::extension::{ByExtension, by_extension};
+pub use self::flatten::{Collision, Flatten, flatten};
+pub use self::hash::{Algorithm, ByHash, Manifest, ManifestEntry, by_hash};
+#[cfg(feature = "audiotags")]
+pub use self::music::{Music, music};
+#[cfg(feature = "exif")]
+pub use self::shoot::{ByShoot, by_shoot};
+#[cfg(feature = "sniff")]
+pub use self::sniff::{FixExtensions, fix_extensions, sniff};
+
+mod anonymize;
+mod date;
+mod dedup;
+mod episode;
+mod error;
+mod extension;
+mod flatten;
+mod hash;
+#[cfg(feature = "audiotags")]
+mod music;
+#[cfg(feature = "exif")]
+mod shoot;
+#[cfg(feature = "sniff")]
+mod sniff;
+
+use crate::walk_dir::{DirEntry, walk_dir};
+use std::io;
+use std::path::Path;
+
+/// Returns the regular files within `root` at any depth, skipping entries
+/// that produce errors.
+fn files(root: &Path) -> io::Result<impl Iterator<Item = DirEntry>> {
+    let iter = walk_dir(root, 0)?.filter(|entry| entry.metadata().is_file());
+    Ok(iter)
+}
@@ -0,0 +1,213 @@
+use super::error::Error;
+use super::files;
+use crate::rename::RenameQueue;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::hash::{BuildHasher, RandomState};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Plans renaming every file within `root` to a random identifier, keeping
+/// its extension.
+///
+/// This is equivalent to [`Anonymize::new`] followed by [`Anonymize::plan`],
+/// returning the queue along with the pseudonyms.
+///
+/// # Errors
+///
+/// See [`Anonymize::plan`] and [`Pseudonyms::queue`].
+pub fn anonymize<P>(root: P) -> Result<(RenameQueue, Pseudonyms), Error>
+where
+    P: AsRef<Path>,
+{
+    let pseudonyms = Anonymize::new().plan(root)?;
+    let queue = pseudonyms.queue()?;
+    Ok((queue, pseudonyms))
+}
+
+/// An organizer renaming files to random identifiers, for sharing data
+/// without leaking file names.
+///
+/// Identifiers are lowercase hexadecimal, drawn from a generator seeded by
+/// the operating system, and unique within the tree. Files are renamed
+/// within their directories, whose names are kept.
+///
+/// The renames can be reverted locally from either of two records, which
+/// should not be shared along with the files:
+///
+/// - The [`RenameQueue`], which can be serialized as a journal once it has
+///   renamed the files, and deserialized later to revert them.
+/// - The [`Pseudonyms`], which can be written as a manifest mapping
+///   identifiers back to the original names.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::Anonymize;
+/// use std::fs::File;
+///
+/// let pseudonyms = Anonymize::new().length(12).plan("dataset").unwrap();
+/// pseudonyms.write(File::create("pseudonyms.tsv").unwrap()).unwrap();
+/// // `dataset/patient-smith.png` -> `dataset/3f9c0a7be214.png`
+/// pseudonyms.queue().unwrap().rename().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Anonymize {
+    length: usize,
+    extension: bool,
+}
+
+/// The identifiers planned by [`Anonymize`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pseudonyms {
+    root: PathBuf,
+    mappings: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Anonymize {
+    /// Creates a new [`Anonymize`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of hexadecimal digits of identifiers, which is kept
+    /// within 8 to 64.
+    ///
+    /// Defaults to 16.
+    pub fn length(mut self, length: usize) -> Self {
+        self.length = length.clamp(8, 64);
+        self
+    }
+
+    /// Sets whether files keep their extensions.
+    ///
+    /// Defaults to `true`.
+    pub fn extension(mut self, extension: bool) -> Self {
+        self.extension = extension;
+        self
+    }
+
+    /// Walks `root`, and draws an identifier for each of its files.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if `root` cannot be read. Entries within it
+    /// that cannot be read are skipped.
+    pub fn plan<P>(&self, root: P) -> Result<Pseudonyms, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let root = root.as_ref();
+        let mut paths: Vec<PathBuf> = files(root)?.map(PathBuf::from).collect();
+        paths.sort();
+
+        let random = RandomState::new();
+        let mut counter = 0u64;
+        let mut identifier = || {
+            let mut hex = String::with_capacity(self.length + 16);
+            while hex.len() < self.length {
+                counter += 1;
+                // Writing into a `String` never fails.
+                let _ = write!(hex, "{:016x}", random.hash_one(counter));
+            }
+            hex.truncate(self.length);
+            hex
+        };
+
+        let mut taken: HashSet<PathBuf> = paths.iter().cloned().collect();
+        let mut mappings = Vec::with_capacity(paths.len());
+        for src in paths {
+            let dst = loop {
+                let mut name = OsString::from(identifier());
+                if self.extension
+                    && let Some(extension) = src.extension()
+                {
+                    name.push(".");
+                    name.push(extension);
+                }
+                let dst = src.with_file_name(name);
+                if taken.insert(dst.clone()) {
+                    break dst;
+                }
+            };
+            mappings.push((src, dst));
+        }
+
+        let root = root.to_path_buf();
+        Ok(Pseudonyms { root, mappings })
+    }
+
+    /// Walks `root`, returning the mappings of the files to rename.
+    ///
+    /// # Errors
+    ///
+    /// See [`Anonymize::plan`].
+    pub fn mappings<P>(&self, root: P) -> Result<Vec<(PathBuf, PathBuf)>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(self.plan(root)?.mappings)
+    }
+
+    /// Walks `root`, and plans renaming its files.
+    ///
+    /// # Errors
+    ///
+    /// See [`Anonymize::plan`] and [`Pseudonyms::queue`].
+    pub fn queue<P>(&self, root: P) -> Result<RenameQueue, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.plan(root)?.queue()
+    }
+}
+
+impl Default for Anonymize {
+    fn default() -> Self {
+        Self {
+            length: 16,
+            extension: true,
+        }
+    }
+}
+
+impl Pseudonyms {
+    /// Returns the mappings of the files to rename, ordered by their
+    /// original paths.
+    #[inline]
+    pub fn mappings(&self) -> &[(PathBuf, PathBuf)] {
+        &self.mappings
+    }
+
+    /// Plans renaming the files.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Rename`] if the renames conflict, as described in
+    /// [`RenameQueue::new`].
+    pub fn queue(&self) -> Result<RenameQueue, Error> {
+        let queue = RenameQueue::new(self.mappings.iter().map(|(src, dst)| (src, dst)))?;
+        Ok(queue)
+    }
+
+    /// Writes a manifest with a line for each file, holding its new path
+    /// and its original path relative to the root, separated by a tab.
+    ///
+    /// Paths that are not valid UTF-8 are written lossily.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        for (src, dst) in &self.mappings {
+            let src = src.strip_prefix(&self.root).unwrap_or(src);
+            let dst = dst.strip_prefix(&self.root).unwrap_or(dst);
+            writeln!(writer, "{}\t{}", dst.display(), src.display())?;
+        }
+        writer.flush()
+    }
+}
@@ -0,0 +1,120 @@
+use super::error::Error;
+use super::files;
+use crate::rename::RenameQueue;
+use crate::rules::DateFormat;
+#[cfg(feature = "exif")]
+use crate::rules::Exif;
+use crate::walk_dir::DirEntry;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Plans moving every file within `root` into subdirectories named after
+/// its date, as in `by_date("photos", "%Y/%m")`.
+///
+/// This is equivalent to [`ByDate::new`] followed by [`ByDate::queue`].
+///
+/// # Errors
+///
+/// See [`ByDate::new`] and [`ByDate::queue`].
+pub fn by_date<P>(root: P, format: &str) -> Result<RenameQueue, Error>
+where
+    P: AsRef<Path>,
+{
+    ByDate::new(format)?.queue(root)
+}
+
+/// An organizer moving files into subdirectories named after their dates.
+///
+/// The date of a file is its last modification time, or with the `exif`
+/// feature, the time a photo was taken where recorded. It is formatted in
+/// UTC with a `strftime`-like format, supporting `%Y`, `%y`, `%m`, `%d`,
+/// `%e`, `%j`, `%H`, `%M`, `%S`, `%s`, `%F` (`%Y-%m-%d`), `%T` (`%H:%M:%S`)
+/// and `%%`, and `/` in it separates nested subdirectories.
+///
+/// Files whose dates cannot be read are left alone, as are files already
+/// in place.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::ByDate;
+///
+/// let mut queue = ByDate::new("%Y/%m").unwrap().queue("photos").unwrap();
+/// // `photos/trip/a.jpg` -> `photos/2024/05/a.jpg`
+/// queue.rename().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ByDate {
+    format: DateFormat,
+}
+
+impl ByDate {
+    /// Creates a new [`ByDate`] from a date format.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidFormat`] if the format has an unsupported or
+    /// trailing `%`.
+    pub fn new(format: &str) -> Result<Self, Error> {
+        let format =
+            DateFormat::parse(format).map_err(|(offset, message)| Error::InvalidFormat {
+                format: format.to_owned(),
+                offset,
+                message: message.to_owned(),
+            })?;
+        Ok(Self { format })
+    }
+
+    /// Walks `root`, returning the mappings of the files to move.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if `root` cannot be read. Entries within it
+    /// that cannot be read are skipped.
+    pub fn mappings<P>(&self, root: P) -> Result<Vec<(PathBuf, PathBuf)>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let root = root.as_ref();
+        let mut mappings = Vec::new();
+        for entry in files(root)? {
+            let Some(time) = date(&entry) else {
+                continue;
+            };
+            let mut directory = String::new();
+            self.format.render(time, &mut directory);
+            let dst = root
+                .join(directory)
+                .join(entry.path().file_name().unwrap_or_default());
+            if dst != entry.path() {
+                mappings.push((PathBuf::from(entry), dst));
+            }
+        }
+        Ok(mappings)
+    }
+
+    /// Walks `root`, and plans moving its files.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Io`] if `root` cannot be read.
+    /// - [`Error::Rename`] if the moves conflict, as described in
+    ///   [`RenameQueue::new`], such as when files of the same name share a
+    ///   date.
+    pub fn queue<P>(&self, root: P) -> Result<RenameQueue, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let queue = RenameQueue::new(self.mappings(root)?)?;
+        Ok(queue)
+    }
+}
+
+/// Returns the date of a file, preferring the time a photo was taken.
+fn date(entry: &DirEntry) -> Option<SystemTime> {
+    #[cfg(feature = "exif")]
+    if let Some(datetime) = Exif::read(entry.path()).and_then(|exif| exif.datetime) {
+        return Some(datetime);
+    }
+    entry.metadata().modified().ok()
+}
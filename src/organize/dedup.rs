@@ -0,0 +1,277 @@
+use super::error::Error;
+use super::hash::{self, Algorithm};
+use crate::walk_dir::WalkDir;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Finds the files within `root` sharing their contents with others, hashed
+/// by SHA-256.
+///
+/// This is equivalent to [`Dedup::new`] followed by [`Dedup::find`].
+///
+/// # Errors
+///
+/// See [`Dedup::find`].
+pub fn dedup<P>(root: P) -> Result<Vec<Duplicates>, Error>
+where
+    P: AsRef<Path>,
+{
+    Dedup::new(Algorithm::Sha256).find(root)
+}
+
+/// A finder of duplicate files, the natural companion to bulk renaming
+/// when cleaning up archives.
+///
+/// Files are first grouped by size, so only those of the same size are
+/// read at all. Among these, a digest of the leading bytes of each file is
+/// compared, as set by [`Dedup::prefix`], so files differing early on are
+/// told apart without reading them in full. Only the remaining files are
+/// hashed in full, by the [`Algorithm`] given, where [`Algorithm::Fnv1a64`]
+/// trades the collision resistance of [`Algorithm::Sha256`] for speed.
+///
+/// Hard links to the same file are counted once, under the first path
+/// reached, since removing the others frees no space.
+///
+/// Files found this way are reported rather than removed, so which of them
+/// to keep is left to the caller.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::{Algorithm, Dedup};
+/// use std::fs;
+///
+/// let groups = Dedup::new(Algorithm::Sha256).find("archive").unwrap();
+/// for group in groups {
+///     // Keeps the first file by path, removing the others.
+///     for path in &group.paths()[1..] {
+///         fs::remove_file(path).unwrap();
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Dedup {
+    algorithm: Algorithm,
+    prefix: u64,
+    empty: bool,
+}
+
+/// A group of files found by [`Dedup`] with the same contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicates {
+    size: u64,
+    digest: String,
+    paths: Vec<PathBuf>,
+}
+
+impl Dedup {
+    /// Creates a new [`Dedup`] comparing contents by `algorithm`.
+    pub fn new(algorithm: Algorithm) -> Self {
+        Self {
+            algorithm,
+            prefix: 4096,
+            empty: false,
+        }
+    }
+
+    /// Sets the number of leading bytes hashed to tell files of the same
+    /// size apart before hashing them in full, where 0 skips this step.
+    ///
+    /// Defaults to 4096.
+    pub fn prefix(mut self, prefix: u64) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// Sets whether empty files are reported as duplicates of each other.
+    ///
+    /// Defaults to `false`.
+    pub fn empty(mut self, empty: bool) -> Self {
+        self.empty = empty;
+        self
+    }
+
+    /// Walks `root`, returning the groups of files with the same contents,
+    /// ordered by their first paths.
+    ///
+    /// Each group holds at least two files, ordered by path.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if `root` cannot be read. Entries within it
+    /// that cannot be read are skipped.
+    pub fn find<P>(&self, root: P) -> Result<Vec<Duplicates>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut sizes: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        let walk_dir = WalkDir::new(root)?.dedup_hard_links(true);
+        for entry in walk_dir.filter_map(Result::ok) {
+            if !entry.metadata().is_file() {
+                continue;
+            }
+            let len = entry.metadata().len();
+            if len > 0 || self.empty {
+                sizes.entry(len).or_default().push(PathBuf::from(entry));
+            }
+        }
+
+        let mut groups = Vec::new();
+        for (len, paths) in sizes {
+            if paths.len() < 2 {
+                continue;
+            }
+            // The prefix covers the whole file, so it is hashed only once.
+            let partial = self.prefix > 0 && self.prefix < len;
+            let candidates = if partial {
+                group(paths, |path| self.digest(path, Some(self.prefix)))
+                    .into_values()
+                    .filter(|paths| paths.len() > 1)
+                    .collect()
+            } else {
+                vec![paths]
+            };
+            for paths in candidates {
+                let full = group(paths, |path| self.digest(path, None));
+                for (digest, mut paths) in full {
+                    if paths.len() < 2 {
+                        continue;
+                    }
+                    paths.sort();
+                    groups.push(Duplicates {
+                        size: len,
+                        digest: hash::hex(&digest),
+                        paths,
+                    });
+                }
+            }
+        }
+        groups.sort_by(|a, b| a.paths.cmp(&b.paths));
+        Ok(groups)
+    }
+
+    /// Hashes the file at `path`, up to `limit` bytes if given.
+    fn digest(&self, path: &Path, limit: Option<u64>) -> io::Result<Vec<u8>> {
+        self.algorithm.digest(path, limit)
+    }
+}
+
+impl Duplicates {
+    /// Returns the size of each file in bytes.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the digest of the contents in lowercase hexadecimal.
+    #[inline]
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    /// Returns the paths of the files, ordered by path.
+    #[inline]
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Returns the number of bytes freed by keeping only one of the files.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// Groups `paths` by the digests returned by `digest`, skipping those
+/// that cannot be read.
+fn group<F>(paths: Vec<PathBuf>, digest: F) -> HashMap<Vec<u8>, Vec<PathBuf>>
+where
+    F: Fn(&Path) -> io::Result<Vec<u8>>,
+{
+    let mut groups: HashMap<_, Vec<_>> = HashMap::new();
+    for path in paths {
+        if let Ok(digest) = digest(&path) {
+            groups.entry(digest).or_default().push(path);
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    /// Runs `test` on a fresh directory holding `files`, removing it after.
+    fn with_tree<F>(name: &str, files: &[(&str, &str)], test: F)
+    where
+        F: FnOnce(&Path),
+    {
+        let root = env::temp_dir().join(format!("mofu-dedup-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, contents) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        test(&root);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    const FILES: &[(&str, &str)] = &[
+        ("a.txt", "same contents"),
+        ("sub/b.txt", "same contents"),
+        ("c.txt", "same_contents"),
+        ("d.txt", "other"),
+        ("e.txt", ""),
+        ("f.txt", ""),
+    ];
+
+    #[test]
+    fn files_are_grouped_by_contents() {
+        for algorithm in [Algorithm::Sha256, Algorithm::Fnv1a64] {
+            for prefix in [0, 4, 4096] {
+                with_tree("group", FILES, |root| {
+                    let groups = Dedup::new(algorithm).prefix(prefix).find(root).unwrap();
+                    assert_eq!(groups.len(), 1);
+                    let group = &groups[0];
+                    assert_eq!(group.paths(), [root.join("a.txt"), root.join("sub/b.txt")]);
+                    assert_eq!(group.size(), 13);
+                    assert_eq!(group.wasted(), 13);
+                    let digits = match algorithm {
+                        Algorithm::Sha256 => 64,
+                        Algorithm::Fnv1a64 => 16,
+                    };
+                    assert_eq!(group.digest().len(), digits);
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn empty_files_are_reported_when_asked() {
+        with_tree("empty", FILES, |root| {
+            let groups = Dedup::new(Algorithm::Sha256)
+                .empty(true)
+                .find(root)
+                .unwrap();
+            assert_eq!(groups.len(), 2);
+            assert_eq!(groups[1].paths(), [root.join("e.txt"), root.join("f.txt")]);
+            assert_eq!(groups[1].wasted(), 0);
+        });
+    }
+
+    #[test]
+    fn hard_links_are_not_duplicates() {
+        with_tree("links", &[("a.txt", "linked")], |root| {
+            fs::hard_link(root.join("a.txt"), root.join("b.txt")).unwrap();
+            assert_eq!(dedup(root).unwrap(), []);
+        });
+    }
+
+    #[test]
+    fn missing_root_is_an_error() {
+        let root = env::temp_dir().join(format!("mofu-dedup-{}-missing", process::id()));
+        assert!(matches!(dedup(root), Err(Error::Io(_))));
+    }
+}
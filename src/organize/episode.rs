@@ -0,0 +1,394 @@
+use super::error::Error;
+use super::files;
+use crate::rename::RenameQueue;
+use crate::rules::{Captures, Regex};
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Plans renaming every episode within `root` after the canonical template
+/// `{show} - {number} - {title}`.
+///
+/// This is equivalent to [`Episodes::new`] with the canonical template,
+/// followed by [`Episodes::queue`].
+///
+/// # Errors
+///
+/// See [`Episodes::queue`].
+pub fn episodes<P>(root: P) -> Result<RenameQueue, Error>
+where
+    P: AsRef<Path>,
+{
+    Episodes::new("{show} - {number} - {title}")?.queue(root)
+}
+
+/// An episode of a TV series, as recognized in a file name.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::organize::{Episode, Number};
+///
+/// let episode = Episode::parse("The.Show.S01E02.Pilot.720p.WEB-DL.x264").unwrap();
+/// assert_eq!(episode.show(), "The Show");
+/// assert_eq!(
+///     episode.number(),
+///     &Number::Episode {
+///         season: 1,
+///         episode: 2,
+///         last: None,
+///     },
+/// );
+/// assert_eq!(episode.title(), "Pilot");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Episode {
+    show: String,
+    number: Number,
+    title: String,
+}
+
+/// The number of an [`Episode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Number {
+    /// An episode of a season, as in `S01E02` or `1x02`, along with the
+    /// last of several episodes in one file, as in `S01E02E03` or
+    /// `S01E02-E03`.
+    Episode {
+        season: u32,
+        episode: u32,
+        last: Option<u32>,
+    },
+
+    /// An episode by its air date, as in `2024.05.01` or `2024-05-01`.
+    Date { year: u32, month: u32, day: u32 },
+}
+
+/// Words at which release details start, such as resolutions, sources and
+/// codecs, which end the title of an episode.
+const RELEASE: [&str; 38] = [
+    "480p", "576p", "720p", "1080i", "1080p", "2160p", "4k", "uhd", "hdr", "hdtv", "pdtv", "web",
+    "webrip", "web-dl", "webdl", "bluray", "blu-ray", "bdrip", "brrip", "dvdrip", "hdrip", "x264",
+    "x265", "h264", "h265", "hevc", "xvid", "10bit", "aac", "ac3", "dts", "proper", "repack",
+    "internal", "amzn", "nf", "dsnp", "hulu",
+];
+
+impl Episode {
+    /// Recognizes an episode in the stem of a file name, returning [`None`]
+    /// if there is no episode number in it.
+    ///
+    /// The text before the number is the name of the show, and the text
+    /// after it the title of the episode, up to any release details such as
+    /// `720p` or `x264`. Dots and underscores are read as spaces, and a
+    /// leading `[group]` tag is dropped.
+    pub fn parse(name: &str) -> Option<Self> {
+        static PATTERNS: OnceLock<[Regex; 3]> = OnceLock::new();
+        let patterns = PATTERNS.get_or_init(|| {
+            [
+                r"(?i)^(?<show>.*?)(?:^|[^a-z0-9])s(?<season>\d{1,3})[ ._-]?e(?<episode>\d{1,4})(?:(?:-?e|-)(?<last>\d{1,4}))?(?<rest>[^0-9].*)?$",
+                r"(?i)^(?<show>.*?)(?:^|[^a-z0-9])(?<year>(?:19|20)\d\d)[ ._-](?<month>\d\d)[ ._-](?<day>\d\d)(?<rest>[^0-9].*)?$",
+                r"(?i)^(?<show>.*?)(?:^|[^a-z0-9])(?<season>\d{1,2})x(?<episode>\d{2,3})(?<rest>[^0-9].*)?$",
+            ]
+            .map(|pattern| Regex::new(pattern).unwrap())
+        });
+
+        let number = |captures: &Captures, name| {
+            captures
+                .name(name)
+                .and_then(|text| text.parse::<u32>().ok())
+        };
+        let (captures, number) = patterns.iter().find_map(|pattern| {
+            let captures = pattern.captures(name)?;
+            let number = match number(&captures, "year") {
+                Some(year) => {
+                    let (month, day) = (number(&captures, "month")?, number(&captures, "day")?);
+                    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+                        return None;
+                    }
+                    Number::Date { year, month, day }
+                }
+                None => Number::Episode {
+                    season: number(&captures, "season")?,
+                    episode: number(&captures, "episode")?,
+                    last: number(&captures, "last"),
+                },
+            };
+            Some((captures, number))
+        })?;
+
+        let show = captures.name("show").unwrap_or_default();
+        let show = match show.trim_start().strip_prefix('[') {
+            Some(rest) => rest.split_once(']').map_or(show, |(_, show)| show),
+            None => show,
+        };
+        let rest = captures.name("rest").unwrap_or_default();
+        let title = words(rest)
+            .take_while(|word| !RELEASE.contains(&word.to_ascii_lowercase().as_str()))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Some(Self {
+            show: words(show).collect::<Vec<_>>().join(" "),
+            number,
+            title,
+        })
+    }
+
+    /// Returns the name of the show, which may be empty.
+    #[inline]
+    pub fn show(&self) -> &str {
+        &self.show
+    }
+
+    /// Returns the number of the episode.
+    #[inline]
+    pub fn number(&self) -> &Number {
+        &self.number
+    }
+
+    /// Returns the title of the episode, which may be empty.
+    #[inline]
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+}
+
+/// Splits text into words, treating dots and underscores as spaces, and
+/// dropping dashes and brackets around them.
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|char: char| char == '.' || char == '_' || char.is_whitespace())
+        .map(|word| word.trim_matches(|char| matches!(char, '-' | '[' | ']' | '(' | ')')))
+        .filter(|word| !word.is_empty())
+}
+
+/// Replaces path separators in a field with `_`.
+fn field(text: &str) -> String {
+    text.replace(['/', '\\'], "_")
+}
+
+/// An organizer renaming the episodes of TV series after a template.
+///
+/// A template is literal text interspersed with tokens in braces:
+///
+/// - `{show}`: the name of the show;
+/// - `{title}`: the title of the episode;
+/// - `{number}`: the number of the episode, as in `S01E02` or `S01E02-E03`,
+///   or its air date, as in `2024-05-01`;
+/// - `{season}` or `{season:N}`: the season, padded with zeros to `N`
+///   digits, defaulting to 2;
+/// - `{episode}` or `{episode:N}`: the episode, padded likewise, as in `02`
+///   or `02-03`;
+/// - `{date}`: the air date, as in `2024-05-01`.
+///
+/// Missing fields, such as the season of an episode numbered by date, render
+/// as nothing, and spaces and dashes left at either end of the name are
+/// trimmed. `/` or `\` in the show or title render as `_`.
+///
+/// Literal braces are written `{{` and `}}`.
+///
+/// Files are recognized by [`Episode::parse`], and others are left alone.
+/// The extension of a file is kept, and the rendered name is joined to the
+/// directory containing it, so it may move the file into a subdirectory.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::Episodes;
+///
+/// let mut queue = Episodes::new("{show}/Season {season}/{show} - {number} - {title}")
+///     .unwrap()
+///     .show("The Show")
+///     .queue("tv")
+///     .unwrap();
+/// // `tv/the.show.1x02.pilot.mkv` -> `tv/The Show/Season 01/The Show - S01E02 - pilot.mkv`
+/// queue.rename().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Episodes {
+    segments: Vec<Segment>,
+    show: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Show,
+    Title,
+    Number,
+    Season(usize),
+    Episode(usize),
+    Date,
+}
+
+impl Episodes {
+    /// Creates a new [`Episodes`] from a template.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidFormat`] if the template has an unknown
+    /// token, an invalid width, or an unmatched brace.
+    pub fn new(template: &str) -> Result<Self, Error> {
+        let error = |offset, message: &str| Error::InvalidFormat {
+            format: template.to_owned(),
+            offset,
+            message: message.to_owned(),
+        };
+
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(index) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..index]);
+            let offset = template.len() - rest.len() + index;
+            let tail = &rest[index + 1..];
+            if rest[index..].starts_with("{{") || rest[index..].starts_with("}}") {
+                literal.push_str(&rest[index..=index]);
+                rest = &tail[1..];
+                continue;
+            }
+            if rest[index..].starts_with('}') {
+                return Err(error(offset, "unmatched `}`"));
+            }
+            let Some(end) = tail.find('}') else {
+                return Err(error(offset, "unmatched `{`"));
+            };
+            let (name, width) = match tail[..end].split_once(':') {
+                Some((name, width)) => {
+                    let width = width
+                        .parse()
+                        .map_err(|_| error(offset + name.len() + 2, "invalid width"))?;
+                    (name, Some(width))
+                }
+                None => (&tail[..end], None),
+            };
+            let segment = match (name, width) {
+                ("show", None) => Segment::Show,
+                ("title", None) => Segment::Title,
+                ("number", None) => Segment::Number,
+                ("date", None) => Segment::Date,
+                ("season", width) => Segment::Season(width.unwrap_or(2)),
+                ("episode", width) => Segment::Episode(width.unwrap_or(2)),
+                ("show" | "title" | "number" | "date", Some(_)) => {
+                    return Err(error(offset + name.len() + 2, "unexpected width"));
+                }
+                _ => return Err(error(offset + 1, "unknown token")),
+            };
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(mem::take(&mut literal)));
+            }
+            segments.push(segment);
+            rest = &tail[end + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self {
+            segments,
+            show: None,
+        })
+    }
+
+    /// Sets the name of the show, replacing the names recognized in files,
+    /// which tend to vary in a folder downloaded over time.
+    pub fn show(mut self, show: &str) -> Self {
+        self.show = Some(show.to_owned());
+        self
+    }
+
+    /// Renders the name of an episode, without its extension.
+    pub fn render(&self, episode: &Episode) -> String {
+        let mut output = String::new();
+        let show = self.show.as_deref().unwrap_or(&episode.show);
+        for segment in &self.segments {
+            // Writing into a `String` never fails.
+            let _ = match (segment, episode.number) {
+                (Segment::Literal(literal), _) => output.write_str(literal),
+                (Segment::Show, _) => output.write_str(&field(show)),
+                (Segment::Title, _) => output.write_str(&field(&episode.title)),
+                (
+                    Segment::Number,
+                    Number::Episode {
+                        season,
+                        episode,
+                        last,
+                    },
+                ) => {
+                    let _ = write!(output, "S{season:02}E{episode:02}");
+                    match last {
+                        Some(last) => write!(output, "-E{last:02}"),
+                        None => Ok(()),
+                    }
+                }
+                (Segment::Season(width), Number::Episode { season, .. }) => {
+                    write!(output, "{season:0width$}")
+                }
+                (Segment::Episode(width), Number::Episode { episode, last, .. }) => {
+                    let _ = write!(output, "{episode:0width$}");
+                    match last {
+                        Some(last) => write!(output, "-{last:0width$}"),
+                        None => Ok(()),
+                    }
+                }
+                (Segment::Number | Segment::Date, Number::Date { year, month, day }) => {
+                    write!(output, "{year:04}-{month:02}-{day:02}")
+                }
+                _ => Ok(()),
+            };
+        }
+        output
+            .trim_matches(|char: char| char == '-' || char.is_whitespace())
+            .to_owned()
+    }
+
+    /// Walks `root`, returning the mappings of the episodes to rename.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if `root` cannot be read. Entries within it
+    /// that cannot be read are skipped.
+    pub fn mappings<P>(&self, root: P) -> Result<Vec<(PathBuf, PathBuf)>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut mappings = Vec::new();
+        for entry in files(root.as_ref())? {
+            let src = entry.path();
+            let Some(stem) = src.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let Some(episode) = Episode::parse(stem) else {
+                continue;
+            };
+            let mut name = OsString::from(self.render(&episode));
+            if let Some(extension) = src.extension() {
+                name.push(".");
+                name.push(extension);
+            }
+            let dst = src.with_file_name(name);
+            if dst != src {
+                mappings.push((PathBuf::from(entry), dst));
+            }
+        }
+        Ok(mappings)
+    }
+
+    /// Walks `root`, and plans renaming its episodes.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Io`] if `root` cannot be read.
+    /// - [`Error::Rename`] if the renames conflict, as described in
+    ///   [`RenameQueue::new`], such as when two files hold the same
+    ///   episode.
+    pub fn queue<P>(&self, root: P) -> Result<RenameQueue, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let queue = RenameQueue::new(self.mappings(root)?)?;
+        Ok(queue)
+    }
+}
@@ -0,0 +1,61 @@
+use crate::rename;
+use std::error;
+use std::fmt;
+use std::io;
+
+/// A enum for error handling.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+
+    InvalidFormat {
+        format: String,
+        offset: usize,
+        message: String,
+    },
+
+    Rename(rename::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Error::Io(value)
+    }
+}
+
+impl From<rename::Error> for Error {
+    fn from(value: rename::Error) -> Self {
+        Error::Rename(value)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const INDENT: &str = "  ";
+
+        match self {
+            Self::Io(error) => {
+                writeln!(f, "{error}")?;
+            }
+
+            Self::InvalidFormat {
+                format,
+                offset,
+                message,
+            } => {
+                let column = format.get(..*offset).map_or(0, |head| head.chars().count());
+                writeln!(f, "invalid format: {message}")?;
+                writeln!(f, "{INDENT}{format}")?;
+                writeln!(f, "{INDENT}{:>width$}", "^", width = column + 1)?;
+            }
+
+            Self::Rename(error) => {
+                write!(f, "{error}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for Error {}
@@ -0,0 +1,148 @@
+use super::error::Error;
+use super::files;
+use crate::rename::RenameQueue;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Plans moving every file within `root` into subdirectories named after
+/// its extension, such as `pdf/` or `jpg/`, with files without one moved
+/// into `misc/`.
+///
+/// This is equivalent to [`ByExtension::new`] followed by
+/// [`ByExtension::queue`].
+///
+/// # Errors
+///
+/// See [`ByExtension::queue`].
+pub fn by_extension<P>(root: P) -> Result<RenameQueue, Error>
+where
+    P: AsRef<Path>,
+{
+    ByExtension::new().queue(root)
+}
+
+/// An organizer moving files into subdirectories named after their
+/// extensions.
+///
+/// Extensions are compared case-insensitively, and by default each one is
+/// given a subdirectory of its own, named after its lowercase form. Groups
+/// of extensions can be gathered into a single subdirectory instead.
+///
+/// Files already in place are left alone.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::ByExtension;
+///
+/// let mut queue = ByExtension::new()
+///     .group("images", ["jpg", "jpeg", "png"])
+///     .group("documents", ["pdf", "docx", "txt"])
+///     .fallback("other")
+///     .queue("downloads")
+///     .unwrap();
+/// // `downloads/a.JPG` -> `downloads/images/a.JPG`
+/// // `downloads/b.zip` -> `downloads/zip/b.zip`
+/// // `downloads/README` -> `downloads/other/README`
+/// queue.rename().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ByExtension {
+    groups: HashMap<String, String>,
+    fallback: String,
+}
+
+impl ByExtension {
+    /// Creates a new [`ByExtension`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gathers files with any of `extensions` into the subdirectory
+    /// `folder`.
+    ///
+    /// Extensions may be given with or without the leading dot. An extension
+    /// in several groups belongs to the last one.
+    pub fn group<I, S>(mut self, folder: &str, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for extension in extensions {
+            let extension = extension.as_ref().trim_start_matches('.');
+            self.groups
+                .insert(extension.to_lowercase(), folder.to_owned());
+        }
+        self
+    }
+
+    /// Sets the subdirectory for files without an extension, or whose
+    /// extension is not valid UTF-8.
+    ///
+    /// Defaults to `misc`.
+    pub fn fallback(mut self, folder: &str) -> Self {
+        self.fallback = folder.to_owned();
+        self
+    }
+
+    /// Returns the subdirectory for a file.
+    fn folder(&self, path: &Path) -> String {
+        let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+            return self.fallback.clone();
+        };
+        let extension = extension.to_lowercase();
+        match self.groups.get(&extension) {
+            Some(folder) => folder.clone(),
+            None => extension,
+        }
+    }
+
+    /// Walks `root`, returning the mappings of the files to move.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if `root` cannot be read. Entries within it
+    /// that cannot be read are skipped.
+    pub fn mappings<P>(&self, root: P) -> Result<Vec<(PathBuf, PathBuf)>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let root = root.as_ref();
+        let mut mappings = Vec::new();
+        for entry in files(root)? {
+            let src = entry.path();
+            let dst = root
+                .join(self.folder(src))
+                .join(src.file_name().unwrap_or_default());
+            if dst != src {
+                mappings.push((PathBuf::from(entry), dst));
+            }
+        }
+        Ok(mappings)
+    }
+
+    /// Walks `root`, and plans moving its files.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Io`] if `root` cannot be read.
+    /// - [`Error::Rename`] if the moves conflict, as described in
+    ///   [`RenameQueue::new`], such as when files of the same name share a
+    ///   subdirectory.
+    pub fn queue<P>(&self, root: P) -> Result<RenameQueue, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let queue = RenameQueue::new(self.mappings(root)?)?;
+        Ok(queue)
+    }
+}
+
+impl Default for ByExtension {
+    fn default() -> Self {
+        Self {
+            groups: HashMap::new(),
+            fallback: "misc".to_owned(),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use super::error::Error;
+use super::files;
+use crate::rename::RenameQueue;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{self, Path, PathBuf};
+
+/// Plans moving every file within `root` into the directory `target`,
+/// renaming files whose names collide with numeric suffixes.
+///
+/// This is equivalent to [`Flatten::new`] followed by [`Flatten::queue`].
+///
+/// # Errors
+///
+/// See [`Flatten::queue`].
+pub fn flatten<P, Q>(root: P, target: Q) -> Result<RenameQueue, Error>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    Flatten::new(target).queue(root)
+}
+
+/// An organizer moving every file within a tree into a single directory.
+///
+/// Files keep their names where possible. Files shallower in the tree are
+/// placed first, and names are never taken from entries already in the
+/// target directory, so later files whose names collide are renamed as set
+/// by [`Flatten::collision`].
+///
+/// Files already in the target directory are left alone.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::{Collision, Flatten};
+///
+/// let mut queue = Flatten::new("all")
+///     .collision(Collision::Prefix)
+///     .queue("nested")
+///     .unwrap();
+/// // `nested/a.txt` -> `all/a.txt`
+/// // `nested/x/y/a.txt` -> `all/x_y_a.txt`
+/// queue.rename().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Flatten {
+    target: PathBuf,
+    collision: Collision,
+    separator: String,
+}
+
+/// How [`Flatten`] renames files whose names collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Collision {
+    /// Appends a number to the stem, as in `a_2.txt`, counting up from 2.
+    #[default]
+    Suffix,
+
+    /// Prepends the directories of the file relative to the root, as in
+    /// `x_y_a.txt`, falling back to [`Collision::Suffix`] if that collides
+    /// too.
+    Prefix,
+}
+
+impl Flatten {
+    /// Creates a new [`Flatten`] moving files into `target`.
+    pub fn new<P>(target: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            target: target.as_ref().to_path_buf(),
+            collision: Collision::default(),
+            separator: "_".to_owned(),
+        }
+    }
+
+    /// Sets how files whose names collide are renamed.
+    ///
+    /// Defaults to [`Collision::Suffix`].
+    pub fn collision(mut self, collision: Collision) -> Self {
+        self.collision = collision;
+        self
+    }
+
+    /// Sets the separator placed before suffixes and after prefixes.
+    ///
+    /// Defaults to `_`.
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_owned();
+        self
+    }
+
+    /// Walks `root`, returning the mappings of the files to move.
+    ///
+    /// Paths are made absolute against the current working directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if `root`, or the target directory if it
+    /// exists, cannot be read. Entries within `root` that cannot be read are
+    /// skipped.
+    pub fn mappings<P>(&self, root: P) -> Result<Vec<(PathBuf, PathBuf)>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let root = path::absolute(root)?;
+        let target = path::absolute(&self.target)?;
+
+        let mut taken = HashSet::new();
+        match fs::read_dir(&target) {
+            Ok(iter) => {
+                for entry in iter {
+                    taken.insert(entry?.file_name());
+                }
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => (),
+            Err(error) => return Err(error.into()),
+        }
+
+        let mut paths: Vec<PathBuf> = files(&root)?
+            .map(PathBuf::from)
+            .filter(|path| path.parent() != Some(&target))
+            .collect();
+        paths.sort_by(|a, b| {
+            let depth = |path: &Path| path.components().count();
+            depth(a).cmp(&depth(b)).then_with(|| a.cmp(b))
+        });
+
+        let mut mappings = Vec::with_capacity(paths.len());
+        for src in paths {
+            let name = src.file_name().unwrap_or_default();
+            let name = if taken.contains(name) {
+                self.resolve(&root, &src, &taken)
+            } else {
+                name.to_owned()
+            };
+            let dst = target.join(&name);
+            taken.insert(name);
+            mappings.push((src, dst));
+        }
+        Ok(mappings)
+    }
+
+    /// Walks `root`, and plans moving its files.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Io`] if `root`, or the target directory if it exists,
+    ///   cannot be read.
+    /// - [`Error::Rename`] if the moves conflict, as described in
+    ///   [`RenameQueue::new`], such as when the target directory is a file.
+    pub fn queue<P>(&self, root: P) -> Result<RenameQueue, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let queue = RenameQueue::new(self.mappings(root)?)?;
+        Ok(queue)
+    }
+
+    /// Returns a free name for a file whose name is taken.
+    fn resolve(&self, root: &Path, src: &Path, taken: &HashSet<OsString>) -> OsString {
+        let name = src.file_name().unwrap_or_default();
+        let directories = src
+            .parent()
+            .and_then(|parent| parent.strip_prefix(root).ok())
+            .filter(|directories| directories.components().next().is_some());
+        let name = match (self.collision, directories) {
+            (Collision::Prefix, Some(directories)) => {
+                let mut prefixed = OsString::new();
+                for directory in directories {
+                    prefixed.push(directory);
+                    prefixed.push(&self.separator);
+                }
+                prefixed.push(name);
+                if !taken.contains(&prefixed) {
+                    return prefixed;
+                }
+                prefixed
+            }
+            _ => name.to_owned(),
+        };
+
+        let name = Path::new(&name);
+        let stem = name.file_stem().unwrap_or_default();
+        let extension = name.extension();
+        (2u64..)
+            .map(|number| {
+                let mut numbered = stem.to_owned();
+                numbered.push(&self.separator);
+                numbered.push(number.to_string());
+                if let Some(extension) = extension {
+                    numbered.push(".");
+                    numbered.push(extension);
+                }
+                numbered
+            })
+            .find(|numbered| !taken.contains(numbered))
+            .unwrap_or_default()
+    }
+}
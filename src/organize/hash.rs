@@ -0,0 +1,371 @@
+use super::error::Error;
+use super::files;
+use crate::rename::RenameQueue;
+use crate::rules::{digest_file, digest_prefix};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Plans moving every file within `root` into a content-addressed layout,
+/// as in `ab/cd/abcdef…`, with `fanout` levels of subdirectories.
+///
+/// This is equivalent to [`ByHash::new`] followed by [`ByHash::fanout`] and
+/// [`ByHash::plan`], returning the queue along with the manifest.
+///
+/// # Errors
+///
+/// See [`ByHash::plan`] and [`Manifest::queue`].
+pub fn by_hash<P>(
+    root: P,
+    algorithm: Algorithm,
+    fanout: usize,
+) -> Result<(RenameQueue, Manifest), Error>
+where
+    P: AsRef<Path>,
+{
+    let manifest = ByHash::new(algorithm).fanout(fanout).plan(root)?;
+    let queue = manifest.queue()?;
+    Ok((queue, manifest))
+}
+
+/// A hash algorithm files are addressed or compared by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Algorithm {
+    /// SHA-256, with 64 hexadecimal digits.
+    #[default]
+    Sha256,
+
+    /// The 64-bit FNV-1a hash, with 16 hexadecimal digits.
+    ///
+    /// This is cheaper than SHA-256, but not cryptographic, so files can be
+    /// crafted to collide, and unrelated files collide once in about 2^32
+    /// pairs, which matters for content-addressed layouts of many files.
+    Fnv1a64,
+}
+
+impl Algorithm {
+    /// Hashes the file at `path`, up to `limit` bytes if given.
+    pub(super) fn digest(self, path: &Path, limit: Option<u64>) -> io::Result<Vec<u8>> {
+        match (self, limit) {
+            (Self::Sha256, Some(limit)) => digest_prefix(path, limit).map(Vec::from),
+            (Self::Sha256, None) => digest_file(path).map(Vec::from),
+            (Self::Fnv1a64, limit) => {
+                let file = File::open(path)?;
+                let hash = match limit {
+                    Some(limit) => fnv1a64(file.take(limit))?,
+                    None => fnv1a64(file)?,
+                };
+                Ok(hash.to_be_bytes().to_vec())
+            }
+        }
+    }
+}
+
+/// Returns `digest` in lowercase hexadecimal.
+pub(super) fn hex(digest: &[u8]) -> String {
+    let mut hex = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        // Writing into a `String` never fails.
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+/// Hashes the contents of `reader` by the 64-bit FNV-1a hash.
+fn fnv1a64<R>(mut reader: R) -> io::Result<u64>
+where
+    R: Read,
+{
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(hash),
+            Ok(read) => {
+                for &byte in &buffer[..read] {
+                    hash = (hash ^ u64::from(byte)).wrapping_mul(PRIME);
+                }
+            }
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// An organizer moving files into a content-addressed layout, where each
+/// file is named after the digest of its contents in lowercase
+/// hexadecimal.
+///
+/// Files are placed under subdirectories named after the leading pairs of
+/// digits of their digests, one pair for each level of fanout, so a fanout
+/// of 2 places a file whose digest is `abcdef…` at `ab/cd/abcdef…`.
+///
+/// Only one file is moved for each digest, preferring one already in place,
+/// and then the first by path. Other files with the same contents are left
+/// in place, and recorded in the [`Manifest`] along with their digests, so
+/// they can be removed once the moves are done.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::{Algorithm, ByHash};
+/// use std::fs::File;
+///
+/// let manifest = ByHash::new(Algorithm::Sha256).plan("archive").unwrap();
+/// manifest.write(File::create("manifest.sha256").unwrap()).unwrap();
+/// // `archive/a.jpg` -> `archive/ab/cd/abcdef…`
+/// manifest.queue().unwrap().rename().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ByHash {
+    algorithm: Algorithm,
+    fanout: usize,
+    extension: bool,
+}
+
+/// The files planned by [`ByHash`], with the digests of their contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    root: PathBuf,
+    entries: Vec<ManifestEntry>,
+}
+
+/// A file recorded in a [`Manifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    path: PathBuf,
+    digest: String,
+    stored: PathBuf,
+    duplicate: bool,
+}
+
+impl ByHash {
+    /// Creates a new [`ByHash`] addressing files by `algorithm`.
+    pub fn new(algorithm: Algorithm) -> Self {
+        Self {
+            algorithm,
+            fanout: 2,
+            extension: false,
+        }
+    }
+
+    /// Sets the number of levels of subdirectories, which is capped at 16,
+    /// or at 8 for [`Algorithm::Fnv1a64`], whose digests are shorter.
+    ///
+    /// Defaults to 2.
+    pub fn fanout(mut self, fanout: usize) -> Self {
+        self.fanout = fanout.min(16);
+        self
+    }
+
+    /// Sets whether files keep their extensions, as in `ab/cd/abcdef….jpg`.
+    ///
+    /// Defaults to `false`.
+    pub fn extension(mut self, extension: bool) -> Self {
+        self.extension = extension;
+        self
+    }
+
+    /// Walks `root`, hashing its files, and records where each is stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if `root` cannot be read. Entries within it
+    /// that cannot be read are skipped.
+    pub fn plan<P>(&self, root: P) -> Result<Manifest, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let root = root.as_ref();
+        let mut files: Vec<_> = files(root)?
+            .filter_map(|entry| {
+                let digest = self.algorithm.digest(entry.path(), None).ok()?;
+                let hex = hex(&digest);
+                let stored = self.address(root, entry.path(), &hex);
+                Some((PathBuf::from(entry), hex, stored))
+            })
+            .collect();
+        // Files already in place come first, so they are kept.
+        files.sort_by(|(a, _, a_stored), (b, _, b_stored)| {
+            (a != a_stored).cmp(&(b != b_stored)).then_with(|| a.cmp(b))
+        });
+
+        let mut claimed = HashSet::new();
+        let mut entries = Vec::with_capacity(files.len());
+        for (path, digest, stored) in files {
+            let duplicate = !claimed.insert(stored.clone());
+            entries.push(ManifestEntry {
+                path,
+                digest,
+                stored,
+                duplicate,
+            });
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let root = root.to_path_buf();
+        Ok(Manifest { root, entries })
+    }
+
+    /// Walks `root`, returning the mappings of the files to move.
+    ///
+    /// # Errors
+    ///
+    /// See [`ByHash::plan`].
+    pub fn mappings<P>(&self, root: P) -> Result<Vec<(PathBuf, PathBuf)>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(self.plan(root)?.mappings())
+    }
+
+    /// Walks `root`, and plans moving its files.
+    ///
+    /// # Errors
+    ///
+    /// See [`ByHash::plan`] and [`Manifest::queue`].
+    pub fn queue<P>(&self, root: P) -> Result<RenameQueue, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.plan(root)?.queue()
+    }
+
+    /// Returns the path a file with the digest `hex` is stored at.
+    fn address(&self, root: &Path, path: &Path, hex: &str) -> PathBuf {
+        let mut stored = root.to_path_buf();
+        for level in 0..self.fanout.min(hex.len() / 2) {
+            stored.push(&hex[level * 2..level * 2 + 2]);
+        }
+        stored.push(hex);
+        if self.extension
+            && let Some(extension) = path.extension()
+        {
+            stored.set_extension(extension);
+        }
+        stored
+    }
+}
+
+impl Manifest {
+    /// Returns the files, ordered by their original paths.
+    #[inline]
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+
+    /// Returns the mappings of the files to move, leaving out those in
+    /// place and those whose contents are stored from another file.
+    pub fn mappings(&self) -> Vec<(PathBuf, PathBuf)> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.duplicate && entry.path != entry.stored)
+            .map(|entry| (entry.path.clone(), entry.stored.clone()))
+            .collect()
+    }
+
+    /// Plans moving the files.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Rename`] if the moves conflict, as described in
+    /// [`RenameQueue::new`], such as when a file is stored where a
+    /// directory of the layout would be.
+    pub fn queue(&self) -> Result<RenameQueue, Error> {
+        let queue = RenameQueue::new(self.mappings())?;
+        Ok(queue)
+    }
+
+    /// Writes the manifest in the format of `sha256sum`, with a line for
+    /// each file holding its digest and its original path relative to the
+    /// root, so the contents can be checked before the files are moved.
+    ///
+    /// Digests by [`Algorithm::Fnv1a64`] are written the same way, though
+    /// `sha256sum` cannot check them.
+    ///
+    /// Paths that are not valid UTF-8 are written lossily.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        for entry in &self.entries {
+            let path = entry.path.strip_prefix(&self.root).unwrap_or(&entry.path);
+            writeln!(writer, "{}  {}", entry.digest, path.display())?;
+        }
+        writer.flush()
+    }
+}
+
+impl ManifestEntry {
+    /// Returns the original path of the file.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the digest of the contents in lowercase hexadecimal.
+    #[inline]
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    /// Returns the path the contents are stored at, which is where the file
+    /// is moved to, or where another file with the same contents is.
+    #[inline]
+    pub fn stored(&self) -> &Path {
+        &self.stored
+    }
+
+    /// Returns `true` if the file is left in place as a duplicate of
+    /// another.
+    #[inline]
+    pub fn is_duplicate(&self) -> bool {
+        self.duplicate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn fnv1a64_matches_known_vectors() {
+        assert_eq!(fnv1a64(&b""[..]).unwrap(), 0xcbf29ce484222325);
+        assert_eq!(fnv1a64(&b"a"[..]).unwrap(), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a64(&b"foobar"[..]).unwrap(), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn digests_of_files_honor_the_limit() {
+        let path = env::temp_dir().join(format!("mofu-hash-{}", process::id()));
+        fs::write(&path, "abcdef").unwrap();
+        for algorithm in [Algorithm::Sha256, Algorithm::Fnv1a64] {
+            let full = algorithm.digest(&path, None).unwrap();
+            assert_eq!(algorithm.digest(&path, Some(6)).unwrap(), full);
+            assert_ne!(algorithm.digest(&path, Some(3)).unwrap(), full);
+        }
+        let sha256 = Algorithm::Sha256.digest(&path, Some(0)).unwrap();
+        assert!(hex(&sha256).starts_with("e3b0c442"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn fanout_is_capped_by_the_digest_length() {
+        let by_hash = ByHash::new(Algorithm::Fnv1a64).fanout(16);
+        let hex = "0123456789abcdef";
+        let stored = by_hash.address(Path::new("root"), Path::new("a.txt"), hex);
+        let expected = "root/01/23/45/67/89/ab/cd/ef/0123456789abcdef";
+        assert_eq!(stored, Path::new(expected));
+    }
+}
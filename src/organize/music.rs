@@ -0,0 +1,235 @@
+use super::error::Error;
+use super::files;
+use crate::rename::RenameQueue;
+use crate::rules::Tags;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Plans moving every audio file within `root` into an
+/// `Artist/Album/NN - Title.ext` layout derived from its tags.
+///
+/// This is equivalent to [`Music::new`] followed by [`Music::queue`].
+///
+/// # Errors
+///
+/// See [`Music::queue`].
+pub fn music<P>(root: P) -> Result<RenameQueue, Error>
+where
+    P: AsRef<Path>,
+{
+    Music::new().queue(root)
+}
+
+/// An organizer laying out audio files by their tags, as in
+/// `Artist/Album/NN - Title.ext`.
+///
+/// Tags are read from MP3, FLAC, Ogg Vorbis and Opus files, and other
+/// files are left alone. Tracks are gathered into albums by their album
+/// artist and album, or where there is no album artist, by their album and
+/// directory, and within an album:
+///
+/// - Track numbers are padded with zeros to at least 2 digits, and omitted
+///   where missing.
+/// - If any track is on a disc other than the first, track numbers are
+///   prefixed with disc numbers, as in `2-01 - Title.ext`.
+/// - If the album is marked as a compilation, its album artist is "Various
+///   Artists", or it has no album artist but several track artists, the
+///   album is placed under the folder for various artists, and the artist
+///   of each track is prepended to its title, as in
+///   `Various Artists/Album/01 - Artist - Title.ext`.
+///
+/// The artist of an album is its album artist, or the artist of its
+/// tracks. Missing artists and albums are replaced with placeholders, and
+/// missing titles with the original file stem. Path separators in tags are
+/// replaced with `_`.
+///
+/// Files already in place are left alone.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::Music;
+///
+/// let mut queue = Music::new()
+///     .various_artists("Compilations")
+///     .queue("music")
+///     .unwrap();
+/// // `music/inbox/track03.mp3` -> `music/Artist/Album/03 - Title.mp3`
+/// queue.rename().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Music {
+    unknown_artist: String,
+    unknown_album: String,
+    various_artists: String,
+}
+
+/// The tracks of an album, by their album artist or directory, and their
+/// album.
+type Album = (Option<String>, Option<PathBuf>, String);
+
+impl Music {
+    /// Creates a new [`Music`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the placeholder for missing artists.
+    ///
+    /// Defaults to `Unknown Artist`.
+    pub fn unknown_artist(mut self, name: &str) -> Self {
+        self.unknown_artist = name.to_owned();
+        self
+    }
+
+    /// Sets the placeholder for missing albums.
+    ///
+    /// Defaults to `Unknown Album`.
+    pub fn unknown_album(mut self, name: &str) -> Self {
+        self.unknown_album = name.to_owned();
+        self
+    }
+
+    /// Sets the folder for albums by various artists.
+    ///
+    /// Defaults to `Various Artists`.
+    pub fn various_artists(mut self, name: &str) -> Self {
+        self.various_artists = name.to_owned();
+        self
+    }
+
+    /// Walks `root`, returning the mappings of the files to move.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if `root` cannot be read. Entries within it
+    /// that cannot be read are skipped.
+    pub fn mappings<P>(&self, root: P) -> Result<Vec<(PathBuf, PathBuf)>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let root = root.as_ref();
+        let mut albums: BTreeMap<Album, Vec<(PathBuf, Tags)>> = BTreeMap::new();
+        for entry in files(root)? {
+            let Some(tags) = Tags::read(entry.path()) else {
+                continue;
+            };
+            let album_artist = tags.album_artist.clone();
+            let directory = match album_artist {
+                Some(_) => None,
+                None => entry.path().parent().map(Path::to_path_buf),
+            };
+            let album = tags.album.clone().unwrap_or_default();
+            let key = (album_artist, directory, album);
+            albums.entry(key).or_default().push((entry.into(), tags));
+        }
+
+        let mut mappings = Vec::new();
+        for ((album_artist, _, album), mut tracks) in albums {
+            tracks.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let artists: BTreeSet<_> = tracks
+                .iter()
+                .filter_map(|(_, tags)| tags.artist.as_deref())
+                .collect();
+            let various = tracks.iter().any(|(_, tags)| tags.compilation)
+                || album_artist
+                    .as_deref()
+                    .is_some_and(|artist| artist.eq_ignore_ascii_case("various artists"))
+                || (album_artist.is_none() && artists.len() > 1);
+            let multi_disc = tracks
+                .iter()
+                .any(|(_, tags)| tags.disc.is_some_and(|disc| disc > 1));
+            let last = tracks
+                .iter()
+                .filter_map(|(_, tags)| tags.track)
+                .max()
+                .unwrap_or_default();
+            let width = last.to_string().len().max(2);
+
+            let artist = if various {
+                self.various_artists.as_str()
+            } else {
+                album_artist
+                    .as_deref()
+                    .or_else(|| artists.first().copied())
+                    .unwrap_or(&self.unknown_artist)
+            };
+            let directory = root
+                .join(component(artist, &self.unknown_artist))
+                .join(component(&album, &self.unknown_album));
+
+            for (src, tags) in tracks {
+                let mut prefix = String::new();
+                // Writing into a `String` never fails.
+                if let Some(track) = tags.track {
+                    if multi_disc {
+                        let _ = write!(prefix, "{}-", tags.disc.unwrap_or(1));
+                    }
+                    let _ = write!(prefix, "{track:0width$} - ");
+                }
+                if various && let Some(artist) = &tags.artist {
+                    prefix.push_str(&component(artist, ""));
+                    prefix.push_str(" - ");
+                }
+                let mut name = OsString::from(prefix);
+                match tags.title.as_deref().map(|title| component(title, "")) {
+                    Some(title) if !title.is_empty() => name.push(title),
+                    _ => name.push(src.file_stem().unwrap_or_default()),
+                }
+                if let Some(extension) = src.extension() {
+                    name.push(".");
+                    name.push(extension);
+                }
+
+                let dst = directory.join(name);
+                if dst != src {
+                    mappings.push((src, dst));
+                }
+            }
+        }
+        Ok(mappings)
+    }
+
+    /// Walks `root`, and plans moving its audio files.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Io`] if `root` cannot be read.
+    /// - [`Error::Rename`] if the moves conflict, as described in
+    ///   [`RenameQueue::new`], such as when tracks of an album share a
+    ///   number and title.
+    pub fn queue<P>(&self, root: P) -> Result<RenameQueue, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let queue = RenameQueue::new(self.mappings(root)?)?;
+        Ok(queue)
+    }
+}
+
+impl Default for Music {
+    fn default() -> Self {
+        Self {
+            unknown_artist: "Unknown Artist".to_owned(),
+            unknown_album: "Unknown Album".to_owned(),
+            various_artists: "Various Artists".to_owned(),
+        }
+    }
+}
+
+/// Makes a tag safe to use as a path component, replacing path separators
+/// with `_`, or returns `fallback` if nothing is left of it.
+fn component(text: &str, fallback: &str) -> String {
+    let text = text.trim();
+    if text.is_empty() || text.chars().all(|char| char == '.') {
+        return fallback.to_owned();
+    }
+    text.chars()
+        .map(|char| match char {
+            '/' | '\\' => '_',
+            char => char,
+        })
+        .collect()
+}
@@ -0,0 +1,148 @@
+use super::error::Error;
+use super::files;
+use crate::rename::RenameQueue;
+use crate::rules::{DateFormat, Exif};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Plans moving every photo within `root` into a folder per shoot, starting
+/// a new shoot wherever more than `gap` passes between two photos.
+///
+/// This is equivalent to [`ByShoot::new`] with `%Y-%m-%d`, followed by
+/// [`ByShoot::gap`] and [`ByShoot::queue`].
+///
+/// # Errors
+///
+/// See [`ByShoot::queue`].
+pub fn by_shoot<P>(root: P, gap: Duration) -> Result<RenameQueue, Error>
+where
+    P: AsRef<Path>,
+{
+    ByShoot::new("%Y-%m-%d")?.gap(gap).queue(root)
+}
+
+/// An organizer grouping photos into shoots by the times they were taken.
+///
+/// Photos are ordered by the time recorded in their EXIF metadata, and a
+/// new shoot starts wherever the time between two consecutive photos is
+/// more than the gap. Each shoot is moved into a folder named after the
+/// time of its first photo, formatted in UTC like [`ByDate`], and folders
+/// of shoots whose names would collide are suffixed with `_2`, `_3`, and
+/// so on.
+///
+/// Files without a recorded time are left alone, as are photos already in
+/// place.
+///
+/// [`ByDate`]: super::ByDate
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::ByShoot;
+/// use std::time::Duration;
+///
+/// let mut queue = ByShoot::new("%Y-%m-%d %H%M")
+///     .unwrap()
+///     .gap(Duration::from_secs(2 * 60 * 60))
+///     .queue("photos")
+///     .unwrap();
+/// // `photos/DSC0001.JPG` -> `photos/2024-05-01 0930/DSC0001.JPG`
+/// queue.rename().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ByShoot {
+    format: DateFormat,
+    gap: Duration,
+}
+
+impl ByShoot {
+    /// Creates a new [`ByShoot`] from the date format of folders.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidFormat`] if the format has an unsupported or
+    /// trailing `%`.
+    pub fn new(format: &str) -> Result<Self, Error> {
+        let format =
+            DateFormat::parse(format).map_err(|(offset, message)| Error::InvalidFormat {
+                format: format.to_owned(),
+                offset,
+                message: message.to_owned(),
+            })?;
+        let gap = Duration::from_secs(4 * 60 * 60);
+        Ok(Self { format, gap })
+    }
+
+    /// Sets the longest time between two photos of the same shoot.
+    ///
+    /// Defaults to 4 hours.
+    pub fn gap(mut self, gap: Duration) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Walks `root`, returning the mappings of the photos to move.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if `root` cannot be read. Entries within it
+    /// that cannot be read are skipped.
+    pub fn mappings<P>(&self, root: P) -> Result<Vec<(PathBuf, PathBuf)>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let root = root.as_ref();
+        let mut photos: Vec<_> = files(root)?
+            .filter_map(|entry| {
+                let time = Exif::read(entry.path())?.datetime?;
+                Some((time, PathBuf::from(entry)))
+            })
+            .collect();
+        photos.sort();
+
+        let mut mappings = Vec::with_capacity(photos.len());
+        let mut folders: HashMap<String, u64> = HashMap::new();
+        let mut folder = PathBuf::new();
+        let mut previous = None;
+        for (time, src) in photos {
+            let within = previous.is_some_and(|previous| {
+                time.duration_since(previous)
+                    .is_ok_and(|elapsed| elapsed <= self.gap)
+            });
+            if !within {
+                let mut name = String::new();
+                self.format.render(time, &mut name);
+                let count = folders.entry(name.clone()).or_default();
+                *count += 1;
+                if *count > 1 {
+                    name = format!("{name}_{count}");
+                }
+                folder = root.join(name);
+            }
+            previous = Some(time);
+
+            let dst = folder.join(src.file_name().unwrap_or_default());
+            if dst != src {
+                mappings.push((src, dst));
+            }
+        }
+        Ok(mappings)
+    }
+
+    /// Walks `root`, and plans moving its photos.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Io`] if `root` cannot be read.
+    /// - [`Error::Rename`] if the moves conflict, as described in
+    ///   [`RenameQueue::new`], such as when photos of the same name belong
+    ///   to a shoot.
+    pub fn queue<P>(&self, root: P) -> Result<RenameQueue, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let queue = RenameQueue::new(self.mappings(root)?)?;
+        Ok(queue)
+    }
+}
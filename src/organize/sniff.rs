@@ -0,0 +1,299 @@
+use super::error::Error;
+use super::files;
+use crate::rename::RenameQueue;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Plans renaming every file within `root` whose extension does not match
+/// its contents.
+///
+/// This is equivalent to [`FixExtensions::new`] followed by
+/// [`FixExtensions::queue`].
+///
+/// # Errors
+///
+/// See [`FixExtensions::queue`].
+pub fn fix_extensions<P>(root: P) -> Result<RenameQueue, Error>
+where
+    P: AsRef<Path>,
+{
+    FixExtensions::new().queue(root)
+}
+
+/// Returns the extensions matching the contents of the file at `path`, the
+/// first of which is canonical, or [`None`] if its type is not recognized.
+///
+/// Types are recognized by the magic bytes at the start of files. Only
+/// signatures long enough to rarely occur by chance are recognized, which
+/// leaves out text formats, as well as formats shared by unrelated types
+/// such as the containers of legacy Office documents.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::sniff;
+///
+/// let extensions = sniff("photo.jpg").unwrap();
+/// if extensions.is_some_and(|extensions| !extensions.contains(&"jpg")) {
+///     println!("not a JPEG image");
+/// }
+/// ```
+pub fn sniff<P>(path: P) -> io::Result<Option<&'static [&'static str]>>
+where
+    P: AsRef<Path>,
+{
+    let mut head = Vec::with_capacity(HEAD as usize);
+    File::open(path)?.take(HEAD).read_to_end(&mut head)?;
+    let signature = SIGNATURES.iter().find(|signature| {
+        signature
+            .magic
+            .iter()
+            .all(|(offset, magic)| head.get(*offset..offset + magic.len()) == Some(*magic))
+    });
+    Ok(signature.map(|signature| signature.extensions))
+}
+
+/// A file type recognized by its magic bytes.
+struct Signature {
+    /// Bytes expected at offsets, all of which must match.
+    magic: &'static [(usize, &'static [u8])],
+    /// The extensions of the type, the first of which is canonical.
+    extensions: &'static [&'static str],
+}
+
+/// Only the head of a file is read, which covers every signature.
+const HEAD: u64 = 64;
+
+/// Signatures are tried in order, so more specific ones come first.
+const SIGNATURES: &[Signature] = &[
+    // Images.
+    Signature {
+        magic: &[(0, b"\xFF\xD8\xFF")],
+        extensions: &["jpg", "jpeg", "jpe", "jfif"],
+    },
+    Signature {
+        magic: &[(0, b"\x89PNG\r\n\x1A\n")],
+        extensions: &["png", "apng"],
+    },
+    Signature {
+        magic: &[(0, b"GIF87a")],
+        extensions: &["gif"],
+    },
+    Signature {
+        magic: &[(0, b"GIF89a")],
+        extensions: &["gif"],
+    },
+    Signature {
+        magic: &[(0, b"RIFF"), (8, b"WEBP")],
+        extensions: &["webp"],
+    },
+    Signature {
+        magic: &[(0, b"II*\0")],
+        extensions: &[
+            "tif", "tiff", "dng", "nef", "nrw", "cr2", "arw", "sr2", "srf", "orf", "pef", "srw",
+            "3fr", "erf", "kdc", "dcr", "mos", "iiq", "rwl",
+        ],
+    },
+    Signature {
+        magic: &[(0, b"MM\0*")],
+        extensions: &["tif", "tiff", "dng", "nef", "pef", "3fr", "mos", "iiq"],
+    },
+    Signature {
+        magic: &[(0, b"8BPS")],
+        extensions: &["psd", "psb"],
+    },
+    Signature {
+        magic: &[(4, b"ftypavif")],
+        extensions: &["avif"],
+    },
+    Signature {
+        magic: &[(4, b"ftypheic")],
+        extensions: &["heic", "heif"],
+    },
+    Signature {
+        magic: &[(4, b"ftypmif1")],
+        extensions: &["heic", "heif", "avif"],
+    },
+    // Audio and video. FLAC files are sometimes prefixed with ID3 tags too.
+    Signature {
+        magic: &[(0, b"ID3")],
+        extensions: &["mp3", "flac", "aac"],
+    },
+    Signature {
+        magic: &[(0, b"fLaC")],
+        extensions: &["flac"],
+    },
+    Signature {
+        magic: &[(0, b"OggS")],
+        extensions: &["ogg", "oga", "ogv", "opus", "spx"],
+    },
+    Signature {
+        magic: &[(0, b"RIFF"), (8, b"WAVE")],
+        extensions: &["wav", "wave"],
+    },
+    Signature {
+        magic: &[(0, b"RIFF"), (8, b"AVI ")],
+        extensions: &["avi"],
+    },
+    Signature {
+        magic: &[(4, b"ftypM4A ")],
+        extensions: &["m4a", "m4b", "mp4"],
+    },
+    Signature {
+        magic: &[(4, b"ftypqt  ")],
+        extensions: &["mov", "qt"],
+    },
+    Signature {
+        magic: &[(4, b"ftyp3gp")],
+        extensions: &["3gp", "mp4"],
+    },
+    Signature {
+        magic: &[(4, b"ftyp")],
+        extensions: &["mp4", "m4v", "m4a", "m4b", "mov", "3gp"],
+    },
+    Signature {
+        magic: &[(0, b"\x1A\x45\xDF\xA3")],
+        extensions: &["mkv", "webm", "mka", "mk3d"],
+    },
+    // Documents and archives.
+    Signature {
+        magic: &[(0, b"%PDF-")],
+        extensions: &["pdf", "ai"],
+    },
+    Signature {
+        magic: &[(0, b"PK\x03\x04"), (30, b"mimetypeapplication/epub+zip")],
+        extensions: &["epub"],
+    },
+    Signature {
+        magic: &[(0, b"PK\x03\x04")],
+        extensions: &[
+            "zip", "docx", "xlsx", "pptx", "odt", "ods", "odp", "epub", "jar", "apk", "aar", "ipa",
+            "xpi", "cbz", "whl", "nupkg", "vsix", "kmz", "3mf",
+        ],
+    },
+    Signature {
+        magic: &[(0, b"\x1F\x8B\x08")],
+        extensions: &["gz", "tgz"],
+    },
+    Signature {
+        magic: &[(0, b"BZh")],
+        extensions: &["bz2", "tbz2"],
+    },
+    Signature {
+        magic: &[(0, b"\xFD7zXZ\0")],
+        extensions: &["xz", "txz"],
+    },
+    Signature {
+        magic: &[(0, b"\x28\xB5\x2F\xFD")],
+        extensions: &["zst", "tzst"],
+    },
+    Signature {
+        magic: &[(0, b"7z\xBC\xAF\x27\x1C")],
+        extensions: &["7z"],
+    },
+    Signature {
+        magic: &[(0, b"Rar!\x1A\x07")],
+        extensions: &["rar", "cbr"],
+    },
+    Signature {
+        magic: &[(0, b"SQLite format 3\0")],
+        extensions: &["sqlite", "sqlite3", "db"],
+    },
+    Signature {
+        magic: &[(0, b"\0asm")],
+        extensions: &["wasm"],
+    },
+];
+
+/// An organizer fixing extensions that do not match the contents of files,
+/// such as a PNG image named `photo.jpg`.
+///
+/// Types are recognized by [`sniff`], and each type accepts several
+/// extensions, compared case-insensitively, so `photo.JPEG` is left alone
+/// if it holds a JPEG image. Otherwise, the extension is replaced with the
+/// canonical one of the type. Files of unrecognized types are left alone,
+/// as are files without an extension unless [`FixExtensions::missing`] is
+/// set.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::organize::FixExtensions;
+///
+/// let mut queue = FixExtensions::new().queue("downloads").unwrap();
+/// // `downloads/photo.jpg` -> `downloads/photo.png`
+/// queue.rename().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FixExtensions {
+    missing: bool,
+}
+
+impl FixExtensions {
+    /// Creates a new [`FixExtensions`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether files without an extension are given one.
+    ///
+    /// Defaults to `false`.
+    pub fn missing(mut self, missing: bool) -> Self {
+        self.missing = missing;
+        self
+    }
+
+    /// Walks `root`, returning the mappings of the files to rename.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if `root` cannot be read. Entries within it
+    /// that cannot be read are skipped.
+    pub fn mappings<P>(&self, root: P) -> Result<Vec<(PathBuf, PathBuf)>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut mappings = Vec::new();
+        for entry in files(root.as_ref())? {
+            let src = entry.path();
+            let extension = src.extension().map(|extension| extension.to_string_lossy());
+            if extension.is_none() && !self.missing {
+                continue;
+            }
+            let Ok(Some(extensions)) = sniff(src) else {
+                continue;
+            };
+            let matches = extension.as_ref().is_some_and(|extension| {
+                extensions
+                    .iter()
+                    .any(|candidate| candidate.eq_ignore_ascii_case(extension))
+            });
+            if !matches {
+                let dst = src.with_extension(extensions[0]);
+                mappings.push((PathBuf::from(entry), dst));
+            }
+        }
+        Ok(mappings)
+    }
+
+    /// Walks `root`, and plans renaming its files.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Io`] if `root` cannot be read.
+    /// - [`Error::Rename`] if the renames conflict, as described in
+    ///   [`RenameQueue::new`], such as when `photo.jpg` and `photo.png`
+    ///   both hold PNG images.
+    pub fn queue<P>(&self, root: P) -> Result<RenameQueue, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let queue = RenameQueue::new(self.mappings(root)?)?;
+        Ok(queue)
+    }
+}
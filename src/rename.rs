@@ -1,9 +1,61 @@
 //! Utilities for batch rename.
 
 pub use self::error::Error;
+pub use self::event::{Event, FnObserver, Observer, observer_fn};
+pub use self::faulty::FaultyFileSystem;
+pub use self::file_system::{FileSystem, OsFileSystem};
+pub use self::ftp::FtpFileSystem;
+pub use self::git::GitFileSystem;
+#[cfg(feature = "test-util")]
+pub use self::harness::{Counterexample, Harness};
+pub use self::json::JsonEmitter;
 pub use self::mapping::Mapping;
+pub use self::memory::MemoryFileSystem;
+pub use self::options::QueueOptions;
+#[cfg(feature = "progress")]
+pub use self::progress::ProgressBar;
 pub use self::queue::RenameQueue;
+pub use self::s3::S3FileSystem;
+#[cfg(feature = "sftp")]
+pub use self::sftp::SftpFileSystem;
+pub use self::sidecar::Sidecars;
+pub use self::snapshot::SnapshotFileSystem;
+pub use self::tar::TarFileSystem;
+pub use self::throttle::Throttle;
+pub use self::throughput::Throughput;
+pub use self::webdav::WebDavFileSystem;
+pub use self::zip::ZipFileSystem;
 
+pub(crate) use self::random::SplitMix64;
+
+mod command;
 mod error;
+mod event;
+mod faulty;
+mod file_system;
+mod ftp;
+mod git;
+#[cfg(feature = "test-util")]
+mod harness;
+mod intern;
+mod json;
 mod mapping;
+mod memory;
+mod options;
+#[cfg(feature = "progress")]
+mod progress;
 mod queue;
+mod random;
+mod s3;
+#[cfg(feature = "sftp")]
+mod sftp;
+mod sidecar;
+mod snapshot;
+mod symlink;
+mod tar;
+mod throttle;
+mod throughput;
+mod trie;
+mod validate;
+mod webdav;
+mod zip;
@@ -0,0 +1,48 @@
+use std::io::{Error, ErrorKind, Result, Write};
+use std::process::{Command, Output, Stdio};
+
+/// Runs a command to completion, feeding `input` to its standard input.
+///
+/// Returns the standard output on success, or an error carrying the trimmed
+/// standard error otherwise. A command that cannot be found is reported as
+/// [`ErrorKind::Unsupported`].
+pub(super) fn run(command: &mut Command, input: &[u8]) -> Result<Vec<u8>> {
+    let output = output(command, input)?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(failure(&output))
+    }
+}
+
+/// Runs a command to completion, feeding `input` to its standard input, and
+/// returns its output whatever its exit status.
+///
+/// A command that cannot be found is reported as [`ErrorKind::Unsupported`].
+pub(super) fn output(command: &mut Command, input: &[u8]) -> Result<Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| match error.kind() {
+            ErrorKind::NotFound => Error::new(ErrorKind::Unsupported, error),
+            _ => error,
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+
+    child.wait_with_output()
+}
+
+/// Returns an error carrying the trimmed standard error of a failed command.
+pub(super) fn failure(output: &Output) -> Error {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = match stderr.trim() {
+        "" => output.status.to_string(),
+        stderr => stderr.to_owned(),
+    };
+    Error::other(message)
+}
@@ -2,7 +2,7 @@ use std::error;
 use std::fmt;
 use std::io;
 use std::path::PathBuf;
-use std::rc::Rc;
+use std::sync::Arc;
 
 /// A enum for error handling.
 #[derive(Debug)]
@@ -10,23 +10,23 @@ pub enum Error {
     Io(io::Error),
 
     OneToMany {
-        src: Rc<PathBuf>,
-        dst: (Rc<PathBuf>, Rc<PathBuf>),
+        src: Arc<PathBuf>,
+        dst: (Arc<PathBuf>, Arc<PathBuf>),
     },
 
     ManyToOne {
-        src: (Rc<PathBuf>, Rc<PathBuf>),
-        dst: Rc<PathBuf>,
+        src: (Arc<PathBuf>, Arc<PathBuf>),
+        dst: Arc<PathBuf>,
     },
 
     NonLeafNode {
-        node: Rc<PathBuf>,
-        descendant: Rc<PathBuf>,
+        node: Arc<PathBuf>,
+        descendant: Arc<PathBuf>,
     },
 
     AlreadyExists {
-        src: Rc<PathBuf>,
-        dst: Rc<PathBuf>,
+        src: Arc<PathBuf>,
+        dst: Arc<PathBuf>,
     },
 
     AtomicActionFailed {
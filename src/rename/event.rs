@@ -0,0 +1,146 @@
+use super::error::Error;
+use super::mapping::Mapping;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+/// An event emitted while a [`RenameQueue`] is being executed.
+///
+/// Events own their data, so they can be sent across threads freely.
+///
+/// [`RenameQueue`]: super::RenameQueue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// Execution has started, with `total` mappings to process.
+    Started { total: usize },
+
+    /// A mapping has been renamed.
+    Renamed { src: PathBuf, dst: PathBuf },
+
+    /// A mapping has failed to be renamed or reverted.
+    Failed {
+        src: PathBuf,
+        dst: PathBuf,
+        error: String,
+    },
+
+    /// A mapping has been reverted.
+    RolledBack { src: PathBuf, dst: PathBuf },
+}
+
+impl Event {
+    pub(super) fn failed(mapping: &Mapping, error: &Error) -> Self {
+        Self::Failed {
+            src: mapping.to_src(),
+            dst: mapping.to_dst(),
+            error: error.to_string().trim_end().to_owned(),
+        }
+    }
+}
+
+/// A trait for observing execution progress of a [`RenameQueue`].
+///
+/// [`RenameQueue`]: super::RenameQueue
+pub trait Observer {
+    /// Called whenever an [`Event`] occurs.
+    fn notify(&mut self, event: &Event);
+}
+
+impl Observer for () {
+    #[inline]
+    fn notify(&mut self, _event: &Event) {}
+}
+
+impl<O> Observer for &mut O
+where
+    O: Observer + ?Sized,
+{
+    #[inline]
+    fn notify(&mut self, event: &Event) {
+        (**self).notify(event);
+    }
+}
+
+impl<A, B> Observer for (A, B)
+where
+    A: Observer,
+    B: Observer,
+{
+    #[inline]
+    fn notify(&mut self, event: &Event) {
+        self.0.notify(event);
+        self.1.notify(event);
+    }
+}
+
+/// An [`Observer`] calling a closure on every [`Event`].
+///
+/// This struct is created by [`observer_fn`].
+#[derive(Debug, Clone)]
+pub struct FnObserver<F> {
+    f: F,
+}
+
+impl<F> Observer for FnObserver<F>
+where
+    F: FnMut(&Event),
+{
+    #[inline]
+    fn notify(&mut self, event: &Event) {
+        (self.f)(event);
+    }
+}
+
+/// Creates an [`Observer`] calling the given closure on every [`Event`].
+///
+/// Closures cannot implement [`Observer`] themselves, as such an
+/// implementation would overlap with the one for `&mut O`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{Event, RenameQueue, observer_fn};
+///
+/// let mut renamed = 0;
+/// let mut observer = observer_fn(|event: &Event| {
+///     if let Event::Renamed { .. } = event {
+///         renamed += 1;
+///     }
+/// });
+///
+/// let mut queue = RenameQueue::new([("foo", "bar")]).unwrap();
+/// queue.rename_with(&mut observer).unwrap();
+/// assert_eq!(renamed, 1);
+/// ```
+#[inline]
+pub fn observer_fn<F>(f: F) -> FnObserver<F>
+where
+    F: FnMut(&Event),
+{
+    FnObserver { f }
+}
+
+impl Observer for Sender<Event> {
+    #[inline]
+    fn notify(&mut self, event: &Event) {
+        // A disconnected receiver should never interrupt the execution.
+        let _ = self.send(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closures_observe_events() {
+        let mut events = Vec::new();
+        let mut observer = observer_fn(|event: &Event| events.push(event.clone()));
+        let mut both = (&mut observer, ());
+        both.notify(&Event::Started { total: 1 });
+        observer.notify(&Event::Started { total: 2 });
+        assert_eq!(
+            events,
+            [Event::Started { total: 1 }, Event::Started { total: 2 }],
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use super::file_system::FileSystem;
+use super::random::SplitMix64;
+use std::collections::BTreeSet;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+/// A [`FileSystem`] wrapper that injects failures into renames.
+///
+/// This is intended for verifying that rollback and
+/// [`Error::AtomicActionFailed`] handling work as expected. Rename calls are
+/// counted from zero, including those issued during rollback.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rename::{Error, FaultyFileSystem, MemoryFileSystem, RenameQueue};
+///
+/// let mut fs = MemoryFileSystem::new();
+/// fs.write("/foo", "foo").unwrap();
+/// fs.write("/bar", "bar").unwrap();
+///
+/// // The second rename fails, and so does every rename after it, including
+/// // the rollback.
+/// let fs = FaultyFileSystem::new(fs).fail_from(1);
+/// let mut queue = RenameQueue::with_fs(fs, [("/foo", "/baz"), ("/bar", "/qux")]).unwrap();
+/// let result = queue.rename_atomic();
+///
+/// assert!(matches!(result, Err(Error::AtomicActionFailed { .. })));
+/// ```
+///
+/// [`Error::AtomicActionFailed`]: super::Error::AtomicActionFailed
+#[derive(Debug, Clone)]
+pub struct FaultyFileSystem<F>
+where
+    F: FileSystem,
+{
+    inner: F,
+    calls: usize,
+    failures: usize,
+    fail_on: BTreeSet<usize>,
+    fail_from: Option<usize>,
+    random: Option<Random>,
+    kind: ErrorKind,
+}
+
+#[derive(Debug, Clone)]
+struct Random {
+    generator: SplitMix64,
+    probability: f64,
+}
+
+impl<F> FaultyFileSystem<F>
+where
+    F: FileSystem,
+{
+    /// Creates a new [`FaultyFileSystem`] wrapping the given backend, without
+    /// any failures configured.
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            calls: 0,
+            failures: 0,
+            fail_on: BTreeSet::new(),
+            fail_from: None,
+            random: None,
+            kind: ErrorKind::Other,
+        }
+    }
+
+    /// Fails the `n`th rename call.
+    ///
+    /// This can be called multiple times to fail several calls.
+    pub fn fail_on(mut self, n: usize) -> Self {
+        self.fail_on.insert(n);
+        self
+    }
+
+    /// Fails every rename call starting from the `n`th one.
+    pub fn fail_from(mut self, n: usize) -> Self {
+        self.fail_from = Some(n);
+        self
+    }
+
+    /// Fails rename calls randomly with the given `probability`, using a
+    /// deterministic generator seeded with `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probability` is not within `0.0..=1.0`.
+    pub fn fail_randomly(mut self, seed: u64, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "probability must be within 0.0..=1.0"
+        );
+        let generator = SplitMix64::new(seed);
+        self.random = Some(Random {
+            generator,
+            probability,
+        });
+        self
+    }
+
+    /// Sets the kind of the injected errors.
+    ///
+    /// Defaults to [`ErrorKind::Other`].
+    pub fn error_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Returns the number of rename calls so far.
+    #[inline]
+    pub fn calls(&self) -> usize {
+        self.calls
+    }
+
+    /// Returns the number of injected failures so far.
+    #[inline]
+    pub fn failures(&self) -> usize {
+        self.failures
+    }
+
+    /// Returns a reference to the wrapped backend.
+    #[inline]
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped backend.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+
+    /// Consumes the [`FaultyFileSystem`], returning the wrapped backend.
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn should_fail(&mut self) -> bool {
+        let n = self.calls;
+        self.calls += 1;
+        // The generator always advances, so that the sequence of failures
+        // does not depend on the other options.
+        let random = self.random.as_mut().is_some_and(Random::next);
+        self.fail_on.contains(&n) || self.fail_from.is_some_and(|from| n >= from) || random
+    }
+}
+
+impl<F> Default for FaultyFileSystem<F>
+where
+    F: FileSystem + Default,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> FileSystem for FaultyFileSystem<F>
+where
+    F: FileSystem,
+{
+    #[inline]
+    fn exists(&mut self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    #[inline]
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        self.inner.create_dir_all(path)
+    }
+
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        if self.should_fail() {
+            self.failures += 1;
+            return Err(Error::new(self.kind, "injected failure"));
+        }
+        self.inner.rename(src, dst)
+    }
+}
+
+impl Random {
+    /// Advances the generator, returning whether to fail.
+    fn next(&mut self) -> bool {
+        self.generator.next_f64() < self.probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rename::MemoryFileSystem;
+
+    /// Returns a backend holding `/0` through `/{n - 1}`.
+    fn files(n: usize) -> MemoryFileSystem {
+        let mut fs = MemoryFileSystem::new();
+        for index in 0..n {
+            fs.write(format!("/{index}"), "").unwrap();
+        }
+        fs
+    }
+
+    /// Renames `/0` through `/{n - 1}` in turn, returning which calls
+    /// failed.
+    fn renames(fs: &mut FaultyFileSystem<MemoryFileSystem>, n: usize) -> Vec<bool> {
+        (0..n)
+            .map(|index| {
+                let src = format!("/{index}");
+                let dst = format!("/renamed-{index}");
+                fs.rename(Path::new(&src), Path::new(&dst)).is_err()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fail_on_fails_the_configured_calls_only() {
+        let mut fs = FaultyFileSystem::new(files(5)).fail_on(1).fail_on(3);
+        assert_eq!(renames(&mut fs, 5), [false, true, false, true, false]);
+        assert_eq!((fs.calls(), fs.failures()), (5, 2));
+
+        // Failed calls leave the backend untouched.
+        assert!(fs.exists(Path::new("/1")));
+        assert!(!fs.exists(Path::new("/renamed-1")));
+        assert!(fs.exists(Path::new("/renamed-2")));
+        fs.rename(Path::new("/1"), Path::new("/renamed-1")).unwrap();
+
+        let mut fs = FaultyFileSystem::new(files(1))
+            .fail_on(0)
+            .error_kind(ErrorKind::PermissionDenied);
+        let error = fs.rename(Path::new("/0"), Path::new("/x")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn fail_from_fails_every_call_after() {
+        let mut fs = FaultyFileSystem::new(files(4)).fail_from(2);
+        assert_eq!(renames(&mut fs, 4), [false, false, true, true]);
+        let error = fs.rename(Path::new("/0"), Path::new("/x")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Other);
+        assert_eq!(fs.failures(), 3);
+    }
+
+    #[test]
+    fn random_failures_are_reproducible() {
+        let run = |fail_on| {
+            let fs = FaultyFileSystem::new(files(64)).fail_randomly(7, 0.5);
+            let mut fs = match fail_on {
+                Some(n) => fs.fail_on(n),
+                None => fs,
+            };
+            renames(&mut fs, 64)
+        };
+        let failed = run(None);
+        assert_eq!(failed, run(None));
+        assert!(failed.contains(&true) && failed.contains(&false));
+
+        // Other options do not shift the random failures.
+        let index = failed.iter().position(|&failed| !failed).unwrap();
+        let mut expected = failed.clone();
+        expected[index] = true;
+        assert_eq!(run(Some(index)), expected);
+    }
+
+    #[test]
+    fn other_operations_pass_through() {
+        let mut fs = FaultyFileSystem::new(MemoryFileSystem::new()).fail_from(0);
+        fs.create_dir_all(Path::new("/a/b")).unwrap();
+        assert!(fs.exists(Path::new("/a/b")));
+        assert_eq!(fs.calls(), 0);
+        assert!(fs.into_inner().is_dir("/a/b"));
+    }
+}
@@ -0,0 +1,93 @@
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+
+/// A trait abstracting the file system operations needed to plan and
+/// execute a [`RenameQueue`].
+///
+/// Implementing this trait allows all the planning and rollback logic to be
+/// reused against alternative backends.
+///
+/// [`RenameQueue`]: super::RenameQueue
+pub trait FileSystem {
+    /// Returns `true` if the path points at an existing entity.
+    ///
+    /// Like [`Path::exists`], this follows symlinks.
+    fn exists(&mut self, path: &Path) -> bool;
+
+    /// Recursively creates a directory and all of its parent components if
+    /// they are missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any directory cannot be created.
+    fn create_dir_all(&mut self, path: &Path) -> Result<()>;
+
+    /// Renames a file or directory to a new name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entity cannot be renamed.
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()>;
+}
+
+impl<F> FileSystem for &mut F
+where
+    F: FileSystem + ?Sized,
+{
+    #[inline]
+    fn exists(&mut self, path: &Path) -> bool {
+        (**self).exists(path)
+    }
+
+    #[inline]
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        (**self).create_dir_all(path)
+    }
+
+    #[inline]
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        (**self).rename(src, dst)
+    }
+}
+
+impl<F> FileSystem for Box<F>
+where
+    F: FileSystem + ?Sized,
+{
+    #[inline]
+    fn exists(&mut self, path: &Path) -> bool {
+        (**self).exists(path)
+    }
+
+    #[inline]
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        (**self).create_dir_all(path)
+    }
+
+    #[inline]
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        (**self).rename(src, dst)
+    }
+}
+
+/// The file system of the operating system, backed by [`std::fs`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFileSystem;
+
+impl FileSystem for OsFileSystem {
+    #[inline]
+    fn exists(&mut self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    #[inline]
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    #[inline]
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        fs::rename(src, dst)
+    }
+}
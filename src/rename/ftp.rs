@@ -0,0 +1,383 @@
+use super::file_system::FileSystem;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::{Component, Path, PathBuf};
+
+/// A [`FileSystem`] that operates on a remote server over plain FTP.
+///
+/// Renames are performed with the `RNFR` and `RNTO` commands. Existence is
+/// checked with `MLST` where supported, falling back to listing the parent
+/// directory with `NLST` over a passive data connection otherwise. Only a
+/// `550` reply is taken as a path that does not exist, while any other
+/// failure, such as a dropped connection, is reported by the next
+/// operation, so conflict checks never pass on a failing session.
+///
+/// Note that plain FTP transmits credentials in clear text.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{FtpFileSystem, RenameQueue};
+///
+/// let mut fs = FtpFileSystem::connect("ftp.example.com:21").unwrap();
+/// fs.login("alice", "secret").unwrap();
+/// let mut queue = RenameQueue::with_fs(fs, [("/www/old.html", "/www/new.html")]).unwrap();
+/// queue.rename_atomic().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FtpFileSystem {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    mlst: bool,
+    /// A failure while checking whether a path exists, which is yet to be
+    /// reported.
+    failure: Option<String>,
+}
+
+#[derive(Debug)]
+struct Reply {
+    code: u16,
+    message: String,
+}
+
+impl FtpFileSystem {
+    /// Connects to an FTP server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails, or if the server does not
+    /// greet with a positive reply.
+    pub fn connect<A>(addr: A) -> Result<Self>
+    where
+        A: ToSocketAddrs,
+    {
+        let writer = TcpStream::connect(addr)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        let mut ftp = Self {
+            reader,
+            writer,
+            mlst: true,
+            failure: None,
+        };
+        ftp.read_reply()?.expect(220)?;
+        Ok(ftp)
+    }
+
+    /// Logs in with the given credentials.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server rejects the credentials.
+    pub fn login(&mut self, user: &str, password: &str) -> Result<()> {
+        let reply = self.command(&format!("USER {user}"))?;
+        if reply.code == 331 {
+            self.command(&format!("PASS {password}"))?.expect(230)?;
+        } else {
+            reply.expect(230)?;
+        }
+        // Transfer names in binary mode to avoid line ending conversions.
+        self.command("TYPE I")?.expect(200)
+    }
+
+    /// Returns the paths of the entries in a directory, using `NLST`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be listed.
+    pub fn list<P>(&mut self, dir: P) -> Result<Vec<PathBuf>>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        let mut data = self.passive()?;
+        let reply = self.command(&format!("NLST {}", remote(dir)?))?;
+        if !matches!(reply.code, 125 | 150) {
+            return Err(reply.into_error());
+        }
+        let mut listing = String::new();
+        data.read_to_string(&mut listing)?;
+        drop(data);
+        self.read_reply()?.expect(226)?;
+
+        // Some servers return bare names, while others return full paths.
+        let paths = listing
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.is_empty())
+            .map(|line| match Path::new(line).file_name() {
+                Some(name) => dir.join(name),
+                None => dir.join(line),
+            })
+            .collect();
+        Ok(paths)
+    }
+
+    /// Closes the session gracefully.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server does not acknowledge.
+    pub fn quit(mut self) -> Result<()> {
+        self.command("QUIT")?.expect(221)
+    }
+
+    fn command(&mut self, command: &str) -> Result<Reply> {
+        self.writer.write_all(command.as_bytes())?;
+        self.writer.write_all(b"\r\n")?;
+        self.writer.flush()?;
+        self.read_reply()
+    }
+
+    fn read_reply(&mut self) -> Result<Reply> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+        let code: u16 = line
+            .get(..3)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "malformed reply"))?;
+        let mut message = line[3..].trim().trim_start_matches('-').to_owned();
+
+        // Multi-line replies end with a line starting with the same code
+        // followed by a space.
+        if line.as_bytes().get(3) == Some(&b'-') {
+            let terminator = format!("{code} ");
+            loop {
+                line.clear();
+                if self.reader.read_line(&mut line)? == 0 {
+                    return Err(Error::from(ErrorKind::UnexpectedEof));
+                }
+                message.push('\n');
+                message.push_str(line.trim_end());
+                if line.starts_with(&terminator) {
+                    break;
+                }
+            }
+        }
+
+        Ok(Reply { code, message })
+    }
+
+    fn passive(&mut self) -> Result<TcpStream> {
+        let reply = self.command("PASV")?;
+        if reply.code != 227 {
+            return Err(reply.into_error());
+        }
+        let start = reply
+            .message
+            .find(|char: char| char.is_ascii_digit())
+            .ok_or_else(|| reply.malformed())?;
+        let numbers: Vec<u8> = reply.message[start..]
+            .split(|char: char| !char.is_ascii_digit())
+            .filter(|number| !number.is_empty())
+            .take(6)
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| reply.malformed())?;
+        let [_, _, _, _, p1, p2] = numbers[..] else {
+            return Err(reply.malformed());
+        };
+
+        // Servers behind NAT often advertise an unroutable address, so the
+        // host of the control connection is used instead.
+        let ip = self.writer.peer_addr()?.ip();
+        let port = u16::from(p1) << 8 | u16::from(p2);
+        TcpStream::connect(SocketAddr::new(ip, port))
+    }
+
+    /// Reports the failure left by [`FileSystem::exists`], if any.
+    fn check(&mut self) -> Result<()> {
+        match self.failure.take() {
+            Some(failure) => Err(Error::other(failure)),
+            None => Ok(()),
+        }
+    }
+
+    fn try_exists(&mut self, path: &Path) -> Result<bool> {
+        let remote = remote(path)?;
+        if self.mlst {
+            let reply = self.command(&format!("MLST {remote}"))?;
+            match reply.code {
+                250 => return Ok(true),
+                550 => return Ok(false),
+                500..=502 => self.mlst = false,
+                _ => return Err(reply.into_error()),
+            }
+        }
+        self.exists_by_listing(path)
+    }
+
+    fn exists_by_listing(&mut self, path: &Path) -> Result<bool> {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return Ok(true);
+        };
+        match self.list(parent) {
+            Ok(listing) => Ok(listing.iter().any(|entry| entry.file_name() == Some(name))),
+            // The parent does not exist, or is empty for some servers.
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl FileSystem for FtpFileSystem {
+    fn exists(&mut self, path: &Path) -> bool {
+        match self.try_exists(path) {
+            Ok(exists) => exists,
+            Err(error) if error.kind() == ErrorKind::InvalidInput => false,
+            Err(error) => {
+                self.failure = Some(error.to_string());
+                false
+            }
+        }
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        self.check()?;
+        let mut ancestor = PathBuf::new();
+        for component in path.components() {
+            ancestor.push(component);
+            if let Component::Normal(_) = component
+                && !self.try_exists(&ancestor)?
+            {
+                self.command(&format!("MKD {}", remote(&ancestor)?))?
+                    .expect(257)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        self.check()?;
+        self.command(&format!("RNFR {}", remote(src)?))?
+            .expect(350)?;
+        self.command(&format!("RNTO {}", remote(dst)?))?.expect(250)
+    }
+}
+
+impl Reply {
+    fn expect(self, code: u16) -> Result<()> {
+        if self.code == code {
+            Ok(())
+        } else {
+            Err(self.into_error())
+        }
+    }
+
+    fn into_error(self) -> Error {
+        let kind = match self.code {
+            530 | 532 => ErrorKind::PermissionDenied,
+            550 => ErrorKind::NotFound,
+            553 => ErrorKind::InvalidInput,
+            _ => ErrorKind::Other,
+        };
+        Error::new(kind, format!("{} {}", self.code, self.message))
+    }
+
+    fn malformed(&self) -> Error {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("malformed reply: {}", self.message),
+        )
+    }
+}
+
+/// Converts a path into a remote path, rejecting line breaks which would
+/// otherwise allow injecting commands.
+fn remote(path: &Path) -> Result<String> {
+    let path = path.to_str().ok_or(ErrorKind::InvalidInput)?;
+    if path.contains(['\r', '\n']) {
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+    Ok(path.replace('\\', "/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Serves a single session, answering each command with the reply
+    /// `respond` returns for it, or dropping the connection on [`None`].
+    /// Returns the commands received once the session ends.
+    fn serve<F>(respond: F) -> (FtpFileSystem, JoinHandle<Vec<String>>)
+    where
+        F: Fn(&str) -> Option<&'static str> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            stream.write_all(b"220 ready\r\n").unwrap();
+            let mut commands = Vec::new();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                let command = line.trim_end().to_owned();
+                line.clear();
+                let Some(reply) = respond(&command) else {
+                    break;
+                };
+                commands.push(command);
+                stream.write_all(format!("{reply}\r\n").as_bytes()).unwrap();
+            }
+            commands
+        });
+        (FtpFileSystem::connect(addr).unwrap(), server)
+    }
+
+    #[test]
+    fn only_550_means_missing() {
+        let (mut fs, server) = serve(|command| match command {
+            "MLST /a" => Some("250-Listing\r\n type=file; /a\r\n250 End"),
+            "MLST /b" => Some("550 No such file"),
+            "MLST /c" => Some("450 Busy"),
+            "RNFR /a" => Some("350 Ready"),
+            "RNTO /b" => Some("250 Renamed"),
+            _ => Some("500 Unexpected"),
+        });
+        assert!(fs.exists(Path::new("/a")));
+        assert!(!fs.exists(Path::new("/b")));
+        assert!(fs.failure.is_none());
+
+        assert!(!fs.exists(Path::new("/c")));
+        assert!(fs.failure.is_some());
+        let error = fs.rename(Path::new("/a"), Path::new("/b")).unwrap_err();
+        assert!(error.to_string().contains("450"));
+        fs.rename(Path::new("/a"), Path::new("/b")).unwrap();
+        drop(fs);
+
+        let commands = server.join().unwrap();
+        assert_eq!(
+            commands,
+            ["MLST /a", "MLST /b", "MLST /c", "RNFR /a", "RNTO /b"]
+        );
+    }
+
+    #[test]
+    fn dropped_connections_are_reported() {
+        let (mut fs, server) = serve(|command| command.starts_with("MKD").then_some("257 Created"));
+        assert!(!fs.exists(Path::new("/a")));
+        assert!(fs.create_dir_all(Path::new("/a")).is_err());
+        assert!(fs.create_dir_all(Path::new("/a")).is_err());
+        drop(fs);
+        assert!(server.join().unwrap().is_empty());
+    }
+
+    #[test]
+    fn listing_failures_are_reported() {
+        let (mut fs, server) = serve(|command| match command {
+            "MLST /dir/a" => Some("502 Not implemented"),
+            "PASV" => Some("421 Too many connections"),
+            _ => Some("500 Unexpected"),
+        });
+        assert!(!fs.exists(Path::new("/dir/a")));
+        assert!(!fs.mlst);
+        let error = fs.create_dir_all(Path::new("/dir")).unwrap_err();
+        assert!(error.to_string().contains("421"));
+        drop(fs);
+        assert_eq!(server.join().unwrap(), ["MLST /dir/a", "PASV"]);
+    }
+}
@@ -0,0 +1,122 @@
+use super::command;
+use super::file_system::FileSystem;
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A [`FileSystem`] that renames tracked paths through `git mv`.
+///
+/// Renaming through `git mv` updates the working tree and the index in one
+/// step, so they never diverge mid-batch and rename detection in history
+/// stays clean. Paths that are not inside a work tree, or are not tracked,
+/// are renamed directly instead.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{GitFileSystem, RenameQueue};
+///
+/// let fs = GitFileSystem::new();
+/// let mut queue = RenameQueue::with_fs(fs, [("src/old.rs", "src/new.rs")]).unwrap();
+/// queue.rename_atomic().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct GitFileSystem {
+    program: PathBuf,
+}
+
+impl GitFileSystem {
+    /// Creates a new [`GitFileSystem`].
+    #[inline]
+    pub fn new() -> Self {
+        let program = PathBuf::from("git");
+        Self { program }
+    }
+
+    /// Sets the path of the `git` program.
+    ///
+    /// Defaults to `git`, looked up in `PATH`.
+    pub fn program<P>(mut self, program: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.program = program.into();
+        self
+    }
+
+    fn git(&self, dir: &Path) -> Command {
+        let mut command = Command::new(&self.program);
+        command.arg("-C").arg(dir);
+        command
+    }
+
+    /// Returns `true` if the path is, or contains, a tracked file.
+    fn is_tracked(&self, path: &Path) -> bool {
+        let Some(parent) = path.parent() else {
+            return false;
+        };
+        let mut command = self.git(parent);
+        // File names such as `*.txt` are not taken as patterns.
+        command
+            .arg("--literal-pathspecs")
+            .arg("ls-files")
+            .arg("--error-unmatch")
+            .arg("--")
+            .arg(path);
+        command::run(&mut command, &[]).is_ok()
+    }
+}
+
+impl Default for GitFileSystem {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem for GitFileSystem {
+    #[inline]
+    fn exists(&mut self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    #[inline]
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        let Some(parent) = src.parent().filter(|_| self.is_tracked(src)) else {
+            return fs::rename(src, dst);
+        };
+        let mut command = self.git(parent);
+        command.arg("mv").arg("--").arg(src).arg(dst);
+        command::run(&mut command, &[]).map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn file_names_are_not_taken_as_patterns() {
+        let dir = env::temp_dir().join(format!("mofu-git-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        fs::write(dir.join("*.txt"), "").unwrap();
+        let mut git = GitFileSystem::new();
+        command::run(git.git(&dir).args(["init", "--quiet"]), &[]).unwrap();
+        command::run(git.git(&dir).args(["add", "b.txt"]), &[]).unwrap();
+
+        // `*.txt` itself is untracked, although it matches `b.txt`.
+        assert!(git.is_tracked(&dir.join("b.txt")));
+        assert!(!git.is_tracked(&dir.join("*.txt")));
+        git.rename(&dir.join("*.txt"), &dir.join("c.txt")).unwrap();
+        assert!(dir.join("c.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,348 @@
+use super::error::Error;
+use super::faulty::FaultyFileSystem;
+use super::file_system::FileSystem;
+use super::memory::MemoryFileSystem;
+use super::options::QueueOptions;
+use super::queue::RenameQueue;
+use super::random::SplitMix64;
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+type Tree = BTreeMap<PathBuf, Vec<u8>>;
+
+/// A property-based test harness running random plans against a
+/// [`MemoryFileSystem`].
+///
+/// Each case generates a random tree of files, asks a planner for mappings
+/// over it, and checks that:
+///
+/// - the mappings are accepted;
+/// - renaming moves every file to where the mappings say, without losing,
+///   duplicating or leaving behind anything, including temporary paths used
+///   to resolve cycles;
+/// - reverting restores the original tree exactly;
+/// - a failure injected at any point during renaming is rolled back, leaving
+///   the original tree intact.
+///
+/// Cases are derived from a single seed, so a failure can be reproduced by
+/// running the harness again with the same seed.
+///
+/// This is only available with the `test-util` feature.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rename::Harness;
+///
+/// Harness::new(42).cases(64).run().unwrap();
+/// ```
+///
+/// A custom planner receives the generated tree and a seed for its own
+/// randomness:
+///
+/// ```
+/// use mofu::rename::Harness;
+/// use std::path::PathBuf;
+///
+/// // Appends a suffix to every file name.
+/// Harness::new(42)
+///     .check(|fs, _seed| {
+///         fs.paths()
+///             .into_iter()
+///             .filter(|path| fs.is_file(path))
+///             .map(|path| {
+///                 let mut dst = path.clone().into_os_string();
+///                 dst.push(".bak");
+///                 (path, PathBuf::from(dst))
+///             })
+///             .collect()
+///     })
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Harness {
+    seed: u64,
+    cases: usize,
+    files: usize,
+}
+
+/// A case for which a [`Harness`] invariant does not hold.
+#[derive(Debug, Clone)]
+pub struct Counterexample {
+    case: usize,
+    seed: u64,
+    mappings: Vec<(PathBuf, PathBuf)>,
+    message: String,
+}
+
+impl Harness {
+    /// Creates a new [`Harness`] deriving its cases from the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            cases: 256,
+            files: 16,
+        }
+    }
+
+    /// Sets the number of cases to run.
+    ///
+    /// Defaults to 256.
+    pub fn cases(mut self, cases: usize) -> Self {
+        self.cases = cases;
+        self
+    }
+
+    /// Sets the maximum number of files in each generated tree.
+    ///
+    /// Defaults to 16.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `files` is zero.
+    pub fn files(mut self, files: usize) -> Self {
+        assert!(files > 0, "files must be non-zero");
+        self.files = files;
+        self
+    }
+
+    /// Runs the harness with randomly generated mappings, mixing chains,
+    /// cycles, and moves into new directories.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`Counterexample`] found.
+    pub fn run(&self) -> Result<(), Counterexample> {
+        self.check(generate)
+    }
+
+    /// Runs the harness with mappings produced by `planner`.
+    ///
+    /// The planner is given the generated tree, whose files all have distinct
+    /// contents, and a seed it may use for its own randomness. Its mappings
+    /// should use absolute paths.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`Counterexample`] found.
+    pub fn check<P>(&self, mut planner: P) -> Result<(), Counterexample>
+    where
+        P: FnMut(&MemoryFileSystem, u64) -> Vec<(PathBuf, PathBuf)>,
+    {
+        let mut generator = SplitMix64::new(self.seed);
+        for case in 0..self.cases {
+            let seed = generator.next_u64();
+            let fs = self.tree(seed);
+            let mappings = planner(&fs, seed);
+            if let Err(message) = verify(fs, &mappings, seed) {
+                return Err(Counterexample {
+                    case,
+                    seed,
+                    mappings,
+                    message,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn tree(&self, seed: u64) -> MemoryFileSystem {
+        let mut generator = SplitMix64::new(seed);
+        let mut fs = MemoryFileSystem::new();
+        let files = 1 + below(&mut generator, self.files);
+        for i in 0..files {
+            // Directories and files are named differently, so no file is
+            // ever the ancestor of another.
+            let mut path = PathBuf::from("/");
+            for _ in 0..below(&mut generator, 3) {
+                path.push(format!("d{}", below(&mut generator, 3)));
+            }
+            path.push(format!("f{i}"));
+            let contents = path.to_string_lossy().into_owned();
+            fs.write(&path, contents)
+                .expect("generated paths never conflict");
+        }
+        fs
+    }
+}
+
+impl Counterexample {
+    /// Returns the index of the failing case.
+    #[inline]
+    pub fn case(&self) -> usize {
+        self.case
+    }
+
+    /// Returns the seed of the failing case, as given to the planner.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the mappings of the failing case.
+    #[inline]
+    pub fn mappings(&self) -> &[(PathBuf, PathBuf)] {
+        &self.mappings
+    }
+
+    /// Returns a description of the violated invariant.
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const INDENT: &str = "  ";
+
+        writeln!(f, "case {} (seed {}) failed:", self.case, self.seed)?;
+        for line in self.message.lines() {
+            writeln!(f, "{INDENT}{line}")?;
+        }
+        writeln!(f, "{INDENT}mappings:")?;
+        for (src, dst) in &self.mappings {
+            writeln!(f, "{INDENT}{INDENT}{} -> {}", src.display(), dst.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for Counterexample {}
+
+/// Generates mappings over the files of a tree.
+///
+/// A random subset of files is permuted among itself, forming chains and
+/// cycles, and some of them are moved to new paths instead.
+fn generate(fs: &MemoryFileSystem, seed: u64) -> Vec<(PathBuf, PathBuf)> {
+    let mut generator = SplitMix64::new(seed);
+    let sources: Vec<PathBuf> = files(fs)
+        .into_keys()
+        .filter(|_| generator.next_u64().is_multiple_of(2))
+        .collect();
+    let mut destinations = sources.clone();
+    // Fisher–Yates shuffle.
+    for i in (1..destinations.len()).rev() {
+        destinations.swap(i, below(&mut generator, i + 1));
+    }
+    for (i, dst) in destinations.iter_mut().enumerate() {
+        if generator.next_u64().is_multiple_of(3) {
+            let dir = format!("d{}", below(&mut generator, 4));
+            *dst = Path::new("/").join(dir).join(format!("n{i}"));
+        }
+    }
+    sources.into_iter().zip(destinations).collect()
+}
+
+/// Checks every invariant for a single case.
+fn verify(fs: MemoryFileSystem, mappings: &[(PathBuf, PathBuf)], seed: u64) -> Result<(), String> {
+    let original = files(&fs);
+    let expected = expect(&original, mappings);
+
+    let mut queue = plan(fs.clone(), mappings)?;
+    let steps = queue.pending().len();
+    queue
+        .rename_atomic()
+        .map_err(|error| describe("renaming failed", &error))?;
+    compare("after renaming", &expected, &files(queue.fs()))?;
+    queue
+        .revert_atomic()
+        .map_err(|error| describe("reverting failed", &error))?;
+    compare("after reverting", &original, &files(queue.fs()))?;
+
+    if steps > 0 {
+        let mut generator = SplitMix64::new(seed);
+        let step = below(&mut generator, steps);
+        let faulty = FaultyFileSystem::new(fs).fail_on(step);
+        let mut queue = plan(faulty, mappings)?;
+        match queue.rename_atomic() {
+            Ok(_) => return Err(format!("renaming succeeded despite failing step {step}")),
+            Err(error @ Error::AtomicActionFailed { .. }) => {
+                return Err(describe("rollback failed", &error));
+            }
+            Err(_) => {}
+        }
+        let context = format!("after failing step {step}");
+        compare(&context, &original, &files(queue.fs().inner()))?;
+    }
+
+    Ok(())
+}
+
+fn plan<F>(fs: F, mappings: &[(PathBuf, PathBuf)]) -> Result<RenameQueue<F>, String>
+where
+    F: FileSystem,
+{
+    let options = QueueOptions::new().deterministic(true);
+    RenameQueue::with_options(fs, options, mappings.iter().cloned())
+        .map_err(|error| describe("planning failed", &error))
+}
+
+/// Returns the tree expected after applying the mappings.
+fn expect(original: &Tree, mappings: &[(PathBuf, PathBuf)]) -> Tree {
+    let mappings: BTreeMap<&Path, &Path> = mappings
+        .iter()
+        .map(|(src, dst)| (src.as_path(), dst.as_path()))
+        .collect();
+    original
+        .iter()
+        .map(|(path, contents)| {
+            let moved = path
+                .ancestors()
+                .find_map(|ancestor| {
+                    let dst = mappings.get(ancestor)?;
+                    let relative = path.strip_prefix(ancestor).ok()?;
+                    Some(if relative.as_os_str().is_empty() {
+                        dst.to_path_buf()
+                    } else {
+                        dst.join(relative)
+                    })
+                })
+                .unwrap_or_else(|| path.clone());
+            (moved, contents.clone())
+        })
+        .collect()
+}
+
+/// Returns every file of a tree, along with its contents.
+fn files(fs: &MemoryFileSystem) -> Tree {
+    fs.paths()
+        .into_iter()
+        .filter(|path| fs.is_file(path))
+        .filter_map(|path| {
+            let contents = fs.read(&path).ok()?.to_vec();
+            Some((path, contents))
+        })
+        .collect()
+}
+
+fn compare(context: &str, expected: &Tree, actual: &Tree) -> Result<(), String> {
+    if expected == actual {
+        return Ok(());
+    }
+    let mut message = format!("unexpected tree {context}:\n");
+    for (path, contents) in expected {
+        match actual.get(path) {
+            None => message.push_str(&format!("  missing {}\n", path.display())),
+            Some(actual) if actual != contents => {
+                message.push_str(&format!("  wrong contents at {}\n", path.display()));
+            }
+            Some(_) => {}
+        }
+    }
+    for path in actual.keys().filter(|path| !expected.contains_key(*path)) {
+        message.push_str(&format!("  unexpected {}\n", path.display()));
+    }
+    Err(message)
+}
+
+fn describe(context: &str, error: &Error) -> String {
+    format!("{context}:\n{error}")
+}
+
+/// Returns a sample uniformly distributed within `0..n`, or nearly so.
+fn below(generator: &mut SplitMix64, n: usize) -> usize {
+    (generator.next_u64() % n as u64) as usize
+}
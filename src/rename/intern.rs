@@ -0,0 +1,164 @@
+use super::mapping::Mapping;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The size of the buffers file names are packed into.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A path stored as a shared parent directory and a file name packed into a
+/// shared buffer.
+///
+/// Large plans tend to rename many files under the same few directories, so
+/// storing each path in full would duplicate the same long prefix over and
+/// over. Parents are instead deduplicated, and file names are packed side by
+/// side into large chunks, rather than each being allocated on its own.
+#[derive(Clone)]
+pub(super) struct SharedPath {
+    parent: Arc<Path>,
+    chunk: Arc<[u8]>,
+    start: u32,
+    len: u32,
+}
+
+impl SharedPath {
+    /// Returns the path in full.
+    pub(super) fn to_path_buf(&self) -> PathBuf {
+        let name = self.name();
+        if name.is_empty() {
+            self.parent.to_path_buf()
+        } else {
+            self.parent.join(name)
+        }
+    }
+
+    fn name(&self) -> &OsStr {
+        let start = self.start as usize;
+        let end = start + self.len as usize;
+        // The range always covers exactly the bytes of a whole `OsStr`, as
+        // written by `Interner::pack`.
+        unsafe { OsStr::from_encoded_bytes_unchecked(&self.chunk[start..end]) }
+    }
+}
+
+impl fmt::Debug for SharedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The chunk holds the names of many other paths, so only the path
+        // itself is printed.
+        fmt::Debug::fmt(&self.to_path_buf(), f)
+    }
+}
+
+/// An arena for the paths of a queue under construction.
+///
+/// Parent directories are deduplicated through a table, and file names are
+/// written into the current chunk. Mappings are held back until their chunk
+/// is full, since a chunk cannot be shared while it is still being written.
+#[derive(Debug, Default)]
+pub(super) struct Interner {
+    parents: HashSet<Arc<Path>>,
+    chunk: Vec<u8>,
+    held: Vec<[Draft; 2]>,
+}
+
+#[derive(Debug)]
+struct Draft {
+    parent: Arc<Path>,
+    start: u32,
+    len: u32,
+}
+
+impl Interner {
+    /// Packs a mapping, and appends it to `queue` once its chunk is full.
+    ///
+    /// Call [`Interner::finish`] to append the mappings held back last.
+    pub(super) fn push(&mut self, src: &Path, dst: &Path, queue: &mut Vec<Mapping>) {
+        let src = self.split(src);
+        let dst = self.split(dst);
+        let len = src.1.len() + dst.1.len();
+        if !self.chunk.is_empty() && self.chunk.len() + len > CHUNK_SIZE {
+            self.flush(queue);
+        }
+        if self.chunk.is_empty() {
+            self.chunk.reserve(CHUNK_SIZE.max(len));
+        }
+        let src = self.pack(src);
+        let dst = self.pack(dst);
+        self.held.push([src, dst]);
+    }
+
+    /// Packs a single mapping into a chunk of its own.
+    pub(super) fn single(src: &Path, dst: &Path) -> Mapping {
+        let mut interner = Self::default();
+        let src = interner.split(src);
+        let dst = interner.split(dst);
+        let src = interner.pack(src);
+        let dst = interner.pack(dst);
+        let chunk = Arc::<[u8]>::from(interner.chunk);
+        Mapping::from_shared(src.into_shared(&chunk), dst.into_shared(&chunk))
+    }
+
+    /// Appends the mappings held back to `queue`.
+    pub(super) fn finish(mut self, queue: &mut Vec<Mapping>) {
+        self.flush(queue);
+    }
+
+    /// Replaces the parent of a path with an equal shared one.
+    pub(super) fn share(&mut self, path: &mut SharedPath) {
+        path.parent = self.parent(&path.parent);
+    }
+
+    /// Splits a path into its shared parent and its file name.
+    fn split<'a>(&mut self, path: &'a Path) -> (Arc<Path>, &'a OsStr) {
+        // Paths such as `/` or `foo/..` cannot be rebuilt by joining their
+        // parent and file name, so they are stored in full.
+        let split = path
+            .parent()
+            .zip(path.file_name())
+            .filter(|(parent, name)| parent.join(name).as_os_str() == path.as_os_str());
+        let (parent, name) = match split {
+            Some((parent, name)) => (parent, name),
+            None => (path, OsStr::new("")),
+        };
+        (self.parent(parent), name)
+    }
+
+    fn pack(&mut self, (parent, name): (Arc<Path>, &OsStr)) -> Draft {
+        let start = self.chunk.len() as u32;
+        self.chunk.extend_from_slice(name.as_encoded_bytes());
+        let len = name.len() as u32;
+        Draft { parent, start, len }
+    }
+
+    fn flush(&mut self, queue: &mut Vec<Mapping>) {
+        let chunk = Arc::<[u8]>::from(mem::take(&mut self.chunk));
+        queue.extend(self.held.drain(..).map(|[src, dst]| {
+            Mapping::from_shared(src.into_shared(&chunk), dst.into_shared(&chunk))
+        }));
+    }
+
+    fn parent(&mut self, parent: &Path) -> Arc<Path> {
+        match self.parents.get(parent) {
+            Some(parent) => Arc::clone(parent),
+            None => {
+                let parent = Arc::<Path>::from(parent);
+                self.parents.insert(Arc::clone(&parent));
+                parent
+            }
+        }
+    }
+}
+
+impl Draft {
+    fn into_shared(self, chunk: &Arc<[u8]>) -> SharedPath {
+        SharedPath {
+            parent: self.parent,
+            chunk: Arc::clone(chunk),
+            start: self.start,
+            len: self.len,
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use super::event::{Event, Observer};
+use super::file_system::FileSystem;
+use super::queue::RenameQueue;
+use std::fmt::Write as _;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+/// An [`Observer`] that writes one JSON object per line for each lifecycle
+/// event, suitable for consumption with tools such as `jq`.
+///
+/// Each object carries an `event` field, which is one of `planned`,
+/// `started`, `renamed`, `failed` or `rolled_back`. Paths that are not valid
+/// UTF-8 are converted lossily.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{JsonEmitter, RenameQueue};
+/// use std::io;
+///
+/// let mut queue = RenameQueue::new([("foo", "bar")]).unwrap();
+/// let mut emitter = JsonEmitter::new(io::stdout());
+/// emitter.plan(&queue).unwrap();
+/// queue.rename_atomic_with(&mut emitter).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct JsonEmitter<W>
+where
+    W: Write,
+{
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W> JsonEmitter<W>
+where
+    W: Write,
+{
+    /// Creates a new [`JsonEmitter`] that writes to the given writer.
+    #[inline]
+    pub fn new(writer: W) -> Self {
+        let error = None;
+        Self { writer, error }
+    }
+
+    /// Writes a `planned` object listing the pending mappings of `queue`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn plan<F>(&mut self, queue: &RenameQueue<F>) -> io::Result<()>
+    where
+        F: FileSystem,
+    {
+        let mut line = String::from(r#"{"event":"planned","pending":["#);
+        for (index, mapping) in queue.pending().iter().enumerate() {
+            if index > 0 {
+                line.push(',');
+            }
+            line.push('{');
+            push_path_field(&mut line, "src", &mapping.to_src());
+            line.push(',');
+            push_path_field(&mut line, "dst", &mapping.to_dst());
+            line.push('}');
+        }
+        line.push_str("]}");
+        writeln!(self.writer, "{line}")?;
+        self.writer.flush()
+    }
+
+    /// Returns the first error that occurred while writing events, if any.
+    ///
+    /// Since observers cannot interrupt the execution, write errors are
+    /// recorded here instead of being propagated.
+    #[inline]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Consumes the [`JsonEmitter`], returning the underlying writer.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W> Observer for JsonEmitter<W>
+where
+    W: Write,
+{
+    fn notify(&mut self, event: &Event) {
+        let mut line = String::from('{');
+        match event {
+            Event::Started { total } => {
+                line.push_str(r#""event":"started""#);
+                let _ = write!(line, r#","total":{total}"#);
+            }
+            Event::Renamed { src, dst } => {
+                line.push_str(r#""event":"renamed","#);
+                push_path_field(&mut line, "src", src);
+                line.push(',');
+                push_path_field(&mut line, "dst", dst);
+            }
+            Event::Failed { src, dst, error } => {
+                line.push_str(r#""event":"failed","#);
+                push_path_field(&mut line, "src", src);
+                line.push(',');
+                push_path_field(&mut line, "dst", dst);
+                line.push(',');
+                push_str_field(&mut line, "error", error);
+            }
+            Event::RolledBack { src, dst } => {
+                line.push_str(r#""event":"rolled_back","#);
+                push_path_field(&mut line, "src", src);
+                line.push(',');
+                push_path_field(&mut line, "dst", dst);
+            }
+        }
+        line.push('}');
+
+        if let Err(error) = writeln!(self.writer, "{line}").and_then(|()| self.writer.flush()) {
+            self.error.get_or_insert(error);
+        }
+    }
+}
+
+fn push_path_field(buf: &mut String, key: &str, value: &Path) {
+    push_str_field(buf, key, &value.to_string_lossy());
+}
+
+fn push_str_field(buf: &mut String, key: &str, value: &str) {
+    push_str(buf, key);
+    buf.push(':');
+    push_str(buf, value);
+}
+
+fn push_str(buf: &mut String, value: &str) {
+    buf.push('"');
+    for char in value.chars() {
+        match char {
+            '"' => buf.push_str(r#"\""#),
+            '\\' => buf.push_str(r"\\"),
+            '\n' => buf.push_str(r"\n"),
+            '\r' => buf.push_str(r"\r"),
+            '\t' => buf.push_str(r"\t"),
+            char if char.is_control() => {
+                let _ = write!(buf, r"\u{:04x}", char as u32);
+            }
+            char => buf.push(char),
+        }
+    }
+    buf.push('"');
+}
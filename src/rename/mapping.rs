@@ -1,48 +1,90 @@
 use super::error::Error;
+use super::file_system::FileSystem;
+use super::intern::{Interner, SharedPath};
 use serde::de::{Deserialize, Deserializer, Error as DeError, MapAccess, Visitor};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 use std::fmt;
-use std::fs;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
 
 /// A struct representing a single source-destination mapping.
+///
+/// Parent directories are shared between mappings of the same queue, so
+/// paths are only rebuilt in full when borrowed, and then kept.
 #[derive(Debug)]
 pub struct Mapping {
-    pub(super) src: Rc<PathBuf>,
-    pub(super) dst: Rc<PathBuf>,
+    src: SharedPath,
+    dst: SharedPath,
+    /// The paths rebuilt in full, once borrowed.
+    src_buf: OnceLock<PathBuf>,
+    dst_buf: OnceLock<PathBuf>,
 }
 
 impl Mapping {
+    pub(super) fn from_shared(src: SharedPath, dst: SharedPath) -> Self {
+        Self {
+            src,
+            dst,
+            src_buf: OnceLock::new(),
+            dst_buf: OnceLock::new(),
+        }
+    }
+
     /// Returns the source.
     #[inline]
     pub fn src(&self) -> &Path {
-        self.src.as_path()
+        self.src_buf.get_or_init(|| self.src.to_path_buf())
     }
 
     /// Returns the destination.
     #[inline]
     pub fn dst(&self) -> &Path {
-        self.dst.as_path()
+        self.dst_buf.get_or_init(|| self.dst.to_path_buf())
+    }
+
+    /// Returns the source as an owned path, without keeping it in full.
+    #[inline]
+    pub fn to_src(&self) -> PathBuf {
+        match self.src_buf.get() {
+            Some(src) => src.clone(),
+            None => self.src.to_path_buf(),
+        }
+    }
+
+    /// Returns the destination as an owned path, without keeping it in full.
+    #[inline]
+    pub fn to_dst(&self) -> PathBuf {
+        match self.dst_buf.get() {
+            Some(dst) => dst.clone(),
+            None => self.dst.to_path_buf(),
+        }
     }
 
-    pub(super) fn rename(&self) -> Result<(), Error> {
-        if self.dst.exists() {
-            let src = Rc::clone(&self.src);
-            let dst = Rc::clone(&self.dst);
+    pub(super) fn rename<F>(&self, fs: &mut F) -> Result<(), Error>
+    where
+        F: FileSystem,
+    {
+        let src = self.to_src();
+        let dst = self.to_dst();
+        if fs.exists(&dst) {
+            let src = Arc::new(src);
+            let dst = Arc::new(dst);
             return Err(Error::AlreadyExists { src, dst });
         }
-        if let Some(parent) = self.dst.parent() {
-            fs::create_dir_all(parent)?;
+        if let Some(parent) = dst.parent() {
+            fs.create_dir_all(parent)?;
         }
-        fs::rename(self.src(), self.dst())?;
+        fs.rename(&src, &dst)?;
         Ok(())
     }
 
     pub(super) fn invert(&self) -> Self {
-        let src = Rc::clone(&self.dst);
-        let dst = Rc::clone(&self.src);
-        Self { src, dst }
+        Self::from_shared(self.dst.clone(), self.src.clone())
+    }
+
+    pub(super) fn share(&mut self, interner: &mut Interner) {
+        interner.share(&mut self.src);
+        interner.share(&mut self.dst);
     }
 }
 
@@ -54,8 +96,8 @@ impl Serialize for Mapping {
         S: Serializer,
     {
         let mut mapping = serializer.serialize_struct("Mapping", 2)?;
-        mapping.serialize_field("src", self.src())?;
-        mapping.serialize_field("dst", self.dst())?;
+        mapping.serialize_field("src", &self.to_src())?;
+        mapping.serialize_field("dst", &self.to_dst())?;
         mapping.end()
     }
 }
@@ -103,14 +145,10 @@ impl<'de> Visitor<'de> for MappingVisitor {
             }
         }
 
-        let src = src
-            .map(Rc::new)
-            .ok_or_else(|| DeError::missing_field("src"))?;
-        let dst = dst
-            .map(Rc::new)
-            .ok_or_else(|| DeError::missing_field("dst"))?;
+        let src = src.ok_or_else(|| DeError::missing_field("src"))?;
+        let dst = dst.ok_or_else(|| DeError::missing_field("dst"))?;
 
-        Ok(Mapping { src, dst })
+        Ok(Interner::single(&src, &dst))
     }
 }
 
@@ -150,3 +188,36 @@ impl Visitor<'_> for FieldVisitor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrowed_and_owned_paths_agree() {
+        let mapping = Interner::single(Path::new("/a/b/c.txt"), Path::new("/a/d"));
+        assert_eq!(mapping.to_src(), Path::new("/a/b/c.txt"));
+        assert_eq!(mapping.src(), Path::new("/a/b/c.txt"));
+        assert_eq!(mapping.dst(), Path::new("/a/d"));
+
+        let inverted = mapping.invert();
+        assert_eq!(inverted.src(), mapping.dst());
+        assert_eq!(inverted.to_dst(), mapping.to_src());
+    }
+
+    #[test]
+    fn paths_without_a_file_name_are_kept_whole() {
+        let mapping = Interner::single(Path::new("/"), Path::new("a/.."));
+        assert_eq!(mapping.src(), Path::new("/"));
+        assert_eq!(mapping.dst().as_os_str(), "a/..");
+    }
+
+    #[test]
+    fn debug_prints_paths() {
+        let mapping = Interner::single(Path::new("/a/b.txt"), Path::new("/a/c.txt"));
+        let debug = format!("{mapping:?}");
+        assert!(debug.contains(r#"src: "/a/b.txt""#), "{debug}");
+        assert!(debug.contains(r#"dst: "/a/c.txt""#), "{debug}");
+        assert!(!debug.contains("chunk"), "{debug}");
+    }
+}
@@ -0,0 +1,548 @@
+use super::file_system::FileSystem;
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// Maximum number of symlinks followed while resolving a single path.
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// An in-memory [`FileSystem`] that models directories, files and symlinks.
+///
+/// This is intended for exercising planning, execution, rollback and error
+/// paths deterministically without touching the disk. Paths are expected to
+/// be absolute, and any path prefix (such as a Windows drive letter) is
+/// ignored.
+///
+/// Renaming follows POSIX semantics: a file may replace an existing file, and
+/// a directory may replace an existing empty directory.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rename::{MemoryFileSystem, RenameQueue};
+///
+/// let mut fs = MemoryFileSystem::new();
+/// fs.write("/foo", "foo").unwrap();
+/// fs.write("/bar", "bar").unwrap();
+///
+/// let mut queue = RenameQueue::with_fs(fs, [("/foo", "/bar"), ("/bar", "/foo")]).unwrap();
+/// queue.rename_atomic().unwrap();
+///
+/// assert_eq!(queue.fs().read("/foo").unwrap(), b"bar");
+/// assert_eq!(queue.fs().read("/bar").unwrap(), b"foo");
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryFileSystem {
+    root: Dir,
+    case_sensitive: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    File(Vec<u8>),
+    Dir(Dir),
+    Symlink(PathBuf),
+}
+
+#[derive(Debug, Clone, Default)]
+struct Dir {
+    entries: BTreeMap<OsString, Entry>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    name: OsString,
+    node: Node,
+}
+
+impl MemoryFileSystem {
+    /// Creates a new, empty, case-sensitive [`MemoryFileSystem`].
+    #[inline]
+    pub fn new() -> Self {
+        let root = Dir::default();
+        let case_sensitive = true;
+        Self {
+            root,
+            case_sensitive,
+        }
+    }
+
+    /// Sets whether file names are case-sensitive.
+    ///
+    /// Case-insensitive file systems preserve the letter case of names, but
+    /// treat names that differ only in letter case as the same entry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file system is not empty.
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        assert!(
+            self.root.entries.is_empty(),
+            "case sensitivity must be set on an empty file system"
+        );
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Writes a file, creating any missing parent directories.
+    ///
+    /// An existing file is overwritten.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path points at a directory, or if any parent
+    /// is not a directory.
+    pub fn write<P, C>(&mut self, path: P, contents: C) -> Result<()>
+    where
+        P: AsRef<Path>,
+        C: Into<Vec<u8>>,
+    {
+        self.insert(path.as_ref(), Node::File(contents.into()))
+    }
+
+    /// Creates a symlink at `link` pointing at `target`, creating any missing
+    /// parent directories.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `link` already exists, or if any parent is not a
+    /// directory.
+    pub fn symlink<P, Q>(&mut self, target: P, link: Q) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let link = link.as_ref();
+        if self.lookup(link, false).is_ok() {
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+        self.insert(link, Node::Symlink(target.as_ref().to_path_buf()))
+    }
+
+    /// Returns the contents of a file, following symlinks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path does not exist or is not a file.
+    pub fn read<P>(&self, path: P) -> Result<&[u8]>
+    where
+        P: AsRef<Path>,
+    {
+        match self.lookup(path.as_ref(), true)? {
+            Node::File(contents) => Ok(contents),
+            Node::Dir(_) => Err(Error::from(ErrorKind::IsADirectory)),
+            Node::Symlink(_) => unreachable!(),
+        }
+    }
+
+    /// Returns the target of a symlink.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path does not exist or is not a symlink.
+    pub fn read_link<P>(&self, path: P) -> Result<&Path>
+    where
+        P: AsRef<Path>,
+    {
+        match self.lookup(path.as_ref(), false)? {
+            Node::Symlink(target) => Ok(target),
+            _ => Err(Error::from(ErrorKind::InvalidInput)),
+        }
+    }
+
+    /// Returns the paths of the direct children of a directory, following
+    /// symlinks, in the order of their names.
+    ///
+    /// The returned paths preserve the letter case of the stored names.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path does not exist or is not a directory.
+    pub fn read_dir<P>(&self, path: P) -> Result<Vec<PathBuf>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        match self.lookup(path, true)? {
+            Node::Dir(dir) => Ok(dir
+                .entries
+                .values()
+                .map(|entry| path.join(&entry.name))
+                .collect()),
+            _ => Err(Error::from(ErrorKind::NotADirectory)),
+        }
+    }
+
+    /// Returns `true` if the path points at a file, following symlinks.
+    #[inline]
+    pub fn is_file<P>(&self, path: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        matches!(self.lookup(path.as_ref(), true), Ok(Node::File(_)))
+    }
+
+    /// Returns `true` if the path points at a directory, following symlinks.
+    #[inline]
+    pub fn is_dir<P>(&self, path: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        matches!(self.lookup(path.as_ref(), true), Ok(Node::Dir(_)))
+    }
+
+    /// Returns `true` if the path points at a symlink.
+    #[inline]
+    pub fn is_symlink<P>(&self, path: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        matches!(self.lookup(path.as_ref(), false), Ok(Node::Symlink(_)))
+    }
+
+    /// Returns the paths of all entries in sorted order, without following
+    /// symlinks.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        let mut stack = vec![(PathBuf::from("/"), &self.root)];
+        while let Some((path, dir)) = stack.pop() {
+            for entry in dir.entries.values().rev() {
+                let path = path.join(&entry.name);
+                if let Node::Dir(dir) = &entry.node {
+                    stack.push((path.clone(), dir));
+                }
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        paths
+    }
+
+    fn key(&self, name: &OsStr) -> OsString {
+        if self.case_sensitive {
+            name.to_os_string()
+        } else {
+            match name.to_str() {
+                Some(name) => name.to_lowercase().into(),
+                None => name.to_os_string(),
+            }
+        }
+    }
+
+    /// Resolves a path into its components, following symlinks in every
+    /// component except, optionally, the last one. The last component is not
+    /// required to exist.
+    fn resolve(&self, path: &Path, follow_last: bool) -> Result<Vec<OsString>> {
+        let mut pending: Vec<OsString> = components(path).into_iter().rev().collect();
+        let mut resolved: Vec<OsString> = Vec::new();
+        let mut hops = 0;
+
+        while let Some(name) = pending.pop() {
+            if name == ".." {
+                resolved.pop();
+                continue;
+            }
+            let is_last = pending.is_empty();
+            let parent = self.dir(&resolved)?;
+            let Some(entry) = parent.entries.get(&self.key(&name)) else {
+                if is_last {
+                    resolved.push(name);
+                    return Ok(resolved);
+                }
+                return Err(Error::from(ErrorKind::NotFound));
+            };
+            match &entry.node {
+                Node::Symlink(target) if !is_last || follow_last => {
+                    hops += 1;
+                    if hops > MAX_SYMLINK_HOPS {
+                        return Err(Error::other("too many levels of symbolic links"));
+                    }
+                    if target.has_root() {
+                        resolved.clear();
+                    }
+                    pending.extend(components(target).into_iter().rev());
+                }
+                Node::File(_) if !is_last => return Err(Error::from(ErrorKind::NotADirectory)),
+                _ => resolved.push(name),
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    fn lookup(&self, path: &Path, follow: bool) -> Result<&Node> {
+        let resolved = self.resolve(path, follow)?;
+        self.lookup_resolved(&resolved)
+    }
+
+    fn lookup_resolved(&self, resolved: &[OsString]) -> Result<&Node> {
+        let Some((name, parent)) = resolved.split_last() else {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        };
+        self.dir(parent)?
+            .entries
+            .get(&self.key(name))
+            .map(|entry| &entry.node)
+            .ok_or_else(|| Error::from(ErrorKind::NotFound))
+    }
+
+    fn dir(&self, components: &[OsString]) -> Result<&Dir> {
+        let mut dir = &self.root;
+        for name in components {
+            match dir.entries.get(&self.key(name)).map(|entry| &entry.node) {
+                Some(Node::Dir(child)) => dir = child,
+                Some(_) => return Err(Error::from(ErrorKind::NotADirectory)),
+                None => return Err(Error::from(ErrorKind::NotFound)),
+            }
+        }
+        Ok(dir)
+    }
+
+    fn dir_mut(&mut self, components: &[OsString]) -> Result<&mut Dir> {
+        let keys: Vec<OsString> = components.iter().map(|name| self.key(name)).collect();
+        let mut dir = &mut self.root;
+        for key in &keys {
+            match dir.entries.get_mut(key).map(|entry| &mut entry.node) {
+                Some(Node::Dir(child)) => dir = child,
+                Some(_) => return Err(Error::from(ErrorKind::NotADirectory)),
+                None => return Err(Error::from(ErrorKind::NotFound)),
+            }
+        }
+        Ok(dir)
+    }
+
+    fn insert(&mut self, path: &Path, node: Node) -> Result<()> {
+        let parent = path.parent().ok_or(ErrorKind::InvalidInput)?;
+        FileSystem::create_dir_all(self, parent)?;
+        let resolved = self.resolve(path, true)?;
+        let Some((name, parent)) = resolved.split_last() else {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        };
+        let key = self.key(name);
+        let name = name.clone();
+        let dir = self.dir_mut(parent)?;
+        if let Some(Entry {
+            node: Node::Dir(_), ..
+        }) = dir.entries.get(&key)
+        {
+            return Err(Error::from(ErrorKind::IsADirectory));
+        }
+        dir.entries.insert(key, Entry { name, node });
+        Ok(())
+    }
+}
+
+impl Default for MemoryFileSystem {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn exists(&mut self, path: &Path) -> bool {
+        components(path).is_empty() || self.lookup(path, true).is_ok()
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        let mut ancestor = PathBuf::from("/");
+        for name in components(path) {
+            ancestor.push(&name);
+            let resolved = self.resolve(&ancestor, true)?;
+            let Some((name, parent)) = resolved.split_last() else {
+                continue;
+            };
+            let key = self.key(name);
+            let name = name.clone();
+            let dir = self.dir_mut(parent)?;
+            match dir.entries.get(&key).map(|entry| &entry.node) {
+                Some(Node::Dir(_)) => (),
+                Some(_) => return Err(Error::from(ErrorKind::AlreadyExists)),
+                None => {
+                    let node = Node::Dir(Dir::default());
+                    dir.entries.insert(key, Entry { name, node });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        let src = self.resolve(src, false)?;
+        let dst = self.resolve(dst, false)?;
+        let (Some((src_name, src_parent)), Some((dst_name, dst_parent))) =
+            (src.split_last(), dst.split_last())
+        else {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        };
+        let src_key = self.key(src_name);
+        let dst_key = self.key(dst_name);
+        let src_keys: Vec<OsString> = src.iter().map(|name| self.key(name)).collect();
+        let dst_keys: Vec<OsString> = dst.iter().map(|name| self.key(name)).collect();
+
+        let src_is_dir = matches!(self.lookup_resolved(&src)?, Node::Dir(_));
+
+        if src_keys == dst_keys {
+            // Renaming to itself only updates the letter case of the name.
+            let entry = self
+                .dir_mut(src_parent)?
+                .entries
+                .get_mut(&src_key)
+                .ok_or(ErrorKind::NotFound)?;
+            entry.name = dst_name.clone();
+            return Ok(());
+        }
+        if src_is_dir && dst_keys.starts_with(&src_keys) {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+
+        match self
+            .dir(dst_parent)?
+            .entries
+            .get(&dst_key)
+            .map(|entry| &entry.node)
+        {
+            None => (),
+            Some(Node::Dir(dir)) if src_is_dir && dir.entries.is_empty() => (),
+            Some(Node::Dir(_)) if src_is_dir => {
+                return Err(Error::from(ErrorKind::DirectoryNotEmpty));
+            }
+            Some(Node::Dir(_)) => return Err(Error::from(ErrorKind::IsADirectory)),
+            Some(_) if src_is_dir => return Err(Error::from(ErrorKind::NotADirectory)),
+            Some(_) => (),
+        }
+
+        let mut entry = self
+            .dir_mut(src_parent)?
+            .entries
+            .remove(&src_key)
+            .ok_or(ErrorKind::NotFound)?;
+        entry.name = dst_name.clone();
+        self.dir_mut(dst_parent)?.entries.insert(dst_key, entry);
+        Ok(())
+    }
+}
+
+/// Returns the normal components of a path, keeping `..` for the resolver
+/// and dropping any prefix, root and `.` components.
+fn components(path: &Path) -> Vec<OsString> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_os_string()),
+            Component::ParentDir => Some(OsString::from("..")),
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> MemoryFileSystem {
+        let mut fs = MemoryFileSystem::new();
+        fs.write("/a.txt", "a").unwrap();
+        fs.write("/b.txt", "b").unwrap();
+        fs.write("/dir/c.txt", "c").unwrap();
+        FileSystem::create_dir_all(&mut fs, Path::new("/empty")).unwrap();
+        fs.symlink("/dir", "/link").unwrap();
+        fs
+    }
+
+    #[test]
+    fn exists_follows_symlinks() {
+        let mut fs = fixture();
+        assert!(fs.exists(Path::new("/")));
+        assert!(fs.exists(Path::new("/dir/c.txt")));
+        assert!(fs.exists(Path::new("/link/c.txt")));
+        assert!(!fs.exists(Path::new("/missing")));
+        assert!(!fs.exists(Path::new("/a.txt/c.txt")));
+
+        fs.symlink("/missing", "/broken").unwrap();
+        assert!(!fs.exists(Path::new("/broken")));
+        assert!(fs.is_symlink("/broken"));
+    }
+
+    #[test]
+    fn create_dir_all_creates_missing_ancestors_only() {
+        let mut fs = fixture();
+        fs.create_dir_all(Path::new("/x/y/z")).unwrap();
+        assert!(fs.is_dir("/x/y/z"));
+        fs.create_dir_all(Path::new("/dir")).unwrap();
+        assert_eq!(fs.read("/dir/c.txt").unwrap(), b"c");
+        fs.create_dir_all(Path::new("/link/sub")).unwrap();
+        assert!(fs.is_dir("/dir/sub"));
+
+        let error = fs.create_dir_all(Path::new("/a.txt/sub")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn rename_moves_files_and_directories() {
+        let mut fs = fixture();
+        fs.rename(Path::new("/a.txt"), Path::new("/dir/a.txt"))
+            .unwrap();
+        assert!(!fs.exists(Path::new("/a.txt")));
+        assert_eq!(fs.read("/dir/a.txt").unwrap(), b"a");
+
+        fs.rename(Path::new("/dir"), Path::new("/moved")).unwrap();
+        assert_eq!(fs.read("/moved/c.txt").unwrap(), b"c");
+        // Symlinks are moved rather than followed.
+        fs.rename(Path::new("/link"), Path::new("/renamed"))
+            .unwrap();
+        assert_eq!(fs.read_link("/renamed").unwrap(), Path::new("/dir"));
+    }
+
+    #[test]
+    fn rename_onto_existing_destinations() {
+        let mut fs = fixture();
+        // A file replaces a file, and a directory an empty directory.
+        fs.rename(Path::new("/a.txt"), Path::new("/b.txt")).unwrap();
+        assert_eq!(fs.read("/b.txt").unwrap(), b"a");
+        fs.write("/full/d.txt", "d").unwrap();
+        fs.rename(Path::new("/full"), Path::new("/empty")).unwrap();
+        assert_eq!(fs.read("/empty/d.txt").unwrap(), b"d");
+
+        let cases = [
+            ("/b.txt", "/dir", ErrorKind::IsADirectory),
+            ("/empty", "/b.txt", ErrorKind::NotADirectory),
+            ("/empty", "/dir", ErrorKind::DirectoryNotEmpty),
+            ("/dir", "/dir/sub", ErrorKind::InvalidInput),
+        ];
+        for (src, dst, kind) in cases {
+            let error = fs.rename(Path::new(src), Path::new(dst)).unwrap_err();
+            assert_eq!(error.kind(), kind, "{src} -> {dst}");
+        }
+        assert_eq!(fs.read("/b.txt").unwrap(), b"a");
+        assert_eq!(fs.read("/dir/c.txt").unwrap(), b"c");
+    }
+
+    #[test]
+    fn rename_requires_source_and_destination_parent() {
+        let mut fs = fixture();
+        let error = fs
+            .rename(Path::new("/missing"), Path::new("/x"))
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        let error = fs
+            .rename(Path::new("/a.txt"), Path::new("/missing/a.txt"))
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        let error = fs
+            .rename(Path::new("/a.txt"), Path::new("/b.txt/a.txt"))
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotADirectory);
+        assert_eq!(fs.read("/a.txt").unwrap(), b"a");
+    }
+
+    #[test]
+    fn case_insensitive_names_match_and_keep_their_case() {
+        let mut fs = MemoryFileSystem::new().case_sensitive(false);
+        fs.write("/Readme.md", "").unwrap();
+        assert!(fs.exists(Path::new("/README.MD")));
+        fs.rename(Path::new("/readme.md"), Path::new("/README.md"))
+            .unwrap();
+        assert_eq!(fs.paths(), [PathBuf::from("/README.md")]);
+    }
+}
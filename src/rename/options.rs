@@ -0,0 +1,55 @@
+/// Options controlling how a [`RenameQueue`] is planned.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{OsFileSystem, QueueOptions, RenameQueue};
+///
+/// let options = QueueOptions::new().deterministic(true);
+/// let queue = RenameQueue::with_options(OsFileSystem, options, [("foo", "bar")]).unwrap();
+/// ```
+///
+/// [`RenameQueue`]: super::RenameQueue
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueueOptions {
+    pub(super) deterministic: bool,
+    pub(super) unchecked: bool,
+}
+
+impl QueueOptions {
+    /// Creates a new [`QueueOptions`] with every option disabled.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the renaming order depends only on the given mappings.
+    ///
+    /// By default, chains and cycles are walked in hash map order, so the
+    /// same mappings may be queued differently across runs. When enabled,
+    /// sources are sorted before walking, so plans, journals and test
+    /// expectations are reproducible, at the cost of a sort.
+    #[inline]
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Sets whether to skip checking for conflicts between mappings.
+    ///
+    /// When enabled, [`Error::ManyToOne`] and [`Error::NonLeafNode`] are not
+    /// detected, which saves the reverse map, and the sort or trie over
+    /// every path. Chains and cycles are still resolved. This is intended for
+    /// input that is known to be conflict-free, such as the output of another
+    /// planner. If that turns out not to be the case, the renaming process may
+    /// stop at a conflict, or may silently move an entity somewhere other than
+    /// expected.
+    ///
+    /// [`Error::ManyToOne`]: super::Error::ManyToOne
+    /// [`Error::NonLeafNode`]: super::Error::NonLeafNode
+    #[inline]
+    pub fn unchecked(mut self, unchecked: bool) -> Self {
+        self.unchecked = unchecked;
+        self
+    }
+}
@@ -0,0 +1,131 @@
+use super::event::{Event, Observer};
+use super::throughput::Throughput;
+use std::io;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const DEFAULT_WIDTH: usize = 40;
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An [`Observer`] that renders a single-line progress bar to a writer.
+///
+/// The bar is redrawn in place using carriage returns, and is throttled so
+/// that rendering never dominates the execution time.
+///
+/// This is only available with the `progress` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{ProgressBar, RenameQueue};
+///
+/// let mut queue = RenameQueue::new([("foo", "bar")]).unwrap();
+/// queue.rename_atomic_with(&mut ProgressBar::stderr()).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ProgressBar<W>
+where
+    W: Write,
+{
+    writer: W,
+    width: usize,
+    interval: Duration,
+    last_drawn: Option<Instant>,
+    throughput: Throughput,
+}
+
+impl ProgressBar<io::Stderr> {
+    /// Creates a new [`ProgressBar`] that renders to the standard error.
+    #[inline]
+    pub fn stderr() -> Self {
+        Self::new(io::stderr())
+    }
+}
+
+impl<W> ProgressBar<W>
+where
+    W: Write,
+{
+    /// Creates a new [`ProgressBar`] that renders to the given writer.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            width: DEFAULT_WIDTH,
+            interval: DEFAULT_INTERVAL,
+            last_drawn: None,
+            throughput: Throughput::new(),
+        }
+    }
+
+    /// Sets the width of the bar in characters.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets the minimum interval between two redraws.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Returns the underlying throughput tracker.
+    #[inline]
+    pub fn throughput(&self) -> &Throughput {
+        &self.throughput
+    }
+
+    /// Consumes the [`ProgressBar`], returning the underlying writer.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let total = self.throughput.total();
+        let completed = self.throughput.completed().min(total);
+        let filled = (self.width * completed)
+            .checked_div(total)
+            .unwrap_or(self.width);
+        let empty = self.width - filled;
+
+        write!(
+            self.writer,
+            "\r[{}{}] {completed}/{total}",
+            "#".repeat(filled),
+            "-".repeat(empty),
+        )?;
+        if let Some(rate) = self.throughput.rate() {
+            write!(self.writer, " {rate:.1}/s")?;
+        }
+        if let Some(eta) = self.throughput.eta() {
+            write!(self.writer, " eta {}s", eta.as_secs())?;
+        }
+        self.writer.flush()
+    }
+}
+
+impl<W> Observer for ProgressBar<W>
+where
+    W: Write,
+{
+    fn notify(&mut self, event: &Event) {
+        self.throughput.notify(event);
+
+        let now = Instant::now();
+        let finished = self.throughput.remaining() == 0;
+        let due = self
+            .last_drawn
+            .is_none_or(|last_drawn| now.duration_since(last_drawn) >= self.interval);
+        if !finished && !due && !matches!(event, Event::Failed { .. }) {
+            return;
+        }
+        self.last_drawn = Some(now);
+
+        // Rendering errors should never interrupt the execution.
+        let _ = self.draw();
+        if finished || matches!(event, Event::Failed { .. }) {
+            let _ = writeln!(self.writer);
+        }
+    }
+}
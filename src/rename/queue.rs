@@ -1,13 +1,25 @@
 use super::error::Error;
+use super::event::{Event, Observer};
+use super::file_system::{FileSystem, OsFileSystem};
+use super::intern::Interner;
 use super::mapping::Mapping;
+use super::options::QueueOptions;
+use super::validate;
 use serde::de::{Deserialize, Deserializer, Error as DeError, MapAccess, Visitor};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::marker::PhantomData;
 use std::path;
 use std::path::Path;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+
+/// A handle to the thread executing a queue, as returned by
+/// [`RenameQueue::rename_with_events`].
+type Execution<F> = JoinHandle<(RenameQueue<F>, Result<(), Error>)>;
 
 /// A queue for batch renaming operations.
 ///
@@ -18,18 +30,51 @@ use std::rc::Rc;
 /// the serialized output is modified or reordered, or if any relevant files
 /// are added, removed, or moved, it will no longer be possible to revert
 /// to the initial state.
+///
+/// # File System Backends
+///
+/// By default, the queue operates on the file system of the operating system.
+/// To operate on an alternative backend, see [`RenameQueue::with_fs`].
+///
+/// # Thread Safety
+///
+/// Paths are shared through [`Arc`], so the queue is [`Send`] and [`Sync`]
+/// whenever its backend is, and can be moved into worker threads or async
+/// tasks.
+///
+/// ```no_run
+/// use mofu::rename::RenameQueue;
+/// use std::thread;
+///
+/// let mut queue = RenameQueue::new([("a.txt", "b.txt")]).unwrap();
+/// let handle = thread::spawn(move || {
+///     queue.rename_atomic()?;
+///     Ok::<_, mofu::rename::Error>(queue)
+/// });
+/// let queue = handle.join().unwrap().unwrap();
+/// ```
 #[derive(Debug)]
-pub struct RenameQueue {
+pub struct RenameQueue<F = OsFileSystem>
+where
+    F: FileSystem,
+{
     queue: Vec<Mapping>,
     renamed: usize,
+    fs: F,
 }
 
 impl RenameQueue {
     /// Creates a new [`RenameQueue`] from an iterator over source–destination
     /// mapping pairs.
     ///
+    /// This is equivalent to [`RenameQueue::with_fs`] with [`OsFileSystem`].
+    ///
     /// The renaming order is not determined by the given iterator. To see the
-    /// exact execution order, use [`RenameQueue::pending`].
+    /// exact execution order, use [`RenameQueue::pending`]. To make it
+    /// reproducible, see [`QueueOptions::deterministic`].
+    ///
+    /// Large inputs are validated on all available cores. Either way, the
+    /// same conflict is reported for the same input.
     ///
     /// # Panics
     ///
@@ -53,7 +98,48 @@ impl RenameQueue {
     /// be in an ambiguous state and may or may not stop executing. Regardless,
     /// the execution is considered incorrect. If no concurrent file access occurs,
     /// it can be safely reverted.
+    #[inline]
     pub fn new<I, S, D>(iter: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = (S, D)>,
+        S: AsRef<Path>,
+        D: AsRef<Path>,
+    {
+        Self::with_fs(OsFileSystem, iter)
+    }
+}
+
+impl<F> RenameQueue<F>
+where
+    F: FileSystem,
+{
+    /// Creates a new [`RenameQueue`] operating on the given file system
+    /// backend, from an iterator over source–destination mapping pairs.
+    ///
+    /// Paths are made absolute against the current working directory
+    /// regardless of the backend.
+    ///
+    /// # Errors
+    ///
+    /// See [`RenameQueue::new`].
+    #[inline]
+    pub fn with_fs<I, S, D>(fs: F, iter: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = (S, D)>,
+        S: AsRef<Path>,
+        D: AsRef<Path>,
+    {
+        Self::with_options(fs, QueueOptions::new(), iter)
+    }
+
+    /// Creates a new [`RenameQueue`] operating on the given file system
+    /// backend and planned with the given [`QueueOptions`], from an iterator
+    /// over source–destination mapping pairs.
+    ///
+    /// # Errors
+    ///
+    /// See [`RenameQueue::new`].
+    pub fn with_options<I, S, D>(mut fs: F, options: QueueOptions, iter: I) -> Result<Self, Error>
     where
         I: IntoIterator<Item = (S, D)>,
         S: AsRef<Path>,
@@ -64,8 +150,8 @@ impl RenameQueue {
         let mut map = HashMap::with_capacity(capacity);
 
         for (src, dst) in iter {
-            let src = path::absolute(src).map(Rc::new)?;
-            let dst = path::absolute(dst).map(Rc::new)?;
+            let src = path::absolute(src).map(Arc::new)?;
+            let dst = path::absolute(dst).map(Arc::new)?;
             match map.entry(src) {
                 Entry::Occupied(entry) => {
                     // Duplicate mappings are ignored.
@@ -83,45 +169,29 @@ impl RenameQueue {
         }
 
         let mut capacity = map.len();
-        let mut rev_map = HashMap::with_capacity(capacity);
-        let mut paths = Vec::with_capacity(capacity);
 
-        for (src, dst) in &map {
-            match rev_map.entry(dst) {
-                Entry::Occupied(entry) => {
-                    let collided = entry.remove();
-                    let src = (Rc::clone(collided), Rc::clone(src));
-                    let dst = Rc::clone(dst);
-                    return Err(Error::ManyToOne { src, dst });
-                }
-                Entry::Vacant(entry) => {
-                    entry.insert(src);
+        if options.unchecked {
+            capacity -= map.iter().filter(|(src, dst)| src == dst).count();
+        } else {
+            let mut mappings = Vec::with_capacity(capacity);
+            let mut paths = Vec::with_capacity(capacity);
+
+            for (src, dst) in &map {
+                mappings.push((src, dst));
+                if src == dst {
+                    capacity -= 1;
+                } else {
+                    paths.push(src);
+                    paths.push(dst);
                 }
             }
-            if src == dst {
-                capacity -= 1;
-            } else {
-                paths.push(src);
-                paths.push(dst);
-            }
-        }
-        drop(rev_map);
-
-        paths.sort();
-        for window in paths.windows(2) {
-            let lower = window[0];
-            let upper = window[1];
-            if upper.as_os_str().len() != lower.as_os_str().len()
-                && upper.starts_with(lower.as_path())
-            {
-                let node = Rc::clone(lower);
-                let descendant = Rc::clone(upper);
-                return Err(Error::NonLeafNode { node, descendant });
-            }
+
+            validate::check_many_to_one(&mappings)?;
+            drop(mappings);
+
+            validate::check_non_leaf(&mut paths)?;
         }
-        drop(paths);
 
-        let mut visited = HashSet::with_capacity(capacity);
         // In the extreme case where every two mappings form a cycle, one
         // extra slot is needed for each temporary path.
         let mut graph = Vec::with_capacity(capacity / 2 * 3 + capacity % 2);
@@ -130,54 +200,58 @@ impl RenameQueue {
         // `walk` may represent a partially truncated path rather than
         // a complete component, which does not affect correctness.
         let mut walk = VecDeque::with_capacity(capacity + 1);
+        let mut interner = Interner::default();
+
+        // Mappings are taken out of the map as they are queued, so that the
+        // path data they own is released while the queue is being built,
+        // rather than kept alive alongside it until the end.
+        let mut sources: Vec<_> = map.keys().map(Arc::clone).collect();
+        if options.deterministic {
+            sources.sort_unstable();
+        }
 
-        for (src, dst) in &map {
-            if src == dst || visited.contains(src.as_path()) {
+        for src in sources {
+            // A missing source has already been queued by an earlier walk.
+            let Some(dst) = map.remove(&src) else {
+                continue;
+            };
+            if src == dst {
                 continue;
             }
 
-            visited.insert(src.as_path());
-
-            walk.push_front(Mapping {
-                src: Rc::clone(src),
-                dst: Rc::clone(dst),
-            });
+            walk.push_front((Arc::clone(&src), Arc::clone(&dst)));
 
             let mut next_src = dst;
-            while let Some(next_dst) = map.get(next_src) {
-                visited.insert(next_src.as_path());
+            // If `next_src` is missing, the rest of the chain has already
+            // been queued by an earlier walk, and will be executed before
+            // this one.
+            while let Some(next_dst) = map.remove(&next_src) {
                 if next_dst == src {
                     let mut temp = next_src.to_path_buf();
                     for i in 0.. {
                         temp.set_extension(format!("temp_{i}"));
-                        if !temp.exists() {
+                        if !fs.exists(&temp) {
                             break;
                         }
                     }
-                    let temp = Rc::new(temp);
-                    walk.push_front(Mapping {
-                        src: Rc::clone(next_src),
-                        dst: Rc::clone(&temp),
-                    });
-                    walk.push_back(Mapping {
-                        src: temp,
-                        dst: Rc::clone(src),
-                    });
+                    let temp = Arc::new(temp);
+                    walk.push_front((next_src, Arc::clone(&temp)));
+                    walk.push_back((temp, src));
                     break;
                 }
-                walk.push_front(Mapping {
-                    src: Rc::clone(next_src),
-                    dst: Rc::clone(next_dst),
-                });
+                walk.push_front((next_src, Arc::clone(&next_dst)));
                 next_src = next_dst;
             }
 
-            graph.extend(walk.drain(..));
+            for (src, dst) in walk.drain(..) {
+                interner.push(&src, &dst, &mut graph);
+            }
         }
+        interner.finish(&mut graph);
 
         let queue = graph;
         let renamed = 0;
-        Ok(Self { queue, renamed })
+        Ok(Self { queue, renamed, fs })
     }
 
     /// Renames the pending mappings atomically.
@@ -188,9 +262,52 @@ impl RenameQueue {
     /// - [`Error::Io`] if an I/O error occurs.
     /// - [`Error::AtomicActionFailed`] if the rename attempt fails and the
     ///   subsequent rollback also fails.
+    #[inline]
     pub fn rename_atomic(&mut self) -> Result<&mut Self, Error> {
-        if let Err(rename_error) = self.rename() {
-            if let Err(revert_error) = self.revert() {
+        self.rename_atomic_with(&mut ())
+    }
+
+    /// Renames the pending mappings atomically, reporting progress to the
+    /// given [`Observer`].
+    ///
+    /// Since [`Sender<Event>`] implements [`Observer`], execution progress can
+    /// be streamed to another thread through a channel.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mofu::rename::{Event, RenameQueue};
+    /// use std::sync::mpsc;
+    /// use std::thread;
+    ///
+    /// let (mut sender, receiver) = mpsc::channel();
+    /// let handle = thread::spawn(move || {
+    ///     for event in receiver {
+    ///         if let Event::Renamed { src, dst } = event {
+    ///             println!("{} -> {}", src.display(), dst.display());
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// let mut queue = RenameQueue::new([("foo", "bar")]).unwrap();
+    /// queue.rename_atomic_with(&mut sender).unwrap();
+    /// drop(sender);
+    /// handle.join().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`RenameQueue::rename_atomic`].
+    ///
+    /// [`Sender<Event>`]: std::sync::mpsc::Sender
+    pub fn rename_atomic_with<O>(&mut self, observer: &mut O) -> Result<&mut Self, Error>
+    where
+        O: Observer,
+    {
+        let total = self.pending().len();
+        observer.notify(&Event::Started { total });
+        if let Err(rename_error) = self.rename_inner(observer) {
+            if let Err(revert_error) = self.revert_inner(observer) {
                 Err(Error::AtomicActionFailed {
                     during_attempt: Box::new(rename_error),
                     during_rollback: Box::new(revert_error),
@@ -211,9 +328,25 @@ impl RenameQueue {
     /// - [`Error::Io`] if an I/O error occurs.
     /// - [`Error::AtomicActionFailed`] if the revert attempt fails and the
     ///   subsequent rollback also fails.
+    #[inline]
     pub fn revert_atomic(&mut self) -> Result<&mut Self, Error> {
-        if let Err(revert_error) = self.revert() {
-            if let Err(rename_error) = self.rename() {
+        self.revert_atomic_with(&mut ())
+    }
+
+    /// Reverts the renamed mappings atomically, reporting progress to the
+    /// given [`Observer`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RenameQueue::revert_atomic`].
+    pub fn revert_atomic_with<O>(&mut self, observer: &mut O) -> Result<&mut Self, Error>
+    where
+        O: Observer,
+    {
+        let total = self.renamed;
+        observer.notify(&Event::Started { total });
+        if let Err(revert_error) = self.revert_inner(observer) {
+            if let Err(rename_error) = self.rename_inner(observer) {
                 Err(Error::AtomicActionFailed {
                     during_attempt: Box::new(revert_error),
                     during_rollback: Box::new(rename_error),
@@ -232,11 +365,24 @@ impl RenameQueue {
     ///
     /// - [`Error::AlreadyExists`] if any destination already exists.
     /// - [`Error::Io`] if an I/O error occurs.
+    #[inline]
     pub fn rename(&mut self) -> Result<&mut Self, Error> {
-        for mapping in self.queue.iter().skip(self.renamed) {
-            mapping.rename()?;
-            self.renamed += 1;
-        }
+        self.rename_with(&mut ())
+    }
+
+    /// Renames the pending mappings, reporting progress to the given
+    /// [`Observer`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RenameQueue::rename`].
+    pub fn rename_with<O>(&mut self, observer: &mut O) -> Result<&mut Self, Error>
+    where
+        O: Observer,
+    {
+        let total = self.pending().len();
+        observer.notify(&Event::Started { total });
+        self.rename_inner(observer)?;
         Ok(self)
     }
 
@@ -246,18 +392,61 @@ impl RenameQueue {
     ///
     /// - [`Error::AlreadyExists`] if any destination already exists.
     /// - [`Error::Io`] if an I/O error occurs.
+    #[inline]
     pub fn revert(&mut self) -> Result<&mut Self, Error> {
-        for mapping in self
-            .queue
-            .iter()
-            .take(self.renamed)
-            .rev()
-            .map(Mapping::invert)
-        {
-            mapping.rename()?;
+        self.revert_with(&mut ())
+    }
+
+    /// Reverts the renamed mappings, reporting progress to the given
+    /// [`Observer`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RenameQueue::revert`].
+    pub fn revert_with<O>(&mut self, observer: &mut O) -> Result<&mut Self, Error>
+    where
+        O: Observer,
+    {
+        let total = self.renamed;
+        observer.notify(&Event::Started { total });
+        self.revert_inner(observer)?;
+        Ok(self)
+    }
+
+    fn rename_inner<O>(&mut self, observer: &mut O) -> Result<(), Error>
+    where
+        O: Observer,
+    {
+        for mapping in self.queue.iter().skip(self.renamed) {
+            if let Err(error) = mapping.rename(&mut self.fs) {
+                observer.notify(&Event::failed(mapping, &error));
+                return Err(error);
+            }
+            self.renamed += 1;
+            observer.notify(&Event::Renamed {
+                src: mapping.to_src(),
+                dst: mapping.to_dst(),
+            });
+        }
+        Ok(())
+    }
+
+    fn revert_inner<O>(&mut self, observer: &mut O) -> Result<(), Error>
+    where
+        O: Observer,
+    {
+        for mapping in self.queue.iter().take(self.renamed).rev() {
+            if let Err(error) = mapping.invert().rename(&mut self.fs) {
+                observer.notify(&Event::failed(mapping, &error));
+                return Err(error);
+            }
             self.renamed -= 1;
+            observer.notify(&Event::RolledBack {
+                src: mapping.to_src(),
+                dst: mapping.to_dst(),
+            });
         }
-        Ok(self)
+        Ok(())
     }
 
     /// Returns the renamed mappings.
@@ -271,11 +460,71 @@ impl RenameQueue {
     pub fn pending(&self) -> &[Mapping] {
         &self.queue[self.renamed..]
     }
+
+    /// Returns a reference to the file system backend.
+    #[inline]
+    pub fn fs(&self) -> &F {
+        &self.fs
+    }
+
+    /// Returns a mutable reference to the file system backend.
+    #[inline]
+    pub fn fs_mut(&mut self) -> &mut F {
+        &mut self.fs
+    }
+}
+
+impl<F> RenameQueue<F>
+where
+    F: FileSystem + Send + 'static,
+{
+    /// Renames the pending mappings atomically on a separate thread,
+    /// returning a receiver of the [`Event`]s emitted as execution proceeds,
+    /// and a handle to join the thread, which gives back the queue along
+    /// with the outcome.
+    ///
+    /// This is a shorthand for [`RenameQueue::rename_atomic_with`] given a
+    /// [`Sender<Event>`], so a GUI thread can render progress without
+    /// callbacks crossing thread boundaries.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mofu::rename::{Event, RenameQueue};
+    ///
+    /// let queue = RenameQueue::new([("foo", "bar")]).unwrap();
+    /// let (events, handle) = queue.rename_with_events();
+    /// for event in events {
+    ///     if let Event::Renamed { src, dst } = event {
+    ///         println!("{} -> {}", src.display(), dst.display());
+    ///     }
+    /// }
+    /// let (queue, result) = handle.join().unwrap();
+    /// result.unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The outcome is an error in the same cases as
+    /// [`RenameQueue::rename_atomic`].
+    ///
+    /// [`Sender<Event>`]: std::sync::mpsc::Sender
+    pub fn rename_with_events(mut self) -> (Receiver<Event>, Execution<F>) {
+        let (mut sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let result = self.rename_atomic_with(&mut sender).map(|_| ());
+            (self, result)
+        });
+        (receiver, handle)
+    }
 }
 
 const FIELDS: &[&str] = &["renamed", "pending"];
 
-impl Serialize for RenameQueue {
+impl<F> Serialize for RenameQueue<F>
+where
+    F: FileSystem,
+{
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
@@ -287,20 +536,29 @@ impl Serialize for RenameQueue {
     }
 }
 
-impl<'de> Deserialize<'de> for RenameQueue {
+/// The file system backend is not serialized, and is restored with its
+/// [`Default`] value.
+impl<'de, F> Deserialize<'de> for RenameQueue<F>
+where
+    F: FileSystem + Default,
+{
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_struct("RenameQueue", FIELDS, RenameQueueVisitor)
+        let visitor = RenameQueueVisitor(PhantomData);
+        deserializer.deserialize_struct("RenameQueue", FIELDS, visitor)
     }
 }
 
 #[derive(Debug)]
-struct RenameQueueVisitor;
+struct RenameQueueVisitor<F>(PhantomData<F>);
 
-impl<'de> Visitor<'de> for RenameQueueVisitor {
-    type Value = RenameQueue;
+impl<'de, F> Visitor<'de> for RenameQueueVisitor<F>
+where
+    F: FileSystem + Default,
+{
+    type Value = RenameQueue<F>;
 
     fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         formatter.write_str("struct RenameQueue")
@@ -337,7 +595,13 @@ impl<'de> Visitor<'de> for RenameQueueVisitor {
         let renamed = queue.len();
         queue.extend(pending);
 
-        Ok(RenameQueue { queue, renamed })
+        let mut interner = Interner::default();
+        for mapping in &mut queue {
+            mapping.share(&mut interner);
+        }
+
+        let fs = F::default();
+        Ok(RenameQueue { queue, renamed, fs })
     }
 }
 
@@ -377,3 +641,46 @@ impl Visitor<'_> for FieldVisitor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rename::{FaultyFileSystem, MemoryFileSystem};
+
+    #[test]
+    fn rename_with_events_streams_and_returns_queue() {
+        let mut fs = MemoryFileSystem::new();
+        fs.write("/a", "a").unwrap();
+        fs.write("/b", "b").unwrap();
+        let queue = RenameQueue::with_fs(fs, [("/a", "/c"), ("/b", "/d")]).unwrap();
+        let (events, handle) = queue.rename_with_events();
+        let events: Vec<_> = events.into_iter().collect();
+        let (queue, result) = handle.join().unwrap();
+        result.unwrap();
+        assert_eq!(events[0], Event::Started { total: 2 });
+        assert_eq!(events.len(), 3);
+        assert!(queue.pending().is_empty());
+        assert!(queue.fs().is_file("/c") && queue.fs().is_file("/d"));
+    }
+
+    #[test]
+    fn rename_with_events_reports_rollback() {
+        let mut fs = MemoryFileSystem::new();
+        fs.write("/a", "a").unwrap();
+        fs.write("/b", "b").unwrap();
+        let fs = FaultyFileSystem::new(fs).fail_on(1);
+        let queue = RenameQueue::with_fs(fs, [("/a", "/c"), ("/b", "/d")]).unwrap();
+        let (events, handle) = queue.rename_with_events();
+        let events: Vec<_> = events.into_iter().collect();
+        let (queue, result) = handle.join().unwrap();
+        assert!(result.is_err());
+        assert!(
+            events
+                .iter()
+                .any(|event| matches!(event, Event::RolledBack { .. }))
+        );
+        assert!(queue.renamed().is_empty());
+        assert!(queue.fs().inner().is_file("/a"));
+        assert!(queue.fs().inner().is_file("/b"));
+    }
+}
@@ -0,0 +1,396 @@
+use super::command;
+use super::file_system::FileSystem;
+use std::ffi::OsString;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// A [`FileSystem`] that operates on the objects of an S3 bucket.
+///
+/// Each operation runs the AWS command line interface (`aws`), so
+/// credentials, regions and endpoints are resolved the usual way, and can be
+/// overridden with [`S3FileSystem::arg`] (e.g. `--profile` or
+/// `--endpoint-url` for S3-compatible stores).
+///
+/// Paths are mapped to object keys by stripping the root, so `/photos/a.jpg`
+/// refers to the key `photos/a.jpg`. Since S3 has no directories, a path also
+/// exists if it is a prefix of any key, and renaming such a path moves every
+/// object under the prefix. Renames are performed by copying and deleting,
+/// so they are not atomic on their own. A rename fails if its destination
+/// exists, which is checked right before moving, so objects written
+/// concurrently may still be overwritten.
+///
+/// Only a missing object, or a prefix of no keys, is taken as a path that
+/// does not exist. Any other failure of `aws` while checking, such as an
+/// expired credential, is reported by the next operation, so conflict
+/// checks never pass on a failing connection.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{RenameQueue, S3FileSystem};
+///
+/// let fs = S3FileSystem::new("my-bucket").arg("--profile").arg("archive");
+/// let mut queue = RenameQueue::with_fs(fs, [("/2023/a.jpg", "/2023/01/a.jpg")]).unwrap();
+/// queue.rename_atomic().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct S3FileSystem {
+    program: PathBuf,
+    args: Vec<OsString>,
+    bucket: String,
+    /// A failure of `aws` while checking whether a path exists, which is
+    /// yet to be reported.
+    failure: Option<String>,
+}
+
+impl S3FileSystem {
+    /// Creates a new [`S3FileSystem`] operating on `bucket`.
+    pub fn new<B>(bucket: B) -> Self
+    where
+        B: Into<String>,
+    {
+        Self {
+            program: PathBuf::from("aws"),
+            args: Vec::new(),
+            bucket: bucket.into(),
+            failure: None,
+        }
+    }
+
+    /// Sets the path of the `aws` program.
+    ///
+    /// Defaults to `aws`, looked up in `PATH`.
+    pub fn program<P>(mut self, program: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.program = program.into();
+        self
+    }
+
+    /// Appends an extra global argument passed to `aws`.
+    pub fn arg<A>(mut self, arg: A) -> Self
+    where
+        A: Into<OsString>,
+    {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Returns the paths of all objects whose keys start with the key of
+    /// `prefix`, treating it as a directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the objects cannot be listed.
+    pub fn list<P>(&self, prefix: P) -> Result<Vec<PathBuf>>
+    where
+        P: AsRef<Path>,
+    {
+        let prefix = dir_key(&key(prefix.as_ref())?);
+        let output = self.run(|command| {
+            command
+                .arg("s3")
+                .arg("ls")
+                .arg("--recursive")
+                .arg(self.url(&prefix));
+        })?;
+
+        let output = String::from_utf8_lossy(&output);
+        let paths = output
+            .lines()
+            .filter_map(listed_key)
+            .map(|key| Path::new("/").join(key))
+            .collect();
+        Ok(paths)
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("s3://{}/{key}", self.bucket)
+    }
+
+    fn command<F>(&self, build: F) -> Command
+    where
+        F: FnOnce(&mut Command),
+    {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        build(&mut command);
+        command
+    }
+
+    fn run<F>(&self, build: F) -> Result<Vec<u8>>
+    where
+        F: FnOnce(&mut Command),
+    {
+        command::run(&mut self.command(build), &[])
+    }
+
+    /// Reports the failure left by [`FileSystem::exists`], if any.
+    fn check(&mut self) -> Result<()> {
+        match self.failure.take() {
+            Some(failure) => Err(Error::other(failure)),
+            None => Ok(()),
+        }
+    }
+
+    fn is_object(&self, key: &str) -> Result<bool> {
+        let mut command = self.command(|command| {
+            command
+                .arg("s3api")
+                .arg("head-object")
+                .arg("--bucket")
+                .arg(&self.bucket)
+                .arg("--key")
+                .arg(key);
+        });
+        let output = command::output(&mut command, &[])?;
+        if output.status.success() {
+            return Ok(true);
+        }
+        // Missing objects are told by the status code of the response, as
+        // in `An error occurred (404) when calling the HeadObject operation`.
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("(404)") {
+            Ok(false)
+        } else {
+            Err(command::failure(&output))
+        }
+    }
+
+    fn is_prefix(&self, key: &str) -> Result<bool> {
+        let output = self.run(|command| {
+            command
+                .arg("s3api")
+                .arg("list-objects-v2")
+                .arg("--bucket")
+                .arg(&self.bucket)
+                .arg("--prefix")
+                .arg(dir_key(key))
+                .arg("--max-items")
+                .arg("1")
+                .arg("--query")
+                .arg("KeyCount")
+                .arg("--output")
+                .arg("text");
+        })?;
+        let count = String::from_utf8_lossy(&output);
+        match count.trim() {
+            "0" | "None" => Ok(false),
+            count if count.parse::<u64>().is_ok() => Ok(true),
+            count => Err(Error::other(format!("unexpected key count `{count}`"))),
+        }
+    }
+
+    fn try_exists(&self, key: &str) -> Result<bool> {
+        Ok(key.is_empty() || self.is_object(key)? || self.is_prefix(key)?)
+    }
+}
+
+impl FileSystem for S3FileSystem {
+    fn exists(&mut self, path: &Path) -> bool {
+        let Ok(key) = key(path) else {
+            return false;
+        };
+        match self.try_exists(&key) {
+            Ok(exists) => exists,
+            Err(error) => {
+                self.failure = Some(error.to_string());
+                false
+            }
+        }
+    }
+
+    fn create_dir_all(&mut self, _path: &Path) -> Result<()> {
+        // Directories are implied by object keys.
+        self.check()
+    }
+
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        self.check()?;
+        let src = key(src)?;
+        let dst = key(dst)?;
+        if dst.is_empty() {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+        // `aws s3 mv` overwrites its destination.
+        if self.try_exists(&dst)? {
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+        if self.is_object(&src)? {
+            self.run(|command| {
+                command
+                    .arg("s3")
+                    .arg("mv")
+                    .arg(self.url(&src))
+                    .arg(self.url(&dst));
+            })?;
+        } else if self.is_prefix(&src)? {
+            self.run(|command| {
+                command
+                    .arg("s3")
+                    .arg("mv")
+                    .arg("--recursive")
+                    .arg(self.url(&dir_key(&src)))
+                    .arg(self.url(&dir_key(&dst)));
+            })?;
+        } else {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+        Ok(())
+    }
+}
+
+fn key(path: &Path) -> Result<String> {
+    let mut key = String::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => (),
+            Component::Normal(name) => {
+                let name = name.to_str().ok_or(ErrorKind::InvalidInput)?;
+                if !key.is_empty() {
+                    key.push('/');
+                }
+                key.push_str(name);
+            }
+            Component::ParentDir => return Err(Error::from(ErrorKind::InvalidInput)),
+        }
+    }
+    Ok(key)
+}
+
+/// Extracts the key from a line reading `<date> <time> <size> <key>`, where
+/// the size is right-aligned and followed by a single space.
+fn listed_key(line: &str) -> Option<&str> {
+    let mut rest = line;
+    for _ in 0..3 {
+        rest = rest.trim_start();
+        let end = rest.find(char::is_whitespace)?;
+        rest = &rest[end..];
+    }
+    rest.strip_prefix(' ').filter(|key| !key.is_empty())
+}
+
+fn dir_key(key: &str) -> String {
+    if key.is_empty() {
+        String::new()
+    } else {
+        format!("{key}/")
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::{env, process};
+
+    /// A stand-in for `aws` serving the keys listed in `objects` next to it,
+    /// and logging the commands it runs to `log`. The key `denied` fails as
+    /// an expired credential would.
+    const STUB: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+echo "$*" >> "$dir/log"
+case "$2" in
+head-object)
+    if [ "$6" = denied ]; then
+        echo "An error occurred (403) when calling the HeadObject operation: Forbidden" >&2
+        exit 254
+    fi
+    if grep -qx "$6" "$dir/objects"; then
+        exit 0
+    fi
+    echo "An error occurred (404) when calling the HeadObject operation: Not Found" >&2
+    exit 254
+    ;;
+list-objects-v2)
+    grep -c "^$6" "$dir/objects"
+    exit 0
+    ;;
+esac
+"#;
+
+    /// Runs `test` against a stub bucket holding `objects`.
+    fn with_stub<F>(name: &str, objects: &[&str], test: F)
+    where
+        F: FnOnce(S3FileSystem, &dyn Fn() -> String),
+    {
+        let dir = env::temp_dir().join(format!("mofu-s3-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("aws");
+        fs::write(&program, STUB).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        let mut listing = objects.join("\n");
+        listing.push('\n');
+        fs::write(dir.join("objects"), listing).unwrap();
+        fs::write(dir.join("log"), "").unwrap();
+
+        let log = || fs::read_to_string(dir.join("log")).unwrap();
+        test(S3FileSystem::new("bucket").program(&program), &log);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn objects_and_prefixes_exist() {
+        with_stub("exists", &["a.jpg", "photos/b.jpg"], |mut fs, _| {
+            assert!(fs.exists(Path::new("/")));
+            assert!(fs.exists(Path::new("/a.jpg")));
+            assert!(fs.exists(Path::new("/photos")));
+            assert!(!fs.exists(Path::new("/b.jpg")));
+            assert!(!fs.exists(Path::new("/photo")));
+            assert!(fs.failure.is_none());
+        });
+    }
+
+    #[test]
+    fn failures_are_reported_by_the_next_operation() {
+        with_stub("failure", &["a.jpg"], |mut fs, log| {
+            assert!(!fs.exists(Path::new("/denied")));
+            assert!(fs.failure.is_some());
+            assert!(fs.rename(Path::new("/a.jpg"), Path::new("/b.jpg")).is_err());
+            assert!(!log().contains(" mv "));
+
+            assert!(!fs.exists(Path::new("/denied")));
+            assert!(fs.create_dir_all(Path::new("/dir")).is_err());
+            assert!(fs.create_dir_all(Path::new("/dir")).is_ok());
+        });
+    }
+
+    #[test]
+    fn renames_never_overwrite() {
+        with_stub(
+            "overwrite",
+            &["a.jpg", "b.jpg", "dir/c.jpg"],
+            |mut fs, log| {
+                for dst in ["/b.jpg", "/dir"] {
+                    let error = fs.rename(Path::new("/a.jpg"), Path::new(dst)).unwrap_err();
+                    assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+                }
+                let error = fs
+                    .rename(Path::new("/a.jpg"), Path::new("/denied"))
+                    .unwrap_err();
+                assert_eq!(error.kind(), ErrorKind::Other);
+                assert!(!log().contains(" mv "));
+
+                let error = fs
+                    .rename(Path::new("/missing"), Path::new("/new"))
+                    .unwrap_err();
+                assert_eq!(error.kind(), ErrorKind::NotFound);
+            },
+        );
+    }
+
+    #[test]
+    fn renames_move_objects_and_prefixes() {
+        with_stub("move", &["a.jpg", "dir/c.jpg"], |mut fs, log| {
+            fs.rename(Path::new("/a.jpg"), Path::new("/b.jpg")).unwrap();
+            fs.rename(Path::new("/dir"), Path::new("/new")).unwrap();
+            let log = log();
+            assert!(log.contains("s3 mv s3://bucket/a.jpg s3://bucket/b.jpg\n"));
+            assert!(log.contains("s3 mv --recursive s3://bucket/dir/ s3://bucket/new/\n"));
+        });
+    }
+}
@@ -0,0 +1,244 @@
+use super::command;
+use super::file_system::FileSystem;
+use std::ffi::OsString;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// A [`FileSystem`] that operates on a remote server over SFTP.
+///
+/// Each operation runs the OpenSSH `sftp` client in batch mode, so
+/// authentication must not require interaction (e.g. use keys or an agent).
+/// To avoid a new handshake per operation, consider enabling connection
+/// multiplexing with `-o ControlMaster=auto -o ControlPath=... -o ControlPersist=...`.
+///
+/// Paths are interpreted on the remote server and should be absolute, since
+/// [`RenameQueue`] makes relative paths absolute against the local working
+/// directory. Paths containing line breaks are rejected, since commands are
+/// fed to `sftp` one per line.
+///
+/// A failure to reach the server while checking whether a path exists is
+/// reported by the next operation, so conflict checks never pass on a dead
+/// session. Paths are checked by changing into them rather than listing
+/// them, since `ls` expands `*`, `?` and `[` in its argument, while `cd`
+/// takes it literally.
+///
+/// This is only available with the `sftp` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{RenameQueue, SftpFileSystem};
+///
+/// let fs = SftpFileSystem::new("deploy@build.example.com").arg("-P").arg("2222");
+/// let mut queue = RenameQueue::with_fs(fs, [("/srv/out/a", "/srv/out/b")]).unwrap();
+/// queue.rename_atomic().unwrap();
+/// ```
+///
+/// [`RenameQueue`]: super::RenameQueue
+#[derive(Debug, Clone)]
+pub struct SftpFileSystem {
+    program: PathBuf,
+    args: Vec<OsString>,
+    destination: String,
+    /// A failure to reach the server while checking whether a path exists,
+    /// which is yet to be reported.
+    failure: Option<String>,
+}
+
+impl SftpFileSystem {
+    /// Creates a new [`SftpFileSystem`] connecting to `destination`, in the
+    /// form of `[user@]host`.
+    pub fn new<D>(destination: D) -> Self
+    where
+        D: Into<String>,
+    {
+        Self {
+            program: PathBuf::from("sftp"),
+            args: Vec::new(),
+            destination: destination.into(),
+            failure: None,
+        }
+    }
+
+    /// Sets the path of the `sftp` program.
+    ///
+    /// Defaults to `sftp`, looked up in `PATH`.
+    pub fn program<P>(mut self, program: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.program = program.into();
+        self
+    }
+
+    /// Appends an extra argument passed to `sftp` before the destination.
+    pub fn arg<A>(mut self, arg: A) -> Self
+    where
+        A: Into<OsString>,
+    {
+        self.args.push(arg.into());
+        self
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command
+            .arg("-q")
+            .arg("-b")
+            .arg("-")
+            .args(&self.args)
+            .arg(&self.destination);
+        command
+    }
+
+    fn batch(&mut self, commands: &str) -> Result<()> {
+        if let Some(failure) = self.failure.take() {
+            return Err(Error::other(failure));
+        }
+        command::run(&mut self.command(), commands.as_bytes()).map(drop)
+    }
+
+    fn try_exists(&self, path: &Path) -> Result<bool> {
+        let commands = format!("cd {}\n", quote(path)?);
+        let output = command::output(&mut self.command(), commands.as_bytes())?;
+        if output.status.success() {
+            return Ok(true);
+        }
+        // A failed command exits with 1, while the client exits with 255 if
+        // the connection fails. Changing into a file fails, though it
+        // exists.
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match output.status.code() {
+            Some(1) if stderr.contains("not a directory") => Ok(true),
+            Some(1) if stderr.contains("No such file") || stderr.contains("not found") => Ok(false),
+            _ => Err(command::failure(&output)),
+        }
+    }
+}
+
+impl FileSystem for SftpFileSystem {
+    fn exists(&mut self, path: &Path) -> bool {
+        match self.try_exists(path) {
+            Ok(exists) => exists,
+            Err(error) if error.kind() == ErrorKind::InvalidInput => false,
+            Err(error) => {
+                self.failure = Some(error.to_string());
+                false
+            }
+        }
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        // Failures of the prefixed commands are ignored, since the
+        // directories may already exist. Changing into the directory last
+        // verifies the outcome.
+        let mut commands = String::new();
+        let mut ancestor = PathBuf::new();
+        for component in path.components() {
+            ancestor.push(component);
+            if let Component::Normal(_) = component {
+                commands.push_str(&format!("-mkdir {}\n", quote(&ancestor)?));
+            }
+        }
+        commands.push_str(&format!("cd {}\n", quote(path)?));
+        self.batch(&commands)
+    }
+
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        self.batch(&format!("rename {} {}\n", quote(src)?, quote(dst)?))
+    }
+}
+
+/// Quotes a path as a single argument of a batch command.
+fn quote(path: &Path) -> Result<String> {
+    let path = path.to_string_lossy();
+    if path.contains(['\r', '\n']) {
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+    let mut quoted = String::with_capacity(path.len() + 2);
+    quoted.push('"');
+    for char in path.chars() {
+        if matches!(char, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(char);
+    }
+    quoted.push('"');
+    Ok(quoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_escapes_quotes_and_backslashes() {
+        let quoted = quote(Path::new(r#"/a "b"\c"#)).unwrap();
+        assert_eq!(quoted, r#""/a \"b\"\\c""#);
+    }
+
+    #[test]
+    fn quote_rejects_line_breaks() {
+        for path in ["/a\nrm /b", "/a\rb"] {
+            let error = quote(Path::new(path)).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    /// Writes a stand-in for `sftp` running `script` on each line of the
+    /// batch, logging the batch to `log` next to it.
+    #[cfg(unix)]
+    fn stub(name: &str, script: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("mofu-sftp-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("sftp");
+        let script = format!(
+            "#!/bin/sh\nwhile read -r line; do\n    echo \"$line\" >> \"$(dirname \"$0\")/log\"\n{script}\ndone\n"
+        );
+        fs::write(&program, script).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        program
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_with_glob_characters_are_checked_literally() {
+        use std::fs;
+
+        let program = stub(
+            "glob",
+            r#"    case "$line" in
+    *missing*) echo 'Couldn'"'"'t canonicalize: No such file or directory' >&2; exit 1 ;;
+    *file*) echo 'Can'"'"'t change directory: "/file" is not a directory' >&2; exit 1 ;;
+    esac"#,
+        );
+        let mut fs = SftpFileSystem::new("localhost").program(&program);
+        assert!(fs.exists(Path::new("/dir/a*b?[c]")));
+        assert!(fs.exists(Path::new("/file[1]")));
+        assert!(!fs.exists(Path::new("/missing*")));
+        assert!(fs.failure.is_none());
+
+        let log = program.with_file_name("log");
+        let log = fs::read_to_string(&log).unwrap();
+        assert_eq!(
+            log,
+            "cd \"/dir/a*b?[c]\"\ncd \"/file[1]\"\ncd \"/missing*\"\n"
+        );
+        fs::remove_dir_all(program.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn failure_to_connect_is_reported_by_the_next_operation() {
+        // `false` exits with 1 without telling that the path is missing.
+        let mut fs = SftpFileSystem::new("localhost").program("false");
+        assert!(!fs.exists(Path::new("/a")));
+        assert!(fs.failure.is_some());
+        assert!(fs.create_dir_all(Path::new("/a")).is_err());
+        assert!(fs.failure.is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use super::error::Error;
+use super::queue::RenameQueue;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// A set of rules for renaming companion files along with their primary
+/// files.
+///
+/// A companion (or sidecar) file lives in the same directory as its primary
+/// file, and is named either after the primary's stem (`movie.srt` for
+/// `movie.mkv`) or after its full file name (`photo.raw.xmp` for
+/// `photo.raw`). Primary extensions are matched case-insensitively, while
+/// companion extensions are looked up in both lower and upper case.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::Sidecars;
+///
+/// let sidecars = Sidecars::new()
+///     .group(["mkv", "mp4"], ["srt", "nfo"])
+///     .group(["raw", "cr2"], ["xmp"]);
+/// let mut queue = sidecars
+///     .queue([("movie.mkv", "Movie (2024).mkv")])
+///     .unwrap();
+/// queue.rename_atomic().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Sidecars {
+    groups: Vec<Group>,
+}
+
+#[derive(Debug, Clone)]
+struct Group {
+    primaries: Vec<String>,
+    companions: Vec<String>,
+}
+
+impl Sidecars {
+    /// Creates a new [`Sidecars`] without any rules.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule where files with any of the `companions` extensions follow
+    /// files with any of the `primaries` extensions.
+    ///
+    /// An empty `primaries` matches every primary file.
+    pub fn group<P, C>(mut self, primaries: P, companions: C) -> Self
+    where
+        P: IntoIterator,
+        P::Item: AsRef<str>,
+        C: IntoIterator,
+        C::Item: AsRef<str>,
+    {
+        let normalize = |ext: &str| ext.trim_start_matches('.').to_lowercase();
+        let primaries = primaries
+            .into_iter()
+            .map(|ext| normalize(ext.as_ref()))
+            .collect();
+        let companions = companions
+            .into_iter()
+            .map(|ext| normalize(ext.as_ref()))
+            .collect();
+        self.groups.push(Group {
+            primaries,
+            companions,
+        });
+        self
+    }
+
+    /// Expands the given source–destination mapping pairs with mappings for
+    /// every existing companion file.
+    ///
+    /// Companions that are explicitly mapped in the input are left as-is.
+    pub fn expand<I, S, D>(&self, iter: I) -> Vec<(PathBuf, PathBuf)>
+    where
+        I: IntoIterator<Item = (S, D)>,
+        S: AsRef<Path>,
+        D: AsRef<Path>,
+    {
+        let mut pairs: Vec<(PathBuf, PathBuf)> = iter
+            .into_iter()
+            .map(|(src, dst)| (src.as_ref().to_path_buf(), dst.as_ref().to_path_buf()))
+            .collect();
+        let explicit: HashSet<PathBuf> = pairs.iter().map(|(src, _)| src.clone()).collect();
+
+        let mut companions = Vec::new();
+        for (src, dst) in &pairs {
+            for (src, dst) in self.companions(src, dst) {
+                if !explicit.contains(&src) {
+                    companions.push((src, dst));
+                }
+            }
+        }
+
+        pairs.extend(companions);
+        pairs
+    }
+
+    /// Creates a new [`RenameQueue`] from the given source–destination
+    /// mapping pairs, along with their companion files.
+    ///
+    /// The primary files and their companions are validated as a unit.
+    ///
+    /// # Errors
+    ///
+    /// See [`RenameQueue::new`].
+    pub fn queue<I, S, D>(&self, iter: I) -> Result<RenameQueue, Error>
+    where
+        I: IntoIterator<Item = (S, D)>,
+        S: AsRef<Path>,
+        D: AsRef<Path>,
+    {
+        RenameQueue::new(self.expand(iter))
+    }
+
+    fn companions(&self, src: &Path, dst: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let mut companions = Vec::new();
+        let Some(extension) = src.extension().and_then(OsStr::to_str) else {
+            return companions;
+        };
+        let extension = extension.to_lowercase();
+        let (Some(src_stem), Some(dst_stem)) = (src.file_stem(), dst.file_stem()) else {
+            return companions;
+        };
+        let (Some(src_name), Some(dst_name)) = (src.file_name(), dst.file_name()) else {
+            return companions;
+        };
+
+        for group in &self.groups {
+            if !group.primaries.is_empty() && !group.primaries.contains(&extension) {
+                continue;
+            }
+            for companion in &group.companions {
+                for (src_base, dst_base) in [(src_stem, dst_stem), (src_name, dst_name)] {
+                    for variant in [companion.clone(), companion.to_uppercase()] {
+                        let src = src.with_file_name(join(src_base, &variant));
+                        if src.symlink_metadata().is_ok() {
+                            let dst = dst.with_file_name(join(dst_base, &variant));
+                            companions.push((src, dst));
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        companions
+    }
+}
+
+fn join(base: &OsStr, extension: &str) -> OsString {
+    let mut name = base.to_os_string();
+    name.push(".");
+    name.push(extension);
+    name
+}
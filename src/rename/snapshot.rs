@@ -0,0 +1,169 @@
+use super::file_system::FileSystem;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+/// A [`FileSystem`] that answers existence checks from directory listings.
+///
+/// The operating system backend stats every destination before renaming it,
+/// and every candidate temporary path while planning. On network file
+/// systems each of those is a round trip. Instead, this backend reads each
+/// parent directory once, with a single [`fs::read_dir`] call, and keeps the
+/// listing up to date as it renames and creates entries.
+///
+/// Changes made by other processes after a directory has been listed are not
+/// seen, so this should only be used while the affected directories are not
+/// modified concurrently. Names are matched exactly; if a name only matches
+/// an entry that differs in letter case, the path is stat-ed as usual, so
+/// conflicts are still detected on case-insensitive file systems. Unlike
+/// [`Path::exists`], dangling symlinks are reported as existing, which only
+/// makes conflict checks stricter.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{RenameQueue, SnapshotFileSystem};
+///
+/// let fs = SnapshotFileSystem::new();
+/// let mut queue = RenameQueue::with_fs(fs, [("/mnt/nfs/foo", "/mnt/nfs/bar")]).unwrap();
+/// queue.rename_atomic().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotFileSystem {
+    // Ordered by components, so the listings under a directory are
+    // contiguous.
+    dirs: BTreeMap<PathBuf, Listing>,
+    reads: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Listing {
+    names: HashSet<OsString>,
+    folded: HashMap<String, usize>,
+}
+
+impl SnapshotFileSystem {
+    /// Creates a new [`SnapshotFileSystem`] with nothing listed yet.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of directories listed so far.
+    #[inline]
+    pub fn reads(&self) -> usize {
+        self.reads
+    }
+
+    /// Discards all listings, so that they are read again on demand.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.dirs.clear();
+    }
+
+    /// Returns the listing of a directory, reading it if needed.
+    ///
+    /// Returns [`None`] if the directory cannot be listed for any reason
+    /// other than not existing.
+    fn listing(&mut self, dir: &Path) -> Option<&mut Listing> {
+        if !self.dirs.contains_key(dir) {
+            self.reads += 1;
+            let listing = match fs::read_dir(dir) {
+                Ok(entries) => {
+                    let mut listing = Listing::default();
+                    for entry in entries {
+                        listing.insert(&entry.ok()?.file_name());
+                    }
+                    listing
+                }
+                Err(error) if error.kind() == ErrorKind::NotFound => Listing::default(),
+                Err(_) => return None,
+            };
+            self.dirs.insert(dir.to_path_buf(), listing);
+        }
+        self.dirs.get_mut(dir)
+    }
+
+    /// Records that a path has been created, if its parent is listed.
+    fn insert(&mut self, path: &Path) {
+        if let (Some(parent), Some(name)) = (path.parent(), path.file_name())
+            && let Some(listing) = self.dirs.get_mut(parent)
+        {
+            listing.insert(name);
+        }
+    }
+
+    /// Records that a path has been removed, along with everything under it.
+    fn remove(&mut self, path: &Path) {
+        if let (Some(parent), Some(name)) = (path.parent(), path.file_name())
+            && let Some(listing) = self.dirs.get_mut(parent)
+        {
+            listing.remove(name);
+        }
+        let stale: Vec<PathBuf> = self
+            .dirs
+            .range(path.to_path_buf()..)
+            .map(|(dir, _)| dir)
+            .take_while(|dir| dir.starts_with(path))
+            .cloned()
+            .collect();
+        for dir in stale {
+            self.dirs.remove(&dir);
+        }
+    }
+}
+
+impl FileSystem for SnapshotFileSystem {
+    fn exists(&mut self, path: &Path) -> bool {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return path.exists();
+        };
+        match self.listing(parent) {
+            Some(listing) if listing.names.contains(name) => true,
+            Some(listing) if !listing.folded.contains_key(&fold(name)) => false,
+            _ => path.exists(),
+        }
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        fs::create_dir_all(path)?;
+        for ancestor in path.ancestors() {
+            self.insert(ancestor);
+        }
+        Ok(())
+    }
+
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        fs::rename(src, dst)?;
+        self.remove(src);
+        self.remove(dst);
+        self.insert(dst);
+        Ok(())
+    }
+}
+
+impl Listing {
+    fn insert(&mut self, name: &OsStr) {
+        if self.names.insert(name.to_os_string()) {
+            *self.folded.entry(fold(name)).or_default() += 1;
+        }
+    }
+
+    fn remove(&mut self, name: &OsStr) {
+        if self.names.remove(name) {
+            let folded = fold(name);
+            if let Some(count) = self.folded.get_mut(&folded) {
+                *count -= 1;
+                if *count == 0 {
+                    self.folded.remove(&folded);
+                }
+            }
+        }
+    }
+}
+
+fn fold(name: &OsStr) -> String {
+    name.to_string_lossy().to_lowercase()
+}
@@ -0,0 +1,246 @@
+use super::error::Error;
+use super::queue::RenameQueue;
+use crate::walk_dir::WalkDir;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path;
+use std::path::{Component, Path, PathBuf};
+
+impl RenameQueue {
+    /// Retargets symlinks under `root` that point at renamed sources, so that
+    /// they point at the corresponding destinations instead.
+    ///
+    /// Targets inside a renamed directory are retargeted as well. Relative
+    /// targets stay relative, and symlinks that were themselves renamed are
+    /// resolved against their original location. Returns the paths of the
+    /// retargeted symlinks.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Io`] if an I/O error occurs.
+    pub fn fixup_symlinks<P>(&self, root: P) -> Result<Vec<PathBuf>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let moves = self.net_moves();
+        let reverse: HashMap<PathBuf, PathBuf> = moves
+            .iter()
+            .map(|(src, dst)| (dst.clone(), src.clone()))
+            .collect();
+        let mut fixed = Vec::new();
+
+        for entry in WalkDir::new(root)? {
+            let entry = entry.map_err(io::Error::from)?;
+            if !entry.metadata().is_symlink() {
+                continue;
+            }
+
+            let link = normalize(&path::absolute(entry.path())?);
+            let target = fs::read_link(&link)?;
+            let original_link = retarget(&reverse, &link).unwrap_or_else(|| link.clone());
+            let Some(original_parent) = original_link.parent() else {
+                continue;
+            };
+            let resolved = normalize(&original_parent.join(&target));
+
+            let moved = original_link != link && target.is_relative();
+            let Some(retargeted) = retarget(&moves, &resolved).or(moved.then_some(resolved)) else {
+                continue;
+            };
+            let new_target = if target.is_relative() {
+                match link.parent() {
+                    Some(parent) => relative_to(&retargeted, parent),
+                    None => retargeted.clone(),
+                }
+            } else {
+                retargeted.clone()
+            };
+            if new_target == target {
+                continue;
+            }
+
+            relink(&link, &new_target, &retargeted)?;
+            fixed.push(link);
+        }
+
+        Ok(fixed)
+    }
+
+    /// Collapses the renamed mappings (including the temporary hops used to
+    /// break cycles) into their net source–destination moves.
+    fn net_moves(&self) -> HashMap<PathBuf, PathBuf> {
+        let mut origins: HashMap<PathBuf, PathBuf> = HashMap::new();
+
+        for mapping in self.renamed() {
+            let src = normalize(&mapping.to_src());
+            let origin = origins.remove(&src).unwrap_or(src);
+            origins.insert(normalize(&mapping.to_dst()), origin);
+        }
+
+        origins
+            .into_iter()
+            .filter(|(dst, src)| dst != src)
+            .map(|(dst, src)| (src, dst))
+            .collect()
+    }
+}
+
+fn retarget(moves: &HashMap<PathBuf, PathBuf>, target: &Path) -> Option<PathBuf> {
+    let mut ancestor = Some(target);
+    while let Some(path) = ancestor {
+        if let Some(dst) = moves.get(path) {
+            // Will not fail since `path` is an ancestor of `target`.
+            let rest = target.strip_prefix(path).ok()?;
+            return Some(if rest.as_os_str().is_empty() {
+                dst.clone()
+            } else {
+                dst.join(rest)
+            });
+        }
+        ancestor = path.parent();
+    }
+    None
+}
+
+/// Lexically resolves `.` and `..` components.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+    while let (Some(lhs), Some(rhs)) = (path_components.peek(), base_components.peek()) {
+        if lhs != rhs {
+            break;
+        }
+        path_components.next();
+        base_components.next();
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in base_components {
+        relative.push(Component::ParentDir);
+    }
+    relative.extend(path_components);
+    if relative.as_os_str().is_empty() {
+        relative.push(Component::CurDir);
+    }
+    relative
+}
+
+/// Replaces the symlink at `link` with one pointing at `target`.
+///
+/// The new link is created under a temporary name and then renamed over the
+/// old one, so `link` is never missing, and is left untouched on failure.
+fn relink(link: &Path, target: &Path, resolved: &Path) -> io::Result<()> {
+    let mut temp = link.to_path_buf();
+    for i in 0.. {
+        temp.set_extension(format!("temp_{i}"));
+        if temp.symlink_metadata().is_err() {
+            break;
+        }
+    }
+    symlink(target, &temp, resolved)?;
+    replace(&temp, link).inspect_err(|_| {
+        let _ = remove(&temp);
+    })
+}
+
+#[cfg_attr(not(windows), allow(unused_variables))]
+fn symlink(target: &Path, link: &Path, resolved: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(target, link);
+
+    #[cfg(windows)]
+    return if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    };
+
+    #[cfg(not(any(unix, windows)))]
+    return Err(io::Error::from(io::ErrorKind::Unsupported));
+}
+
+/// Renames the symlink at `src` over the one at `dst`.
+#[cfg(not(windows))]
+fn replace(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::rename(src, dst)
+}
+
+/// Renames the symlink at `src` over the one at `dst`.
+///
+/// Directory symlinks cannot be renamed over on Windows, so the old link is
+/// moved aside first, and moved back if the new one cannot take its place.
+#[cfg(windows)]
+fn replace(src: &Path, dst: &Path) -> io::Result<()> {
+    let mut old = dst.to_path_buf();
+    for i in 0.. {
+        old.set_extension(format!("temp_{i}"));
+        if old != src && old.symlink_metadata().is_err() {
+            break;
+        }
+    }
+    fs::rename(dst, &old)?;
+    if let Err(error) = fs::rename(src, dst) {
+        let _ = fs::rename(&old, dst);
+        return Err(error);
+    }
+    remove(&old)
+}
+
+/// Removes the symlink at `link`.
+fn remove(link: &Path) -> io::Result<()> {
+    // Directory symlinks are removed as directories on Windows.
+    #[cfg(windows)]
+    if link.metadata().is_ok_and(|metadata| metadata.is_dir()) {
+        return fs::remove_dir(link);
+    }
+    fs::remove_file(link)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn relink_replaces_the_link_in_place() {
+        let dir = env::temp_dir().join(format!("mofu-symlink-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("full")).unwrap();
+        fs::write(dir.join("full/a.txt"), "").unwrap();
+        let link = dir.join("link");
+        symlink(Path::new("a.txt"), &link, Path::new("")).unwrap();
+
+        relink(&link, Path::new("b.txt"), Path::new("")).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("b.txt"));
+
+        // A link that cannot be renamed into place leaves the path as it was.
+        let full = dir.join("full");
+        assert!(relink(&full, Path::new("b.txt"), Path::new("")).is_err());
+        assert!(full.join("a.txt").exists());
+
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["full", "link"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
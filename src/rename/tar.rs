@@ -0,0 +1,814 @@
+use super::file_system::FileSystem;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+const BLOCK_LEN: usize = 512;
+const NAME_LEN: usize = 100;
+const PREFIX_LEN: usize = 155;
+
+/// A [`FileSystem`] that operates on the entries of a tar archive.
+///
+/// Renames only update the entry names in memory. The result is written out
+/// with [`TarFileSystem::save`], which re-packs the archive in a single
+/// streaming pass, copying the contents of each entry as-is. Hard links
+/// pointing at renamed entries are updated accordingly, and names that no
+/// longer fit into the header are stored as PAX extended headers.
+///
+/// Gzip-compressed archives are detected automatically, and are handled by
+/// piping through the `gzip` program.
+///
+/// Entry names are mapped to paths by prepending a root, so the entry
+/// `docs/a.txt`, or `./docs/a.txt`, is referred to as `/docs/a.txt`.
+/// Directories are implied by entry names, and renaming a directory renames
+/// every entry under it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{RenameQueue, TarFileSystem};
+///
+/// let fs = TarFileSystem::open("backup.tar.gz").unwrap();
+/// let mut queue = RenameQueue::with_fs(fs, [("/old", "/new")]).unwrap();
+/// queue.rename_atomic().unwrap();
+/// queue.fs_mut().save("backup-restructured.tar.gz").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TarFileSystem {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    link: Option<String>,
+    renamed: bool,
+    dir: bool,
+    len: u64,
+}
+
+/// A logical entry, including the extended headers preceding it.
+#[derive(Debug)]
+struct Record {
+    header: [u8; BLOCK_LEN],
+    /// Raw headers and data to be copied as-is, such as global headers.
+    passthrough: Vec<u8>,
+    /// PAX records other than `path` and `linkpath`, including `size`, which
+    /// is written back as is along with the data it describes.
+    pax: Vec<(String, Vec<u8>)>,
+    name: String,
+    link: String,
+    data_len: u64,
+}
+
+impl TarFileSystem {
+    /// Opens a tar archive, reading the names of its entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or if it is not a valid
+    /// tar archive.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let mut reader = open_reader(&path)?;
+        let mut entries = Vec::new();
+        while let Some(record) = Record::read(&mut reader)? {
+            let data_len = padded(record.data_len);
+            if io::copy(&mut (&mut reader).take(data_len), &mut io::sink())? != data_len {
+                return Err(Error::from(ErrorKind::UnexpectedEof));
+            }
+            let typeflag = record.header[156];
+            let link = (typeflag == b'1').then(|| normalize(&record.link));
+            let file = matches!(typeflag, 0 | b'0' | b'7');
+            entries.push(Entry {
+                name: normalize(&record.name),
+                link,
+                renamed: false,
+                dir: typeflag == b'5' || record.name.ends_with('/'),
+                len: if file { record.data_len } else { 0 },
+            });
+        }
+        reader.finish()?;
+        Ok(Self { path, entries })
+    }
+
+    /// Returns the paths of all entries, in archive order.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.entries
+            .iter()
+            .map(|entry| Path::new("/").join(entry.name.trim_end_matches('/')))
+            .collect()
+    }
+
+    /// Returns the names of all entries, in archive order, along with
+    /// whether they are directories and the sizes of regular files.
+    pub(crate) fn members(&self) -> Vec<(String, bool, u64)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.name.clone(), entry.dir, entry.len))
+            .collect()
+    }
+
+    /// Writes the re-packed archive to `path`, compressing it with `gzip` if
+    /// the extension is `gz` or `tgz`.
+    ///
+    /// The destination must not be the archive being read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive cannot be read or written.
+    pub fn save<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file = File::create(path)?;
+        let compress = matches!(path.extension().and_then(OsStr::to_str), Some("gz" | "tgz"));
+        if !compress {
+            let mut writer = BufWriter::new(file);
+            self.write_to(&mut writer)?;
+            return writer.flush();
+        }
+
+        let mut child = Command::new("gzip")
+            .arg("-c")
+            .stdin(Stdio::piped())
+            .stdout(file)
+            .spawn()?;
+        if let Some(stdin) = child.stdin.take() {
+            let mut writer = BufWriter::new(stdin);
+            self.write_to(&mut writer)?;
+            writer.flush()?;
+        }
+        check(child.wait()?.success(), "gzip")
+    }
+
+    /// Writes the re-packed, uncompressed archive to the given writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive cannot be read or written.
+    pub fn write_to<W>(&self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let mut reader = open_reader(&self.path)?;
+        let mut entries = self.entries.iter();
+
+        while let Some(mut record) = Record::read(&mut reader)? {
+            let entry = entries
+                .next()
+                .ok_or_else(|| invalid("archive changed since it was opened"))?;
+            if entry.renamed {
+                record.name.clone_from(&entry.name);
+            }
+            if let Some(link) = &entry.link {
+                record.link.clone_from(link);
+            }
+            let data_len = padded(record.data_len);
+            record.write(writer)?;
+            if io::copy(&mut (&mut reader).take(data_len), writer)? != data_len {
+                return Err(Error::from(ErrorKind::UnexpectedEof));
+            }
+        }
+        reader.finish()?;
+
+        writer.write_all(&[0; BLOCK_LEN * 2])
+    }
+
+    /// Returns the indices of the entries at or under `key`.
+    fn matching(&self, key: &str) -> Vec<usize> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| is_at_or_under(&entry.name, key))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+impl FileSystem for TarFileSystem {
+    fn exists(&mut self, path: &Path) -> bool {
+        match key(path) {
+            Ok(key) => {
+                key.is_empty()
+                    || self
+                        .entries
+                        .iter()
+                        .any(|entry| is_at_or_under(&entry.name, &key))
+            }
+            Err(_) => false,
+        }
+    }
+
+    #[inline]
+    fn create_dir_all(&mut self, _path: &Path) -> Result<()> {
+        // Directories are implied by entry names.
+        Ok(())
+    }
+
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        let src = key(src)?;
+        let dst = key(dst)?;
+        if src.is_empty() || dst.is_empty() || is_at_or_under(&dst, &src) {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+        if !self.matching(&dst).is_empty() {
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+        let matching = self.matching(&src);
+        if matching.is_empty() {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        for index in matching {
+            let entry = &mut self.entries[index];
+            entry.name = format!("{dst}{}", &entry.name[src.len()..]);
+            entry.renamed = true;
+        }
+        for entry in &mut self.entries {
+            if let Some(link) = &mut entry.link
+                && is_at_or_under(link, &src)
+            {
+                *link = format!("{dst}{}", &link[src.len()..]);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Record {
+    fn read<R>(reader: &mut R) -> Result<Option<Self>>
+    where
+        R: Read,
+    {
+        let mut passthrough = Vec::new();
+        let mut pax = Vec::new();
+        let mut long_name = None;
+        let mut long_link = None;
+        let mut pax_name = None;
+        let mut pax_link = None;
+        let mut pax_size = None;
+
+        loop {
+            let mut header = [0; BLOCK_LEN];
+            if !read_block(reader, &mut header)? || header.iter().all(|&byte| byte == 0) {
+                return Ok(None);
+            }
+            verify_checksum(&header)?;
+            let size = parse_size(&header[124..136])?;
+
+            match header[156] {
+                b'L' | b'K' => {
+                    let data = read_data(reader, size)?;
+                    let value = String::from_utf8_lossy(trim_nul(&data)).into_owned();
+                    if header[156] == b'L' {
+                        long_name = Some(value);
+                    } else {
+                        long_link = Some(value);
+                    }
+                }
+                b'x' => {
+                    let data = read_data(reader, size)?;
+                    for (key, value) in parse_pax(&data)? {
+                        match key.as_str() {
+                            "path" => pax_name = Some(String::from_utf8_lossy(&value).into_owned()),
+                            "linkpath" => {
+                                pax_link = Some(String::from_utf8_lossy(&value).into_owned());
+                            }
+                            "size" => {
+                                pax_size = Some(parse_pax_size(&value)?);
+                                pax.push((key, value));
+                            }
+                            _ => pax.push((key, value)),
+                        }
+                    }
+                }
+                b'g' => {
+                    passthrough.extend_from_slice(&header);
+                    let data = read_data(reader, size)?;
+                    passthrough.extend_from_slice(&data);
+                    passthrough.resize(passthrough.len() + pad_len(data.len() as u64), 0);
+                }
+                typeflag => {
+                    let name = pax_name
+                        .or(long_name)
+                        .unwrap_or_else(|| header_name(&header));
+                    let link = pax_link
+                        .or(long_link)
+                        .unwrap_or_else(|| field(&header[157..257]));
+                    let data_len = match typeflag {
+                        b'1'..=b'6' => 0,
+                        _ => pax_size.unwrap_or(size),
+                    };
+                    return Ok(Some(Self {
+                        header,
+                        passthrough,
+                        pax,
+                        name,
+                        link,
+                        data_len,
+                    }));
+                }
+            }
+        }
+    }
+
+    fn write<W>(mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        writer.write_all(&self.passthrough)?;
+
+        let posix = &self.header[257..263] == b"ustar\0";
+        let mut pax = std::mem::take(&mut self.pax);
+        match split_name(&self.name, posix) {
+            Some((prefix, name)) => {
+                set_field(&mut self.header[0..100], name.as_bytes());
+                if posix {
+                    set_field(&mut self.header[345..500], prefix.as_bytes());
+                }
+            }
+            None => {
+                set_field(&mut self.header[0..100], truncate(&self.name, NAME_LEN));
+                if posix {
+                    set_field(&mut self.header[345..500], b"");
+                }
+                pax.push((String::from("path"), self.name.clone().into_bytes()));
+            }
+        }
+        if self.link.len() <= NAME_LEN {
+            set_field(&mut self.header[157..257], self.link.as_bytes());
+        } else {
+            set_field(&mut self.header[157..257], truncate(&self.link, NAME_LEN));
+            pax.push((String::from("linkpath"), self.link.clone().into_bytes()));
+        }
+
+        if !pax.is_empty() {
+            let data = format_pax(&pax);
+            let mut header = [0; BLOCK_LEN];
+            set_field(&mut header[0..100], b"././@PaxHeader");
+            header[100..108].copy_from_slice(b"0000644\0");
+            header[108..116].copy_from_slice(b"0000000\0");
+            header[116..124].copy_from_slice(b"0000000\0");
+            header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+            header[136..148].copy_from_slice(&self.header[136..148]);
+            header[156] = b'x';
+            header[257..263].copy_from_slice(b"ustar\0");
+            header[263..265].copy_from_slice(b"00");
+            write_checksum(&mut header);
+            writer.write_all(&header)?;
+            writer.write_all(&data)?;
+            writer.write_all(&vec![0; pad_len(data.len() as u64)])?;
+        }
+
+        write_checksum(&mut self.header);
+        writer.write_all(&self.header)
+    }
+}
+
+/// A reader over the uncompressed contents of an archive.
+enum ArchiveReader {
+    Plain(BufReader<File>),
+    Gzip(Child, BufReader<ChildStdout>),
+}
+
+impl ArchiveReader {
+    fn finish(self) -> Result<()> {
+        match self {
+            Self::Plain(_) => Ok(()),
+            Self::Gzip(mut child, stdout) => {
+                drop(stdout);
+                check(child.wait()?.success(), "gzip")
+            }
+        }
+    }
+}
+
+impl Read for ArchiveReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self {
+            Self::Plain(reader) => reader.read(buf),
+            Self::Gzip(_, reader) => reader.read(buf),
+        }
+    }
+}
+
+fn open_reader(path: &Path) -> Result<ArchiveReader> {
+    let mut file = File::open(path)?;
+    let mut magic = [0; 2];
+    let is_gzip = file.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b];
+    if !is_gzip {
+        return Ok(ArchiveReader::Plain(BufReader::new(File::open(path)?)));
+    }
+
+    let mut child = Command::new("gzip")
+        .arg("-dc")
+        .stdin(File::open(path)?)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|error| Error::new(ErrorKind::Unsupported, error))?;
+    let stdout = child.stdout.take().ok_or(ErrorKind::BrokenPipe)?;
+    Ok(ArchiveReader::Gzip(child, BufReader::new(stdout)))
+}
+
+fn read_block<R>(reader: &mut R, block: &mut [u8; BLOCK_LEN]) -> Result<bool>
+where
+    R: Read,
+{
+    // Some archives end without the blocks of zeros, but never within a
+    // block.
+    let mut read = 0;
+    while read < BLOCK_LEN {
+        match reader.read(&mut block[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err(Error::from(ErrorKind::UnexpectedEof)),
+            Ok(len) => read += len,
+            Err(error) if error.kind() == ErrorKind::Interrupted => (),
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(true)
+}
+
+fn read_data<R>(reader: &mut R, size: u64) -> Result<Vec<u8>>
+where
+    R: Read,
+{
+    let len = usize::try_from(size).map_err(|_| invalid("extended header too large"))?;
+    let mut data = vec![0; len];
+    reader.read_exact(&mut data)?;
+    io::copy(
+        &mut reader.by_ref().take(pad_len(size) as u64),
+        &mut io::sink(),
+    )?;
+    Ok(data)
+}
+
+fn parse_size(field: &[u8]) -> Result<u64> {
+    if field[0] & 0x80 != 0 {
+        // Base-256 encoding, as used by GNU tar for large files.
+        let mut size: u64 = u64::from(field[0] & 0x7f);
+        for &byte in &field[1..] {
+            size = size
+                .checked_mul(256)
+                .and_then(|size| size.checked_add(byte.into()))
+                .ok_or_else(|| invalid("size out of range"))?;
+        }
+        return Ok(size);
+    }
+    parse_octal(field)
+}
+
+fn parse_octal(field: &[u8]) -> Result<u64> {
+    let digits = String::from_utf8_lossy(trim_nul(field));
+    let digits = digits.trim();
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8).map_err(|_| invalid("invalid octal number"))
+}
+
+fn verify_checksum(header: &[u8; BLOCK_LEN]) -> Result<()> {
+    let expected = parse_octal(&header[148..156])?;
+    if checksum(header) != expected {
+        return Err(invalid("invalid header checksum"));
+    }
+    Ok(())
+}
+
+fn checksum(header: &[u8; BLOCK_LEN]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(index, &byte)| {
+            if (148..156).contains(&index) {
+                u64::from(b' ')
+            } else {
+                u64::from(byte)
+            }
+        })
+        .sum()
+}
+
+fn write_checksum(header: &mut [u8; BLOCK_LEN]) {
+    let checksum = checksum(header);
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+}
+
+fn parse_pax(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut records = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() && rest[0] != 0 {
+        let space = rest
+            .iter()
+            .position(|&byte| byte == b' ')
+            .ok_or_else(|| invalid("invalid pax record"))?;
+        let len: usize = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|len| len.parse().ok())
+            .filter(|&len| len > space + 1 && len <= rest.len())
+            .ok_or_else(|| invalid("invalid pax record"))?;
+        let record = &rest[space + 1..len - 1];
+        let equals = record
+            .iter()
+            .position(|&byte| byte == b'=')
+            .ok_or_else(|| invalid("invalid pax record"))?;
+        let key = String::from_utf8_lossy(&record[..equals]).into_owned();
+        records.push((key, record[equals + 1..].to_vec()));
+        rest = &rest[len..];
+    }
+    Ok(records)
+}
+
+/// Parses the decimal value of a pax `size` record, which overrides the
+/// size field of the header, as for entries too large for it.
+fn parse_pax_size(value: &[u8]) -> Result<u64> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| invalid("invalid pax size"))
+}
+
+fn format_pax(records: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (key, value) in records {
+        // The length prefix counts its own digits.
+        let body = key.len() + value.len() + 3;
+        let mut len = body + body.to_string().len();
+        if len.to_string().len() + body != len {
+            len += 1;
+        }
+        data.extend_from_slice(format!("{len} {key}=").as_bytes());
+        data.extend_from_slice(value);
+        data.push(b'\n');
+    }
+    data
+}
+
+fn header_name(header: &[u8; BLOCK_LEN]) -> String {
+    let name = field(&header[0..100]);
+    if &header[257..263] == b"ustar\0" {
+        let prefix = field(&header[345..500]);
+        if !prefix.is_empty() {
+            return format!("{prefix}/{name}");
+        }
+    }
+    name
+}
+
+/// Splits a name into the prefix and name fields of a header, if it fits.
+fn split_name(name: &str, posix: bool) -> Option<(&str, &str)> {
+    if name.len() <= NAME_LEN {
+        return Some(("", name));
+    }
+    if !posix {
+        return None;
+    }
+    name.char_indices()
+        .filter(|&(_, char)| char == '/')
+        .map(|(index, _)| (&name[..index], &name[index + 1..]))
+        .find(|(prefix, name)| prefix.len() <= PREFIX_LEN && name.len() <= NAME_LEN)
+}
+
+fn field(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(trim_nul(bytes)).into_owned()
+}
+
+fn set_field(field: &mut [u8], value: &[u8]) {
+    field.fill(0);
+    let len = value.len().min(field.len());
+    field[..len].copy_from_slice(&value[..len]);
+}
+
+fn truncate(value: &str, len: usize) -> &[u8] {
+    &value.as_bytes()[..value.len().min(len)]
+}
+
+fn trim_nul(bytes: &[u8]) -> &[u8] {
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    &bytes[..end]
+}
+
+fn pad_len(len: u64) -> usize {
+    (BLOCK_LEN - (len % BLOCK_LEN as u64) as usize) % BLOCK_LEN
+}
+
+fn padded(len: u64) -> u64 {
+    len + pad_len(len) as u64
+}
+
+/// Normalizes an entry name as a path relative to the root of the archive,
+/// as in `docs/a.txt` for `./docs//a.txt`, keeping the trailing slash of
+/// directories.
+fn normalize(name: &str) -> String {
+    let mut normalized = name
+        .split('/')
+        .filter(|component| !matches!(*component, "" | "."))
+        .collect::<Vec<_>>()
+        .join("/");
+    if name.ends_with('/') && !normalized.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+fn is_at_or_under(name: &str, key: &str) -> bool {
+    let name = name.strip_suffix('/').unwrap_or(name);
+    name.starts_with(key) && matches!(name.as_bytes().get(key.len()), None | Some(b'/'))
+}
+
+fn key(path: &Path) -> Result<String> {
+    let mut key = String::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => (),
+            Component::Normal(name) => {
+                let name = name.to_str().ok_or(ErrorKind::InvalidInput)?;
+                if !key.is_empty() {
+                    key.push('/');
+                }
+                key.push_str(name);
+            }
+            Component::ParentDir => return Err(Error::from(ErrorKind::InvalidInput)),
+        }
+    }
+    Ok(key)
+}
+
+fn check(success: bool, program: &str) -> Result<()> {
+    if success {
+        Ok(())
+    } else {
+        Err(Error::other(format!("{program} failed")))
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    /// Builds a ustar archive of regular files, ending with the blocks of
+    /// zeros.
+    fn archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (name, contents) in entries {
+            let mut header = [0; BLOCK_LEN];
+            set_field(&mut header[0..100], name.as_bytes());
+            header[100..108].copy_from_slice(b"0000644\0");
+            header[124..136].copy_from_slice(format!("{:011o}\0", contents.len()).as_bytes());
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            header[263..265].copy_from_slice(b"00");
+            write_checksum(&mut header);
+            data.extend_from_slice(&header);
+            data.extend_from_slice(contents);
+            data.resize(data.len() + pad_len(contents.len() as u64), 0);
+        }
+        data.extend_from_slice(&[0; BLOCK_LEN * 2]);
+        data
+    }
+
+    fn temp(name: &str, contents: &[u8]) -> PathBuf {
+        let path = env::temp_dir().join(format!("mofu-tar-{}-{name}", process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn open(name: &str, contents: &[u8]) -> Result<TarFileSystem> {
+        let path = temp(name, contents);
+        let fs = TarFileSystem::open(&path);
+        fs::remove_file(&path)?;
+        fs
+    }
+
+    #[test]
+    fn empty_file_is_an_empty_archive() {
+        let fs = open("empty", b"").unwrap();
+        assert!(fs.paths().is_empty());
+    }
+
+    #[test]
+    fn truncated_archives_are_invalid() {
+        let archive = archive(&[("a.txt", &[b'a'; 600])]);
+        for len in [100, BLOCK_LEN + 10, BLOCK_LEN * 2] {
+            let error = open("truncated", &archive[..len]).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        }
+        let mut corrupt = archive.clone();
+        corrupt[0] = b'b';
+        let error = open("corrupt", &corrupt).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn names_are_normalized() {
+        assert_eq!(normalize("./dir/a.txt"), "dir/a.txt");
+        assert_eq!(normalize("dir//sub/./"), "dir/sub/");
+        assert_eq!(normalize("/abs/a"), "abs/a");
+        assert_eq!(normalize("./"), "");
+
+        let archive = archive(&[("./dir/a.txt", b"a"), ("./dir/b.txt", b"b")]);
+        let mut fs = open("dot", &archive).unwrap();
+        assert_eq!(fs.paths()[0], Path::new("/dir/a.txt"));
+        assert!(fs.exists(Path::new("/dir/a.txt")));
+        fs.rename(Path::new("/dir"), Path::new("/docs")).unwrap();
+        assert!(fs.exists(Path::new("/docs/b.txt")));
+        assert!(!fs.exists(Path::new("/dir")));
+    }
+
+    #[test]
+    fn rename_round_trips() {
+        let long = format!("{}/b.txt", "d".repeat(150));
+        let archive = archive(&[("a.txt", b"alpha"), ("keep.txt", b"keep")]);
+        let path = temp("round-trip", &archive);
+        let mut fs = TarFileSystem::open(&path).unwrap();
+        fs.rename(Path::new("/a.txt"), &Path::new("/").join(&long))
+            .unwrap();
+
+        let mut repacked = Vec::new();
+        fs.write_to(&mut repacked).unwrap();
+        fs::remove_file(&path).unwrap();
+        let fs = open("repacked", &repacked).unwrap();
+        let paths = [Path::new("/").join(&long), PathBuf::from("/keep.txt")];
+        assert_eq!(fs.paths(), paths);
+        assert!(repacked.windows(5).any(|window| window == b"alpha"));
+    }
+
+    #[test]
+    fn pax_records_round_trip() {
+        let records = vec![
+            (String::from("path"), b"a/b".to_vec()),
+            (String::from("comment"), vec![b'x'; 95]),
+        ];
+        assert_eq!(parse_pax(&format_pax(&records)).unwrap(), records);
+        assert!(parse_pax(b"99 path=a\n").is_err());
+        assert!(parse_pax(b"path=a\n").is_err());
+    }
+
+    #[test]
+    fn pax_size_overrides_header_size() {
+        let records = [(String::from("size"), b"600".to_vec())];
+        let data = format_pax(&records);
+        let mut extended = archive(&[("pax", &data)]);
+        extended.truncate(BLOCK_LEN + padded(data.len() as u64) as usize);
+        extended[156] = b'x';
+        write_checksum((&mut extended[..BLOCK_LEN]).try_into().unwrap());
+        let mut header = archive(&[("big.bin", b"")]);
+        header.truncate(BLOCK_LEN);
+        let contents = archive(&[("after.txt", b"after")]);
+
+        let mut tar = extended;
+        tar.extend_from_slice(&header);
+        tar.extend_from_slice(&[b'x'; 600]);
+        tar.resize(tar.len() + pad_len(600), 0);
+        tar.extend_from_slice(&contents);
+
+        let path = temp("pax-size", &tar);
+        let mut fs = TarFileSystem::open(&path).unwrap();
+        assert_eq!(fs.paths(), [Path::new("/big.bin"), Path::new("/after.txt")]);
+        assert_eq!(fs.members()[0], (String::from("big.bin"), false, 600));
+        fs.rename(Path::new("/big.bin"), Path::new("/huge.bin"))
+            .unwrap();
+
+        let mut repacked = Vec::new();
+        fs.write_to(&mut repacked).unwrap();
+        fs::remove_file(&path).unwrap();
+        let fs = open("pax-size-repacked", &repacked).unwrap();
+        assert_eq!(
+            fs.paths(),
+            [Path::new("/huge.bin"), Path::new("/after.txt")]
+        );
+        assert_eq!(fs.members()[0], (String::from("huge.bin"), false, 600));
+        assert!(parse_pax_size(b"12x").is_err());
+    }
+
+    #[test]
+    fn sizes_are_parsed_in_octal_and_base_256() {
+        assert_eq!(parse_size(b"00000000017\0").unwrap(), 15);
+        assert_eq!(parse_size(b"           \0").unwrap(), 0);
+        let mut field = [0; 12];
+        field[0] = 0x80;
+        field[11] = 1;
+        field[10] = 2;
+        assert_eq!(parse_size(&field).unwrap(), 513);
+        assert!(parse_size(b"0000000009\0\0").is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use super::event::{Event, Observer};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// An [`Observer`] that throttles the execution by blocking after each
+/// processed mapping.
+///
+/// This is useful for huge batches on shared storage, where issuing renames
+/// as fast as possible may saturate the server.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{RenameQueue, Throttle};
+///
+/// let mut queue = RenameQueue::new([("foo", "bar")]).unwrap();
+/// queue.rename_atomic_with(&mut Throttle::per_second(50)).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    mode: Mode,
+    next: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Interval(Duration),
+    Delay(Duration),
+}
+
+impl Throttle {
+    /// Creates a new [`Throttle`] that limits the execution to at most `ops`
+    /// mappings per second.
+    ///
+    /// Unlike [`Throttle::delay`], the time spent on each rename counts
+    /// towards the interval.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ops` is zero.
+    pub fn per_second(ops: u32) -> Self {
+        assert!(ops > 0, "ops must be positive");
+        let mode = Mode::Interval(Duration::from_secs(1) / ops);
+        let next = None;
+        Self { mode, next }
+    }
+
+    /// Creates a new [`Throttle`] that sleeps for a fixed `delay` after each
+    /// processed mapping.
+    pub fn delay(delay: Duration) -> Self {
+        let mode = Mode::Delay(delay);
+        let next = None;
+        Self { mode, next }
+    }
+}
+
+impl Observer for Throttle {
+    fn notify(&mut self, event: &Event) {
+        match event {
+            Event::Started { .. } => self.next = Some(Instant::now()),
+            Event::Renamed { .. } | Event::RolledBack { .. } => match self.mode {
+                Mode::Interval(interval) => {
+                    let now = Instant::now();
+                    let next = self.next.unwrap_or(now) + interval;
+                    if let Some(remaining) = next.checked_duration_since(now) {
+                        thread::sleep(remaining);
+                    }
+                    // Never accumulate credit while the backend is slower
+                    // than the limit.
+                    self.next = Some(next.max(now));
+                }
+                Mode::Delay(delay) => thread::sleep(delay),
+            },
+            Event::Failed { .. } => (),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use super::event::{Event, Observer};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const DEFAULT_WINDOW: usize = 256;
+
+/// An [`Observer`] that tracks the rolling throughput of an execution and
+/// estimates the remaining time.
+///
+/// Throughput is measured over the most recent completions only, so the
+/// estimate adapts when the speed changes during long runs (e.g. when the
+/// renames move on to a slower device).
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{RenameQueue, Throughput};
+///
+/// let mut queue = RenameQueue::new([("foo", "bar")]).unwrap();
+/// let mut throughput = Throughput::new();
+/// queue.rename_atomic_with(&mut throughput).unwrap();
+/// println!("{:.1} mappings/s", throughput.rate().unwrap_or_default());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Throughput {
+    total: usize,
+    completed: usize,
+    rolled_back: usize,
+    /// Whether a failure has been reported, after which the mappings
+    /// processed are rolled back.
+    failed: bool,
+    started: Option<Instant>,
+    window: VecDeque<Instant>,
+    capacity: usize,
+}
+
+impl Throughput {
+    /// Creates a new [`Throughput`] with the default window size.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_WINDOW)
+    }
+
+    /// Creates a new [`Throughput`] that measures the rate over the last
+    /// `window` completions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is less than 2.
+    pub fn with_window(window: usize) -> Self {
+        assert!(window >= 2, "window must hold at least 2 completions");
+        Self {
+            total: 0,
+            completed: 0,
+            rolled_back: 0,
+            failed: false,
+            started: None,
+            window: VecDeque::with_capacity(window),
+            capacity: window,
+        }
+    }
+
+    /// Returns the number of mappings to process in the current execution.
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the number of mappings processed in the current execution,
+    /// less those rolled back.
+    #[inline]
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Returns the number of mappings rolled back after a failure in the
+    /// current execution.
+    #[inline]
+    pub fn rolled_back(&self) -> usize {
+        self.rolled_back
+    }
+
+    /// Returns the number of mappings yet to be processed.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.total.saturating_sub(self.completed)
+    }
+
+    /// Returns the time elapsed since the current execution started.
+    #[inline]
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.started.map(|started| started.elapsed())
+    }
+
+    /// Returns the rolling throughput in mappings per second.
+    ///
+    /// Returns [`None`] if nothing has been processed yet.
+    pub fn rate(&self) -> Option<f64> {
+        let started = self.started?;
+        let (count, since) = match (self.window.front(), self.window.back()) {
+            (Some(front), Some(back)) if self.window.len() >= 2 && front != back => {
+                (self.window.len() - 1, back.duration_since(*front))
+            }
+            (Some(back), _) => (self.window.len(), back.duration_since(started)),
+            _ => return None,
+        };
+        let secs = since.as_secs_f64();
+        if secs > 0.0 {
+            Some(count as f64 / secs)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the estimated time until the current execution finishes.
+    ///
+    /// Returns [`None`] if the rate cannot be estimated yet.
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.remaining();
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rate()?;
+        Duration::try_from_secs_f64(remaining as f64 / rate).ok()
+    }
+
+    fn record(&mut self) {
+        if self.window.len() == self.capacity {
+            self.window.pop_front();
+        }
+        self.window.push_back(Instant::now());
+        self.completed += 1;
+    }
+}
+
+impl Default for Throughput {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Observer for Throughput {
+    fn notify(&mut self, event: &Event) {
+        match event {
+            Event::Started { total } => {
+                self.total = *total;
+                self.completed = 0;
+                self.rolled_back = 0;
+                self.failed = false;
+                self.started = Some(Instant::now());
+                self.window.clear();
+            }
+            // Atomic executions undo what they have done after a failure.
+            Event::Renamed { .. } | Event::RolledBack { .. } if self.failed => {
+                self.completed = self.completed.saturating_sub(1);
+                self.rolled_back += 1;
+            }
+            Event::Renamed { .. } | Event::RolledBack { .. } => self.record(),
+            Event::Failed { .. } => self.failed = true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn renamed() -> Event {
+        let src = PathBuf::from("a");
+        let dst = PathBuf::from("b");
+        Event::Renamed { src, dst }
+    }
+
+    #[test]
+    fn rollback_after_failure_is_not_completion() {
+        let mut throughput = Throughput::new();
+        throughput.notify(&Event::Started { total: 3 });
+        throughput.notify(&renamed());
+        throughput.notify(&renamed());
+        throughput.notify(&Event::Failed {
+            src: PathBuf::from("c"),
+            dst: PathBuf::from("d"),
+            error: String::new(),
+        });
+        for _ in 0..2 {
+            throughput.notify(&Event::RolledBack {
+                src: PathBuf::from("a"),
+                dst: PathBuf::from("b"),
+            });
+        }
+        assert_eq!(throughput.completed(), 0);
+        assert_eq!(throughput.rolled_back(), 2);
+        assert_eq!(throughput.remaining(), 3);
+    }
+
+    #[test]
+    fn revert_counts_as_completion() {
+        let mut throughput = Throughput::new();
+        throughput.notify(&Event::Started { total: 1 });
+        throughput.notify(&Event::RolledBack {
+            src: PathBuf::from("a"),
+            dst: PathBuf::from("b"),
+        });
+        assert_eq!(throughput.completed(), 1);
+        assert_eq!(throughput.rolled_back(), 0);
+        assert_eq!(throughput.eta(), Some(Duration::ZERO));
+    }
+}
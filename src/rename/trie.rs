@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::path::{Component, PathBuf};
+use std::sync::Arc;
+
+/// A trie over path components.
+///
+/// Children are ordered like [`Path`] itself, so a depth-first walk visits
+/// paths in sorted order, without ever comparing whole paths.
+///
+/// [`Path`]: std::path::Path
+#[derive(Debug, Default)]
+pub(super) struct PathTrie<'a> {
+    // The least path ending at this node, and the next least one if another
+    // spelling of the same components, like a trailing separator, exists.
+    path: Option<&'a Arc<PathBuf>>,
+    alias: Option<&'a Arc<PathBuf>>,
+    children: BTreeMap<Component<'a>, PathTrie<'a>>,
+}
+
+impl<'a> PathTrie<'a> {
+    /// Inserts a path, in time proportional to its number of components.
+    pub(super) fn insert(&mut self, path: &'a Arc<PathBuf>) {
+        let mut node = self;
+        for component in path.components() {
+            node = node.children.entry(component).or_default();
+        }
+
+        let spelling = path.as_os_str();
+        match node.path {
+            None => node.path = Some(path),
+            Some(least) if spelling == least.as_os_str() => {}
+            Some(least) if spelling < least.as_os_str() => {
+                node.alias = Some(least);
+                node.path = Some(path);
+            }
+            Some(_) => {
+                if node.alias.is_none_or(|alias| spelling < alias.as_os_str()) {
+                    node.alias = Some(path);
+                }
+            }
+        }
+    }
+
+    /// Returns the first path, in sorted order, that is an ancestor of
+    /// another path, along with the first such descendant.
+    pub(super) fn first_conflict(&self) -> Option<(&'a Arc<PathBuf>, &'a Arc<PathBuf>)> {
+        if let Some(path) = self.path {
+            if let Some(alias) = self.alias {
+                return Some((path, alias));
+            }
+            if let Some(descendant) = self.children.values().find_map(Self::first_path) {
+                return Some((path, descendant));
+            }
+        }
+        self.children.values().find_map(Self::first_conflict)
+    }
+
+    fn first_path(&self) -> Option<&'a Arc<PathBuf>> {
+        self.path
+            .or_else(|| self.children.values().find_map(Self::first_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_conflict(paths: &[&str]) -> Option<(PathBuf, PathBuf)> {
+        let paths: Vec<_> = paths.iter().map(PathBuf::from).map(Arc::new).collect();
+        let mut trie = PathTrie::default();
+        for path in &paths {
+            trie.insert(path);
+        }
+        trie.first_conflict()
+            .map(|(node, descendant)| (node.to_path_buf(), descendant.to_path_buf()))
+    }
+
+    fn conflict(node: &str, descendant: &str) -> Option<(PathBuf, PathBuf)> {
+        Some((node.into(), descendant.into()))
+    }
+
+    #[test]
+    fn siblings_and_prefixes_of_names_do_not_conflict() {
+        assert_eq!(first_conflict(&[]), None);
+        assert_eq!(first_conflict(&["/a/b", "/a/c", "/b"]), None);
+        // `/a/bc` starts with `/a/b`, but is not within it.
+        assert_eq!(first_conflict(&["/a/b", "/a/bc", "/a/b.txt"]), None);
+        // Duplicate paths are a single one.
+        assert_eq!(first_conflict(&["/a/b", "/a/b"]), None);
+    }
+
+    #[test]
+    fn ancestors_conflict_with_their_descendants() {
+        assert_eq!(first_conflict(&["/a/b/c", "/a"]), conflict("/a", "/a/b/c"));
+        assert_eq!(
+            first_conflict(&["/a/b/d", "/a/b/c", "/a/b"]),
+            conflict("/a/b", "/a/b/c"),
+        );
+        assert_eq!(first_conflict(&["/", "/a"]), conflict("/", "/a"));
+    }
+
+    #[test]
+    fn the_first_conflict_in_sorted_order_is_reported() {
+        let paths = ["/z", "/z/1", "/b/x", "/b", "/c/d/e", "/c/d"];
+        assert_eq!(first_conflict(&paths), conflict("/b", "/b/x"));
+    }
+
+    #[test]
+    fn other_spellings_of_a_path_conflict() {
+        assert_eq!(
+            first_conflict(&["/a/b/", "/a/b"]),
+            conflict("/a/b", "/a/b/")
+        );
+        assert_eq!(
+            first_conflict(&["/a/./b", "/a/b"]),
+            conflict("/a/./b", "/a/b")
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use super::error::Error;
+use super::trie::PathTrie;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::num::NonZero;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+/// Inputs smaller than this are validated on the calling thread, since
+/// spawning workers would cost more than it saves.
+const PARALLEL_THRESHOLD: usize = 1 << 16;
+
+/// A conflicting destination, along with the two least sources mapped to it.
+type ManyToOne<'a> = (&'a Arc<PathBuf>, &'a Arc<PathBuf>, &'a Arc<PathBuf>);
+
+/// Returns the number of workers to validate `len` items with.
+fn workers(len: usize) -> usize {
+    if len < PARALLEL_THRESHOLD {
+        return 1;
+    }
+    thread::available_parallelism().map_or(1, NonZero::get)
+}
+
+/// Checks that no two sources map to the same destination.
+///
+/// If there are several conflicts, the one with the least destination is
+/// reported, so the error does not depend on the number of workers or on
+/// the iteration order of the input.
+pub(super) fn check_many_to_one(mappings: &[(&Arc<PathBuf>, &Arc<PathBuf>)]) -> Result<(), Error> {
+    many_to_one(mappings, workers(mappings.len()))
+}
+
+/// Checks as [`check_many_to_one`] does, on the given number of workers.
+fn many_to_one(mappings: &[(&Arc<PathBuf>, &Arc<PathBuf>)], workers: usize) -> Result<(), Error> {
+    let conflict = if workers == 1 {
+        find_many_to_one(mappings.iter().copied())
+    } else {
+        // Mappings are sharded by destination, so conflicting mappings
+        // always end up in the same shard.
+        let state = RandomState::new();
+        let chunk_size = mappings.len().div_ceil(workers);
+        let shards: Vec<Vec<Vec<_>>> = thread::scope(|scope| {
+            let handles: Vec<_> = mappings
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let state = &state;
+                    scope.spawn(move || {
+                        let mut shards = vec![Vec::new(); workers];
+                        for &(src, dst) in chunk {
+                            let shard = state.hash_one(dst) as usize % workers;
+                            shards[shard].push((src, dst));
+                        }
+                        shards
+                    })
+                })
+                .collect();
+            handles.into_iter().map(join).collect()
+        });
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|shard| {
+                    let shards = &shards;
+                    scope.spawn(move || {
+                        find_many_to_one(
+                            shards.iter().flat_map(|chunk| chunk[shard].iter().copied()),
+                        )
+                    })
+                })
+                .collect();
+            handles.into_iter().filter_map(join).min()
+        })
+    };
+
+    match conflict {
+        Some((dst, lower, upper)) => Err(Error::ManyToOne {
+            src: (Arc::clone(lower), Arc::clone(upper)),
+            dst: Arc::clone(dst),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn find_many_to_one<'a, I>(mappings: I) -> Option<ManyToOne<'a>>
+where
+    I: Iterator<Item = (&'a Arc<PathBuf>, &'a Arc<PathBuf>)>,
+{
+    let mut rev_map = HashMap::new();
+    let mut conflict = None;
+    for (src, dst) in mappings {
+        let Some(collided) = rev_map.get_mut(dst) else {
+            rev_map.insert(dst, src);
+            continue;
+        };
+        // Keeping the least source makes the reported pair independent of
+        // the order the mappings are visited in.
+        let (lower, upper) = if *collided < src {
+            (*collided, src)
+        } else {
+            (src, *collided)
+        };
+        *collided = lower;
+        let candidate = (dst, lower, upper);
+        if conflict.is_none_or(|current| candidate < current) {
+            conflict = Some(candidate);
+        }
+    }
+    conflict
+}
+
+/// Checks that no path in `paths` is an ancestor of another.
+///
+/// The first conflict in sorted order is reported, whichever strategy and
+/// number of workers is used. On a single worker, the paths are inserted into
+/// a [`PathTrie`], which takes time proportional to the total number of
+/// components. Otherwise, they are sorted in parallel and adjacent paths are
+/// compared.
+pub(super) fn check_non_leaf(paths: &mut [&Arc<PathBuf>]) -> Result<(), Error> {
+    non_leaf(paths, workers(paths.len()))
+}
+
+/// Checks as [`check_non_leaf`] does, on the given number of workers.
+fn non_leaf(paths: &mut [&Arc<PathBuf>], workers: usize) -> Result<(), Error> {
+    let conflict = if workers == 1 {
+        let mut trie = PathTrie::default();
+        for path in paths.iter() {
+            trie.insert(path);
+        }
+        trie.first_conflict()
+    } else {
+        sort(paths, workers);
+        let paths = &*paths;
+        let windows = paths.len() - 1;
+        let chunk_size = windows.div_ceil(workers);
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..windows)
+                .step_by(chunk_size)
+                .map(|start| {
+                    let end = windows.min(start + chunk_size);
+                    scope.spawn(move || find_non_leaf(paths, start..end))
+                })
+                .collect();
+            // Chunks are in sorted order, so the first conflict found is
+            // also the first overall.
+            handles.into_iter().find_map(join)
+        })
+    };
+
+    match conflict {
+        Some((node, descendant)) => Err(Error::NonLeafNode {
+            node: Arc::clone(node),
+            descendant: Arc::clone(descendant),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Sorts paths by their components, breaking ties by their spelling.
+fn sort(paths: &mut [&Arc<PathBuf>], workers: usize) {
+    let compare = |lower: &&Arc<PathBuf>, upper: &&Arc<PathBuf>| {
+        lower
+            .cmp(upper)
+            .then_with(|| lower.as_os_str().cmp(upper.as_os_str()))
+    };
+    let chunk_size = paths.len().div_ceil(workers);
+    thread::scope(|scope| {
+        for chunk in paths.chunks_mut(chunk_size) {
+            scope.spawn(move || chunk.sort_by(compare));
+        }
+    });
+    // The standard sort detects the presorted runs, and only has to merge
+    // them.
+    paths.sort_by(compare);
+}
+
+fn find_non_leaf<'a>(
+    paths: &[&'a Arc<PathBuf>],
+    windows: Range<usize>,
+) -> Option<(&'a Arc<PathBuf>, &'a Arc<PathBuf>)> {
+    windows.into_iter().find_map(|index| {
+        let lower = paths[index];
+        let upper = paths[index + 1];
+        (upper.as_os_str() != lower.as_os_str() && upper.starts_with(lower.as_path()))
+            .then_some((lower, upper))
+    })
+}
+
+fn join<T>(handle: thread::ScopedJoinHandle<'_, T>) -> T {
+    match handle.join() {
+        Ok(value) => value,
+        Err(payload) => std::panic::resume_unwind(payload),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// The number of workers forcing the parallel path, whatever the size
+    /// of the input.
+    const WORKERS: usize = 4;
+
+    fn paths<I>(paths: I) -> Vec<Arc<PathBuf>>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        paths.into_iter().map(PathBuf::from).map(Arc::new).collect()
+    }
+
+    #[test]
+    fn parallel_many_to_one_reports_the_serial_conflict() {
+        let mut srcs = paths((0..1000).map(|index| format!("/src/{index}")));
+        let mut dsts = paths((0..1000).map(|index| format!("/dst/{index}")));
+        let pairs = |srcs: &[_], dsts: &[_]| -> Result<_, Error> {
+            let mappings: Vec<_> = srcs.iter().zip(dsts).collect();
+            let serial = format!("{:?}", many_to_one(&mappings, 1));
+            let parallel = format!("{:?}", many_to_one(&mappings, WORKERS));
+            assert_eq!(serial, parallel);
+            many_to_one(&mappings, WORKERS)
+        };
+        assert!(pairs(&srcs, &dsts).is_ok());
+
+        srcs.extend(paths(["/src/x1", "/src/x0"].map(String::from)));
+        dsts.extend(paths(["/dst/500", "/dst/20"].map(String::from)));
+        let Err(Error::ManyToOne { src, dst }) = pairs(&srcs, &dsts) else {
+            panic!("conflict not reported");
+        };
+        assert_eq!(*dst, Path::new("/dst/20"));
+        assert_eq!(
+            (&**src.0, &**src.1),
+            (Path::new("/src/20"), Path::new("/src/x0"))
+        );
+    }
+
+    #[test]
+    fn parallel_non_leaf_reports_the_serial_conflict() {
+        let mut all = paths((0..1000).map(|index| format!("/p/{index}")));
+        let check = |all: &[Arc<PathBuf>]| -> Result<(), Error> {
+            let mut serial: Vec<_> = all.iter().collect();
+            let mut parallel: Vec<_> = all.iter().rev().collect();
+            let serial = non_leaf(&mut serial, 1);
+            let result = format!("{serial:?}");
+            assert_eq!(result, format!("{:?}", non_leaf(&mut parallel, WORKERS)));
+            serial
+        };
+        assert!(check(&all).is_ok());
+
+        all.extend(paths(["/p/500/child", "/p/20/a/b"].map(String::from)));
+        let Err(Error::NonLeafNode { node, descendant }) = check(&all) else {
+            panic!("conflict not reported");
+        };
+        assert_eq!(*node, Path::new("/p/20"));
+        assert_eq!(*descendant, Path::new("/p/20/a/b"));
+    }
+}
@@ -0,0 +1,296 @@
+use super::command;
+use super::file_system::FileSystem;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// A [`FileSystem`] that operates on a WebDAV share, such as a Nextcloud or
+/// ownCloud instance.
+///
+/// Each operation runs `curl`, issuing `PROPFIND`, `MKCOL` and `MOVE`
+/// requests, so renames are performed on the server without transferring
+/// any file contents. Credentials can be supplied with [`WebDavFileSystem::arg`]
+/// (e.g. `--netrc` or `--user`).
+///
+/// Paths are appended to the base URL after stripping the root, so `/a/b`
+/// refers to `<base>/a/b`.
+///
+/// Only a `404 Not Found` response is taken as a path that does not exist.
+/// Any other failure while checking, such as a rejected credential or an
+/// unreachable server, is reported by the next operation, so conflict
+/// checks never pass on a failing connection.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{RenameQueue, WebDavFileSystem};
+///
+/// let fs = WebDavFileSystem::new("https://cloud.example.com/remote.php/dav/files/alice")
+///     .arg("--netrc");
+/// let mut queue = RenameQueue::with_fs(fs, [("/Photos/a.jpg", "/Photos/2024/a.jpg")]).unwrap();
+/// queue.rename_atomic().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct WebDavFileSystem {
+    program: PathBuf,
+    args: Vec<OsString>,
+    base: String,
+    /// A failure while checking whether a path exists, which is yet to be
+    /// reported.
+    failure: Option<String>,
+}
+
+impl WebDavFileSystem {
+    /// Creates a new [`WebDavFileSystem`] rooted at the `base` URL.
+    pub fn new<B>(base: B) -> Self
+    where
+        B: Into<String>,
+    {
+        let mut base = base.into();
+        while base.ends_with('/') {
+            base.pop();
+        }
+        Self {
+            program: PathBuf::from("curl"),
+            args: Vec::new(),
+            base,
+            failure: None,
+        }
+    }
+
+    /// Sets the path of the `curl` program.
+    ///
+    /// Defaults to `curl`, looked up in `PATH`.
+    pub fn program<P>(mut self, program: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.program = program.into();
+        self
+    }
+
+    /// Appends an extra argument passed to `curl`.
+    pub fn arg<A>(mut self, arg: A) -> Self
+    where
+        A: Into<OsString>,
+    {
+        self.args.push(arg.into());
+        self
+    }
+
+    fn url(&self, path: &Path) -> Result<String> {
+        let mut url = self.base.clone();
+        for component in path.components() {
+            match component {
+                Component::Prefix(_) | Component::RootDir | Component::CurDir => (),
+                Component::Normal(name) => {
+                    let name = name.to_str().ok_or(ErrorKind::InvalidInput)?;
+                    url.push('/');
+                    percent_encode(&mut url, name);
+                }
+                Component::ParentDir => return Err(Error::from(ErrorKind::InvalidInput)),
+            }
+        }
+        Ok(url)
+    }
+
+    fn command<F>(&self, method: &str, url: &str, build: F) -> Command
+    where
+        F: FnOnce(&mut Command),
+    {
+        let mut command = Command::new(&self.program);
+        command
+            .arg("--silent")
+            .arg("--show-error")
+            .arg("--request")
+            .arg(method)
+            .args(&self.args);
+        build(&mut command);
+        command.arg("--url").arg(url);
+        command
+    }
+
+    fn request<F>(&self, method: &str, url: &str, build: F) -> Result<()>
+    where
+        F: FnOnce(&mut Command),
+    {
+        let mut command = self.command(method, url, |command| {
+            command.arg("--fail");
+            build(command);
+        });
+        command::run(&mut command, &[]).map(drop)
+    }
+
+    /// Returns whether the resource at `url` exists, telling a `404 Not
+    /// Found` response apart from other failures.
+    fn propfind(&self, url: &str) -> Result<bool> {
+        let mut command = self.command("PROPFIND", url, |command| {
+            command
+                .arg("--header")
+                .arg("Depth: 0")
+                .arg("--write-out")
+                .arg("\\n%{http_code}");
+        });
+        let output = command::run(&mut command, &[])?;
+        let output = String::from_utf8_lossy(&output);
+        let status = output.lines().last().unwrap_or_default().trim();
+        match status {
+            "404" => Ok(false),
+            status if status.starts_with('2') => Ok(true),
+            status => Err(Error::other(format!("PROPFIND {url}: HTTP {status}"))),
+        }
+    }
+
+    /// Reports the failure left by [`FileSystem::exists`], if any.
+    fn check(&mut self) -> Result<()> {
+        match self.failure.take() {
+            Some(failure) => Err(Error::other(failure)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FileSystem for WebDavFileSystem {
+    fn exists(&mut self, path: &Path) -> bool {
+        let Ok(url) = self.url(path) else {
+            return false;
+        };
+        match self.propfind(&url) {
+            Ok(exists) => exists,
+            Err(error) => {
+                self.failure = Some(error.to_string());
+                false
+            }
+        }
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> Result<()> {
+        self.check()?;
+        let mut ancestor = PathBuf::new();
+        for component in path.components() {
+            ancestor.push(component);
+            if let Component::Normal(_) = component {
+                let url = self.url(&ancestor)?;
+                if !self.propfind(&url)? {
+                    self.request("MKCOL", &url, |_| ())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        self.check()?;
+        let src = self.url(src)?;
+        let dst = self.url(dst)?;
+        self.request("MOVE", &src, |command| {
+            command
+                .arg("--header")
+                .arg(format!("Destination: {dst}"))
+                .arg("--header")
+                .arg("Overwrite: F");
+        })
+    }
+}
+
+fn percent_encode(buf: &mut String, value: &str) {
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            buf.push(byte as char);
+        } else {
+            let _ = write!(buf, "%{byte:02X}");
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::{env, process};
+
+    /// A stand-in for `curl` answering `PROPFIND` by the last segment of
+    /// the URL, which is the status code to respond with, or `down` for an
+    /// unreachable server, and logging the methods and URLs to `log`.
+    const STUB: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+while [ $# -gt 0 ]; do
+    case "$1" in
+    --request) method=$2; shift ;;
+    --url) url=$2; shift ;;
+    esac
+    shift
+done
+echo "$method $url" >> "$dir/log"
+status=${url##*/}
+if [ "$status" = down ]; then
+    echo "curl: (7) Failed to connect" >&2
+    exit 7
+fi
+if [ "$method" = PROPFIND ]; then
+    printf '<multistatus/>\n%s' "$status"
+fi
+"#;
+
+    fn with_stub<F>(name: &str, test: F)
+    where
+        F: FnOnce(WebDavFileSystem, &dyn Fn() -> String),
+    {
+        let dir = env::temp_dir().join(format!("mofu-webdav-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("curl");
+        fs::write(&program, STUB).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(dir.join("log"), "").unwrap();
+
+        let log = || fs::read_to_string(dir.join("log")).unwrap();
+        test(
+            WebDavFileSystem::new("https://dav/").program(&program),
+            &log,
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_404_means_missing() {
+        with_stub("status", |mut fs, _| {
+            assert!(fs.exists(Path::new("/207")));
+            assert!(!fs.exists(Path::new("/404")));
+            assert!(fs.failure.is_none());
+            for status in ["401", "503", "down"] {
+                assert!(!fs.exists(&Path::new("/").join(status)));
+                assert!(fs.failure.take().is_some(), "{status}");
+            }
+        });
+    }
+
+    #[test]
+    fn failures_are_reported_by_the_next_operation() {
+        with_stub("failure", |mut fs, log| {
+            assert!(!fs.exists(Path::new("/403")));
+            assert!(fs.rename(Path::new("/a"), Path::new("/b")).is_err());
+            assert!(!log().contains("MOVE"));
+            fs.rename(Path::new("/a"), Path::new("/b")).unwrap();
+            assert!(log().ends_with("MOVE https://dav/a\n"));
+        });
+    }
+
+    #[test]
+    fn missing_ancestors_are_created() {
+        with_stub("mkcol", |mut fs, log| {
+            fs.create_dir_all(Path::new("/207/404")).unwrap();
+            assert_eq!(
+                log(),
+                "PROPFIND https://dav/207\n\
+                 PROPFIND https://dav/207/404\n\
+                 MKCOL https://dav/207/404\n",
+            );
+            let error = fs.create_dir_all(Path::new("/500")).unwrap_err();
+            assert!(error.to_string().contains("HTTP 500"));
+        });
+    }
+}
@@ -0,0 +1,546 @@
+use super::file_system::FileSystem;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+
+const LOCAL_HEADER_LEN: usize = 30;
+const CENTRAL_HEADER_LEN: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+const FLAG_UTF8: u16 = 1 << 11;
+
+/// A [`FileSystem`] that operates on the entries of a zip archive.
+///
+/// Renames only update the entry names in memory. The result is written out
+/// with [`ZipFileSystem::save`], which re-packs the archive by copying the
+/// compressed data of each entry as-is, so nothing is ever decompressed or
+/// extracted to disk.
+///
+/// Entry names are mapped to paths by prepending a root, so the entry
+/// `docs/a.txt` is referred to as `/docs/a.txt`. Directories are implied by
+/// entry names, and renaming a directory renames every entry under it.
+///
+/// Zip64 archives and archives spanning multiple disks are not supported.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rename::{RenameQueue, ZipFileSystem};
+///
+/// let fs = ZipFileSystem::open("backup.zip").unwrap();
+/// let mut queue = RenameQueue::with_fs(fs, [("/old", "/new")]).unwrap();
+/// queue.rename_atomic().unwrap();
+/// queue.fs_mut().save("backup-restructured.zip").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ZipFileSystem {
+    file: File,
+    entries: Vec<Entry>,
+    comment: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: Vec<u8>,
+    header: [u8; CENTRAL_HEADER_LEN],
+    extra: Vec<u8>,
+    comment: Vec<u8>,
+    renamed: bool,
+}
+
+impl ZipFileSystem {
+    /// Opens a zip archive, reading its central directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or if it is not a
+    /// supported zip archive.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = File::open(path)?;
+        let (count, size, offset, comment) = read_end_of_central_directory(&mut file)?;
+
+        let mut directory = vec![0; size as usize];
+        file.seek(SeekFrom::Start(offset.into()))?;
+        file.read_exact(&mut directory)?;
+
+        let mut entries = Vec::with_capacity(count.into());
+        let mut cursor = directory.as_slice();
+        for _ in 0..count {
+            entries.push(Entry::parse(&mut cursor)?);
+        }
+
+        Ok(Self {
+            file,
+            entries,
+            comment,
+        })
+    }
+
+    /// Returns the paths of all entries, in archive order.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let name = String::from_utf8_lossy(&entry.name);
+                Path::new("/").join(name.trim_end_matches('/'))
+            })
+            .collect()
+    }
+
+    /// Writes the re-packed archive to `path`.
+    ///
+    /// The destination must not be the archive being read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive cannot be read or written.
+    pub fn save<P>(&mut self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Writes the re-packed archive to the given writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive cannot be read or written.
+    pub fn write_to<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let mut offset: u64 = 0;
+        let mut offsets = Vec::with_capacity(self.entries.len());
+
+        for entry in &self.entries {
+            offsets.push(to_u32(offset)?);
+            self.file
+                .seek(SeekFrom::Start(entry.local_offset().into()))?;
+
+            let mut header = [0; LOCAL_HEADER_LEN];
+            self.file.read_exact(&mut header)?;
+            if read_u32(&header, 0) != LOCAL_HEADER_SIGNATURE {
+                return Err(invalid("invalid local file header"));
+            }
+            let name_len = read_u16(&header, 26);
+            let extra_len = read_u16(&header, 28);
+            self.file.seek(SeekFrom::Current(name_len.into()))?;
+            let mut extra = vec![0; extra_len.into()];
+            self.file.read_exact(&mut extra)?;
+
+            write_u16(&mut header, 6, entry.flags());
+            write_u16(&mut header, 26, to_u16(entry.name.len())?);
+            writer.write_all(&header)?;
+            writer.write_all(&entry.name)?;
+            writer.write_all(&extra)?;
+
+            let mut data_len = u64::from(entry.compressed_size());
+            if entry.flags() & FLAG_DATA_DESCRIPTOR != 0 {
+                // The descriptor may or may not carry a signature.
+                data_len += 12;
+                let mut signature = [0; 4];
+                let position = self.file.stream_position()?;
+                self.file
+                    .seek(SeekFrom::Current(entry.compressed_size().into()))?;
+                if self.file.read_exact(&mut signature).is_ok()
+                    && u32::from_le_bytes(signature) == DATA_DESCRIPTOR_SIGNATURE
+                {
+                    data_len += 4;
+                }
+                self.file.seek(SeekFrom::Start(position))?;
+            }
+            let copied = io::copy(&mut (&mut self.file).take(data_len), writer)?;
+            if copied != data_len {
+                return Err(Error::from(ErrorKind::UnexpectedEof));
+            }
+
+            offset += (LOCAL_HEADER_LEN + entry.name.len() + extra.len()) as u64 + data_len;
+        }
+
+        let directory_offset = to_u32(offset)?;
+        let mut directory_size: u64 = 0;
+        for (entry, offset) in self.entries.iter().zip(offsets) {
+            let mut header = entry.header;
+            write_u16(&mut header, 8, entry.flags());
+            write_u16(&mut header, 28, to_u16(entry.name.len())?);
+            write_u32(&mut header, 42, offset);
+            writer.write_all(&header)?;
+            writer.write_all(&entry.name)?;
+            writer.write_all(&entry.extra)?;
+            writer.write_all(&entry.comment)?;
+            directory_size +=
+                (CENTRAL_HEADER_LEN + entry.name.len() + entry.extra.len() + entry.comment.len())
+                    as u64;
+        }
+
+        let count = to_u16(self.entries.len())?;
+        let mut end = [0; END_OF_CENTRAL_DIRECTORY_LEN];
+        write_u32(&mut end, 0, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        write_u16(&mut end, 8, count);
+        write_u16(&mut end, 10, count);
+        write_u32(&mut end, 12, to_u32(directory_size)?);
+        write_u32(&mut end, 16, directory_offset);
+        write_u16(&mut end, 20, to_u16(self.comment.len())?);
+        writer.write_all(&end)?;
+        writer.write_all(&self.comment)?;
+        Ok(())
+    }
+
+    /// Returns the names of all entries, in archive order, along with
+    /// whether they are directories and their uncompressed sizes.
+    pub(crate) fn members(&self) -> Vec<(String, bool, u64)> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let name = String::from_utf8_lossy(&entry.name).into_owned();
+                let dir = name.ends_with('/');
+                (name, dir, read_u32(&entry.header, 24).into())
+            })
+            .collect()
+    }
+
+    /// Returns the indices of the entries at or under `key`.
+    fn matching(&self, key: &[u8]) -> Vec<usize> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.is_at_or_under(key))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+impl FileSystem for ZipFileSystem {
+    fn exists(&mut self, path: &Path) -> bool {
+        match key(path) {
+            Ok(key) => {
+                key.is_empty() || self.entries.iter().any(|entry| entry.is_at_or_under(&key))
+            }
+            Err(_) => false,
+        }
+    }
+
+    #[inline]
+    fn create_dir_all(&mut self, _path: &Path) -> Result<()> {
+        // Directories are implied by entry names.
+        Ok(())
+    }
+
+    fn rename(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        let src = key(src)?;
+        let dst = key(dst)?;
+        if src.is_empty() || dst.is_empty() {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+        if dst.starts_with(&src) && dst.get(src.len()) == Some(&b'/') {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+        if !self.matching(&dst).is_empty() {
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+        let matching = self.matching(&src);
+        if matching.is_empty() {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        for index in matching {
+            let entry = &mut self.entries[index];
+            let mut name = dst.clone();
+            name.extend_from_slice(&entry.name[src.len()..]);
+            entry.name = name;
+            entry.renamed = true;
+        }
+        Ok(())
+    }
+}
+
+impl Entry {
+    fn parse(cursor: &mut &[u8]) -> Result<Self> {
+        let buf = *cursor;
+        if buf.len() < CENTRAL_HEADER_LEN || read_u32(buf, 0) != CENTRAL_HEADER_SIGNATURE {
+            return Err(invalid("invalid central directory header"));
+        }
+        let name_len = usize::from(read_u16(buf, 28));
+        let extra_len = usize::from(read_u16(buf, 30));
+        let comment_len = usize::from(read_u16(buf, 32));
+        let len = CENTRAL_HEADER_LEN + name_len + extra_len + comment_len;
+        if buf.len() < len {
+            return Err(invalid("truncated central directory"));
+        }
+
+        let mut header = [0; CENTRAL_HEADER_LEN];
+        header.copy_from_slice(&buf[..CENTRAL_HEADER_LEN]);
+        let rest = &buf[CENTRAL_HEADER_LEN..len];
+        let (name, rest) = rest.split_at(name_len);
+        let (extra, comment) = rest.split_at(extra_len);
+
+        let entry = Self {
+            name: name.to_vec(),
+            header,
+            extra: extra.to_vec(),
+            comment: comment.to_vec(),
+            renamed: false,
+        };
+        if entry.compressed_size() == u32::MAX || entry.local_offset() == u32::MAX {
+            return Err(invalid("zip64 archives are not supported"));
+        }
+
+        *cursor = &buf[len..];
+        Ok(entry)
+    }
+
+    /// Returns the general purpose flags, marking names given by renames as
+    /// UTF-8.
+    ///
+    /// Names left untouched keep their flags, since they may be in another
+    /// encoding such as CP437.
+    fn flags(&self) -> u16 {
+        let flags = read_u16(&self.header, 8);
+        if self.renamed && !self.name.is_ascii() && str::from_utf8(&self.name).is_ok() {
+            flags | FLAG_UTF8
+        } else {
+            flags
+        }
+    }
+
+    fn compressed_size(&self) -> u32 {
+        read_u32(&self.header, 20)
+    }
+
+    fn local_offset(&self) -> u32 {
+        read_u32(&self.header, 42)
+    }
+
+    fn is_at_or_under(&self, key: &[u8]) -> bool {
+        let name = self.name.strip_suffix(b"/").unwrap_or(&self.name);
+        name.starts_with(key) && matches!(name.get(key.len()), None | Some(b'/'))
+    }
+}
+
+fn read_end_of_central_directory(file: &mut File) -> Result<(u16, u32, u32, Vec<u8>)> {
+    let len = file.seek(SeekFrom::End(0))?;
+    // The comment is at most `u16::MAX` bytes long.
+    let tail_len = len.min((END_OF_CENTRAL_DIRECTORY_LEN + usize::from(u16::MAX)) as u64);
+    let mut tail = vec![0; tail_len as usize];
+    file.seek(SeekFrom::Start(len - tail_len))?;
+    file.read_exact(&mut tail)?;
+    if tail.len() < END_OF_CENTRAL_DIRECTORY_LEN {
+        return Err(invalid("end of central directory not found"));
+    }
+
+    let start = (0..=tail.len() - END_OF_CENTRAL_DIRECTORY_LEN)
+        .rev()
+        .find(|&index| {
+            tail.get(index..index + END_OF_CENTRAL_DIRECTORY_LEN)
+                .is_some_and(|end| {
+                    read_u32(end, 0) == END_OF_CENTRAL_DIRECTORY_SIGNATURE
+                        && index + end.len() + usize::from(read_u16(end, 20)) == tail.len()
+                })
+        })
+        .ok_or_else(|| invalid("end of central directory not found"))?;
+    let end = &tail[start..];
+
+    if read_u16(end, 4) != 0 || read_u16(end, 6) != 0 || read_u16(end, 8) != read_u16(end, 10) {
+        return Err(invalid("multi-disk archives are not supported"));
+    }
+    let count = read_u16(end, 10);
+    let size = read_u32(end, 12);
+    let offset = read_u32(end, 16);
+    if count == u16::MAX || size == u32::MAX || offset == u32::MAX {
+        return Err(invalid("zip64 archives are not supported"));
+    }
+    if u64::from(offset) + u64::from(size) > len {
+        return Err(invalid("truncated central directory"));
+    }
+    let comment = end[END_OF_CENTRAL_DIRECTORY_LEN..].to_vec();
+    Ok((count, size, offset, comment))
+}
+
+fn key(path: &Path) -> Result<Vec<u8>> {
+    let mut key = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => (),
+            Component::Normal(name) => {
+                let name = name.to_str().ok_or(ErrorKind::InvalidInput)?;
+                if !key.is_empty() {
+                    key.push(b'/');
+                }
+                key.extend_from_slice(name.as_bytes());
+            }
+            Component::ParentDir => return Err(Error::from(ErrorKind::InvalidInput)),
+        }
+    }
+    Ok(key)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+fn to_u16(value: usize) -> Result<u16> {
+    u16::try_from(value).map_err(|_| invalid("value out of range"))
+}
+
+fn to_u32(value: u64) -> Result<u32> {
+    u32::try_from(value).map_err(|_| invalid("archive too large without zip64"))
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn write_u16(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    /// Builds a zip archive storing `entries` uncompressed.
+    fn archive<N>(entries: &[(N, &[u8])]) -> Vec<u8>
+    where
+        N: AsRef<[u8]>,
+    {
+        let mut data = Vec::new();
+        let mut directory = Vec::new();
+        for (name, contents) in entries {
+            let name = name.as_ref();
+            let offset = data.len() as u32;
+            let mut local = [0; LOCAL_HEADER_LEN];
+            write_u32(&mut local, 0, LOCAL_HEADER_SIGNATURE);
+            write_u32(&mut local, 18, contents.len() as u32);
+            write_u32(&mut local, 22, contents.len() as u32);
+            write_u16(&mut local, 26, name.len() as u16);
+            data.extend_from_slice(&local);
+            data.extend_from_slice(name);
+            data.extend_from_slice(contents);
+
+            let mut central = [0; CENTRAL_HEADER_LEN];
+            write_u32(&mut central, 0, CENTRAL_HEADER_SIGNATURE);
+            write_u32(&mut central, 20, contents.len() as u32);
+            write_u32(&mut central, 24, contents.len() as u32);
+            write_u16(&mut central, 28, name.len() as u16);
+            write_u32(&mut central, 42, offset);
+            directory.extend_from_slice(&central);
+            directory.extend_from_slice(name);
+        }
+        let mut end = [0; END_OF_CENTRAL_DIRECTORY_LEN];
+        write_u32(&mut end, 0, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        write_u16(&mut end, 8, entries.len() as u16);
+        write_u16(&mut end, 10, entries.len() as u16);
+        write_u32(&mut end, 12, directory.len() as u32);
+        write_u32(&mut end, 16, data.len() as u32);
+        data.extend_from_slice(&directory);
+        data.extend_from_slice(&end);
+        data
+    }
+
+    fn open(name: &str, contents: &[u8]) -> Result<ZipFileSystem> {
+        let path = env::temp_dir().join(format!("mofu-zip-{}-{name}", process::id()));
+        fs::write(&path, contents)?;
+        let fs = ZipFileSystem::open(&path);
+        fs::remove_file(&path)?;
+        fs
+    }
+
+    #[test]
+    fn empty_and_short_files_are_invalid() {
+        for (name, contents) in [("empty", &b""[..]), ("short", b"PK\x05\x06")] {
+            let error = open(name, contents).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn truncated_archives_are_invalid() {
+        let archive = archive(&[("a.txt", b"a")]);
+        for len in [archive.len() - 1, archive.len() / 2, 21] {
+            assert!(open("truncated", &archive[..len]).is_err());
+        }
+        let mut dangling = archive.clone();
+        let end = dangling.len() - END_OF_CENTRAL_DIRECTORY_LEN;
+        write_u32(&mut dangling[end..], 16, 1 << 20);
+        let error = open("dangling", &dangling).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rename_round_trips() {
+        let archive = archive(&[("docs/a.txt", b"alpha"), ("b.txt", b"beta")]);
+        let mut fs = open("round-trip", &archive).unwrap();
+        assert!(fs.exists(Path::new("/docs")));
+        fs.rename(Path::new("/docs"), Path::new("/text")).unwrap();
+        assert!(!fs.exists(Path::new("/docs/a.txt")));
+
+        let mut repacked = Vec::new();
+        fs.write_to(&mut repacked).unwrap();
+        let fs = open("repacked", &repacked).unwrap();
+        let paths = [PathBuf::from("/text/a.txt"), PathBuf::from("/b.txt")];
+        assert_eq!(fs.paths(), paths);
+        assert_eq!(fs.members()[0], ("text/a.txt".to_owned(), false, 5));
+        assert!(repacked.windows(5).any(|window| window == b"alpha"));
+    }
+
+    #[test]
+    fn rename_rejects_conflicts() {
+        let archive = archive(&[("a", b""), ("b/c", b"")]);
+        let mut fs = open("conflicts", &archive).unwrap();
+        let error = fs.rename(Path::new("/a"), Path::new("/b")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        let error = fs.rename(Path::new("/b"), Path::new("/b/d")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        let error = fs.rename(Path::new("/x"), Path::new("/y")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn only_renamed_names_are_marked_utf8() {
+        // `é` in CP437, which is not valid UTF-8.
+        let cp437 = b"caf\x82.txt";
+        let archive = archive(&[(&cp437[..], b"cafe"), (b"b.txt", b"beta")]);
+        let mut fs = open("cp437", &archive).unwrap();
+        fs.rename(Path::new("/b.txt"), Path::new("/é.txt")).unwrap();
+
+        let mut repacked = Vec::new();
+        fs.write_to(&mut repacked).unwrap();
+        let fs = open("cp437-repacked", &repacked).unwrap();
+        let names = fs.entries.iter().map(|entry| &entry.name[..]);
+        assert!(names.eq([&cp437[..], "é.txt".as_bytes()]));
+        for (entry, utf8) in fs.entries.iter().zip([false, true]) {
+            let local = read_u16(&repacked, entry.local_offset() as usize + 6);
+            assert_eq!(local & FLAG_UTF8 != 0, utf8);
+            assert_eq!(read_u16(&entry.header, 8) & FLAG_UTF8 != 0, utf8);
+        }
+    }
+}
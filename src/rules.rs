@@ -0,0 +1,62 @@
+//! Utilities for deriving new file names from rules.
+
+pub use self::affix::AffixRule;
+pub use self::case::{Case, CaseRule};
+pub use self::conditional::Conditional;
+pub use self::error::Error;
+pub use self::extension::ExtensionRule;
+pub use self::natural::natural_cmp;
+pub use self::pad::PadRule;
+pub use self::pad_numbers::PadNumbers;
+pub use self::pipeline::{Pipeline, Step};
+pub use self::predicate::Predicate;
+pub use self::preview::Preview;
+pub use self::regex::{Captures, Regex};
+pub use self::regex_rule::RegexRule;
+pub use self::renumber::Renumber;
+pub use self::replace::ReplaceRule;
+pub use self::sanitize::{CharClass, SanitizeRule};
+pub use self::slug::SlugRule;
+pub use self::template::Template;
+pub use self::transform::Transform;
+
+pub(crate) use self::date::DateFormat;
+#[cfg(feature = "exif")]
+pub(crate) use self::exif::Exif;
+#[cfg(any(feature = "globset", feature = "ignore"))]
+pub(crate) use self::glob::matches as glob_matches;
+pub(crate) use self::sha256::{digest_file, digest_prefix};
+#[cfg(feature = "audiotags")]
+pub(crate) use self::tags::Tags;
+
+mod affix;
+mod case;
+mod conditional;
+mod counter;
+mod date;
+mod error;
+#[cfg(feature = "exif")]
+mod exif;
+mod expression;
+mod extension;
+mod glob;
+mod natural;
+mod pad;
+mod pad_numbers;
+mod parts;
+mod pipeline;
+mod predicate;
+mod preview;
+mod recipe;
+mod regex;
+mod regex_rule;
+mod renumber;
+mod replace;
+mod sanitize;
+mod sha256;
+mod slug;
+mod subject;
+#[cfg(feature = "audiotags")]
+mod tags;
+mod template;
+mod transform;
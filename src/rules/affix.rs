@@ -0,0 +1,113 @@
+use super::parts::Parts;
+use super::predicate::Predicate;
+use super::recipe::{Fields, Value};
+use super::subject::Subject;
+use std::path::{Path, PathBuf};
+
+/// A rename rule inserting a prefix and a suffix around file stems.
+///
+/// The suffix goes before the extension. Leading dots of hidden files stay
+/// in front of the prefix.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rules::{AffixRule, Predicate};
+/// use std::path::Path;
+/// use std::time::{Duration, SystemTime};
+///
+/// let cutoff = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+/// let rule = AffixRule::new()
+///     .prefix("draft_")
+///     .when(Predicate::All(vec![
+///         Predicate::Extension("docx".to_owned()),
+///         Predicate::ModifiedBefore(cutoff),
+///     ]));
+/// let dst = rule.apply(Path::new("docs/plan.docx"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AffixRule {
+    prefix: String,
+    suffix: String,
+    when: Option<Predicate>,
+}
+
+impl AffixRule {
+    /// Creates a new [`AffixRule`] inserting nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the text to prepend to stems.
+    pub fn prefix<S>(mut self, prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets the text to append to stems.
+    pub fn suffix<S>(mut self, suffix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.suffix = suffix.into();
+        self
+    }
+
+    /// Sets a predicate files must match for the rule to apply.
+    ///
+    /// By default, the rule applies to every file.
+    pub fn when(mut self, predicate: Predicate) -> Self {
+        self.when = Some(predicate);
+        self
+    }
+
+    /// Applies the rule to a path, returning the new path.
+    ///
+    /// Returns [`None`] if the file name is not valid UTF-8, if the predicate
+    /// does not match, or if there is nothing to insert.
+    pub fn apply(&self, path: &Path) -> Option<PathBuf> {
+        self.apply_subject(&Subject::from_path(path))
+    }
+
+    pub(super) fn apply_subject(&self, subject: &Subject) -> Option<PathBuf> {
+        if self.prefix.is_empty() && self.suffix.is_empty() {
+            return None;
+        }
+        let path = subject.path();
+        let original = path.file_name()?.to_str()?;
+        if let Some(predicate) = &self.when
+            && !predicate.eval(subject)
+        {
+            return None;
+        }
+        let parts = Parts::new(original);
+        let stem = format!("{}{}{}", self.prefix, parts.stem, self.suffix);
+        Some(path.with_file_name(parts.join(&stem, parts.extension)))
+    }
+}
+
+impl AffixRule {
+    pub(super) fn to_fields(&self) -> Vec<(String, Value)> {
+        let mut fields = vec![
+            ("prefix".to_owned(), Value::str(&self.prefix)),
+            ("suffix".to_owned(), Value::str(&self.suffix)),
+        ];
+        if let Some(predicate) = &self.when {
+            fields.push(("when".to_owned(), predicate.to_value()));
+        }
+        fields
+    }
+
+    pub(super) fn from_fields(fields: &mut Fields) -> Result<Self, String> {
+        let mut rule = Self::new()
+            .prefix(fields.string("prefix")?.unwrap_or_default())
+            .suffix(fields.string("suffix")?.unwrap_or_default());
+        if let Some(predicate) = fields.take("when")? {
+            rule = rule.when(Predicate::from_value(predicate)?);
+        }
+        Ok(rule)
+    }
+}
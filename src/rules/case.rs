@@ -0,0 +1,191 @@
+use super::parts::Parts;
+use super::recipe::{Fields, Value};
+use std::path::{Path, PathBuf};
+
+/// A letter case to convert names to.
+///
+/// Except for [`Case::Lower`] and [`Case::Upper`], which convert every
+/// letter in place, names are split into words at non-alphanumeric
+/// characters and at case boundaries, so `myHTTPServer-v2 final` consists of
+/// `my`, `HTTP`, `Server`, `v2` and `final`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Case {
+    /// `my http server`
+    Lower,
+    /// `MY HTTP SERVER`
+    Upper,
+    /// `My Http Server`
+    Title,
+    /// `my_http_server`
+    Snake,
+    /// `my-http-server`
+    Kebab,
+    /// `myHttpServer`
+    Camel,
+}
+
+/// A rename rule converting the letter case of file names.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::{Case, CaseRule};
+/// use std::path::Path;
+///
+/// let rule = CaseRule::new(Case::Snake);
+/// let dst = rule.apply(Path::new("docs/Quarterly Report.PDF")).unwrap();
+/// assert_eq!(dst, Path::new("docs/quarterly_report.PDF"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CaseRule {
+    case: Case,
+    preserve_extension: bool,
+}
+
+impl Case {
+    /// Converts a text to this case.
+    pub fn convert(self, text: &str) -> String {
+        match self {
+            Self::Lower => return text.to_lowercase(),
+            Self::Upper => return text.to_uppercase(),
+            _ => {}
+        }
+
+        let separator = match self {
+            Self::Title => " ",
+            Self::Snake => "_",
+            Self::Kebab => "-",
+            _ => "",
+        };
+        let mut output = String::with_capacity(text.len());
+        for (index, word) in words(text).into_iter().enumerate() {
+            if index > 0 {
+                output.push_str(separator);
+            }
+            match self {
+                Self::Camel if index == 0 => output.push_str(&word.to_lowercase()),
+                Self::Title | Self::Camel => {
+                    let mut chars = word.chars();
+                    if let Some(first) = chars.next() {
+                        output.extend(first.to_uppercase());
+                        output.push_str(&chars.as_str().to_lowercase());
+                    }
+                }
+                _ => output.push_str(&word.to_lowercase()),
+            }
+        }
+        output
+    }
+
+    /// Returns the name of the case, such as `snake`.
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Self::Lower => "lower",
+            Self::Upper => "upper",
+            Self::Title => "title",
+            Self::Snake => "snake",
+            Self::Kebab => "kebab",
+            Self::Camel => "camel",
+        }
+    }
+
+    /// Returns the case with the given name, such as `snake`.
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lower" => Some(Self::Lower),
+            "upper" => Some(Self::Upper),
+            "title" => Some(Self::Title),
+            "snake" => Some(Self::Snake),
+            "kebab" => Some(Self::Kebab),
+            "camel" => Some(Self::Camel),
+            _ => None,
+        }
+    }
+}
+
+impl CaseRule {
+    /// Creates a new [`CaseRule`].
+    pub fn new(case: Case) -> Self {
+        Self {
+            case,
+            preserve_extension: true,
+        }
+    }
+
+    /// Sets whether to leave the extension untouched.
+    ///
+    /// Otherwise, the extension is converted on its own, keeping the dot.
+    ///
+    /// Defaults to `true`.
+    pub fn preserve_extension(mut self, preserve_extension: bool) -> Self {
+        self.preserve_extension = preserve_extension;
+        self
+    }
+
+    /// Returns the case names are converted to.
+    #[inline]
+    pub fn case(&self) -> Case {
+        self.case
+    }
+
+    /// Applies the rule to a path, returning the new path.
+    ///
+    /// Leading dots of hidden files are kept. Returns [`None`] if the file
+    /// name is not valid UTF-8, or if it is already in the target case.
+    pub fn apply(&self, path: &Path) -> Option<PathBuf> {
+        let original = path.file_name()?.to_str()?;
+        let parts = Parts::new(original);
+        let stem = self.case.convert(parts.stem);
+        let extension = match parts.extension {
+            Some(extension) if !self.preserve_extension => Some(self.case.convert(extension)),
+            extension => extension.map(str::to_owned),
+        };
+        let converted = parts.join(&stem, extension.as_deref());
+        (converted != original).then(|| path.with_file_name(converted))
+    }
+}
+
+/// Splits a text into words.
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    for run in text.split(|char: char| !char.is_alphanumeric()) {
+        let chars: Vec<(usize, char)> = run.char_indices().collect();
+        let mut start = 0;
+        for (index, &(offset, char)) in chars.iter().enumerate().skip(1) {
+            let previous = chars[index - 1].1;
+            let next = chars.get(index + 1).map(|&(_, char)| char);
+            // Splits `myWord` before `W`, and `HTTPServer` before `S`.
+            let boundary = char.is_uppercase()
+                && (previous.is_lowercase()
+                    || previous.is_numeric()
+                    || (previous.is_uppercase() && next.is_some_and(char::is_lowercase)));
+            if boundary {
+                words.push(&run[start..offset]);
+                start = offset;
+            }
+        }
+        if start < run.len() {
+            words.push(&run[start..]);
+        }
+    }
+    words
+}
+
+impl CaseRule {
+    pub(super) fn to_fields(self) -> Vec<(String, Value)> {
+        vec![
+            ("case".to_owned(), Value::str(self.case.name())),
+            (
+                "preserve_extension".to_owned(),
+                Value::Bool(self.preserve_extension),
+            ),
+        ]
+    }
+
+    pub(super) fn from_fields(fields: &mut Fields) -> Result<Self, String> {
+        let name = fields.require("case")?.into_str()?;
+        let case = Case::from_name(&name).ok_or_else(|| format!("unknown case `{name}`"))?;
+        let preserve_extension = fields.bool("preserve_extension")?.unwrap_or(true);
+        Ok(Self::new(case).preserve_extension(preserve_extension))
+    }
+}
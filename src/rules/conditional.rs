@@ -0,0 +1,104 @@
+use super::pipeline::Pipeline;
+use super::predicate::Predicate;
+use super::recipe::{Fields, Value};
+use super::subject::Subject;
+use std::path::{Path, PathBuf};
+
+/// A rename rule choosing a pipeline by the first predicate a file matches.
+///
+/// Predicates on metadata are evaluated against the original file, even
+/// after earlier rules have planned to rename it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rules::{Case, CaseRule, Conditional, Pipeline, Predicate, ReplaceRule};
+///
+/// // For `*.png`, lowercase; for `*.jpg`, replace spaces; leave the rest.
+/// let rule = Conditional::new()
+///     .when(Predicate::Glob("*.png".to_owned()), CaseRule::new(Case::Lower))
+///     .when(Predicate::Glob("*.jpg".to_owned()), ReplaceRule::new(" ", "_"));
+/// let pipeline = Pipeline::new().then(rule);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Conditional {
+    branches: Vec<(Predicate, Pipeline)>,
+    otherwise: Option<Pipeline>,
+}
+
+impl Conditional {
+    /// Creates a new [`Conditional`] leaving every file alone.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a branch applying `then` to files matching `predicate`, and
+    /// not matching any earlier branch.
+    pub fn when<T>(mut self, predicate: Predicate, then: T) -> Self
+    where
+        T: Into<Pipeline>,
+    {
+        self.branches.push((predicate, then.into()));
+        self
+    }
+
+    /// Sets the pipeline applied to files matching no branch.
+    ///
+    /// By default, such files are left alone.
+    pub fn otherwise<T>(mut self, otherwise: T) -> Self
+    where
+        T: Into<Pipeline>,
+    {
+        self.otherwise = Some(otherwise.into());
+        self
+    }
+
+    /// Applies the rule to a path, returning the new path, or [`None`] if
+    /// the chosen pipeline leaves it unchanged.
+    pub fn apply(&mut self, path: &Path) -> Option<PathBuf> {
+        self.apply_subject(&Subject::from_path(path))
+    }
+
+    pub(super) fn apply_subject(&mut self, subject: &Subject) -> Option<PathBuf> {
+        let pipeline = self
+            .branches
+            .iter_mut()
+            .find(|(predicate, _)| predicate.eval(subject))
+            .map(|(_, pipeline)| pipeline)
+            .or(self.otherwise.as_mut())?;
+        pipeline.apply_subject(subject)
+    }
+
+    pub(super) fn to_fields(&self) -> Vec<(String, Value)> {
+        let branches = self
+            .branches
+            .iter()
+            .map(|(predicate, pipeline)| {
+                Value::Map(vec![
+                    ("when".to_owned(), predicate.to_value()),
+                    ("then".to_owned(), pipeline.to_value()),
+                ])
+            })
+            .collect();
+        let mut fields = vec![("branches".to_owned(), Value::List(branches))];
+        if let Some(otherwise) = &self.otherwise {
+            fields.push(("otherwise".to_owned(), otherwise.to_value()));
+        }
+        fields
+    }
+
+    pub(super) fn from_fields(fields: &mut Fields) -> Result<Self, String> {
+        let mut rule = Self::new();
+        for branch in fields.require("branches")?.into_list()? {
+            let mut branch = Fields::new("branch", branch)?;
+            let predicate = Predicate::from_value(branch.require("when")?)?;
+            let then = Pipeline::from_value(branch.require("then")?)?;
+            branch.finish()?;
+            rule = rule.when(predicate, then);
+        }
+        if let Some(otherwise) = fields.take("otherwise")? {
+            rule = rule.otherwise(Pipeline::from_value(otherwise)?);
+        }
+        Ok(rule)
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// The state of a `{counter}` token, as documented on [`Template`].
+///
+/// [`Template`]: super::Template
+#[derive(Debug, Clone)]
+pub(super) struct Counter {
+    start: u64,
+    step: u64,
+    width: usize,
+    style: Style,
+    per_directory: bool,
+    next: u64,
+    directories: HashMap<PathBuf, u64>,
+}
+
+/// How the numbers of a counter are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    /// `1, 2, ..., 10`
+    Decimal,
+    /// `a, b, ..., z, aa, ab`
+    LowerAlpha,
+    /// `A, B, ..., Z, AA, AB`
+    UpperAlpha,
+    /// `i, ii, iii, iv`
+    LowerRoman,
+    /// `I, II, III, IV`
+    UpperRoman,
+}
+
+impl Counter {
+    /// Parses the options of a counter, returning the byte offset and a
+    /// description of the first invalid option on failure.
+    pub(super) fn parse(options: &str) -> Result<Self, (usize, &'static str)> {
+        let mut counter = Self::default();
+        let mut offset = 0;
+        for option in options.split(',') {
+            let number = |value: &str| value.parse::<u64>().ok();
+            match option.split_once('=') {
+                None if option == "reset" => counter.per_directory = true,
+                None => match number(option) {
+                    Some(width) => counter.width = width as usize,
+                    None => return Err((offset, "unknown counter option")),
+                },
+                Some(("style", value)) => {
+                    counter.style = match value {
+                        "decimal" => Style::Decimal,
+                        "lower-alpha" => Style::LowerAlpha,
+                        "upper-alpha" => Style::UpperAlpha,
+                        "lower-roman" => Style::LowerRoman,
+                        "upper-roman" => Style::UpperRoman,
+                        _ => return Err((offset + "style=".len(), "unknown counter style")),
+                    };
+                }
+                Some((key, value)) => {
+                    let parsed = number(value).ok_or((offset + key.len() + 1, "invalid number"))?;
+                    match key {
+                        "start" => counter.start = parsed,
+                        "step" if parsed == 0 => {
+                            return Err((offset + key.len() + 1, "step must be non-zero"));
+                        }
+                        "step" => counter.step = parsed,
+                        "width" => counter.width = parsed as usize,
+                        _ => return Err((offset, "unknown counter option")),
+                    }
+                }
+            }
+            offset += option.len() + 1;
+        }
+        counter.next = counter.start;
+        Ok(counter)
+    }
+
+    /// Renders the number for an entry within `directory`, and advances the
+    /// counter.
+    pub(super) fn render(&mut self, directory: &Path, output: &mut String) {
+        let next = if self.per_directory {
+            match self.directories.get_mut(directory) {
+                Some(next) => next,
+                None => self
+                    .directories
+                    .entry(directory.to_path_buf())
+                    .or_insert(self.start),
+            }
+        } else {
+            &mut self.next
+        };
+        let number = *next;
+        *next = next.saturating_add(self.step);
+
+        let start = output.len();
+        match self.style {
+            Style::LowerAlpha | Style::UpperAlpha if number > 0 => alpha(number, output),
+            Style::LowerRoman | Style::UpperRoman if (1..4000).contains(&number) => {
+                roman(number, output);
+            }
+            // Numbers the style cannot write fall back to decimal.
+            _ => {
+                // Writing into a `String` never fails.
+                let _ = write!(output, "{number:0width$}", width = self.width);
+                return;
+            }
+        }
+        if matches!(self.style, Style::UpperAlpha | Style::UpperRoman) {
+            output[start..].make_ascii_uppercase();
+        }
+    }
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Self {
+            start: 1,
+            step: 1,
+            width: 0,
+            style: Style::Decimal,
+            per_directory: false,
+            next: 1,
+            directories: HashMap::new(),
+        }
+    }
+}
+
+/// Writes a positive number in bijective base 26, as in spreadsheet columns.
+fn alpha(mut number: u64, output: &mut String) {
+    let mut letters = Vec::new();
+    while number > 0 {
+        number -= 1;
+        letters.push(b'a' + (number % 26) as u8);
+        number /= 26;
+    }
+    output.extend(letters.iter().rev().map(|&letter| char::from(letter)));
+}
+
+/// Writes a number from 1 to 3999 in lowercase roman numerals.
+fn roman(mut number: u64, output: &mut String) {
+    const NUMERALS: [(u64, &str); 13] = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+
+    for (value, numeral) in NUMERALS {
+        while number >= value {
+            output.push_str(numeral);
+            number -= value;
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt::Write;
+use std::mem;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A parsed `strftime`-like format, rendered in UTC.
+///
+/// Supported specifiers are `%Y`, `%y`, `%m`, `%d`, `%e`, `%j`, `%H`, `%M`,
+/// `%S`, `%s`, `%F` (`%Y-%m-%d`), `%T` (`%H:%M:%S`) and `%%`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DateFormat {
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    Literal(String),
+    Specifier(char),
+}
+
+/// A broken-down UTC time.
+struct Civil {
+    year: i64,
+    month: u32,
+    day: u32,
+    ordinal: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    timestamp: i64,
+}
+
+impl DateFormat {
+    /// Parses a format, returning the byte offset and a description of the
+    /// first invalid specifier on failure.
+    pub(crate) fn parse(format: &str) -> Result<Self, (usize, &'static str)> {
+        let mut items = Vec::new();
+        let mut literal = String::new();
+        let mut chars = format.char_indices();
+        while let Some((offset, char)) = chars.next() {
+            if char != '%' {
+                literal.push(char);
+                continue;
+            }
+            match chars.next() {
+                Some((_, '%')) => literal.push('%'),
+                Some((_, 'F')) => {
+                    flush(&mut literal, &mut items);
+                    items.extend(['Y', '-', 'm', '-', 'd'].map(item));
+                }
+                Some((_, 'T')) => {
+                    flush(&mut literal, &mut items);
+                    items.extend(['H', ':', 'M', ':', 'S'].map(item));
+                }
+                Some((_, char @ ('Y' | 'y' | 'm' | 'd' | 'e' | 'j' | 'H' | 'M' | 'S' | 's'))) => {
+                    flush(&mut literal, &mut items);
+                    items.push(Item::Specifier(char));
+                }
+                Some(_) => return Err((offset, "unsupported date specifier")),
+                None => return Err((offset, "trailing `%`")),
+            }
+        }
+        flush(&mut literal, &mut items);
+        Ok(Self { items })
+    }
+
+    /// Renders a time into `output`.
+    pub(crate) fn render(&self, time: SystemTime, output: &mut String) {
+        let civil = Civil::new(time);
+        for item in &self.items {
+            // Writing into a `String` never fails.
+            let _ = match item {
+                Item::Literal(literal) => output.write_str(literal),
+                Item::Specifier('Y') => write!(output, "{:04}", civil.year),
+                Item::Specifier('y') => write!(output, "{:02}", civil.year.rem_euclid(100)),
+                Item::Specifier('m') => write!(output, "{:02}", civil.month),
+                Item::Specifier('d') => write!(output, "{:02}", civil.day),
+                Item::Specifier('e') => write!(output, "{:>2}", civil.day),
+                Item::Specifier('j') => write!(output, "{:03}", civil.ordinal),
+                Item::Specifier('H') => write!(output, "{:02}", civil.hour),
+                Item::Specifier('M') => write!(output, "{:02}", civil.minute),
+                Item::Specifier('S') => write!(output, "{:02}", civil.second),
+                Item::Specifier('s') => write!(output, "{}", civil.timestamp),
+                Item::Specifier(char) => output.write_char(*char),
+            };
+        }
+    }
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        Self {
+            items: ['Y', '-', 'm', '-', 'd'].map(item).to_vec(),
+        }
+    }
+}
+
+impl Civil {
+    fn new(time: SystemTime) -> Self {
+        let timestamp = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(error) => -(error.duration().as_nanos().div_ceil(1_000_000_000) as i64),
+        };
+        let days = timestamp.div_euclid(86_400);
+        let seconds = timestamp.rem_euclid(86_400) as u32;
+
+        // Converts days since the epoch to a proleptic Gregorian date, using
+        // Howard Hinnant's `civil_from_days`.
+        let shifted = days + 719_468;
+        let era = shifted.div_euclid(146_097);
+        let day_of_era = shifted.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        const CUMULATIVE: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+        let ordinal = CUMULATIVE[month as usize - 1] + day + u32::from(leap && month > 2);
+
+        Self {
+            year,
+            month,
+            day,
+            ordinal,
+            hour: seconds / 3600,
+            minute: seconds / 60 % 60,
+            second: seconds % 60,
+            timestamp,
+        }
+    }
+}
+
+/// Returns the time at a UTC date and time of day, or [`None`] if there is
+/// no such date or time of day.
+#[cfg(feature = "exif")]
+pub(super) fn from_civil(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> Option<SystemTime> {
+    use std::time::Duration;
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let length = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if !(1..=length).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    // Converts a proleptic Gregorian date to days since the epoch, using
+    // Howard Hinnant's `days_from_civil`.
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = i64::from((153 * ((month + 9) % 12) + 2) / 5 + day - 1);
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era.checked_mul(146_097)? + day_of_era - 719_468;
+
+    let seconds = days.checked_mul(86_400)? + i64::from(hour * 3600 + minute * 60 + second);
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+    }
+}
+
+fn item(char: char) -> Item {
+    if char.is_ascii_alphabetic() {
+        Item::Specifier(char)
+    } else {
+        Item::Literal(char.to_string())
+    }
+}
+
+fn flush(literal: &mut String, items: &mut Vec<Item>) {
+    if !literal.is_empty() {
+        items.push(Item::Literal(mem::take(literal)));
+    }
+}
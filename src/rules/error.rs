@@ -0,0 +1,59 @@
+use std::error;
+use std::fmt;
+
+/// A enum for error handling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    InvalidRegex {
+        pattern: String,
+        offset: usize,
+        message: String,
+    },
+
+    InvalidTemplate {
+        template: String,
+        offset: usize,
+        message: String,
+    },
+
+    InvalidExpression {
+        expression: String,
+        offset: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const INDENT: &str = "  ";
+
+        let (kind, source, offset, message) = match self {
+            Self::InvalidRegex {
+                pattern,
+                offset,
+                message,
+            } => ("regex", pattern, offset, message),
+
+            Self::InvalidTemplate {
+                template,
+                offset,
+                message,
+            } => ("template", template, offset, message),
+
+            Self::InvalidExpression {
+                expression,
+                offset,
+                message,
+            } => ("expression", expression, offset, message),
+        };
+
+        let column = source.get(..*offset).map_or(0, |head| head.chars().count());
+        writeln!(f, "invalid {kind}: {message}")?;
+        writeln!(f, "{INDENT}{source}")?;
+        writeln!(f, "{INDENT}{:>width$}", "^", width = column + 1)?;
+
+        Ok(())
+    }
+}
+
+impl error::Error for Error {}
@@ -0,0 +1,492 @@
+use super::date::{self, DateFormat};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Metadata read from the EXIF block of an image.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Exif {
+    pub(crate) datetime: Option<SystemTime>,
+    pub(super) make: Option<String>,
+    pub(super) model: Option<String>,
+    pub(super) lens: Option<String>,
+    pub(super) iso: Option<u32>,
+}
+
+/// A field of [`Exif`] a template token refers to.
+#[derive(Debug, Clone)]
+pub(super) enum Field {
+    DateTime(DateFormat),
+    Make,
+    Camera,
+    Lens,
+    Iso,
+}
+
+/// Only the head of a file is searched for metadata, which is where JPEG
+/// and PNG files, as well as the raw formats of common cameras, keep it.
+const LIMIT: u64 = 256 * 1024;
+
+const MAKE: u16 = 0x010F;
+const MODEL: u16 = 0x0110;
+const DATE_TIME: u16 = 0x0132;
+const EXIF_IFD: u16 = 0x8769;
+const ISO: u16 = 0x8827;
+const DATE_TIME_ORIGINAL: u16 = 0x9003;
+const LENS_MODEL: u16 = 0xA434;
+
+impl Exif {
+    /// Reads the metadata of a JPEG, PNG, WebP or TIFF-based raw image, or
+    /// returns [`None`] if it cannot be read or has none.
+    pub(crate) fn read(path: &Path) -> Option<Self> {
+        let mut buffer = Vec::new();
+        File::open(path)
+            .ok()?
+            .take(LIMIT)
+            .read_to_end(&mut buffer)
+            .ok()?;
+        Self::parse(locate(&buffer)?)
+    }
+
+    /// Parses a TIFF structure, as embedded in images.
+    fn parse(data: &[u8]) -> Option<Self> {
+        let tiff = Tiff::new(data)?;
+        let mut exif = Self::default();
+        let mut datetime = None;
+        let mut exif_ifd = None;
+        for entry in tiff.entries(tiff.u32(4)? as usize)? {
+            match entry.tag {
+                MAKE => exif.make = tiff.string(&entry),
+                MODEL => exif.model = tiff.string(&entry),
+                DATE_TIME => datetime = tiff.string(&entry),
+                EXIF_IFD => exif_ifd = tiff.number(&entry),
+                _ => {}
+            }
+        }
+        let exif_entries = exif_ifd.and_then(|offset| tiff.entries(offset as usize));
+        for entry in exif_entries.unwrap_or_default() {
+            match entry.tag {
+                DATE_TIME_ORIGINAL => datetime = tiff.string(&entry).or(datetime),
+                ISO => exif.iso = tiff.number(&entry),
+                LENS_MODEL => exif.lens = tiff.string(&entry),
+                _ => {}
+            }
+        }
+        exif.datetime = datetime.as_deref().and_then(parse_datetime);
+        Some(exif)
+    }
+}
+
+impl Field {
+    /// Renders the field into `output`, or nothing if it is missing.
+    ///
+    /// Path separators in text fields are replaced with `_`.
+    pub(super) fn render(&self, exif: &Exif, output: &mut String) {
+        let text = match self {
+            Self::DateTime(format) => {
+                if let Some(datetime) = exif.datetime {
+                    format.render(datetime, output);
+                }
+                return;
+            }
+            Self::Iso => {
+                if let Some(iso) = exif.iso {
+                    output.push_str(&iso.to_string());
+                }
+                return;
+            }
+            Self::Make => &exif.make,
+            Self::Camera => &exif.model,
+            Self::Lens => &exif.lens,
+        };
+        if let Some(text) = text {
+            output.extend(text.chars().map(|char| match char {
+                '/' | '\\' => '_',
+                char => char,
+            }));
+        }
+    }
+}
+
+/// Returns the TIFF structure holding the metadata of an image.
+fn locate(data: &[u8]) -> Option<&[u8]> {
+    match data {
+        [0xFF, 0xD8, ..] => jpeg(data),
+        [b'I', b'I', ..] | [b'M', b'M', ..] => Some(data),
+        [0x89, b'P', b'N', b'G', ..] => png(data),
+        [
+            b'R',
+            b'I',
+            b'F',
+            b'F',
+            _,
+            _,
+            _,
+            _,
+            b'W',
+            b'E',
+            b'B',
+            b'P',
+            ..,
+        ] => webp(data),
+        _ => None,
+    }
+}
+
+/// Finds the `APP1` segment starting with `Exif\0\0`.
+fn jpeg(data: &[u8]) -> Option<&[u8]> {
+    let mut offset = 2;
+    loop {
+        let marker = match data.get(offset..offset + 2)? {
+            [0xFF, marker] => *marker,
+            _ => return None,
+        };
+        match marker {
+            // Fill bytes may precede a marker.
+            0xFF => {
+                offset += 1;
+                continue;
+            }
+            // Markers without a segment.
+            0x01 | 0xD0..=0xD8 => {
+                offset += 2;
+                continue;
+            }
+            // The image data starts, and no metadata follows.
+            0xD9 | 0xDA => return None,
+            _ => {}
+        }
+        let length = usize::from(u16::from_be_bytes(
+            data.get(offset + 2..offset + 4)?.try_into().ok()?,
+        ));
+        let segment = data.get(offset + 4..offset + 2 + length)?;
+        if marker == 0xE1
+            && let Some(tiff) = segment.strip_prefix(b"Exif\0\0")
+        {
+            return Some(tiff);
+        }
+        offset += 2 + length;
+    }
+}
+
+/// Finds the `eXIf` chunk.
+fn png(data: &[u8]) -> Option<&[u8]> {
+    let mut offset = 8;
+    loop {
+        let length = u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize;
+        let kind = data.get(offset + 4..offset + 8)?;
+        let chunk = data.get(offset + 8..(offset + 8).checked_add(length)?)?;
+        match kind {
+            b"eXIf" => return Some(chunk),
+            b"IEND" => return None,
+            _ => offset += 12 + length,
+        }
+    }
+}
+
+/// Finds the `EXIF` chunk, which some writers start with `Exif\0\0` as in
+/// JPEG files.
+fn webp(data: &[u8]) -> Option<&[u8]> {
+    let mut offset = 12;
+    loop {
+        let kind = data.get(offset..offset + 4)?;
+        let length =
+            u32::from_le_bytes(data.get(offset + 4..offset + 8)?.try_into().ok()?) as usize;
+        let chunk = data.get(offset + 8..(offset + 8).checked_add(length)?)?;
+        if kind == b"EXIF" {
+            return Some(chunk.strip_prefix(b"Exif\0\0").unwrap_or(chunk));
+        }
+        // Chunks are padded to an even length.
+        offset += 8 + length + (length & 1);
+    }
+}
+
+/// Parses a date and time such as `2024:05:01 12:34:56`.
+///
+/// EXIF records local times without a time zone, so the time is taken as
+/// UTC, which renders it as recorded.
+fn parse_datetime(text: &str) -> Option<SystemTime> {
+    let mut numbers = text
+        .split(|char: char| !char.is_ascii_digit())
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse::<u32>().ok());
+    let mut next = || numbers.next().flatten();
+    let (year, month, day) = (next()?, next()?, next()?);
+    let (hour, minute, second) = (next()?, next()?, next()?);
+    date::from_civil(i64::from(year), month, day, hour, minute, second)
+}
+
+#[derive(Debug)]
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+#[derive(Debug)]
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    /// The offset of the value, which is stored in the entry itself if it
+    /// fits in 4 bytes.
+    offset: usize,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.get(..2)? {
+            b"II" => false,
+            b"MM" => true,
+            _ => return None,
+        };
+        Some(Self { data, big_endian })
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        if self.big_endian {
+            Some(u16::from_be_bytes(bytes))
+        } else {
+            Some(u16::from_le_bytes(bytes))
+        }
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        if self.big_endian {
+            Some(u32::from_be_bytes(bytes))
+        } else {
+            Some(u32::from_le_bytes(bytes))
+        }
+    }
+
+    /// Returns the entries of the image file directory at `offset`.
+    fn entries(&self, offset: usize) -> Option<Vec<Entry>> {
+        let count = usize::from(self.u16(offset)?);
+        let mut entries = Vec::with_capacity(count);
+        for index in 0..count {
+            let start = offset + 2 + index * 12;
+            let tag = self.u16(start)?;
+            let kind = self.u16(start + 2)?;
+            let count = self.u32(start + 4)?;
+            let size = match kind {
+                1 | 2 | 6 | 7 => 1,
+                3 | 8 => 2,
+                4 | 9 | 11 => 4,
+                5 | 10 | 12 => 8,
+                // Unknown types are skipped.
+                _ => continue,
+            };
+            let offset = match (count as usize).checked_mul(size)? {
+                0..=4 => start + 8,
+                _ => self.u32(start + 8)? as usize,
+            };
+            entries.push(Entry {
+                tag,
+                kind,
+                count,
+                offset,
+            });
+        }
+        Some(entries)
+    }
+
+    /// Returns the value of an ASCII entry, trimmed, or [`None`] if empty.
+    fn string(&self, entry: &Entry) -> Option<String> {
+        if !matches!(entry.kind, 2 | 7) {
+            return None;
+        }
+        let end = entry.offset.checked_add(entry.count as usize)?;
+        let bytes = self.data.get(entry.offset..end)?;
+        let bytes = bytes.split(|&byte| byte == 0).next()?;
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_owned())
+    }
+
+    /// Returns the first value of a short or long entry.
+    fn number(&self, entry: &Entry) -> Option<u32> {
+        if entry.count == 0 {
+            return None;
+        }
+        match entry.kind {
+            3 => self.u16(entry.offset).map(u32::from),
+            4 => self.u32(entry.offset),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Builds the entries of an image file directory at `at` in a
+    /// little-endian TIFF structure, storing values longer than 4 bytes
+    /// after it, followed by the offset of the next one.
+    fn ifd(entries: &[(u16, u16, u32, &[u8])], at: usize) -> Vec<u8> {
+        let mut values_at = at + 2 + entries.len() * 12 + 4;
+        let mut directory = (entries.len() as u16).to_le_bytes().to_vec();
+        let mut values = Vec::new();
+        for (tag, kind, count, value) in entries {
+            directory.extend_from_slice(&tag.to_le_bytes());
+            directory.extend_from_slice(&kind.to_le_bytes());
+            directory.extend_from_slice(&count.to_le_bytes());
+            if value.len() <= 4 {
+                let mut inline = [0; 4];
+                inline[..value.len()].copy_from_slice(value);
+                directory.extend_from_slice(&inline);
+            } else {
+                directory.extend_from_slice(&(values_at as u32).to_le_bytes());
+                values.extend_from_slice(value);
+                values_at += value.len();
+            }
+        }
+        directory.extend_from_slice(&[0; 4]);
+        directory.extend_from_slice(&values);
+        directory
+    }
+
+    fn ascii(text: &str) -> (u32, Vec<u8>) {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(0);
+        (bytes.len() as u32, bytes)
+    }
+
+    fn tiff() -> Vec<u8> {
+        let (make_len, make) = ascii("Maker");
+        let (model_len, model) = ascii("Cam/One");
+        let (date_len, date) = ascii("2000:01:01 00:00:00");
+        let (original_len, original) = ascii("2024:05:01 12:34:56");
+        let (lens_len, lens) = ascii("Lens 50mm");
+
+        let mut data = b"II*\0\x08\0\0\0".to_vec();
+        // The EXIF directory follows the first one, of 4 entries.
+        let first_len = 2 + 4 * 12 + 4 + make.len() + model.len() + date.len();
+        let exif_at = 8 + first_len;
+        let pointer = (exif_at as u32).to_le_bytes();
+        data.extend(ifd(
+            &[
+                (MAKE, 2, make_len, &make),
+                (MODEL, 2, model_len, &model),
+                (DATE_TIME, 2, date_len, &date),
+                (EXIF_IFD, 4, 1, &pointer),
+            ],
+            8,
+        ));
+        assert_eq!(data.len(), exif_at);
+        data.extend(ifd(
+            &[
+                (ISO, 3, 1, &400u16.to_le_bytes()),
+                (DATE_TIME_ORIGINAL, 2, original_len, &original),
+                (LENS_MODEL, 2, lens_len, &lens),
+            ],
+            exif_at,
+        ));
+        data
+    }
+
+    fn expected() -> Exif {
+        Exif {
+            datetime: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1714566896)),
+            make: Some("Maker".to_owned()),
+            model: Some("Cam/One".to_owned()),
+            lens: Some("Lens 50mm".to_owned()),
+            iso: Some(400),
+        }
+    }
+
+    #[test]
+    fn tiff_structures_are_parsed() {
+        let tiff = tiff();
+        assert_eq!(Exif::parse(locate(&tiff).unwrap()), Some(expected()));
+
+        // Entries pointing past the end are left out, so the time falls
+        // back to that of the file.
+        let exif = Exif::parse(&tiff[..tiff.len() - 20]).unwrap();
+        assert_eq!(exif.make.as_deref(), Some("Maker"));
+        assert_eq!(exif.iso, Some(400));
+        assert_eq!(exif.lens, None);
+        let fallback = SystemTime::UNIX_EPOCH + Duration::from_secs(946684800);
+        assert_eq!(exif.datetime, Some(fallback));
+    }
+
+    #[test]
+    fn empty_and_truncated_data_have_no_metadata() {
+        assert_eq!(locate(b""), None);
+        assert_eq!(locate(b"GIF89a"), None);
+        assert_eq!(Exif::parse(b""), None);
+        assert_eq!(Exif::parse(b"II*\0"), None);
+        assert_eq!(Exif::parse(b"II*\0\xff\xff\xff\xff"), None);
+        let tiff = tiff();
+        for len in [0, 2, 9, 20] {
+            assert_eq!(Exif::parse(&tiff[..len]), None);
+        }
+    }
+
+    #[test]
+    fn containers_are_searched() {
+        let tiff = tiff();
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 4, 0, 0, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&(tiff.len() as u16 + 8).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(&tiff);
+        jpeg.extend_from_slice(&[0xFF, 0xDA]);
+        assert_eq!(locate(&jpeg), Some(tiff.as_slice()));
+        assert_eq!(locate(&jpeg[..20]), None);
+        assert_eq!(locate(&[0xFF, 0xD8, 0xFF, 0xDA]), None);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend_from_slice(&0u32.to_be_bytes());
+        png.extend_from_slice(b"sRGB\0\0\0\0");
+        png.extend_from_slice(&(tiff.len() as u32).to_be_bytes());
+        png.extend_from_slice(b"eXIf");
+        png.extend_from_slice(&tiff);
+        assert_eq!(locate(&png), Some(tiff.as_slice()));
+        assert_eq!(locate(&png[..png.len() - 1]), None);
+
+        let mut webp = b"RIFF\0\0\0\0WEBP".to_vec();
+        webp.extend_from_slice(b"VP8X");
+        webp.extend_from_slice(&1u32.to_le_bytes());
+        webp.extend_from_slice(&[0, 0]);
+        webp.extend_from_slice(b"EXIF");
+        webp.extend_from_slice(&(tiff.len() as u32 + 6).to_le_bytes());
+        webp.extend_from_slice(b"Exif\0\0");
+        webp.extend_from_slice(&tiff);
+        assert_eq!(locate(&webp), Some(tiff.as_slice()));
+        assert_eq!(locate(&webp[..webp.len() - 1]), None);
+    }
+
+    #[test]
+    fn datetimes_are_parsed() {
+        let expected = SystemTime::UNIX_EPOCH + Duration::from_secs(1714566896);
+        assert_eq!(parse_datetime("2024:05:01 12:34:56"), Some(expected));
+        assert_eq!(parse_datetime("2024-05-01T12:34:56"), Some(expected));
+        assert_eq!(parse_datetime("2024:05:01"), None);
+        assert_eq!(parse_datetime("    :  :     :  :  "), None);
+    }
+
+    #[test]
+    fn fields_are_rendered() {
+        let exif = expected();
+        let mut output = String::new();
+        for field in [
+            Field::DateTime(DateFormat::default()),
+            Field::Make,
+            Field::Camera,
+            Field::Lens,
+            Field::Iso,
+        ] {
+            field.render(&exif, &mut output);
+            output.push(' ');
+        }
+        assert_eq!(output, "2024-05-01 Maker Cam_One Lens 50mm 400 ");
+
+        let mut output = String::new();
+        Field::Iso.render(&Exif::default(), &mut output);
+        assert_eq!(output, "");
+    }
+}
@@ -0,0 +1,353 @@
+use super::affix::AffixRule;
+use super::case::{Case, CaseRule};
+use super::error::Error;
+use super::extension::ExtensionRule;
+use super::pad::PadRule;
+use super::pipeline::{Pipeline, Step};
+use super::regex_rule::RegexRule;
+use super::replace::ReplaceRule;
+use super::sanitize::{CharClass, SanitizeRule};
+use super::slug::SlugRule;
+use super::template::Template;
+
+/// Parses an expression into a pipeline, as described in
+/// [`Pipeline::parse`].
+pub(super) fn parse(expression: &str) -> Result<Pipeline, Error> {
+    let mut parser = Parser {
+        expression,
+        offset: 0,
+    };
+    let mut pipeline = Pipeline::new();
+    loop {
+        parser.skip_whitespace();
+        pipeline = pipeline.then(parser.step()?);
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => return Ok(pipeline),
+            Some('|') => parser.offset += 1,
+            Some(_) => return Err(parser.error(parser.offset, "expected `|`")),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Parser<'a> {
+    expression: &'a str,
+    offset: usize,
+}
+
+#[derive(Debug)]
+struct Argument {
+    text: String,
+    offset: usize,
+    /// Whether the text is a verbatim slice of the expression, so offsets
+    /// into it can be mapped back exactly.
+    verbatim: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn step(&mut self) -> Result<Step, Error> {
+        let start = self.offset;
+        let mut chars = self.rest().chars();
+        if chars.next() == Some('s')
+            && let Some(delimiter) = chars.next()
+            && is_delimiter(delimiter)
+        {
+            self.offset += 1 + delimiter.len_utf8();
+            return self.substitution(start, delimiter);
+        }
+
+        let name = self.word();
+        if name.is_empty() {
+            return Err(self.error(start, "expected a step"));
+        }
+        let arguments = if self.peek() == Some('(') {
+            self.arguments()?
+        } else {
+            Vec::new()
+        };
+        self.call(start, name, arguments)
+    }
+
+    fn substitution(&mut self, start: usize, delimiter: char) -> Result<Step, Error> {
+        let pattern_offset = self.offset;
+        let pattern = self.until(start, delimiter)?;
+        let replacement = self.until(start, delimiter)?;
+        let replacement = replacement.replace(&format!("\\{delimiter}"), &delimiter.to_string());
+
+        let (mut all, mut insensitive, mut full_path) = (false, false, false);
+        let flags_offset = self.offset;
+        for (index, flag) in self.word().char_indices() {
+            match flag {
+                'g' => all = true,
+                'i' => insensitive = true,
+                'p' => full_path = true,
+                _ => return Err(self.error(flags_offset + index, "unknown flag")),
+            }
+        }
+
+        // Case folding is enabled through the pattern itself, and offsets of
+        // errors are shifted back past the prefix.
+        const INSENSITIVE: &str = "(?i)";
+        let (pattern, shift) = if insensitive {
+            (format!("{INSENSITIVE}{pattern}"), INSENSITIVE.len())
+        } else {
+            (pattern.to_owned(), 0)
+        };
+        match RegexRule::new(&pattern, replacement) {
+            Ok(rule) => Ok(rule.all(all).full_path(full_path).into()),
+            Err(Error::InvalidRegex {
+                offset, message, ..
+            }) => Err(self.error(pattern_offset + offset.saturating_sub(shift), &message)),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn call(&self, start: usize, name: &str, arguments: Vec<Argument>) -> Result<Step, Error> {
+        if let Some(case) = Case::from_name(name) {
+            self.arity(start, name, &arguments, 0, 0)?;
+            return Ok(CaseRule::new(case).into());
+        }
+
+        let mut arguments = arguments.into_iter();
+        let step = match name {
+            "ext" => {
+                self.arity(start, name, arguments.as_slice(), 1, 2)?;
+                let first = arguments.next().unwrap();
+                match (first.text.as_str(), arguments.next()) {
+                    (from, Some(to)) => ExtensionRule::new().replace(from, to.text),
+                    ("lower", None) => ExtensionRule::new().lowercase(true),
+                    ("strip", None) => ExtensionRule::new().strip(true),
+                    _ => return Err(self.error(first.offset, "expected `lower` or `strip`")),
+                }
+                .into()
+            }
+            "pad" => {
+                self.arity(start, name, arguments.as_slice(), 1, 1)?;
+                let width = self.number(arguments.next().unwrap())?;
+                PadRule::new(width).into()
+            }
+            "prefix" => {
+                self.arity(start, name, arguments.as_slice(), 1, 1)?;
+                AffixRule::new()
+                    .prefix(arguments.next().unwrap().text)
+                    .into()
+            }
+            "replace" => {
+                self.arity(start, name, arguments.as_slice(), 2, 3)?;
+                let find = arguments.next().unwrap().text;
+                let with = arguments.next().unwrap().text;
+                let count = match arguments.next() {
+                    Some(count) => self.number(count)?,
+                    None => 0,
+                };
+                ReplaceRule::new(find, with).count(count).into()
+            }
+            "sanitize" => {
+                let mut rule = SanitizeRule::new();
+                if arguments.as_slice().is_empty() {
+                    rule = rule
+                        .class(CharClass::Whitespace)
+                        .class(CharClass::Control)
+                        .class(CharClass::Reserved);
+                }
+                for argument in arguments {
+                    match CharClass::from_name(&argument.text) {
+                        Some(class) => rule = rule.class(class),
+                        None => return Err(self.error(argument.offset, "unknown character class")),
+                    }
+                }
+                rule.into()
+            }
+            "slug" => {
+                self.arity(start, name, arguments.as_slice(), 0, 1)?;
+                let mut rule = SlugRule::new();
+                if let Some(separator) = arguments.next() {
+                    let mut chars = separator.text.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(char), None) => rule = rule.separator(char),
+                        _ => {
+                            return Err(self.error(separator.offset, "expected a single character"));
+                        }
+                    }
+                }
+                rule.into()
+            }
+            "suffix" => {
+                self.arity(start, name, arguments.as_slice(), 1, 1)?;
+                AffixRule::new()
+                    .suffix(arguments.next().unwrap().text)
+                    .into()
+            }
+            "template" => {
+                self.arity(start, name, arguments.as_slice(), 1, 1)?;
+                let template = arguments.next().unwrap();
+                match Template::new(&template.text) {
+                    Ok(template) => template.into(),
+                    Err(Error::InvalidTemplate {
+                        offset, message, ..
+                    }) => {
+                        let offset = if template.verbatim {
+                            template.offset + offset
+                        } else {
+                            template.offset
+                        };
+                        return Err(self.error(offset, &message));
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+            _ => return Err(self.error(start, "unknown step")),
+        };
+        Ok(step)
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Argument>, Error> {
+        let open = self.offset;
+        self.offset += 1;
+        let mut arguments = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(')') {
+            self.offset += 1;
+            return Ok(arguments);
+        }
+        loop {
+            self.skip_whitespace();
+            arguments.push(self.argument()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.offset += 1,
+                Some(')') => {
+                    self.offset += 1;
+                    return Ok(arguments);
+                }
+                Some(_) => return Err(self.error(self.offset, "expected `,` or `)`")),
+                None => return Err(self.error(open, "unclosed `(`")),
+            }
+        }
+    }
+
+    fn argument(&mut self) -> Result<Argument, Error> {
+        let start = self.offset;
+        let Some(quote @ ('"' | '\'')) = self.peek() else {
+            let word = self.word();
+            if word.is_empty() {
+                return Err(self.error(start, "expected an argument"));
+            }
+            return Ok(Argument {
+                text: word.to_owned(),
+                offset: start,
+                verbatim: true,
+            });
+        };
+
+        self.offset += 1;
+        let mut text = String::new();
+        let mut verbatim = true;
+        let mut chars = self.rest().char_indices();
+        while let Some((index, char)) = chars.next() {
+            match char {
+                '\\' => {
+                    let Some((_, char)) = chars.next() else {
+                        break;
+                    };
+                    text.push(char);
+                    verbatim = false;
+                }
+                char if char == quote => {
+                    self.offset += index + 1;
+                    return Ok(Argument {
+                        text,
+                        offset: start + 1,
+                        verbatim,
+                    });
+                }
+                char => text.push(char),
+            }
+        }
+        Err(self.error(start, "unclosed string"))
+    }
+
+    /// Takes the text up to an unescaped delimiter, and the delimiter.
+    fn until(&mut self, start: usize, delimiter: char) -> Result<&'a str, Error> {
+        let rest = self.rest();
+        let mut chars = rest.char_indices();
+        while let Some((index, char)) = chars.next() {
+            if char == '\\' {
+                chars.next();
+            } else if char == delimiter {
+                self.offset += index + delimiter.len_utf8();
+                return Ok(&rest[..index]);
+            }
+        }
+        Err(self.error(start, "unterminated substitution"))
+    }
+
+    fn arity(
+        &self,
+        start: usize,
+        name: &str,
+        arguments: &[Argument],
+        min: usize,
+        max: usize,
+    ) -> Result<(), Error> {
+        if (min..=max).contains(&arguments.len()) {
+            return Ok(());
+        }
+        let expected = match (min, max) {
+            (0, 0) => "no arguments".to_owned(),
+            (1, 1) => "1 argument".to_owned(),
+            (min, max) if min == max => format!("{min} arguments"),
+            (min, max) => format!("{min} to {max} arguments"),
+        };
+        Err(self.error(start, &format!("`{name}` takes {expected}")))
+    }
+
+    fn number(&self, argument: Argument) -> Result<usize, Error> {
+        argument
+            .text
+            .parse()
+            .map_err(|_| self.error(argument.offset, "expected a number"))
+    }
+
+    /// Takes a run of letters, digits, `_`, `-` and `.`.
+    fn word(&mut self) -> &'a str {
+        let rest = self.rest();
+        let end = rest
+            .find(|char: char| !(char.is_alphanumeric() || matches!(char, '_' | '-' | '.')))
+            .unwrap_or(rest.len());
+        self.offset += end;
+        &rest[..end]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    #[inline]
+    fn rest(&self) -> &'a str {
+        &self.expression[self.offset..]
+    }
+
+    fn error(&self, offset: usize, message: &str) -> Error {
+        Error::InvalidExpression {
+            expression: self.expression.to_owned(),
+            offset,
+            message: message.to_owned(),
+        }
+    }
+}
+
+/// Returns `true` if `char` may delimit the parts of a substitution, as `/`
+/// does in `s/a/b/`.
+fn is_delimiter(char: char) -> bool {
+    !(char.is_alphanumeric()
+        || char.is_whitespace()
+        || matches!(char, '_' | '-' | '.' | '(' | '\\'))
+}
@@ -0,0 +1,161 @@
+use super::parts::Parts;
+use super::recipe::{Fields, Value};
+use crate::rename::{self, RenameQueue};
+use std::path::{Path, PathBuf};
+
+/// A rename rule normalizing file extensions.
+///
+/// Compound extensions such as `tar.gz` are treated as a whole, so they can
+/// be lowercased or replaced without touching the stem, and are never split.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::ExtensionRule;
+/// use std::path::Path;
+///
+/// let rule = ExtensionRule::new()
+///     .lowercase(true)
+///     .replace("jpeg", "jpg")
+///     .replace("tar.gz", "tgz");
+/// assert_eq!(rule.apply(Path::new("IMG.JPEG")).unwrap(), Path::new("IMG.jpg"));
+/// assert_eq!(rule.apply(Path::new("src.v2.TAR.GZ")).unwrap(), Path::new("src.v2.tgz"));
+/// assert_eq!(rule.apply(Path::new("Notes.TXT")).unwrap(), Path::new("Notes.txt"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ExtensionRule {
+    lowercase: bool,
+    replacements: Vec<(String, String)>,
+    strip: bool,
+}
+
+impl ExtensionRule {
+    /// Creates a new [`ExtensionRule`] changing nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to lowercase extensions.
+    ///
+    /// Defaults to `false`.
+    pub fn lowercase(mut self, lowercase: bool) -> Self {
+        self.lowercase = lowercase;
+        self
+    }
+
+    /// Replaces the extension `from`, ignoring ASCII case, with `to`, or
+    /// strips it if `to` is empty.
+    ///
+    /// Extensions are given without the leading dot. The first matching
+    /// replacement wins, and replaced extensions are not lowercased.
+    pub fn replace<F, T>(mut self, from: F, to: T) -> Self
+    where
+        F: Into<String>,
+        T: Into<String>,
+    {
+        self.replacements.push((from.into(), to.into()));
+        self
+    }
+
+    /// Sets whether to strip every extension.
+    ///
+    /// Defaults to `false`.
+    pub fn strip(mut self, strip: bool) -> Self {
+        self.strip = strip;
+        self
+    }
+
+    /// Applies the rule to a path, returning the new path.
+    ///
+    /// Returns [`None`] if the file name is not valid UTF-8, has no
+    /// extension, or is left unchanged.
+    pub fn apply(&self, path: &Path) -> Option<PathBuf> {
+        let original = path.file_name()?.to_str()?;
+        let parts = Parts::compound(original);
+        let extension = parts.extension?;
+
+        let replaced = if self.strip {
+            None
+        } else if let Some((_, to)) = self
+            .replacements
+            .iter()
+            .find(|(from, _)| from.eq_ignore_ascii_case(extension))
+        {
+            Some(to.clone()).filter(|to| !to.is_empty())
+        } else if self.lowercase {
+            Some(extension.to_lowercase())
+        } else {
+            Some(extension.to_owned())
+        };
+
+        let name = parts.join(parts.stem, replaced.as_deref());
+        (name != original).then(|| path.with_file_name(name))
+    }
+
+    /// Applies the rule to every path, returning the mappings of those that
+    /// change.
+    pub fn mappings<I, P>(&self, paths: I) -> Vec<(PathBuf, PathBuf)>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let path = path.as_ref();
+                let dst = self.apply(path)?;
+                Some((path.to_path_buf(), dst))
+            })
+            .collect()
+    }
+
+    /// Applies the rule to every path, and plans the resulting renames.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mappings conflict, as described in
+    /// [`RenameQueue::new`].
+    pub fn queue<I, P>(&self, paths: I) -> Result<RenameQueue, rename::Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        RenameQueue::new(self.mappings(paths))
+    }
+}
+
+impl ExtensionRule {
+    pub(super) fn to_fields(&self) -> Vec<(String, Value)> {
+        let replacements = self
+            .replacements
+            .iter()
+            .map(|(from, to)| {
+                Value::Map(vec![
+                    ("from".to_owned(), Value::str(from)),
+                    ("to".to_owned(), Value::str(to)),
+                ])
+            })
+            .collect();
+        vec![
+            ("lowercase".to_owned(), Value::Bool(self.lowercase)),
+            ("replacements".to_owned(), Value::List(replacements)),
+            ("strip".to_owned(), Value::Bool(self.strip)),
+        ]
+    }
+
+    pub(super) fn from_fields(fields: &mut Fields) -> Result<Self, String> {
+        let mut rule = Self::new()
+            .lowercase(fields.bool("lowercase")?.unwrap_or(false))
+            .strip(fields.bool("strip")?.unwrap_or(false));
+        if let Some(replacements) = fields.take("replacements")? {
+            for replacement in replacements.into_list()? {
+                let mut replacement = Fields::new("replacement", replacement)?;
+                let from = replacement.require("from")?.into_str()?;
+                let to = replacement.require("to")?.into_str()?;
+                replacement.finish()?;
+                rule = rule.replace(from, to);
+            }
+        }
+        Ok(rule)
+    }
+}
@@ -0,0 +1,187 @@
+/// Returns `true` if `text` matches the glob `pattern`.
+///
+/// `?` matches any character and `*` any run of characters, except for `/`,
+/// while `**` matches across `/` too. A leading `**/`, or `/**/`, may also
+/// match no directories at all, so `docs/**/*.md` matches `docs/a.md`.
+/// `[abc]`, `[a-z]` and their negations
+/// `[!abc]` or `[^abc]` match a single character from a set. A backslash
+/// matches the next character literally. Malformed sets are matched
+/// literally.
+pub(crate) fn matches(pattern: &str, text: &str) -> bool {
+    let tokens = parse(pattern);
+    let text: Vec<char> = text.chars().collect();
+
+    // `reachable[i]` is whether the first `i` characters of the text can be
+    // matched by the tokens processed so far.
+    let mut reachable = vec![false; text.len() + 1];
+    reachable[0] = true;
+    for token in &tokens {
+        let mut next = vec![false; text.len() + 1];
+        match token {
+            Token::Star { cross } => {
+                let mut open = false;
+                for (index, slot) in next.iter_mut().enumerate() {
+                    open = (open && (*cross || text[index - 1] != '/')) || reachable[index];
+                    *slot = open;
+                }
+            }
+            Token::Dirs => {
+                // Either nothing, or any run of characters ending with `/`.
+                let mut open = false;
+                for (index, slot) in next.iter_mut().enumerate() {
+                    *slot = reachable[index] || (open && text[index - 1] == '/');
+                    open = open || reachable[index];
+                }
+            }
+            token => {
+                for index in 0..text.len() {
+                    if reachable[index] && token.accepts(text[index]) {
+                        next[index + 1] = true;
+                    }
+                }
+            }
+        }
+        reachable = next;
+    }
+    reachable[text.len()]
+}
+
+#[derive(Debug)]
+enum Token {
+    Char(char),
+    Any,
+    Star {
+        cross: bool,
+    },
+    /// `**/` at the start of the pattern or of a component.
+    Dirs,
+    Set {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Token {
+    fn accepts(&self, char: char) -> bool {
+        match self {
+            Self::Char(expected) => char == *expected,
+            Self::Any => char != '/',
+            Self::Star { .. } | Self::Dirs => unreachable!(),
+            Self::Set { negated, ranges } => {
+                char != '/'
+                    && ranges
+                        .iter()
+                        .any(|&(low, high)| low <= char && char <= high)
+                        != *negated
+            }
+        }
+    }
+}
+
+fn parse(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        match chars[index] {
+            '*' if chars.get(index + 1) == Some(&'*')
+                && chars.get(index + 2) == Some(&'/')
+                && (index == 0 || chars[index - 1] == '/') =>
+            {
+                index += 3;
+                tokens.push(Token::Dirs);
+                continue;
+            }
+            '*' => {
+                let cross = chars.get(index + 1) == Some(&'*');
+                index += if cross { 2 } else { 1 };
+                tokens.push(Token::Star { cross });
+                continue;
+            }
+            '?' => tokens.push(Token::Any),
+            '\\' if index + 1 < chars.len() => {
+                index += 1;
+                tokens.push(Token::Char(chars[index]));
+            }
+            '[' => match set(&chars[index + 1..]) {
+                Some((token, len)) => {
+                    tokens.push(token);
+                    index += len + 1;
+                    continue;
+                }
+                None => tokens.push(Token::Char('[')),
+            },
+            char => tokens.push(Token::Char(char)),
+        }
+        index += 1;
+    }
+    tokens
+}
+
+/// Parses a set after its opening bracket, returning it along with the
+/// number of characters up to and including the closing bracket.
+fn set(chars: &[char]) -> Option<(Token, usize)> {
+    let mut index = 0;
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    if negated {
+        index += 1;
+    }
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let low = *chars.get(index)?;
+        if low == ']' && !first {
+            return Some((Token::Set { negated, ranges }, index + 1));
+        }
+        first = false;
+        if chars.get(index + 1) == Some(&'-')
+            && let Some(&high) = chars.get(index + 2)
+            && high != ']'
+        {
+            ranges.push((low, high));
+            index += 3;
+        } else {
+            ranges.push((low, low));
+            index += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stars_stop_at_separators() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "src/main.rs"));
+        assert!(matches("src/*", "src/main.rs"));
+        assert!(matches("src/**", "src/walk_dir/list.rs"));
+        assert!(matches("a**b", "a/x/b"));
+        assert!(!matches("a?b", "a/b"));
+    }
+
+    #[test]
+    fn double_star_directories_may_be_empty() {
+        assert!(matches("docs/**/*.md", "docs/top.md"));
+        assert!(matches("docs/**/*.md", "docs/a/b/deep.md"));
+        assert!(!matches("docs/**/*.md", "docsx/top.md"));
+        assert!(matches("**/target", "target"));
+        assert!(matches("**/target", "a/b/target"));
+        assert!(!matches("**/target", "a/btarget"));
+        assert!(matches("a/**/b/**/c", "a/b/c"));
+        assert!(!matches("x**/y", "xy"));
+    }
+
+    #[test]
+    fn sets_and_escapes() {
+        assert!(matches("[a-c]x", "bx"));
+        assert!(!matches("[!a-c]x", "bx"));
+        assert!(matches("[^a-c]x", "dx"));
+        assert!(!matches("[/]", "/"));
+        assert!(matches(r"\*", "*"));
+        assert!(!matches(r"\*", "a"));
+        assert!(matches("[a", "[a"));
+        assert!(matches("[]]", "]"));
+    }
+}
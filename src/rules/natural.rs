@@ -0,0 +1,57 @@
+use std::cmp::Ordering;
+
+/// Compares two strings in natural order, so `img2` sorts before `img10`.
+///
+/// Runs of ASCII digits are compared by their numeric value, and other
+/// characters by their lowercase forms. Ties are broken by fewer leading
+/// zeros first, and finally by comparing the strings as they are, so the
+/// order is total.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::natural_cmp;
+///
+/// let mut names = ["img10.jpg", "IMG2.jpg", "img1.jpg"];
+/// names.sort_by(|a, b| natural_cmp(a, b));
+/// assert_eq!(names, ["img1.jpg", "IMG2.jpg", "img10.jpg"]);
+/// ```
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut left, mut right) = (a, b);
+    let mut zeros = Ordering::Equal;
+    while let (Some(x), Some(y)) = (left.chars().next(), right.chars().next()) {
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (x, rest_x) = split_digits(left);
+            let (y, rest_y) = split_digits(right);
+            let (trimmed_x, trimmed_y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+            let ordering = trimmed_x
+                .len()
+                .cmp(&trimmed_y.len())
+                .then_with(|| trimmed_x.cmp(trimmed_y));
+            if ordering.is_ne() {
+                return ordering;
+            }
+            zeros = zeros.then(x.len().cmp(&y.len()));
+            (left, right) = (rest_x, rest_y);
+            continue;
+        }
+        let ordering = x.to_lowercase().cmp(y.to_lowercase());
+        if ordering.is_ne() {
+            return ordering;
+        }
+        (left, right) = (&left[x.len_utf8()..], &right[y.len_utf8()..]);
+    }
+    left.is_empty()
+        .cmp(&right.is_empty())
+        .reverse()
+        .then(zeros)
+        .then_with(|| a.cmp(b))
+}
+
+/// Splits a string after its leading run of ASCII digits.
+fn split_digits(text: &str) -> (&str, &str) {
+    let end = text
+        .find(|char: char| !char.is_ascii_digit())
+        .unwrap_or(text.len());
+    text.split_at(end)
+}
@@ -0,0 +1,79 @@
+use super::parts::Parts;
+use super::recipe::{Fields, Value};
+use std::path::{Path, PathBuf};
+
+/// A rename rule padding numbers in file names with leading zeros.
+///
+/// Every run of ASCII digits in the stem shorter than the width is padded,
+/// while longer runs and the extension are left alone.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::PadRule;
+/// use std::path::Path;
+///
+/// let rule = PadRule::new(3);
+/// let dst = rule.apply(Path::new("disc2_track7.mp3")).unwrap();
+/// assert_eq!(dst, Path::new("disc002_track007.mp3"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PadRule {
+    width: usize,
+}
+
+impl PadRule {
+    /// Creates a new [`PadRule`] padding numbers to `width` digits.
+    pub fn new(width: usize) -> Self {
+        Self { width }
+    }
+
+    /// Returns the width numbers are padded to.
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Pads every number in a text.
+    pub fn convert(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(|char: char| char.is_ascii_digit()) {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest
+                .find(|char: char| !char.is_ascii_digit())
+                .unwrap_or(rest.len());
+            for _ in end..self.width {
+                output.push('0');
+            }
+            output.push_str(&rest[..end]);
+            rest = &rest[end..];
+        }
+        output.push_str(rest);
+        output
+    }
+
+    /// Applies the rule to a path, returning the new path.
+    ///
+    /// Returns [`None`] if the file name is not valid UTF-8, or if every
+    /// number is already wide enough.
+    pub fn apply(&self, path: &Path) -> Option<PathBuf> {
+        let original = path.file_name()?.to_str()?;
+        let parts = Parts::new(original);
+        let stem = self.convert(parts.stem);
+        let padded = parts.join(&stem, parts.extension);
+        (padded != original).then(|| path.with_file_name(padded))
+    }
+}
+
+impl PadRule {
+    pub(super) fn to_fields(self) -> Vec<(String, Value)> {
+        vec![("width".to_owned(), Value::uint(self.width as u64))]
+    }
+
+    pub(super) fn from_fields(fields: &mut Fields) -> Result<Self, String> {
+        let width = fields.require("width")?.into_u64()?;
+        Ok(Self::new(width as usize))
+    }
+}
@@ -0,0 +1,200 @@
+use super::parts::Parts;
+use crate::rename::{self, RenameQueue};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A helper padding numbers in file names with zeros, to a uniform width
+/// across each directory, so they sort lexicographically.
+///
+/// Files are grouped by their directory and by their stem with its numbers
+/// left out, so `track1.mp3`, `track10.flac` and `track2.mp3` form a group,
+/// while `2024-05-01.txt` forms another. Within a group, each number is
+/// padded to the width of the widest number at the same position, or to the
+/// minimum width if wider. Unlike [`PadRule`], numbers at other positions,
+/// such as years, do not widen each other. Files without a number in their
+/// stem are left alone.
+///
+/// [`PadRule`]: super::PadRule
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::PadNumbers;
+/// use std::path::PathBuf;
+///
+/// let paths = ["track1.mp3", "track10.mp3", "track2.mp3"];
+/// let mappings = PadNumbers::new().mappings(paths);
+/// assert_eq!(
+///     mappings,
+///     [
+///         (PathBuf::from("track1.mp3"), PathBuf::from("track01.mp3")),
+///         (PathBuf::from("track2.mp3"), PathBuf::from("track02.mp3")),
+///     ],
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PadNumbers {
+    width: usize,
+}
+
+/// A group of files, by their directory and their stem with its numbers
+/// left out.
+type Group = (PathBuf, String);
+
+impl PadNumbers {
+    /// Creates a new [`PadNumbers`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the minimum width numbers are padded to.
+    ///
+    /// Defaults to 0.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Pads the numbers of every path, returning the mappings of those that
+    /// change, ordered by group and then by path.
+    ///
+    /// Paths whose file names are not valid UTF-8 are left alone.
+    pub fn mappings<I, P>(&self, paths: I) -> Vec<(PathBuf, PathBuf)>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut groups: BTreeMap<Group, Vec<PathBuf>> = BTreeMap::new();
+        for path in paths {
+            let path = path.as_ref();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let parts = Parts::new(name);
+            let mut skeleton = String::from(parts.dots);
+            let mut found = false;
+            for run in Runs::new(parts.stem) {
+                match run {
+                    Run::Text(text) => skeleton.push_str(text),
+                    // A character never found in file names marks numbers.
+                    Run::Number(_) => {
+                        skeleton.push('\0');
+                        found = true;
+                    }
+                }
+            }
+            if found {
+                let directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+                groups
+                    .entry((directory, skeleton))
+                    .or_default()
+                    .push(path.to_path_buf());
+            }
+        }
+
+        let mut mappings = Vec::new();
+        for (_, mut paths) in groups {
+            paths.sort();
+            let mut widths = Vec::new();
+            for path in &paths {
+                let numbers = Runs::new(Parts::new(name(path)).stem).filter_map(Run::number);
+                for (index, number) in numbers.enumerate() {
+                    match widths.get_mut(index) {
+                        Some(width) => *width = number.len().max(*width),
+                        None => widths.push(number.len().max(self.width)),
+                    }
+                }
+            }
+
+            for src in paths {
+                let parts = Parts::new(name(&src));
+                let mut stem = String::with_capacity(parts.stem.len());
+                let mut widths = widths.iter();
+                for run in Runs::new(parts.stem) {
+                    match run {
+                        Run::Text(text) => stem.push_str(text),
+                        Run::Number(number) => {
+                            let width = widths.next().copied().unwrap_or_default();
+                            stem.push_str(&format!("{number:0>width$}"));
+                        }
+                    }
+                }
+                let renamed = parts.join(&stem, parts.extension);
+                if renamed != name(&src) {
+                    let dst = src.with_file_name(renamed);
+                    mappings.push((src, dst));
+                }
+            }
+        }
+        mappings
+    }
+
+    /// Pads the numbers of every path, and plans the resulting renames.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mappings conflict, as described in
+    /// [`RenameQueue::new`].
+    pub fn queue<I, P>(&self, paths: I) -> Result<RenameQueue, rename::Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        RenameQueue::new(self.mappings(paths))
+    }
+}
+
+/// Returns the file name of a grouped path, which is valid UTF-8.
+fn name(path: &Path) -> &str {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+}
+
+/// A run of text or ASCII digits.
+enum Run<'a> {
+    Text(&'a str),
+    Number(&'a str),
+}
+
+impl<'a> Run<'a> {
+    fn number(self) -> Option<&'a str> {
+        match self {
+            Self::Number(number) => Some(number),
+            Self::Text(_) => None,
+        }
+    }
+}
+
+/// An iterator splitting text into alternating runs of text and digits.
+struct Runs<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Runs<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { rest: text }
+    }
+}
+
+impl<'a> Iterator for Runs<'a> {
+    type Item = Run<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let digits = self.rest.starts_with(|char: char| char.is_ascii_digit());
+        let end = self
+            .rest
+            .find(|char: char| char.is_ascii_digit() != digits)
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return None;
+        }
+        let (run, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(if digits {
+            Run::Number(run)
+        } else {
+            Run::Text(run)
+        })
+    }
+}
@@ -0,0 +1,66 @@
+/// A file name split into its leading dots, stem and extension.
+///
+/// Leading dots mark hidden files rather than extensions, so `.bashrc` has no
+/// extension, while `.config.toml` has the stem `config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Parts<'a> {
+    pub(super) dots: &'a str,
+    pub(super) stem: &'a str,
+    pub(super) extension: Option<&'a str>,
+}
+
+/// Extensions spanning several dots, which are kept together.
+const COMPOUND: [&str; 8] = [
+    "tar.br", "tar.bz2", "tar.gz", "tar.lz", "tar.lzma", "tar.xz", "tar.z", "tar.zst",
+];
+
+impl<'a> Parts<'a> {
+    pub(super) fn new(name: &'a str) -> Self {
+        let hidden = name.len() - name.trim_start_matches('.').len();
+        let (dots, name) = name.split_at(hidden);
+        let (stem, extension) = match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+            _ => (name, None),
+        };
+        Self {
+            dots,
+            stem,
+            extension,
+        }
+    }
+
+    /// Splits a file name like [`Parts::new`], except that compound
+    /// extensions such as `tar.gz` are kept whole.
+    pub(super) fn compound(name: &'a str) -> Self {
+        let mut parts = Self::new(name);
+        let full = &name[parts.dots.len()..];
+        for compound in COMPOUND {
+            let Some(dot) = full.len().checked_sub(compound.len() + 1) else {
+                continue;
+            };
+            if dot > 0
+                && full.is_char_boundary(dot)
+                && full[dot..].starts_with('.')
+                && full[dot + 1..].eq_ignore_ascii_case(compound)
+            {
+                parts.stem = &full[..dot];
+                parts.extension = Some(&full[dot + 1..]);
+                break;
+            }
+        }
+        parts
+    }
+
+    /// Joins the parts back into a file name, with the given stem and
+    /// extension.
+    pub(super) fn join(&self, stem: &str, extension: Option<&str>) -> String {
+        let mut name = String::with_capacity(self.dots.len() + stem.len());
+        name.push_str(self.dots);
+        name.push_str(stem);
+        if let Some(extension) = extension {
+            name.push('.');
+            name.push_str(extension);
+        }
+        name
+    }
+}
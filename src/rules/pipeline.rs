@@ -0,0 +1,429 @@
+use super::affix::AffixRule;
+use super::case::CaseRule;
+use super::conditional::Conditional;
+use super::error::Error;
+use super::expression;
+use super::extension::ExtensionRule;
+use super::pad::PadRule;
+use super::preview::Preview;
+use super::recipe::{Fields, Value};
+use super::regex_rule::RegexRule;
+use super::replace::ReplaceRule;
+use super::sanitize::SanitizeRule;
+use super::slug::SlugRule;
+use super::subject::Subject;
+use super::template::Template;
+use super::transform::Transform;
+use crate::rename::{self, RenameQueue};
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde::ser::{Serialize, Serializer};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A chain of transforms, each applied to the output of the previous one.
+///
+/// This type implements [`Serialize`] and [`Deserialize`], so a pipeline can
+/// be saved as a recipe and replayed later. Each step is a map with a `type`
+/// key naming the rule, and the options of the rule as other keys. Counters
+/// of templates are not saved, and start over when a recipe is loaded.
+///
+/// Deserialization requires a self-describing format, such as JSON, TOML or
+/// YAML.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::{Case, CaseRule, Pipeline, ReplaceRule, Template, Transform};
+/// use std::path::Path;
+///
+/// let mut pipeline = Pipeline::new()
+///     .then(ReplaceRule::new(" ", "_"))
+///     .then(CaseRule::new(Case::Lower))
+///     .then(Template::new("{name}_{counter:03}.{ext}").unwrap());
+/// let dst = pipeline.apply(Path::new("Summer Trip.jpg")).unwrap();
+/// assert_eq!(dst, Path::new("summer_trip_001.jpg"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    steps: Vec<Step>,
+}
+
+/// A single step of a [`Pipeline`].
+#[derive(Debug, Clone)]
+pub enum Step {
+    Affix(AffixRule),
+    Case(CaseRule),
+    Conditional(Conditional),
+    Extension(ExtensionRule),
+    Pad(PadRule),
+    Regex(RegexRule),
+    Replace(ReplaceRule),
+    Sanitize(SanitizeRule),
+    Slug(SlugRule),
+    Template(Template),
+}
+
+impl Pipeline {
+    /// Creates a new empty [`Pipeline`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a pipeline from an expression.
+    ///
+    /// An expression is a list of steps separated by `|`. A step is either a
+    /// substitution `s/PATTERN/REPLACEMENT/FLAGS` as in `sed`, compiled to a
+    /// [`RegexRule`], or a name optionally followed by arguments in
+    /// parentheses. Any punctuation may delimit a substitution instead of
+    /// `/`, and is escaped with a backslash. Its flags are `g` to replace
+    /// every match, `i` to ignore case, and `p` to match the full path.
+    ///
+    /// Arguments are separated by commas, and are either bare words of
+    /// letters, digits, `_`, `-` and `.`, or strings quoted with `"` or `'`,
+    /// in which a backslash escapes the next character. The steps are:
+    ///
+    /// - `lower`, `upper`, `title`, `snake`, `kebab` and `camel`: a
+    ///   [`CaseRule`];
+    /// - `slug` or `slug(SEPARATOR)`: a [`SlugRule`];
+    /// - `replace(FIND, WITH)` or `replace(FIND, WITH, COUNT)`: a
+    ///   [`ReplaceRule`];
+    /// - `prefix(TEXT)` and `suffix(TEXT)`: an [`AffixRule`];
+    /// - `ext(lower)`, `ext(strip)` and `ext(FROM, TO)`: an
+    ///   [`ExtensionRule`];
+    /// - `sanitize(CLASS, ...)`: a [`SanitizeRule`] of the named classes,
+    ///   such as `whitespace` or `non_ascii`, defaulting to whitespace,
+    ///   control and reserved characters;
+    /// - `pad(WIDTH)`: a [`PadRule`];
+    /// - `template(TEMPLATE)`: a [`Template`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidExpression`] if the expression is malformed,
+    /// or if a regex or template in it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::rules::{Pipeline, Transform};
+    /// use std::path::Path;
+    ///
+    /// let mut pipeline = Pipeline::parse("s/IMG_/Vacation_/ | lower | pad(3)").unwrap();
+    /// let dst = pipeline.apply(Path::new("IMG_7.JPG")).unwrap();
+    /// assert_eq!(dst, Path::new("vacation_007.JPG"));
+    /// ```
+    pub fn parse(expression: &str) -> Result<Self, Error> {
+        expression::parse(expression)
+    }
+
+    /// Appends a step.
+    pub fn then<S>(mut self, step: S) -> Self
+    where
+        S: Into<Step>,
+    {
+        self.steps.push(step.into());
+        self
+    }
+
+    /// Returns the steps.
+    #[inline]
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Applies the pipeline to every path, returning the mappings of those
+    /// that change.
+    pub fn mappings<I, P>(&mut self, paths: I) -> Vec<(PathBuf, PathBuf)>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let path = path.as_ref();
+                let dst = self.apply(path)?;
+                Some((path.to_path_buf(), dst))
+            })
+            .collect()
+    }
+
+    /// Applies the pipeline to every path, returning a [`Preview`] of each,
+    /// including those left unchanged.
+    ///
+    /// Unlike [`Pipeline::mappings`], this works on a copy of the pipeline,
+    /// so counters are not advanced, and previewing the same paths again
+    /// gives the same results. No queue is planned, so conflicts between
+    /// destinations are not reported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::rules::{Case, CaseRule, Pipeline, ReplaceRule};
+    ///
+    /// let pipeline = Pipeline::new()
+    ///     .then(ReplaceRule::new("IMG_", ""))
+    ///     .then(CaseRule::new(Case::Lower));
+    /// let previews = pipeline.preview(["IMG_0001.JPG", "notes.txt"]);
+    /// assert_eq!(previews[0].dst().to_str(), Some("0001.JPG"));
+    /// assert_eq!(previews[0].steps(), [0]);
+    /// assert!(!previews[1].is_changed());
+    /// ```
+    pub fn preview<I, P>(&self, paths: I) -> Vec<Preview>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut pipeline = self.clone();
+        paths
+            .into_iter()
+            .map(|path| {
+                let src = path.as_ref().to_path_buf();
+                let mut steps = Vec::new();
+                let dst = pipeline
+                    .apply_traced(&Subject::from_path(&src), &mut steps)
+                    .unwrap_or_else(|| src.clone());
+                Preview::new(src, dst, steps)
+            })
+            .collect()
+    }
+
+    /// Applies the pipeline to every path, and plans the resulting renames.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mappings conflict, as described in
+    /// [`RenameQueue::new`].
+    pub fn queue<I, P>(&mut self, paths: I) -> Result<RenameQueue, rename::Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        RenameQueue::new(self.mappings(paths))
+    }
+}
+
+impl Pipeline {
+    pub(super) fn apply_subject(&mut self, subject: &Subject) -> Option<PathBuf> {
+        self.apply_traced(subject, &mut Vec::new())
+    }
+
+    /// Applies the pipeline, pushing the indices of the steps that change
+    /// the path to `fired`.
+    fn apply_traced(&mut self, subject: &Subject, fired: &mut Vec<usize>) -> Option<PathBuf> {
+        let mut current: Option<PathBuf> = None;
+        for (index, step) in self.steps.iter_mut().enumerate() {
+            let output = match &current {
+                Some(path) => step.apply_subject(&subject.renamed(path)),
+                None => step.apply_subject(subject),
+            };
+            if output.is_some() {
+                fired.push(index);
+                current = output;
+            }
+        }
+        current.filter(|dst| dst != subject.path())
+    }
+
+    pub(super) fn to_value(&self) -> Value {
+        Value::List(self.steps.iter().map(Step::to_value).collect())
+    }
+
+    pub(super) fn from_value(value: Value) -> Result<Self, String> {
+        let steps = value.into_list()?;
+        let steps = steps.into_iter().map(Step::from_value);
+        Ok(Self {
+            steps: steps.collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl Transform for Pipeline {
+    fn apply(&mut self, path: &Path) -> Option<PathBuf> {
+        self.apply_subject(&Subject::from_path(path))
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl<S> From<S> for Pipeline
+where
+    S: Into<Step>,
+{
+    fn from(value: S) -> Self {
+        Self::new().then(value)
+    }
+}
+
+impl Step {
+    /// Returns the name of the step in recipes, such as `regex`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Affix(_) => "affix",
+            Self::Case(_) => "case",
+            Self::Conditional(_) => "conditional",
+            Self::Extension(_) => "extension",
+            Self::Pad(_) => "pad",
+            Self::Regex(_) => "regex",
+            Self::Replace(_) => "replace",
+            Self::Sanitize(_) => "sanitize",
+            Self::Slug(_) => "slug",
+            Self::Template(_) => "template",
+        }
+    }
+
+    pub(super) fn to_value(&self) -> Value {
+        let mut fields = match self {
+            Self::Affix(rule) => rule.to_fields(),
+            Self::Case(rule) => rule.to_fields(),
+            Self::Conditional(rule) => rule.to_fields(),
+            Self::Extension(rule) => rule.to_fields(),
+            Self::Pad(rule) => rule.to_fields(),
+            Self::Regex(rule) => rule.to_fields(),
+            Self::Replace(rule) => rule.to_fields(),
+            Self::Sanitize(rule) => rule.to_fields(),
+            Self::Slug(rule) => rule.to_fields(),
+            Self::Template(rule) => rule.to_fields(),
+        };
+        fields.insert(0, ("type".to_owned(), Value::str(self.name())));
+        Value::Map(fields)
+    }
+
+    pub(super) fn from_value(value: Value) -> Result<Self, String> {
+        let mut fields = Fields::new("step", value)?;
+        let name = fields.require("type")?.into_str()?;
+        let mut fields = fields.rename(format!("{name} step"));
+        let step = match name.as_str() {
+            "affix" => Self::Affix(AffixRule::from_fields(&mut fields)?),
+            "case" => Self::Case(CaseRule::from_fields(&mut fields)?),
+            "conditional" => Self::Conditional(Conditional::from_fields(&mut fields)?),
+            "extension" => Self::Extension(ExtensionRule::from_fields(&mut fields)?),
+            "pad" => Self::Pad(PadRule::from_fields(&mut fields)?),
+            "regex" => Self::Regex(RegexRule::from_fields(&mut fields)?),
+            "replace" => Self::Replace(ReplaceRule::from_fields(&mut fields)?),
+            "sanitize" => Self::Sanitize(SanitizeRule::from_fields(&mut fields)?),
+            "slug" => Self::Slug(SlugRule::from_fields(&mut fields)?),
+            "template" => Self::Template(Template::from_fields(&mut fields)?),
+            _ => return Err(format!("unknown step type `{name}`")),
+        };
+        fields.finish()?;
+        Ok(step)
+    }
+}
+
+impl Step {
+    fn apply_subject(&mut self, subject: &Subject) -> Option<PathBuf> {
+        let path = subject.path();
+        match self {
+            Self::Affix(rule) => rule.apply_subject(subject),
+            Self::Case(rule) => rule.apply(path),
+            Self::Conditional(rule) => rule.apply_subject(subject),
+            Self::Extension(rule) => rule.apply(path),
+            Self::Pad(rule) => rule.apply(path),
+            Self::Regex(rule) => rule.apply(path),
+            Self::Replace(rule) => rule.apply(path),
+            Self::Sanitize(rule) => rule.apply(path),
+            Self::Slug(rule) => rule.apply(path),
+            Self::Template(template) => {
+                let dst = template.render_subject(subject);
+                (dst != path).then_some(dst)
+            }
+        }
+    }
+}
+
+impl Transform for Step {
+    fn apply(&mut self, path: &Path) -> Option<PathBuf> {
+        self.apply_subject(&Subject::from_path(path))
+    }
+}
+
+macro_rules! impl_from_rule {
+    ($($variant:ident($rule:ty)),* $(,)?) => {
+        $(
+            impl From<$rule> for Step {
+                #[inline]
+                fn from(value: $rule) -> Self {
+                    Self::$variant(value)
+                }
+            }
+
+            impl Transform for $rule {
+                #[inline]
+                fn apply(&mut self, path: &Path) -> Option<PathBuf> {
+                    <$rule>::apply(self, path)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_rule!(
+    Affix(AffixRule),
+    Case(CaseRule),
+    Conditional(Conditional),
+    Extension(ExtensionRule),
+    Pad(PadRule),
+    Regex(RegexRule),
+    Replace(ReplaceRule),
+    Sanitize(SanitizeRule),
+    Slug(SlugRule),
+);
+
+impl From<Template> for Step {
+    #[inline]
+    fn from(value: Template) -> Self {
+        Self::Template(value)
+    }
+}
+
+impl Transform for Template {
+    fn apply(&mut self, path: &Path) -> Option<PathBuf> {
+        let dst = self.render_path(path);
+        (dst != path).then_some(dst)
+    }
+}
+
+impl Serialize for Step {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Step {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Self::from_value(value).map_err(DeError::custom)
+    }
+}
+
+impl Serialize for Pipeline {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pipeline {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Self::from_value(value).map_err(DeError::custom)
+    }
+}
@@ -0,0 +1,184 @@
+use super::glob;
+use super::recipe::Value;
+use super::regex::Regex;
+use super::subject::Subject;
+use crate::walk_dir::DirEntry;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// A condition on files, gating whether a rule applies.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rules::Predicate;
+/// use std::path::Path;
+/// use std::time::{Duration, SystemTime};
+///
+/// let a_year_ago = SystemTime::now() - Duration::from_secs(365 * 24 * 60 * 60);
+/// let stale_docs = Predicate::All(vec![
+///     Predicate::Extension("docx".to_owned()),
+///     Predicate::ModifiedBefore(a_year_ago),
+/// ]);
+/// let stale = stale_docs.matches(Path::new("report.docx"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Predicate {
+    /// Matches file names ending with a dot and the extension, ignoring ASCII
+    /// case, so `tar.gz` matches `backup.TAR.GZ`.
+    Extension(String),
+
+    /// Matches paths against a glob, as described below.
+    ///
+    /// Patterns without `/` are matched against the file name, and others
+    /// against the full path. `?` matches any character and `*` any run of
+    /// characters, except for `/`, while `**` matches across `/` too. A
+    /// leading `**/`, or `/**/`, may also match no directories at all.
+    /// `[abc]`, `[a-z]` and their negations `[!abc]` or `[^abc]` match a
+    /// single character from a set. A backslash matches the next character
+    /// literally.
+    Glob(String),
+
+    /// Matches file names containing a match of the regex.
+    Regex(Regex),
+
+    /// Matches files larger than the size, in bytes.
+    LargerThan(u64),
+
+    /// Matches files smaller than the size, in bytes.
+    SmallerThan(u64),
+
+    /// Matches files last modified before the time.
+    ModifiedBefore(SystemTime),
+
+    /// Matches files last modified at or after the time.
+    ModifiedAfter(SystemTime),
+
+    /// Matches files the predicate does not match.
+    Not(Box<Predicate>),
+
+    /// Matches files every predicate matches.
+    All(Vec<Predicate>),
+
+    /// Matches files any predicate matches.
+    Any(Vec<Predicate>),
+}
+
+impl Predicate {
+    /// Returns `true` if the file at `path` matches.
+    ///
+    /// The metadata of the file is read if needed, without following
+    /// symbolic links. Predicates on metadata do not match files whose
+    /// metadata cannot be read.
+    pub fn matches(&self, path: &Path) -> bool {
+        self.eval(&Subject::from_path(path))
+    }
+
+    /// Returns `true` if the entry matches, using its cached metadata.
+    pub fn matches_entry(&self, entry: &DirEntry) -> bool {
+        self.eval(&Subject::from_entry(entry))
+    }
+
+    pub(super) fn eval(&self, subject: &Subject) -> bool {
+        match self {
+            Self::Extension(extension) => subject
+                .path()
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| {
+                    let split = name.len().checked_sub(extension.len() + 1)?;
+                    let (stem, suffix) = name.split_at_checked(split)?;
+                    Some(
+                        !stem.trim_start_matches('.').is_empty()
+                            && suffix.starts_with('.')
+                            && suffix[1..].eq_ignore_ascii_case(extension),
+                    )
+                })
+                .unwrap_or(false),
+            Self::Glob(pattern) => {
+                let path = subject.path();
+                let text = if pattern.contains('/') {
+                    path.to_str()
+                } else {
+                    path.file_name().and_then(|name| name.to_str())
+                };
+                text.is_some_and(|text| glob::matches(pattern, text))
+            }
+            Self::Regex(regex) => subject
+                .path()
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| regex.is_match(name)),
+            Self::LargerThan(size) => subject
+                .metadata()
+                .is_some_and(|metadata| metadata.len() > *size),
+            Self::SmallerThan(size) => subject
+                .metadata()
+                .is_some_and(|metadata| metadata.len() < *size),
+            Self::ModifiedBefore(time) => {
+                subject.modified().is_some_and(|modified| modified < *time)
+            }
+            Self::ModifiedAfter(time) => {
+                subject.modified().is_some_and(|modified| modified >= *time)
+            }
+            Self::Not(predicate) => !predicate.eval(subject),
+            Self::All(predicates) => predicates.iter().all(|predicate| predicate.eval(subject)),
+            Self::Any(predicates) => predicates.iter().any(|predicate| predicate.eval(subject)),
+        }
+    }
+}
+
+impl Predicate {
+    pub(super) fn to_value(&self) -> Value {
+        let (key, value) = match self {
+            Self::Extension(extension) => ("extension", Value::str(extension)),
+            Self::Glob(pattern) => ("glob", Value::str(pattern)),
+            Self::Regex(regex) => ("regex", Value::str(regex.as_str())),
+            Self::LargerThan(size) => ("larger_than", Value::uint(*size)),
+            Self::SmallerThan(size) => ("smaller_than", Value::uint(*size)),
+            Self::ModifiedBefore(time) => ("modified_before", Value::time(*time)),
+            Self::ModifiedAfter(time) => ("modified_after", Value::time(*time)),
+            Self::Not(predicate) => ("not", predicate.to_value()),
+            Self::All(predicates) => ("all", Self::to_list(predicates)),
+            Self::Any(predicates) => ("any", Self::to_list(predicates)),
+        };
+        Value::Map(vec![(key.to_owned(), value)])
+    }
+
+    pub(super) fn from_value(value: Value) -> Result<Self, String> {
+        let Value::Map(mut entries) = value else {
+            return Err("expected a predicate to be a map".to_owned());
+        };
+        if entries.len() != 1 {
+            return Err("expected a predicate to have exactly one key".to_owned());
+        }
+        let (key, value) = entries.remove(0);
+        match key.as_str() {
+            "extension" => Ok(Self::Extension(value.into_str()?)),
+            "glob" => Ok(Self::Glob(value.into_str()?)),
+            "regex" => Regex::new(&value.into_str()?)
+                .map(Self::Regex)
+                .map_err(|error| error.to_string().trim_end().to_owned()),
+            "larger_than" => Ok(Self::LargerThan(value.into_u64()?)),
+            "smaller_than" => Ok(Self::SmallerThan(value.into_u64()?)),
+            "modified_before" => Ok(Self::ModifiedBefore(value.into_time()?)),
+            "modified_after" => Ok(Self::ModifiedAfter(value.into_time()?)),
+            "not" => Ok(Self::Not(Box::new(Self::from_value(value)?))),
+            "all" => Ok(Self::All(Self::from_list(value)?)),
+            "any" => Ok(Self::Any(Self::from_list(value)?)),
+            _ => Err(format!("unknown predicate `{key}`")),
+        }
+    }
+
+    fn to_list(predicates: &[Self]) -> Value {
+        Value::List(predicates.iter().map(Self::to_value).collect())
+    }
+
+    fn from_list(value: Value) -> Result<Vec<Self>, String> {
+        value
+            .into_list()?
+            .into_iter()
+            .map(Self::from_value)
+            .collect()
+    }
+}
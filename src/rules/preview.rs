@@ -0,0 +1,47 @@
+use std::path::{Path, PathBuf};
+
+/// The outcome of a [`Pipeline`] for a single path, as returned by
+/// [`Pipeline::preview`].
+///
+/// [`Pipeline`]: super::Pipeline
+/// [`Pipeline::preview`]: super::Pipeline::preview
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Preview {
+    src: PathBuf,
+    dst: PathBuf,
+    steps: Vec<usize>,
+}
+
+impl Preview {
+    pub(super) fn new(src: PathBuf, dst: PathBuf, steps: Vec<usize>) -> Self {
+        Self { src, dst, steps }
+    }
+
+    /// Returns the source.
+    #[inline]
+    pub fn src(&self) -> &Path {
+        &self.src
+    }
+
+    /// Returns the destination, which equals the source if the path is left
+    /// unchanged.
+    #[inline]
+    pub fn dst(&self) -> &Path {
+        &self.dst
+    }
+
+    /// Returns the indices of the steps that changed the path, in order.
+    ///
+    /// A path may be changed by some steps and still end up unchanged, for
+    /// example if a later step reverts an earlier one.
+    #[inline]
+    pub fn steps(&self) -> &[usize] {
+        &self.steps
+    }
+
+    /// Returns `true` if the destination differs from the source.
+    #[inline]
+    pub fn is_changed(&self) -> bool {
+        self.src != self.dst
+    }
+}
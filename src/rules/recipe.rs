@@ -0,0 +1,302 @@
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A self-describing value, which rules are converted to and from when
+/// saved as part of a recipe.
+///
+/// Going through this representation keeps each rule to a pair of plain
+/// conversion functions, rather than a serializer and a visitor apiece.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+/// The fields of a map being converted back into a rule.
+///
+/// Every field must be taken, so that misspelled options are reported
+/// rather than silently ignored.
+#[derive(Debug)]
+pub(super) struct Fields {
+    kind: String,
+    entries: Vec<(String, Value)>,
+}
+
+impl Value {
+    pub(super) fn str<S>(value: S) -> Self
+    where
+        S: ToString,
+    {
+        Self::Str(value.to_string())
+    }
+
+    /// Returns an integer, saturating at [`i64::MAX`].
+    pub(super) fn uint(value: u64) -> Self {
+        Self::Int(i64::try_from(value).unwrap_or(i64::MAX))
+    }
+
+    pub(super) fn time(time: SystemTime) -> Self {
+        let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => (duration.as_secs() as i64, duration.subsec_nanos()),
+            Err(error) => {
+                let duration = error.duration();
+                let secs = -(duration.as_secs() as i64);
+                match duration.subsec_nanos() {
+                    0 => (secs, 0),
+                    nanos => (secs - 1, 1_000_000_000 - nanos),
+                }
+            }
+        };
+        let mut fields = vec![("secs".to_owned(), Self::Int(secs))];
+        if nanos != 0 {
+            fields.push(("nanos".to_owned(), Self::Int(i64::from(nanos))));
+        }
+        Self::Map(fields)
+    }
+
+    pub(super) fn into_str(self) -> Result<String, String> {
+        match self {
+            Self::Str(value) => Ok(value),
+            other => Err(format!("expected a string, found {}", other.describe())),
+        }
+    }
+
+    pub(super) fn into_bool(self) -> Result<bool, String> {
+        match self {
+            Self::Bool(value) => Ok(value),
+            other => Err(format!("expected a boolean, found {}", other.describe())),
+        }
+    }
+
+    pub(super) fn into_u64(self) -> Result<u64, String> {
+        match self {
+            Self::Int(value) if value >= 0 => Ok(value as u64),
+            other => Err(format!(
+                "expected a non-negative integer, found {}",
+                other.describe()
+            )),
+        }
+    }
+
+    pub(super) fn into_list(self) -> Result<Vec<Value>, String> {
+        match self {
+            Self::List(values) => Ok(values),
+            other => Err(format!("expected a list, found {}", other.describe())),
+        }
+    }
+
+    pub(super) fn into_time(self) -> Result<SystemTime, String> {
+        let mut fields = Fields::new("time", self)?;
+        let secs = match fields.require("secs")? {
+            Self::Int(secs) => secs,
+            other => return Err(format!("expected an integer, found {}", other.describe())),
+        };
+        let nanos = fields.u64("nanos")?.unwrap_or(0);
+        fields.finish()?;
+        if nanos >= 1_000_000_000 {
+            return Err("`nanos` must be less than 1000000000".to_owned());
+        }
+        let time = if secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(secs as u64, nanos as u32))
+        } else {
+            UNIX_EPOCH
+                .checked_sub(Duration::from_secs(secs.unsigned_abs()))
+                .and_then(|time| time.checked_add(Duration::from_nanos(nanos)))
+        };
+        time.ok_or_else(|| "time out of range".to_owned())
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Bool(_) => "a boolean",
+            Self::Int(_) => "an integer",
+            Self::Str(_) => "a string",
+            Self::List(_) => "a list",
+            Self::Map(_) => "a map",
+        }
+    }
+}
+
+impl Fields {
+    /// Takes the fields of a map, describing it as `kind` in errors.
+    pub(super) fn new<K>(kind: K, value: Value) -> Result<Self, String>
+    where
+        K: Into<String>,
+    {
+        let kind = kind.into();
+        match value {
+            Value::Map(entries) => Ok(Self { kind, entries }),
+            other => Err(format!(
+                "expected {kind} to be a map, found {}",
+                other.describe()
+            )),
+        }
+    }
+
+    /// Describes the map as `kind` in errors from now on.
+    pub(super) fn rename<K>(self, kind: K) -> Self
+    where
+        K: Into<String>,
+    {
+        let kind = kind.into();
+        Self { kind, ..self }
+    }
+
+    /// Takes a field, if present.
+    pub(super) fn take(&mut self, key: &str) -> Result<Option<Value>, String> {
+        let Some(index) = self.entries.iter().position(|(name, _)| name == key) else {
+            return Ok(None);
+        };
+        let (_, value) = self.entries.remove(index);
+        if self.entries.iter().any(|(name, _)| name == key) {
+            return Err(format!("duplicate field `{key}` in {}", self.kind));
+        }
+        Ok(Some(value))
+    }
+
+    /// Takes a field that must be present.
+    pub(super) fn require(&mut self, key: &str) -> Result<Value, String> {
+        self.take(key)?
+            .ok_or_else(|| format!("missing field `{key}` in {}", self.kind))
+    }
+
+    /// Takes a string field, if present.
+    pub(super) fn string(&mut self, key: &str) -> Result<Option<String>, String> {
+        self.take(key)?.map(Value::into_str).transpose()
+    }
+
+    /// Takes a boolean field, if present.
+    pub(super) fn bool(&mut self, key: &str) -> Result<Option<bool>, String> {
+        self.take(key)?.map(Value::into_bool).transpose()
+    }
+
+    /// Takes a non-negative integer field, if present.
+    pub(super) fn u64(&mut self, key: &str) -> Result<Option<u64>, String> {
+        self.take(key)?.map(Value::into_u64).transpose()
+    }
+
+    /// Checks that every field has been taken.
+    pub(super) fn finish(self) -> Result<(), String> {
+        match self.entries.first() {
+            Some((key, _)) => Err(format!("unknown field `{key}` in {}", self.kind)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Null => serializer.serialize_none(),
+            Self::Bool(value) => serializer.serialize_bool(*value),
+            Self::Int(value) => serializer.serialize_i64(*value),
+            Self::Str(value) => serializer.serialize_str(value),
+            Self::List(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Self::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+#[derive(Debug)]
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a boolean, integer, string, list or map")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Value::Int(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        i64::try_from(value)
+            .map(Value::Int)
+            .map_err(|_| E::custom("integer out of range"))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Value::Str(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+        Ok(Value::Str(value))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::List(values))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Value::Map(entries))
+    }
+}
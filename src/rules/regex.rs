@@ -0,0 +1,965 @@
+use super::error::Error;
+use std::borrow::Cow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Repetitions are expanded when compiled, so they are bounded to keep
+/// programs small.
+const MAX_REPEAT: u32 = 1000;
+
+/// The maximum number of instructions of a compiled program, since nested
+/// repetitions multiply, and each search allocates a bit per instruction
+/// and position in the text.
+const MAX_PROGRAM: usize = 1 << 16;
+
+/// A compiled regular expression.
+///
+/// This is a small backtracking engine with a Perl-like syntax, supporting:
+///
+/// - literals, `.`, and the escapes `\d`, `\w`, `\s`, their negations, and
+///   `\n`, `\r`, `\t`, where classes and word boundaries are ASCII-only;
+/// - character classes such as `[a-z_]` and `[^0-9]`;
+/// - the anchors `^`, `$`, `\b` and `\B`;
+/// - capturing groups `(...)`, named groups `(?<name>...)` or
+///   `(?P<name>...)`, and non-capturing groups `(?:...)`;
+/// - alternation `|`;
+/// - the quantifiers `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`, and their
+///   lazy variants suffixed with `?`;
+/// - case-insensitive matching with a leading `(?i)`.
+///
+/// Every state is visited at most once per search, so matching takes time
+/// proportional to the length of the text times the size of the pattern.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::Regex;
+///
+/// let regex = Regex::new(r"IMG_(\d+)").unwrap();
+/// assert_eq!(regex.replace("IMG_0042.jpg", "photo-$1"), "photo-0042.jpg");
+/// ```
+#[derive(Clone)]
+pub struct Regex {
+    pattern: String,
+    program: Vec<Inst>,
+    names: Vec<Option<String>>,
+}
+
+/// The capture groups of a single match.
+#[derive(Debug, Clone)]
+pub struct Captures<'a> {
+    text: &'a str,
+    slots: Vec<Option<usize>>,
+    names: &'a [Option<String>],
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    Start,
+    End,
+    WordBoundary(bool),
+    Match,
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+    folded: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    WordBoundary(bool),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+impl Regex {
+    /// Compiles a regular expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidRegex`] if the pattern is malformed.
+    pub fn new(pattern: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            pattern,
+            offset: 0,
+            folded: false,
+            names: vec![None],
+        };
+        if let Some(rest) = pattern.strip_prefix("(?i)") {
+            parser.offset = pattern.len() - rest.len();
+            parser.folded = true;
+        }
+        let node = parser.alternation()?;
+        if parser.offset < pattern.len() {
+            return Err(parser.error("unmatched `)`"));
+        }
+        if node.len() > MAX_PROGRAM {
+            return Err(parser.error("pattern too large"));
+        }
+
+        let mut program = vec![Inst::Save(0)];
+        compile(&node, &mut program, parser.folded);
+        program.push(Inst::Save(1));
+        program.push(Inst::Match);
+
+        Ok(Self {
+            pattern: pattern.to_owned(),
+            program,
+            names: parser.names,
+        })
+    }
+
+    /// Returns the pattern this was compiled from.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns `true` if the regex matches anywhere in the text.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.search(text, 0).is_some()
+    }
+
+    /// Returns the capture groups of the leftmost match, if any.
+    pub fn captures<'a>(&'a self, text: &'a str) -> Option<Captures<'a>> {
+        let slots = self.search(text, 0)?;
+        let names = &self.names;
+        Some(Captures { text, slots, names })
+    }
+
+    /// Replaces the leftmost match with the expanded `replacement`.
+    ///
+    /// See [`Captures::expand`] for the replacement syntax.
+    pub fn replace<'a>(&self, text: &'a str, replacement: &str) -> Cow<'a, str> {
+        self.replacen(text, 1, replacement)
+    }
+
+    /// Replaces every non-overlapping match with the expanded `replacement`.
+    ///
+    /// See [`Captures::expand`] for the replacement syntax.
+    pub fn replace_all<'a>(&self, text: &'a str, replacement: &str) -> Cow<'a, str> {
+        self.replacen(text, 0, replacement)
+    }
+
+    /// Replaces at most `limit` non-overlapping matches with the expanded
+    /// `replacement`. A `limit` of zero replaces every match.
+    ///
+    /// See [`Captures::expand`] for the replacement syntax.
+    pub fn replacen<'a>(&self, text: &'a str, limit: usize, replacement: &str) -> Cow<'a, str> {
+        let mut output = String::new();
+        let mut copied = 0;
+        let mut start = 0;
+        let mut count = 0;
+
+        while start <= text.len() && (limit == 0 || count < limit) {
+            let Some(slots) = self.search(text, start) else {
+                break;
+            };
+            let (Some(begin), Some(end)) = (slots[0], slots[1]) else {
+                break;
+            };
+            let names = &self.names;
+            let captures = Captures { text, slots, names };
+            output.push_str(&text[copied..begin]);
+            captures.expand(replacement, &mut output);
+            copied = end;
+            count += 1;
+            // An empty match would be found again at the same position, so
+            // the search resumes after the next character.
+            start = if end > begin {
+                end
+            } else {
+                end + text[end..].chars().next().map_or(1, char::len_utf8)
+            };
+        }
+
+        if count == 0 {
+            return Cow::Borrowed(text);
+        }
+        output.push_str(&text[copied..]);
+        Cow::Owned(output)
+    }
+
+    /// Returns the slots of the leftmost match starting at or after `start`.
+    fn search(&self, text: &str, start: usize) -> Option<Vec<Option<usize>>> {
+        let slots = vec![None; self.names.len() * 2];
+        let mut vm = Vm {
+            program: &self.program,
+            text,
+            visited: vec![0; (self.program.len() * (text.len() + 1)).div_ceil(64)],
+            stack: Vec::new(),
+            slots,
+        };
+        let mut position = start;
+        loop {
+            if text.is_char_boundary(position)
+                && let Some(slots) = vm.run(position)
+            {
+                return Some(slots);
+            }
+            if position >= text.len() {
+                return None;
+            }
+            position += 1;
+        }
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for Regex {}
+
+impl Hash for Regex {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.pattern.hash(state);
+    }
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.pattern).finish()
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+impl<'a> Captures<'a> {
+    /// Returns the text matched by the group at `index`, where `0` is the
+    /// whole match.
+    pub fn get(&self, index: usize) -> Option<&'a str> {
+        let start = (*self.slots.get(index * 2)?)?;
+        let end = (*self.slots.get(index * 2 + 1)?)?;
+        Some(&self.text[start..end])
+    }
+
+    /// Returns the text matched by the group with the given name.
+    pub fn name(&self, name: &str) -> Option<&'a str> {
+        let index = self
+            .names
+            .iter()
+            .position(|group| group.as_deref() == Some(name))?;
+        self.get(index)
+    }
+
+    /// Returns the number of groups, including the whole match.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Always returns `false`, since the whole match is always a group.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Expands `replacement` into `output`, substituting group references.
+    ///
+    /// `$n` and `${n}` refer to a group by index, `$name` and `${name}` by
+    /// name, and `$$` is a literal `$`. A name extends as far as possible
+    /// over letters, digits and underscores, so `${1}a` must be used to
+    /// follow a group by a letter. Groups that did not participate in the
+    /// match expand to nothing.
+    pub fn expand(&self, replacement: &str, output: &mut String) {
+        let mut rest = replacement;
+        while let Some(index) = rest.find('$') {
+            output.push_str(&rest[..index]);
+            rest = &rest[index + 1..];
+
+            if let Some(tail) = rest.strip_prefix('$') {
+                output.push('$');
+                rest = tail;
+                continue;
+            }
+
+            let (reference, tail) = if let Some(tail) = rest.strip_prefix('{') {
+                match tail.find('}') {
+                    Some(end) => (&tail[..end], &tail[end + 1..]),
+                    None => ("", rest),
+                }
+            } else {
+                let end = rest
+                    .find(|char: char| !(char.is_alphanumeric() || char == '_'))
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            };
+
+            if reference.is_empty() {
+                output.push('$');
+                continue;
+            }
+            let group = match reference.parse::<usize>() {
+                Ok(index) => self.get(index),
+                Err(_) => self.name(reference),
+            };
+            output.push_str(group.unwrap_or_default());
+            rest = tail;
+        }
+        output.push_str(rest);
+    }
+}
+
+struct Parser<'a> {
+    pattern: &'a str,
+    offset: usize,
+    folded: bool,
+    names: Vec<Option<String>>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.pattern[self.offset..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let char = self.peek()?;
+        self.offset += char.len_utf8();
+        Some(char)
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.pattern[self.offset..].starts_with(prefix) {
+            self.offset += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::InvalidRegex {
+            pattern: self.pattern.to_owned(),
+            offset: self.offset,
+            message: message.to_owned(),
+        }
+    }
+
+    fn alternation(&mut self) -> Result<Node, Error> {
+        let mut branches = vec![self.concat()?];
+        while self.eat("|") {
+            branches.push(self.concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.remove(0)
+        } else {
+            Node::Alternate(branches)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, Error> {
+        let mut nodes = Vec::new();
+        while let Some(char) = self.peek() {
+            if char == '|' || char == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.repeat(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.remove(0),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn repeat(&mut self, mut node: Node) -> Result<Node, Error> {
+        loop {
+            let start = self.offset;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => match self.counted()? {
+                    Some(bounds) => bounds,
+                    None => return Ok(node),
+                },
+                _ => return Ok(node),
+            };
+            if self.offset == start {
+                self.bump();
+            }
+            if matches!(node, Node::Empty | Node::Start | Node::End) {
+                self.offset = start;
+                return Err(self.error("nothing to repeat"));
+            }
+            let greedy = !self.eat("?");
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+            if node.len() > MAX_PROGRAM {
+                self.offset = start;
+                return Err(self.error("repetition too large"));
+            }
+        }
+    }
+
+    /// Parses `{n}`, `{n,}` or `{n,m}`, or returns [`None`] if the brace is
+    /// a literal.
+    fn counted(&mut self) -> Result<Option<(u32, Option<u32>)>, Error> {
+        let start = self.offset;
+        let rest = &self.pattern[start + 1..];
+        let Some(end) = rest.find('}') else {
+            return Ok(None);
+        };
+        let body = &rest[..end];
+        let parse = |bound: &str| bound.parse::<u32>().ok();
+        let bounds = match body.split_once(',') {
+            None => parse(body).map(|n| (n, Some(n))),
+            Some((min, "")) => parse(min).map(|min| (min, None)),
+            Some((min, max)) => parse(min)
+                .zip(parse(max))
+                .map(|(min, max)| (min, Some(max))),
+        };
+        let Some((min, max)) = bounds else {
+            return Ok(None);
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error("invalid repetition bounds"));
+        }
+        if max.unwrap_or(min) > MAX_REPEAT {
+            return Err(self.error("repetition bound too large"));
+        }
+        self.offset = start + 1 + end + 1;
+        Ok(Some((min, max)))
+    }
+
+    fn atom(&mut self) -> Result<Node, Error> {
+        let start = self.offset;
+        let Some(char) = self.bump() else {
+            return Ok(Node::Empty);
+        };
+        match char {
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '(' => self.group(),
+            '[' => self.class().map(Node::Class),
+            '\\' => self.escape(),
+            '*' | '+' | '?' => {
+                self.offset = start;
+                Err(self.error("nothing to repeat"))
+            }
+            char => Ok(Node::Char(char)),
+        }
+    }
+
+    fn group(&mut self) -> Result<Node, Error> {
+        let index = if self.eat("?:") {
+            None
+        } else if self.eat("?<") || self.eat("?P<") {
+            let rest = &self.pattern[self.offset..];
+            let end = rest
+                .find('>')
+                .ok_or_else(|| self.error("unterminated group name"))?;
+            let name = &rest[..end];
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|char| char.is_alphanumeric() || char == '_')
+            {
+                return Err(self.error("invalid group name"));
+            }
+            if self.names.iter().flatten().any(|other| other == name) {
+                return Err(self.error("duplicate group name"));
+            }
+            self.offset += end + 1;
+            self.names.push(Some(name.to_owned()));
+            Some(self.names.len() - 1)
+        } else if self.peek() == Some('?') {
+            return Err(self.error("unsupported group flag"));
+        } else {
+            self.names.push(None);
+            Some(self.names.len() - 1)
+        };
+
+        let node = self.alternation()?;
+        if !self.eat(")") {
+            return Err(self.error("unclosed group"));
+        }
+        Ok(Node::Group(Box::new(node), index))
+    }
+
+    fn escape(&mut self) -> Result<Node, Error> {
+        let char = self
+            .bump()
+            .ok_or_else(|| self.error("trailing backslash"))?;
+        Ok(match char {
+            'b' => Node::WordBoundary(true),
+            'B' => Node::WordBoundary(false),
+            _ => match self.perl_class(char) {
+                Some(class) => Node::Class(class),
+                None => Node::Char(self.escaped(char)?),
+            },
+        })
+    }
+
+    fn escaped(&self, char: char) -> Result<char, Error> {
+        match char {
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            char if char.is_ascii_alphanumeric() => Err(self.error("unsupported escape")),
+            char => Ok(char),
+        }
+    }
+
+    fn perl_class(&self, char: char) -> Option<Class> {
+        let (ranges, negated): (&[(char, char)], bool) = match char {
+            'd' => (DIGIT, false),
+            'D' => (DIGIT, true),
+            'w' => (WORD, false),
+            'W' => (WORD, true),
+            's' => (SPACE, false),
+            'S' => (SPACE, true),
+            _ => return None,
+        };
+        Some(Class {
+            negated,
+            ranges: ranges.to_vec(),
+            folded: self.folded,
+        })
+    }
+
+    fn class(&mut self) -> Result<Class, Error> {
+        let negated = self.eat("^");
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let char = self
+                .bump()
+                .ok_or_else(|| self.error("unclosed character class"))?;
+            let low = match char {
+                ']' if !first => break,
+                '\\' => {
+                    let char = self
+                        .bump()
+                        .ok_or_else(|| self.error("trailing backslash"))?;
+                    if let Some(class) = self.perl_class(char) {
+                        if class.negated {
+                            return Err(self.error("negated class inside a class"));
+                        }
+                        ranges.extend(class.ranges);
+                        first = false;
+                        continue;
+                    }
+                    self.escaped(char)?
+                }
+                char => char,
+            };
+            first = false;
+
+            let rest = &self.pattern[self.offset..];
+            if rest.starts_with('-') && !rest.starts_with("-]") && rest.len() > 1 {
+                self.bump();
+                let high = match self.bump() {
+                    Some('\\') => {
+                        let char = self
+                            .bump()
+                            .ok_or_else(|| self.error("trailing backslash"))?;
+                        self.escaped(char)?
+                    }
+                    Some(char) => char,
+                    None => return Err(self.error("unclosed character class")),
+                };
+                if high < low {
+                    return Err(self.error("invalid class range"));
+                }
+                ranges.push((low, high));
+            } else {
+                ranges.push((low, low));
+            }
+        }
+        Ok(Class {
+            negated,
+            ranges,
+            folded: self.folded,
+        })
+    }
+}
+
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[('\t', '\r'), (' ', ' ')];
+
+impl Node {
+    /// Returns the number of instructions the node compiles to.
+    fn len(&self) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::Char(_)
+            | Self::Any
+            | Self::Class(_)
+            | Self::Start
+            | Self::End
+            | Self::WordBoundary(_) => 1,
+            Self::Group(node, index) => node.len().saturating_add(2 * usize::from(index.is_some())),
+            Self::Concat(nodes) => nodes
+                .iter()
+                .fold(0, |len, node| len.saturating_add(node.len())),
+            Self::Alternate(branches) => {
+                branches.iter().fold(2 * (branches.len() - 1), |len, node| {
+                    len.saturating_add(node.len())
+                })
+            }
+            Self::Repeat { node, min, max, .. } => {
+                let len = node.len();
+                let optional = match max {
+                    None => len.saturating_add(2),
+                    Some(max) => ((max - min) as usize).saturating_mul(len.saturating_add(1)),
+                };
+                (*min as usize).saturating_mul(len).saturating_add(optional)
+            }
+        }
+    }
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>, folded: bool) {
+    match node {
+        Node::Empty => {}
+        Node::Char(char) if folded && has_case(*char) => {
+            program.push(Inst::Class(Class {
+                negated: false,
+                ranges: vec![(*char, *char)],
+                folded,
+            }));
+        }
+        Node::Char(char) => program.push(Inst::Char(*char)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(class) => program.push(Inst::Class(class.clone())),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::WordBoundary(expected) => program.push(Inst::WordBoundary(*expected)),
+        Node::Group(node, index) => match index {
+            Some(index) => {
+                program.push(Inst::Save(index * 2));
+                compile(node, program, folded);
+                program.push(Inst::Save(index * 2 + 1));
+            }
+            None => compile(node, program, folded),
+        },
+        Node::Concat(nodes) => {
+            for node in nodes {
+                compile(node, program, folded);
+            }
+        }
+        Node::Alternate(branches) => {
+            let mut jumps = Vec::new();
+            for (index, branch) in branches.iter().enumerate() {
+                if index + 1 < branches.len() {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(branch, program, folded);
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    let next = program.len();
+                    program[split] = Inst::Split(split + 1, next);
+                } else {
+                    compile(branch, program, folded);
+                }
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat {
+            node,
+            min,
+            max,
+            greedy,
+        } => {
+            for _ in 0..*min {
+                compile(node, program, folded);
+            }
+            match max {
+                None => {
+                    // L: split body, end; body; jump L; end:
+                    let split = program.len();
+                    program.push(Inst::Split(0, 0));
+                    compile(node, program, folded);
+                    program.push(Inst::Jump(split));
+                    let end = program.len();
+                    program[split] = branch(split + 1, end, *greedy);
+                }
+                Some(max) => {
+                    // Each optional copy is nested inside the previous one,
+                    // so skipping one skips the rest.
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        compile(node, program, folded);
+                    }
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = branch(split + 1, end, *greedy);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn branch(body: usize, end: usize, greedy: bool) -> Inst {
+    if greedy {
+        Inst::Split(body, end)
+    } else {
+        Inst::Split(end, body)
+    }
+}
+
+fn has_case(char: char) -> bool {
+    char.is_lowercase() || char.is_uppercase()
+}
+
+impl Class {
+    fn contains(&self, char: char) -> bool {
+        let contains = |char: char| {
+            self.ranges
+                .iter()
+                .any(|&(low, high)| low <= char && char <= high)
+        };
+        let found = contains(char)
+            || (self.folded
+                && (char.to_lowercase().any(contains) || char.to_uppercase().any(contains)));
+        found != self.negated
+    }
+}
+
+struct Vm<'a> {
+    program: &'a [Inst],
+    text: &'a str,
+    // Bitset of visited `(instruction, position)` states, shared between
+    // start positions since whether a state leads to a match does not
+    // depend on where the search started.
+    visited: Vec<u64>,
+    stack: Vec<Job>,
+    slots: Vec<Option<usize>>,
+}
+
+enum Job {
+    Explore(usize, usize),
+    Restore(usize, Option<usize>),
+}
+
+impl Vm<'_> {
+    fn run(&mut self, start: usize) -> Option<Vec<Option<usize>>> {
+        self.slots.fill(None);
+        self.stack.clear();
+        self.stack.push(Job::Explore(0, start));
+        while let Some(job) = self.stack.pop() {
+            let (mut pc, mut position) = match job {
+                Job::Explore(pc, position) => (pc, position),
+                Job::Restore(slot, value) => {
+                    self.slots[slot] = value;
+                    continue;
+                }
+            };
+            loop {
+                let state = pc * (self.text.len() + 1) + position;
+                let (word, bit) = (state / 64, 1 << (state % 64));
+                if self.visited[word] & bit != 0 {
+                    break;
+                }
+                self.visited[word] |= bit;
+
+                let next = self.text[position..].chars().next();
+                match &self.program[pc] {
+                    Inst::Char(expected) => match next {
+                        Some(char) if char == *expected => {
+                            pc += 1;
+                            position += char.len_utf8();
+                        }
+                        _ => break,
+                    },
+                    Inst::Any => match next {
+                        Some(char) if char != '\n' => {
+                            pc += 1;
+                            position += char.len_utf8();
+                        }
+                        _ => break,
+                    },
+                    Inst::Class(class) => match next {
+                        Some(char) if class.contains(char) => {
+                            pc += 1;
+                            position += char.len_utf8();
+                        }
+                        _ => break,
+                    },
+                    Inst::Split(first, second) => {
+                        self.stack.push(Job::Explore(*second, position));
+                        pc = *first;
+                    }
+                    Inst::Jump(target) => pc = *target,
+                    Inst::Save(slot) => {
+                        self.stack.push(Job::Restore(*slot, self.slots[*slot]));
+                        self.slots[*slot] = Some(position);
+                        pc += 1;
+                    }
+                    Inst::Start if position == 0 => pc += 1,
+                    Inst::End if position == self.text.len() => pc += 1,
+                    Inst::Start | Inst::End => break,
+                    Inst::WordBoundary(expected) => {
+                        let before = self.text[..position].chars().next_back();
+                        let is_word = |char: Option<char>| {
+                            char.is_some_and(|char| char.is_ascii_alphanumeric() || char == '_')
+                        };
+                        if (is_word(before) != is_word(next)) != *expected {
+                            break;
+                        }
+                        pc += 1;
+                    }
+                    Inst::Match => return Some(self.slots.clone()),
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Option<String> {
+        let regex = Regex::new(pattern).unwrap();
+        let captures = regex.captures(text)?;
+        captures.get(0).map(str::to_owned)
+    }
+
+    fn message(pattern: &str) -> String {
+        match Regex::new(pattern) {
+            Err(Error::InvalidRegex { message, .. }) => message,
+            result => panic!("expected an error for {pattern:?}, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn literals_classes_and_anchors() {
+        assert_eq!(find(r"\d+", "IMG_0042.jpg").as_deref(), Some("0042"));
+        assert_eq!(find(r"[a-c]+", "xxabcbd").as_deref(), Some("abcb"));
+        assert_eq!(find(r"[^0-9_]+", "12ab_3").as_deref(), Some("ab"));
+        assert_eq!(find(r"^\w+$", "file_1").as_deref(), Some("file_1"));
+        assert_eq!(find(r"^a", "ba").as_deref(), None);
+        assert_eq!(find(r"a.c", "a\nc abc").as_deref(), Some("abc"));
+        assert_eq!(find(r"\s\S", "a  b").as_deref(), Some(" b"));
+    }
+
+    #[test]
+    fn repetitions_are_greedy_or_lazy() {
+        assert_eq!(find(r"a+", "caaat").as_deref(), Some("aaa"));
+        assert_eq!(find(r"a+?", "caaat").as_deref(), Some("a"));
+        assert_eq!(find(r"<.*>", "<a><b>").as_deref(), Some("<a><b>"));
+        assert_eq!(find(r"<.*?>", "<a><b>").as_deref(), Some("<a>"));
+        assert_eq!(find(r"a{2,3}", "aaaa").as_deref(), Some("aaa"));
+        assert_eq!(find(r"a{2,}", "aaaa").as_deref(), Some("aaaa"));
+        assert_eq!(find(r"a{2}", "a").as_deref(), None);
+        assert_eq!(find(r"x{a}", "x{a}").as_deref(), Some("x{a}"));
+    }
+
+    #[test]
+    fn groups_and_alternation() {
+        let regex = Regex::new(r"(?<year>\d{4})-(\d{2})|none").unwrap();
+        let captures = regex.captures("on 2024-05").unwrap();
+        assert_eq!(captures.len(), 3);
+        assert_eq!(captures.name("year"), Some("2024"));
+        assert_eq!(captures.get(2), Some("05"));
+        let captures = regex.captures("none").unwrap();
+        assert_eq!(captures.get(1), None);
+        assert_eq!(find(r"(?:ab)+", "ababa").as_deref(), Some("abab"));
+        assert_eq!(find(r"(?P<x>b)", "abc").as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn case_insensitive_matching() {
+        assert_eq!(find(r"(?i)img_\d+", "IMG_12").as_deref(), Some("IMG_12"));
+        assert_eq!(find(r"(?i)[a-c]+", "xABCd").as_deref(), Some("ABC"));
+        assert_eq!(
+            find(r"(?i)straße", "STRASSE STRAßE").as_deref(),
+            Some("STRAßE")
+        );
+    }
+
+    #[test]
+    fn word_classes_and_boundaries_agree() {
+        assert_eq!(find(r"\bcat\b", "a cat.").as_deref(), Some("cat"));
+        assert_eq!(find(r"\bcat\b", "concat").as_deref(), None);
+        assert_eq!(find(r"\Bcat", "concat").as_deref(), Some("cat"));
+        // Both treat non-ASCII letters as non-word characters.
+        assert_eq!(find(r"\w+", "éa").as_deref(), Some("a"));
+        assert_eq!(find(r"\bé", "é").as_deref(), None);
+        assert_eq!(find(r"\ba", "éa").as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn replacements() {
+        let regex = Regex::new(r"(\w+)\.(\w+)").unwrap();
+        assert_eq!(regex.replace("a.txt b.md", "$2.$1"), "txt.a b.md");
+        assert_eq!(regex.replace_all("a.txt b.md", "${1}_$$"), "a_$ b_$");
+        assert_eq!(regex.replacen("a.x b.y c.z", 2, "-"), "- - c.z");
+        let regex = Regex::new(r"x*").unwrap();
+        assert_eq!(regex.replace_all("abc", "-"), "-a-b-c-");
+        assert_eq!(regex.replace_all("ü", "-"), "-ü-");
+        assert!(matches!(
+            Regex::new("z").unwrap().replace("abc", "-"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn malformed_patterns_are_rejected() {
+        assert_eq!(message("(a"), "unclosed group");
+        assert_eq!(message("a)"), "unmatched `)`");
+        assert_eq!(message("*a"), "nothing to repeat");
+        assert_eq!(message("a{3,2}"), "invalid repetition bounds");
+        assert_eq!(message("a{1001}"), "repetition bound too large");
+        assert_eq!(message("[a"), "unclosed character class");
+        assert_eq!(message("a\\"), "trailing backslash");
+        assert_eq!(message(r"\q"), "unsupported escape");
+    }
+
+    #[test]
+    fn programs_are_bounded() {
+        assert_eq!(message("(a{1000}){1000}"), "repetition too large");
+        assert_eq!(message("((a{100}){100}){100}"), "repetition too large");
+        assert!(Regex::new("(a{100}){100}").is_ok());
+        let pattern = "a{1000}".repeat(66);
+        assert_eq!(message(&pattern), "pattern too large");
+    }
+}
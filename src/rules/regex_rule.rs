@@ -0,0 +1,151 @@
+use super::error::Error;
+use super::recipe::{Fields, Value};
+use super::regex::Regex;
+use crate::rename::{self, RenameQueue};
+use std::path::{Path, PathBuf};
+
+/// A rename rule substituting matches of a regular expression.
+///
+/// By default, only the file name is matched, and only the first match is
+/// replaced. The replacement may refer to capture groups, as described in
+/// [`Captures::expand`].
+///
+/// [`Captures::expand`]: super::Captures::expand
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rules::RegexRule;
+/// use mofu::walk_dir::walk_dir;
+///
+/// let rule = RegexRule::new(r"IMG_(\d+)", "photo_$1").unwrap();
+/// let mut queue = rule.queue(walk_dir("photos", 0).unwrap()).unwrap();
+/// queue.rename().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexRule {
+    pattern: Regex,
+    replacement: String,
+    full_path: bool,
+    all: bool,
+}
+
+impl RegexRule {
+    /// Creates a new [`RegexRule`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidRegex`] if the pattern is malformed.
+    pub fn new<R>(pattern: &str, replacement: R) -> Result<Self, Error>
+    where
+        R: Into<String>,
+    {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.into(),
+            full_path: false,
+            all: false,
+        })
+    }
+
+    /// Sets whether to match the full path instead of the file name.
+    ///
+    /// Defaults to `false`.
+    pub fn full_path(mut self, full_path: bool) -> Self {
+        self.full_path = full_path;
+        self
+    }
+
+    /// Sets whether to replace every match instead of the first one.
+    ///
+    /// Defaults to `false`.
+    pub fn all(mut self, all: bool) -> Self {
+        self.all = all;
+        self
+    }
+
+    /// Returns the regular expression.
+    #[inline]
+    pub fn pattern(&self) -> &Regex {
+        &self.pattern
+    }
+
+    /// Returns the replacement.
+    #[inline]
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+
+    /// Applies the rule to a path, returning the new path.
+    ///
+    /// Returns [`None`] if nothing matches, if the matched part is not valid
+    /// UTF-8, or if the replacement leaves the path unchanged.
+    pub fn apply(&self, path: &Path) -> Option<PathBuf> {
+        if self.full_path {
+            let path = path.to_str()?;
+            let replaced = self.replace(path)?;
+            return Some(PathBuf::from(replaced));
+        }
+        let name = path.file_name()?.to_str()?;
+        let replaced = self.replace(name)?;
+        Some(path.with_file_name(replaced))
+    }
+
+    /// Applies the rule to every path, returning the mappings of those that
+    /// change.
+    pub fn mappings<I, P>(&self, paths: I) -> Vec<(PathBuf, PathBuf)>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let path = path.as_ref();
+                let dst = self.apply(path)?;
+                Some((path.to_path_buf(), dst))
+            })
+            .collect()
+    }
+
+    /// Applies the rule to every path, and plans the resulting renames.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mappings conflict, as described in
+    /// [`RenameQueue::new`].
+    pub fn queue<I, P>(&self, paths: I) -> Result<RenameQueue, rename::Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        RenameQueue::new(self.mappings(paths))
+    }
+
+    fn replace(&self, text: &str) -> Option<String> {
+        let limit = if self.all { 0 } else { 1 };
+        let replaced = self.pattern.replacen(text, limit, &self.replacement);
+        (replaced != text).then(|| replaced.into_owned())
+    }
+}
+
+impl RegexRule {
+    pub(super) fn to_fields(&self) -> Vec<(String, Value)> {
+        vec![
+            ("pattern".to_owned(), Value::str(self.pattern.as_str())),
+            ("replacement".to_owned(), Value::str(&self.replacement)),
+            ("full_path".to_owned(), Value::Bool(self.full_path)),
+            ("all".to_owned(), Value::Bool(self.all)),
+        ]
+    }
+
+    pub(super) fn from_fields(fields: &mut Fields) -> Result<Self, String> {
+        let pattern = fields.require("pattern")?.into_str()?;
+        let replacement = fields.require("replacement")?.into_str()?;
+        let rule = Self::new(&pattern, replacement)
+            .map_err(|error| error.to_string().trim_end().to_owned())?;
+        Ok(rule
+            .full_path(fields.bool("full_path")?.unwrap_or(false))
+            .all(fields.bool("all")?.unwrap_or(false)))
+    }
+}
@@ -0,0 +1,168 @@
+use super::natural::natural_cmp;
+use super::parts::Parts;
+use crate::rename::{self, RenameQueue};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A helper renumbering series of files in natural order, with consistent
+/// zero padding.
+///
+/// Files are grouped into series by their directory and by the text around
+/// the last number in their stem, ignoring the extension, so `img1.jpg`,
+/// `img2.png` and `img10.jpg` form a series, while `doc1.pdf` forms
+/// another. Each series is sorted with [`natural_cmp`], and its numbers are
+/// replaced with consecutive ones, padded with zeros to the width of the
+/// largest. Files without a number in their stem are left alone.
+///
+/// [`natural_cmp`]: super::natural_cmp
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::Renumber;
+/// use std::path::PathBuf;
+///
+/// let paths = ["img1.jpg", "img10.jpg", "img11.jpg", "img2.jpg"];
+/// let mappings = Renumber::new().width(2).mappings(paths);
+/// assert_eq!(
+///     mappings,
+///     [
+///         (PathBuf::from("img1.jpg"), PathBuf::from("img01.jpg")),
+///         (PathBuf::from("img2.jpg"), PathBuf::from("img02.jpg")),
+///         (PathBuf::from("img10.jpg"), PathBuf::from("img03.jpg")),
+///         (PathBuf::from("img11.jpg"), PathBuf::from("img04.jpg")),
+///     ],
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Renumber {
+    start: u64,
+    step: u64,
+    width: usize,
+}
+
+/// A series of files, by their directory and the text before and after
+/// their number.
+type Series = (PathBuf, String, String);
+
+impl Renumber {
+    /// Creates a new [`Renumber`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the first number of each series.
+    ///
+    /// Defaults to 1.
+    pub fn start(mut self, start: u64) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Sets the increment between numbers, which is raised to 1 if zero.
+    ///
+    /// Defaults to 1.
+    pub fn step(mut self, step: u64) -> Self {
+        self.step = step.max(1);
+        self
+    }
+
+    /// Sets the minimum width numbers are padded to.
+    ///
+    /// Numbers are always padded to the width of the largest in their
+    /// series. Defaults to 0.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Renumbers every path, returning the mappings of those that change,
+    /// ordered by series and then by number.
+    ///
+    /// Paths whose file names are not valid UTF-8 are left alone.
+    pub fn mappings<I, P>(&self, paths: I) -> Vec<(PathBuf, PathBuf)>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut series: BTreeMap<Series, Vec<PathBuf>> = BTreeMap::new();
+        for path in paths {
+            let path = path.as_ref();
+            let Some(key) = split(path) else {
+                continue;
+            };
+            series.entry(key).or_default().push(path.to_path_buf());
+        }
+
+        let mut mappings = Vec::new();
+        for ((_, before, after), mut paths) in series {
+            paths.sort_by(|a, b| natural_cmp(name(a), name(b)));
+            let count = paths.len() as u64;
+            let last = self
+                .start
+                .saturating_add(self.step.saturating_mul(count - 1));
+            let width = self.width.max(last.to_string().len());
+            let mut number = self.start;
+            for src in paths {
+                let parts = Parts::new(name(&src));
+                // The leading dots are part of the series, and kept by
+                // `Parts::join`.
+                let before = &before[parts.dots.len()..];
+                let stem = format!("{before}{number:0width$}{after}");
+                let renamed = parts.join(&stem, parts.extension);
+                if renamed != name(&src) {
+                    let dst = src.with_file_name(renamed);
+                    mappings.push((src, dst));
+                }
+                number = number.saturating_add(self.step);
+            }
+        }
+        mappings
+    }
+
+    /// Renumbers every path, and plans the resulting renames.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mappings conflict, as described in
+    /// [`RenameQueue::new`].
+    pub fn queue<I, P>(&self, paths: I) -> Result<RenameQueue, rename::Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        RenameQueue::new(self.mappings(paths))
+    }
+}
+
+impl Default for Renumber {
+    fn default() -> Self {
+        Self {
+            start: 1,
+            step: 1,
+            width: 0,
+        }
+    }
+}
+
+/// Returns the file name of a path grouped by [`split`], which is valid
+/// UTF-8.
+fn name(path: &Path) -> &str {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+}
+
+/// Returns the series of a path, or [`None`] if there is no number in its
+/// stem.
+fn split(path: &Path) -> Option<Series> {
+    let parts = Parts::new(path.file_name()?.to_str()?);
+    let stem = parts.stem;
+    let end = stem.rfind(|char: char| char.is_ascii_digit())? + 1;
+    let start = stem[..end]
+        .trim_end_matches(|char: char| char.is_ascii_digit())
+        .len();
+    let directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let before = format!("{}{}", parts.dots, &stem[..start]);
+    Some((directory, before, stem[end..].to_owned()))
+}
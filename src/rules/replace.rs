@@ -0,0 +1,107 @@
+use super::parts::Parts;
+use super::recipe::{Fields, Value};
+use std::path::{Path, PathBuf};
+
+/// A rename rule replacing literal text in file stems.
+///
+/// The extension is left untouched, so replacing `.` only affects the stem.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::ReplaceRule;
+/// use std::path::Path;
+///
+/// let rule = ReplaceRule::new(" - Copy", "");
+/// let dst = rule.apply(Path::new("notes - Copy.txt")).unwrap();
+/// assert_eq!(dst, Path::new("notes.txt"));
+///
+/// let rule = ReplaceRule::new(" ", "_");
+/// let dst = rule.apply(Path::new("my holiday photo.jpg")).unwrap();
+/// assert_eq!(dst, Path::new("my_holiday_photo.jpg"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReplaceRule {
+    find: String,
+    with: String,
+    count: usize,
+}
+
+impl ReplaceRule {
+    /// Creates a new [`ReplaceRule`] replacing every occurrence of `find`
+    /// with `with`.
+    pub fn new<F, W>(find: F, with: W) -> Self
+    where
+        F: Into<String>,
+        W: Into<String>,
+    {
+        Self {
+            find: find.into(),
+            with: with.into(),
+            count: 0,
+        }
+    }
+
+    /// Sets the maximum number of occurrences to replace, from the start of
+    /// the stem. A count of zero replaces every occurrence.
+    ///
+    /// Defaults to 0.
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Returns the text to find.
+    #[inline]
+    pub fn find(&self) -> &str {
+        &self.find
+    }
+
+    /// Returns the text to replace it with.
+    #[inline]
+    pub fn with(&self) -> &str {
+        &self.with
+    }
+
+    /// Applies the rule to a path, returning the new path.
+    ///
+    /// Leading dots of hidden files are kept. Returns [`None`] if the file
+    /// name is not valid UTF-8, if nothing is found, or if nothing would be
+    /// left of the stem.
+    pub fn apply(&self, path: &Path) -> Option<PathBuf> {
+        if self.find.is_empty() {
+            return None;
+        }
+        let original = path.file_name()?.to_str()?;
+        let parts = Parts::new(original);
+        if !parts.stem.contains(&self.find) {
+            return None;
+        }
+        let stem = match self.count {
+            0 => parts.stem.replace(&self.find, &self.with),
+            count => parts.stem.replacen(&self.find, &self.with, count),
+        };
+        if stem.is_empty() {
+            return None;
+        }
+        let replaced = parts.join(&stem, parts.extension);
+        (replaced != original).then(|| path.with_file_name(replaced))
+    }
+}
+
+impl ReplaceRule {
+    pub(super) fn to_fields(&self) -> Vec<(String, Value)> {
+        vec![
+            ("find".to_owned(), Value::str(&self.find)),
+            ("with".to_owned(), Value::str(&self.with)),
+            ("count".to_owned(), Value::uint(self.count as u64)),
+        ]
+    }
+
+    pub(super) fn from_fields(fields: &mut Fields) -> Result<Self, String> {
+        let find = fields.require("find")?.into_str()?;
+        let with = fields.require("with")?.into_str()?;
+        let count = fields.u64("count")?.unwrap_or(0);
+        Ok(Self::new(find, with).count(count as usize))
+    }
+}
@@ -0,0 +1,245 @@
+use super::parts::Parts;
+use super::recipe::{Fields, Value};
+use std::path::{Path, PathBuf};
+
+/// A class of characters to sanitize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CharClass {
+    /// Unicode whitespace, such as spaces, tabs and no-break spaces.
+    Whitespace,
+
+    /// Control characters, such as newlines and escapes.
+    Control,
+
+    /// Emoji, along with the joiners, modifiers and variation selectors
+    /// composing them.
+    Emoji,
+
+    /// Characters Windows reserves in file names: `<>:"/\|?*`.
+    Reserved,
+
+    /// Every character outside ASCII.
+    NonAscii,
+}
+
+/// A rename rule replacing whole classes of characters in file stems.
+///
+/// Every run of characters from the selected classes is replaced with a
+/// single replacement, and runs at either end of the stem are removed. The
+/// extension is left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::{CharClass, SanitizeRule};
+/// use std::path::Path;
+///
+/// let rule = SanitizeRule::new()
+///     .class(CharClass::Whitespace)
+///     .class(CharClass::Control)
+///     .class(CharClass::Emoji);
+/// let dst = rule.apply(Path::new("  party \u{1F389}\u{1F389} pics\t.zip")).unwrap();
+/// assert_eq!(dst, Path::new("party_pics.zip"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SanitizeRule {
+    classes: Vec<CharClass>,
+    replacement: String,
+    collapse: bool,
+    trim: bool,
+}
+
+impl CharClass {
+    /// Returns `true` if the character belongs to this class.
+    pub fn contains(self, char: char) -> bool {
+        match self {
+            Self::Whitespace => char.is_whitespace(),
+            Self::Control => char.is_control(),
+            Self::Emoji => is_emoji(char),
+            Self::Reserved => matches!(char, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*'),
+            Self::NonAscii => !char.is_ascii(),
+        }
+    }
+}
+
+impl SanitizeRule {
+    /// Creates a new [`SanitizeRule`] with no classes selected.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects a class of characters to replace.
+    pub fn class(mut self, class: CharClass) -> Self {
+        if !self.classes.contains(&class) {
+            self.classes.push(class);
+        }
+        self
+    }
+
+    /// Sets the replacement, which may be empty to remove characters.
+    ///
+    /// Defaults to `_`.
+    pub fn replacement<S>(mut self, replacement: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.replacement = replacement.into();
+        self
+    }
+
+    /// Sets whether a run of characters is replaced once, rather than once
+    /// per character.
+    ///
+    /// Defaults to `true`.
+    pub fn collapse(mut self, collapse: bool) -> Self {
+        self.collapse = collapse;
+        self
+    }
+
+    /// Sets whether runs at either end of the stem are removed, rather than
+    /// replaced.
+    ///
+    /// Defaults to `true`.
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Sanitizes a text.
+    pub fn convert(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut run = 0;
+        for char in text.chars() {
+            if self.classes.iter().any(|class| class.contains(char)) {
+                run += 1;
+                continue;
+            }
+            self.flush(&mut output, &mut run);
+            output.push(char);
+        }
+        if !self.trim {
+            self.flush(&mut output, &mut run);
+        }
+        output
+    }
+
+    /// Applies the rule to a path, returning the new path.
+    ///
+    /// Leading dots of hidden files are kept. Returns [`None`] if the file
+    /// name is not valid UTF-8, if nothing would be left of the stem, or if
+    /// nothing is replaced.
+    pub fn apply(&self, path: &Path) -> Option<PathBuf> {
+        let original = path.file_name()?.to_str()?;
+        let parts = Parts::new(original);
+        let stem = self.convert(parts.stem);
+        if stem.is_empty() {
+            return None;
+        }
+        let sanitized = parts.join(&stem, parts.extension);
+        (sanitized != original).then(|| path.with_file_name(sanitized))
+    }
+
+    fn flush(&self, output: &mut String, run: &mut usize) {
+        if *run == 0 {
+            return;
+        }
+        if !(self.trim && output.is_empty()) {
+            let count = if self.collapse { 1 } else { *run };
+            for _ in 0..count {
+                output.push_str(&self.replacement);
+            }
+        }
+        *run = 0;
+    }
+}
+
+impl Default for SanitizeRule {
+    fn default() -> Self {
+        Self {
+            classes: Vec::new(),
+            replacement: "_".to_owned(),
+            collapse: true,
+            trim: true,
+        }
+    }
+}
+
+fn is_emoji(char: char) -> bool {
+    matches!(
+        char as u32,
+        // Pictographs, emoticons, transport and map symbols, and the
+        // regional indicators forming flags.
+        0x1F000..=0x1FAFF
+            // Miscellaneous symbols and dingbats.
+            | 0x2600..=0x27BF
+            // Stars, arrows and squares such as `⭐`.
+            | 0x2B00..=0x2BFF
+            // Watches and media controls such as `⌚` and `⏩`.
+            | 0x231A..=0x231B
+            | 0x23E9..=0x23FA
+            // Zero width joiner, keycap, variation selectors and tags.
+            | 0x200D
+            | 0x20E3
+            | 0xFE00..=0xFE0F
+            | 0xE0020..=0xE007F
+    )
+}
+
+impl CharClass {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Whitespace => "whitespace",
+            Self::Control => "control",
+            Self::Emoji => "emoji",
+            Self::Reserved => "reserved",
+            Self::NonAscii => "non_ascii",
+        }
+    }
+
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "whitespace" => Some(Self::Whitespace),
+            "control" => Some(Self::Control),
+            "emoji" => Some(Self::Emoji),
+            "reserved" => Some(Self::Reserved),
+            "non_ascii" => Some(Self::NonAscii),
+            _ => None,
+        }
+    }
+}
+
+impl SanitizeRule {
+    pub(super) fn to_fields(&self) -> Vec<(String, Value)> {
+        let classes = self
+            .classes
+            .iter()
+            .map(|class| Value::str(class.name()))
+            .collect();
+        vec![
+            ("classes".to_owned(), Value::List(classes)),
+            ("replacement".to_owned(), Value::str(&self.replacement)),
+            ("collapse".to_owned(), Value::Bool(self.collapse)),
+            ("trim".to_owned(), Value::Bool(self.trim)),
+        ]
+    }
+
+    pub(super) fn from_fields(fields: &mut Fields) -> Result<Self, String> {
+        let mut rule = Self::new();
+        for class in fields.require("classes")?.into_list()? {
+            let name = class.into_str()?;
+            let class =
+                CharClass::from_name(&name).ok_or_else(|| format!("unknown class `{name}`"))?;
+            rule = rule.class(class);
+        }
+        if let Some(replacement) = fields.string("replacement")? {
+            rule = rule.replacement(replacement);
+        }
+        if let Some(collapse) = fields.bool("collapse")? {
+            rule = rule.collapse(collapse);
+        }
+        if let Some(trim) = fields.bool("trim")? {
+            rule = rule.trim(trim);
+        }
+        Ok(rule)
+    }
+}
@@ -0,0 +1,217 @@
+use super::parts::Parts;
+use super::recipe::{Fields, Value};
+use std::path::{Path, PathBuf};
+
+/// A rename rule turning file names into URL and shell safe slugs.
+///
+/// Latin, Greek and Cyrillic letters are transliterated to ASCII, `&` is
+/// spelled out, apostrophes are dropped, and every other run of characters
+/// outside `a-z`, `A-Z` and `0-9` is replaced with a single separator. The
+/// stem and the extension are slugified separately.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::SlugRule;
+/// use std::path::Path;
+///
+/// let rule = SlugRule::new().ampersand("und");
+/// let dst = rule.apply(Path::new("Füße & Hände.PDF")).unwrap();
+/// assert_eq!(dst, Path::new("fusse-und-hande.pdf"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SlugRule {
+    separator: char,
+    ampersand: String,
+    lowercase: bool,
+}
+
+impl SlugRule {
+    /// Creates a new [`SlugRule`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the separator replacing unsafe characters.
+    ///
+    /// Defaults to `-`.
+    pub fn separator(mut self, separator: char) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Sets the word `&` is spelled as, or removes it if empty.
+    ///
+    /// Defaults to `and`.
+    pub fn ampersand<S>(mut self, ampersand: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.ampersand = ampersand.into();
+        self
+    }
+
+    /// Sets whether to lowercase the slug.
+    ///
+    /// Defaults to `true`.
+    pub fn lowercase(mut self, lowercase: bool) -> Self {
+        self.lowercase = lowercase;
+        self
+    }
+
+    /// Slugifies a text.
+    pub fn convert(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        // Separators are only written before the next word, so none are left
+        // at either end.
+        let mut pending = false;
+        let mut buffer = [0; 4];
+        for char in text.chars() {
+            let word = match char {
+                'a'..='z' | 'A'..='Z' | '0'..='9' => &*char.encode_utf8(&mut buffer),
+                '\'' | '\u{2019}' => continue,
+                '&' => {
+                    pending = true;
+                    self.ampersand.as_str()
+                }
+                char => match transliterate(char) {
+                    Some(ascii) => ascii,
+                    None => {
+                        pending = true;
+                        continue;
+                    }
+                },
+            };
+            if word.is_empty() {
+                continue;
+            }
+            if pending && !output.is_empty() {
+                output.push(self.separator);
+            }
+            pending = char == '&';
+            if self.lowercase {
+                output.extend(word.chars().map(|char| char.to_ascii_lowercase()));
+            } else {
+                output.push_str(word);
+            }
+        }
+        output
+    }
+
+    /// Applies the rule to a path, returning the new path.
+    ///
+    /// Leading dots of hidden files are kept. Returns [`None`] if the file
+    /// name is not valid UTF-8, if nothing is left of it, or if it is already
+    /// a slug.
+    pub fn apply(&self, path: &Path) -> Option<PathBuf> {
+        let original = path.file_name()?.to_str()?;
+        let parts = Parts::new(original);
+        let stem = self.convert(parts.stem);
+        if stem.is_empty() {
+            return None;
+        }
+        let extension = parts
+            .extension
+            .map(|extension| self.convert(extension))
+            .filter(|extension| !extension.is_empty());
+        let slug = parts.join(&stem, extension.as_deref());
+        (slug != original).then(|| path.with_file_name(slug))
+    }
+}
+
+impl Default for SlugRule {
+    fn default() -> Self {
+        Self {
+            separator: '-',
+            ampersand: "and".to_owned(),
+            lowercase: true,
+        }
+    }
+}
+
+/// Latin-1 Supplement and Latin Extended-A letters from `U+00C0` to
+/// `U+017F`, stripped of their diacritics.
+#[rustfmt::skip]
+const LATIN: [&str; 192] = [
+    "A", "A", "A", "A", "A", "A", "AE", "C", "E", "E", "E", "E", "I", "I", "I", "I",
+    "D", "N", "O", "O", "O", "O", "O", "x", "O", "U", "U", "U", "U", "Y", "Th", "ss",
+    "a", "a", "a", "a", "a", "a", "ae", "c", "e", "e", "e", "e", "i", "i", "i", "i",
+    "d", "n", "o", "o", "o", "o", "o", "", "o", "u", "u", "u", "u", "y", "th", "y",
+    "A", "a", "A", "a", "A", "a", "C", "c", "C", "c", "C", "c", "C", "c", "D", "d",
+    "D", "d", "E", "e", "E", "e", "E", "e", "E", "e", "E", "e", "G", "g", "G", "g",
+    "G", "g", "G", "g", "H", "h", "H", "h", "I", "i", "I", "i", "I", "i", "I", "i",
+    "I", "i", "IJ", "ij", "J", "j", "K", "k", "k", "L", "l", "L", "l", "L", "l", "L",
+    "l", "L", "l", "N", "n", "N", "n", "N", "n", "n", "N", "n", "O", "o", "O", "o",
+    "O", "o", "OE", "oe", "R", "r", "R", "r", "R", "r", "S", "s", "S", "s", "S", "s",
+    "S", "s", "T", "t", "T", "t", "T", "t", "U", "u", "U", "u", "U", "u", "U", "u",
+    "U", "u", "U", "u", "W", "w", "Y", "y", "Y", "Z", "z", "Z", "z", "Z", "z", "s",
+];
+
+/// Greek letters from `U+03B1` to `U+03C9`.
+#[rustfmt::skip]
+const GREEK: [&str; 25] = [
+    "a", "v", "g", "d", "e", "z", "i", "th", "i", "k", "l", "m", "n", "x", "o", "p",
+    "r", "s", "s", "t", "y", "f", "ch", "ps", "o",
+];
+
+/// Cyrillic letters from `U+0430` to `U+044F`.
+#[rustfmt::skip]
+const CYRILLIC: [&str; 32] = [
+    "a", "b", "v", "g", "d", "e", "zh", "z", "i", "y", "k", "l", "m", "n", "o", "p",
+    "r", "s", "t", "u", "f", "kh", "ts", "ch", "sh", "shch", "", "y", "", "e", "yu", "ya",
+];
+
+/// Returns the ASCII spelling of a letter, or [`None`] if it is not a known
+/// letter.
+///
+/// Uppercase Greek and Cyrillic letters are spelled in lowercase, which only
+/// matters when the slug is not lowercased.
+fn transliterate(char: char) -> Option<&'static str> {
+    let code = char as u32;
+    let ascii = match code {
+        0xC0..=0x17F => LATIN[(code - 0xC0) as usize],
+        // Uppercase Greek, skipping the unassigned `U+03A2`.
+        0x391..=0x3A9 if code != 0x3A2 => GREEK[(code - 0x391) as usize],
+        0x3B1..=0x3C9 => GREEK[(code - 0x3B1) as usize],
+        0x386 | 0x3AC => "a",
+        0x388 | 0x3AD => "e",
+        0x389 | 0x38A | 0x3AE | 0x3AF => "i",
+        0x38C | 0x38F | 0x3CC | 0x3CE => "o",
+        0x38E | 0x3CD => "y",
+        0x410..=0x42F => CYRILLIC[(code - 0x410) as usize],
+        0x430..=0x44F => CYRILLIC[(code - 0x430) as usize],
+        0x401 | 0x451 => "e",
+        _ => return None,
+    };
+    // Symbols such as `÷` have no spelling, and act as separators, unlike
+    // the silent Cyrillic hard and soft signs.
+    (!ascii.is_empty() || matches!(code, 0x42A | 0x42C | 0x44A | 0x44C)).then_some(ascii)
+}
+
+impl SlugRule {
+    pub(super) fn to_fields(&self) -> Vec<(String, Value)> {
+        vec![
+            ("separator".to_owned(), Value::str(self.separator)),
+            ("ampersand".to_owned(), Value::str(&self.ampersand)),
+            ("lowercase".to_owned(), Value::Bool(self.lowercase)),
+        ]
+    }
+
+    pub(super) fn from_fields(fields: &mut Fields) -> Result<Self, String> {
+        let mut rule = Self::new();
+        if let Some(separator) = fields.string("separator")? {
+            let mut chars = separator.chars();
+            match (chars.next(), chars.next()) {
+                (Some(separator), None) => rule = rule.separator(separator),
+                _ => return Err("`separator` must be a single character".to_owned()),
+            }
+        }
+        if let Some(ampersand) = fields.string("ampersand")? {
+            rule = rule.ampersand(ampersand);
+        }
+        if let Some(lowercase) = fields.bool("lowercase")? {
+            rule = rule.lowercase(lowercase);
+        }
+        Ok(rule)
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(feature = "exif")]
+use super::exif::Exif;
+use super::sha256;
+#[cfg(feature = "audiotags")]
+use super::tags::Tags;
+use crate::walk_dir::DirEntry;
+use std::cell::OnceCell;
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// A file rules are applied to, whose metadata is read at most once, and
+/// only if needed.
+#[derive(Debug)]
+pub(super) struct Subject<'a> {
+    path: &'a Path,
+    cached: Option<&'a Metadata>,
+    metadata: OnceCell<Option<Metadata>>,
+    sha256: OnceCell<Option<[u8; 32]>>,
+    #[cfg(feature = "exif")]
+    exif: OnceCell<Option<Exif>>,
+    #[cfg(feature = "audiotags")]
+    tags: OnceCell<Option<Tags>>,
+    origin: Option<&'a Subject<'a>>,
+}
+
+impl<'a> Subject<'a> {
+    pub(super) fn from_path(path: &'a Path) -> Self {
+        Self {
+            path,
+            cached: None,
+            metadata: OnceCell::new(),
+            sha256: OnceCell::new(),
+            #[cfg(feature = "exif")]
+            exif: OnceCell::new(),
+            #[cfg(feature = "audiotags")]
+            tags: OnceCell::new(),
+            origin: None,
+        }
+    }
+
+    pub(super) fn from_entry(entry: &'a DirEntry) -> Self {
+        Self {
+            path: entry.path(),
+            cached: Some(entry.metadata()),
+            metadata: OnceCell::new(),
+            sha256: OnceCell::new(),
+            #[cfg(feature = "exif")]
+            exif: OnceCell::new(),
+            #[cfg(feature = "audiotags")]
+            tags: OnceCell::new(),
+            origin: None,
+        }
+    }
+
+    /// Returns the subject renamed to `path`, as planned by earlier rules.
+    ///
+    /// The file has not moved yet, so metadata is still read from the
+    /// original path.
+    pub(super) fn renamed(&'a self, path: &'a Path) -> Self {
+        Self {
+            path,
+            cached: None,
+            metadata: OnceCell::new(),
+            sha256: OnceCell::new(),
+            #[cfg(feature = "exif")]
+            exif: OnceCell::new(),
+            #[cfg(feature = "audiotags")]
+            tags: OnceCell::new(),
+            origin: Some(self.origin.unwrap_or(self)),
+        }
+    }
+
+    #[inline]
+    pub(super) fn path(&self) -> &'a Path {
+        self.path
+    }
+
+    /// Returns the metadata of the file, without following symbolic links,
+    /// or [`None`] if it cannot be read.
+    pub(super) fn metadata(&self) -> Option<&Metadata> {
+        if let Some(origin) = self.origin {
+            return origin.metadata();
+        }
+        self.cached.or_else(|| {
+            self.metadata
+                .get_or_init(|| self.path.symlink_metadata().ok())
+                .as_ref()
+        })
+    }
+
+    pub(super) fn modified(&self) -> Option<SystemTime> {
+        self.metadata()?.modified().ok()
+    }
+
+    /// Returns the creation time of the file, or its modification time where
+    /// creation times are not recorded.
+    pub(super) fn created(&self) -> Option<SystemTime> {
+        let metadata = self.metadata()?;
+        metadata.created().or_else(|_| metadata.modified()).ok()
+    }
+
+    /// Returns the SHA-256 digest of the contents of the file, or [`None`]
+    /// if it cannot be read.
+    pub(super) fn sha256(&self) -> Option<&[u8; 32]> {
+        if let Some(origin) = self.origin {
+            return origin.sha256();
+        }
+        self.sha256
+            .get_or_init(|| sha256::digest_file(self.path).ok())
+            .as_ref()
+    }
+
+    /// Returns the EXIF metadata of the file, or [`None`] if it cannot be
+    /// read or has none.
+    #[cfg(feature = "exif")]
+    pub(super) fn exif(&self) -> Option<&Exif> {
+        if let Some(origin) = self.origin {
+            return origin.exif();
+        }
+        self.exif.get_or_init(|| Exif::read(self.path)).as_ref()
+    }
+
+    /// Returns the audio tags of the file, or [`None`] if they cannot be
+    /// read or there are none.
+    #[cfg(feature = "audiotags")]
+    pub(super) fn tags(&self) -> Option<&Tags> {
+        if let Some(origin) = self.origin {
+            return origin.tags();
+        }
+        self.tags.get_or_init(|| Tags::read(self.path)).as_ref()
+    }
+}
@@ -0,0 +1,576 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Metadata read from the tags of an audio file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Tags {
+    pub(crate) artist: Option<String>,
+    pub(crate) album_artist: Option<String>,
+    pub(crate) album: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) track: Option<u32>,
+    pub(crate) disc: Option<u32>,
+    pub(crate) year: Option<u32>,
+    pub(crate) compilation: bool,
+}
+
+/// A field of [`Tags`] a template token refers to.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Field {
+    Artist,
+    AlbumArtist,
+    Album,
+    Title,
+    /// The track number, padded with zeros to the width.
+    Track(usize),
+    /// The disc number, padded with zeros to the width.
+    Disc(usize),
+    Year,
+}
+
+type Reader = BufReader<File>;
+
+impl Tags {
+    /// Reads the tags of an MP3, FLAC, Ogg Vorbis or Opus file, or returns
+    /// [`None`] if they cannot be read or there are none.
+    ///
+    /// ID3v2 tags take precedence over ID3v1 tags, and the first of
+    /// repeated fields wins.
+    pub(crate) fn read(path: &Path) -> Option<Self> {
+        let mut reader = BufReader::new(File::open(path).ok()?);
+        let mut tags = Self::default();
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).ok()?;
+        if &magic[..3] == b"ID3" {
+            tags.id3v2(&mut reader, magic[3]);
+            // FLAC files are sometimes prefixed with an ID3v2 tag.
+            if reader.read_exact(&mut magic).is_err() {
+                magic = [0; 4];
+            }
+        }
+        // Whatever has been read is kept if the rest is malformed.
+        match &magic {
+            b"fLaC" => {
+                tags.flac(&mut reader);
+            }
+            b"OggS" => {
+                if reader.seek_relative(-4).is_ok() {
+                    tags.ogg(&mut reader);
+                }
+            }
+            _ => {
+                tags.id3v1(&mut reader);
+            }
+        }
+        (tags != Self::default()).then_some(tags)
+    }
+
+    /// Sets a field by its Vorbis comment name, unless it is already set.
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.trim_matches(|char: char| char == '\0' || char.is_whitespace());
+        if value.is_empty() {
+            return;
+        }
+        let text = |field: &mut Option<String>| {
+            field.get_or_insert_with(|| value.to_owned());
+        };
+        let number = |field: &mut Option<u32>| {
+            // Numbers may be followed by a total, as in `3/12`, or be part
+            // of a date, as in `2024-05-01`.
+            let end = value
+                .find(|char: char| !char.is_ascii_digit())
+                .unwrap_or(value.len());
+            if field.is_none() {
+                *field = value[..end].parse().ok();
+            }
+        };
+        match key.to_ascii_uppercase().as_str() {
+            "ARTIST" => text(&mut self.artist),
+            "ALBUMARTIST" | "ALBUM ARTIST" | "ALBUM_ARTIST" => text(&mut self.album_artist),
+            "ALBUM" => text(&mut self.album),
+            "TITLE" => text(&mut self.title),
+            "TRACKNUMBER" => number(&mut self.track),
+            "DISCNUMBER" => number(&mut self.disc),
+            "DATE" | "YEAR" => number(&mut self.year),
+            "COMPILATION" => self.compilation |= value == "1",
+            _ => {}
+        }
+    }
+
+    /// Reads an ID3v2 tag, right after its magic number and major version.
+    fn id3v2(&mut self, reader: &mut Reader, major: u8) -> Option<()> {
+        let mut header = [0; 6];
+        reader.read_exact(&mut header).ok()?;
+        let flags = header[1];
+        let size = syncsafe(&header[2..6]);
+        let mut body = Vec::new();
+        reader
+            .by_ref()
+            .take(size as u64)
+            .read_to_end(&mut body)
+            .ok()?;
+        // Version 2.4 tags may end with a copy of the header.
+        if flags & 0x10 != 0 {
+            reader.seek_relative(10).ok()?;
+        }
+        let unsynchronized = flags & 0x80 != 0;
+        // Before version 2.4, the whole tag is unsynchronized at once.
+        let body = if unsynchronized && major < 4 {
+            resync(&body)
+        } else {
+            Cow::Borrowed(body.as_slice())
+        };
+
+        let mut offset = 0;
+        if flags & 0x40 != 0 {
+            offset = match major {
+                3 => 4 + u32::from_be_bytes(body.get(..4)?.try_into().ok()?) as usize,
+                4 => syncsafe(body.get(..4)?) as usize,
+                // Version 2.2 uses this flag for compression instead.
+                _ => return Some(()),
+            };
+        }
+
+        let header_length = if major == 2 { 6 } else { 10 };
+        while let Some(header) = body.get(offset..offset + header_length) {
+            if header[0] == 0 {
+                // Padding follows.
+                break;
+            }
+            let (id, size, format) = match major {
+                2 => (
+                    &header[..3],
+                    u32::from_be_bytes([0, header[3], header[4], header[5]]),
+                    0,
+                ),
+                3 => (
+                    &header[..4],
+                    u32::from_be_bytes(header[4..8].try_into().ok()?),
+                    header[9],
+                ),
+                _ => (&header[..4], syncsafe(&header[4..8]), header[9]),
+            };
+            let start = offset + header_length;
+            offset = start + size as usize;
+            let Some(mut data) = body.get(start..offset) else {
+                break;
+            };
+            if id[0] != b'T' {
+                continue;
+            }
+
+            let mut frame = Cow::Borrowed(data);
+            if major == 3 {
+                // Compressed and encrypted frames are skipped.
+                if format & 0xC0 != 0 {
+                    continue;
+                }
+                if format & 0x20 != 0 {
+                    frame = Cow::Borrowed(data.get(1..)?);
+                }
+            } else if major == 4 {
+                if format & 0x0C != 0 {
+                    continue;
+                }
+                if format & 0x40 != 0 {
+                    data = data.get(1..)?;
+                }
+                if format & 0x01 != 0 {
+                    data = data.get(4..)?;
+                }
+                frame = if format & 0x02 != 0 || unsynchronized {
+                    resync(data)
+                } else {
+                    Cow::Borrowed(data)
+                };
+            }
+
+            let key = match id {
+                b"TPE1" | b"TP1" => "ARTIST",
+                b"TPE2" | b"TP2" => "ALBUMARTIST",
+                b"TALB" | b"TAL" => "ALBUM",
+                b"TIT2" | b"TT2" => "TITLE",
+                b"TRCK" | b"TRK" => "TRACKNUMBER",
+                b"TPOS" | b"TPA" => "DISCNUMBER",
+                b"TDRC" | b"TYER" | b"TYE" => "DATE",
+                b"TCMP" | b"TCP" => "COMPILATION",
+                _ => continue,
+            };
+            if let Some(value) = decode(&frame) {
+                self.set(key, &value);
+            }
+        }
+        Some(())
+    }
+
+    /// Reads an ID3v1 tag at the end of the file, if any.
+    fn id3v1(&mut self, reader: &mut Reader) -> Option<()> {
+        let mut tag = [0; 128];
+        reader.seek(SeekFrom::End(-128)).ok()?;
+        reader.read_exact(&mut tag).ok()?;
+        if &tag[..3] != b"TAG" {
+            return None;
+        }
+        let latin1 = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|&byte| char::from(byte))
+                .collect::<String>()
+        };
+        self.set("TITLE", &latin1(&tag[3..33]));
+        self.set("ARTIST", &latin1(&tag[33..63]));
+        self.set("ALBUM", &latin1(&tag[63..93]));
+        self.set("DATE", &latin1(&tag[93..97]));
+        // ID3v1.1 stores the track in the last byte of the comment.
+        if tag[125] == 0 && tag[126] != 0 {
+            self.set("TRACKNUMBER", &tag[126].to_string());
+        }
+        Some(())
+    }
+
+    /// Reads the metadata blocks of a FLAC file, right after its magic
+    /// number.
+    fn flac(&mut self, reader: &mut Reader) -> Option<()> {
+        loop {
+            let mut header = [0; 4];
+            reader.read_exact(&mut header).ok()?;
+            let last = header[0] & 0x80 != 0;
+            let length = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+            if header[0] & 0x7F == 4 {
+                let mut block = Vec::new();
+                reader
+                    .by_ref()
+                    .take(u64::from(length))
+                    .read_to_end(&mut block)
+                    .ok()?;
+                return self.vorbis(&block);
+            }
+            if last {
+                return Some(());
+            }
+            reader.seek_relative(i64::from(length)).ok()?;
+        }
+    }
+
+    /// Reads the comment header of an Ogg Vorbis or Opus stream, which is
+    /// its second packet.
+    fn ogg(&mut self, reader: &mut Reader) -> Option<()> {
+        let mut packet = Vec::new();
+        let mut index = 0;
+        loop {
+            let mut header = [0; 27];
+            reader.read_exact(&mut header).ok()?;
+            if &header[..4] != b"OggS" {
+                return None;
+            }
+            let mut lacing = vec![0; usize::from(header[26])];
+            reader.read_exact(&mut lacing).ok()?;
+            for length in lacing {
+                let start = packet.len();
+                packet.resize(start + usize::from(length), 0);
+                reader.read_exact(&mut packet[start..]).ok()?;
+                // A packet ends with a segment shorter than 255 bytes.
+                if length == 255 {
+                    continue;
+                }
+                if index == 1 {
+                    let comments = packet
+                        .strip_prefix(b"\x03vorbis")
+                        .or_else(|| packet.strip_prefix(b"OpusTags"))?;
+                    return self.vorbis(comments);
+                }
+                index += 1;
+                packet.clear();
+            }
+        }
+    }
+
+    /// Reads Vorbis comments, as found in FLAC and Ogg files.
+    fn vorbis(&mut self, mut data: &[u8]) -> Option<()> {
+        let u32 = |data: &mut &[u8]| {
+            let (bytes, rest) = data.split_first_chunk::<4>()?;
+            *data = rest;
+            Some(u32::from_le_bytes(*bytes))
+        };
+        let vendor = u32(&mut data)? as usize;
+        data = data.get(vendor..)?;
+        let count = u32(&mut data)?;
+        for _ in 0..count {
+            let length = u32(&mut data)? as usize;
+            let (comment, rest) = data.split_at_checked(length)?;
+            data = rest;
+            let comment = String::from_utf8_lossy(comment);
+            if let Some((key, value)) = comment.split_once('=') {
+                self.set(key, value);
+            }
+        }
+        Some(())
+    }
+}
+
+impl Field {
+    /// Renders the field into `output`, or nothing if it is missing.
+    ///
+    /// Path separators in text fields are replaced with `_`.
+    pub(super) fn render(self, tags: &Tags, output: &mut String) {
+        let text = match self {
+            Self::Artist => &tags.artist,
+            Self::AlbumArtist => &tags.album_artist,
+            Self::Album => &tags.album,
+            Self::Title => &tags.title,
+            Self::Track(width) => return number(tags.track, width, output),
+            Self::Disc(width) => return number(tags.disc, width, output),
+            Self::Year => return number(tags.year, 0, output),
+        };
+        if let Some(text) = text {
+            output.extend(text.chars().map(|char| match char {
+                '/' | '\\' => '_',
+                char => char,
+            }));
+        }
+    }
+}
+
+/// Renders a number padded with zeros to `width`, or nothing if missing.
+fn number(number: Option<u32>, width: usize, output: &mut String) {
+    if let Some(number) = number {
+        // Writing into a `String` never fails.
+        let _ = write!(output, "{number:0width$}");
+    }
+}
+
+/// Decodes an ID3v2 text frame, returning its first value.
+fn decode(frame: &[u8]) -> Option<String> {
+    let (&encoding, text) = frame.split_first()?;
+    let text = match encoding {
+        0 => text.iter().map(|&byte| char::from(byte)).collect(),
+        1 | 2 => {
+            let (big_endian, text) = match text {
+                [0xFF, 0xFE, text @ ..] => (false, text),
+                [0xFE, 0xFF, text @ ..] => (true, text),
+                text => (true, text),
+            };
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|pair| {
+                    if big_endian {
+                        u16::from_be_bytes([pair[0], pair[1]])
+                    } else {
+                        u16::from_le_bytes([pair[0], pair[1]])
+                    }
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(text).into_owned(),
+        _ => return None,
+    };
+    // Version 2.4 separates multiple values with null characters.
+    text.split('\0').next().map(str::to_owned)
+}
+
+/// Decodes a 28-bit integer stored in the low 7 bits of 4 bytes.
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .take(4)
+        .fold(0, |value, &byte| value << 7 | u32::from(byte & 0x7F))
+}
+
+/// Undoes unsynchronization, which inserts a zero after every `0xFF`.
+fn resync(data: &[u8]) -> Cow<'_, [u8]> {
+    if !data.windows(2).any(|pair| pair == [0xFF, 0x00]) {
+        return Cow::Borrowed(data);
+    }
+    let mut output = Vec::with_capacity(data.len());
+    let mut previous = 0;
+    for &byte in data {
+        if !(previous == 0xFF && byte == 0x00) {
+            output.push(byte);
+        }
+        previous = byte;
+    }
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    /// Reads the tags of a temporary file holding `contents`.
+    fn read(name: &str, contents: &[u8]) -> Option<Tags> {
+        let path = env::temp_dir().join(format!("mofu-tags-{}-{name}", process::id()));
+        fs::write(&path, contents).unwrap();
+        let tags = Tags::read(&path);
+        fs::remove_file(&path).unwrap();
+        tags
+    }
+
+    /// Builds an ID3v2.3 tag of text frames encoded in Latin-1.
+    fn id3v2(frames: &[(&[u8; 4], &str)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (id, text) in frames {
+            body.extend_from_slice(*id);
+            body.extend_from_slice(&(text.len() as u32 + 1).to_be_bytes());
+            body.extend_from_slice(&[0, 0, 0]);
+            body.extend_from_slice(text.as_bytes());
+        }
+        body.extend_from_slice(&[0; 16]);
+        let len = body.len() as u32;
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend((0..4).rev().map(|index| (len >> (index * 7)) as u8 & 0x7F));
+        tag.extend_from_slice(&body);
+        tag
+    }
+
+    /// Builds Vorbis comments.
+    fn vorbis(comments: &[&str]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&6u32.to_le_bytes());
+        data.extend_from_slice(b"vendor");
+        data.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            data.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            data.extend_from_slice(comment.as_bytes());
+        }
+        data
+    }
+
+    fn expected() -> Tags {
+        Tags {
+            artist: Some("Artist".to_owned()),
+            album: Some("Album".to_owned()),
+            title: Some("Title".to_owned()),
+            track: Some(3),
+            year: Some(2024),
+            ..Tags::default()
+        }
+    }
+
+    #[test]
+    fn empty_and_untagged_files_have_no_tags() {
+        assert_eq!(read("empty", b""), None);
+        assert_eq!(read("short", b"ID"), None);
+        assert_eq!(read("untagged", &[0x55; 300]), None);
+    }
+
+    #[test]
+    fn id3v2_tags_are_read() {
+        let tag = id3v2(&[
+            (b"TPE1", "Artist"),
+            (b"TALB", "Album"),
+            (b"TIT2", "Title"),
+            (b"TRCK", "3/12"),
+            (b"TYER", "2024"),
+            (b"TPE1", "Ignored"),
+        ]);
+        assert_eq!(read("id3v2", &tag), Some(expected()));
+
+        // Frames cut off by the end of the file are skipped.
+        let tag = id3v2(&[(b"TPE1", "Artist"), (b"TALB", "Album")]);
+        let truncated = read("id3v2-truncated", &tag[..tag.len() - 28]).unwrap();
+        assert_eq!(truncated.artist.as_deref(), Some("Artist"));
+        assert_eq!(truncated.album, None);
+    }
+
+    #[test]
+    fn id3v1_tags_are_read() {
+        let mut tag = vec![0x55; 200];
+        let start = tag.len();
+        tag.resize(start + 128, 0);
+        tag[start..start + 3].copy_from_slice(b"TAG");
+        tag[start + 3..start + 8].copy_from_slice(b"Title");
+        tag[start + 33..start + 39].copy_from_slice(b"Artist");
+        tag[start + 63..start + 68].copy_from_slice(b"Album");
+        tag[start + 93..start + 97].copy_from_slice(b"2024");
+        tag[start + 126] = 3;
+        assert_eq!(read("id3v1", &tag), Some(expected()));
+    }
+
+    #[test]
+    fn flac_comments_are_read() {
+        let comments = vorbis(&[
+            "ARTIST=Artist",
+            "album=Album",
+            "TITLE=Title",
+            "TRACKNUMBER=03",
+            "DATE=2024-05-01",
+        ]);
+        let mut flac = b"fLaC".to_vec();
+        // A stream info block precedes the comments.
+        flac.extend_from_slice(&[0, 0, 0, 34]);
+        flac.extend_from_slice(&[0; 34]);
+        flac.push(0x84);
+        flac.extend_from_slice(&(comments.len() as u32).to_be_bytes()[1..]);
+        flac.extend_from_slice(&comments);
+        assert_eq!(read("flac", &flac), Some(expected()));
+
+        let mut prefixed = id3v2(&[(b"TCMP", "1")]);
+        prefixed.extend_from_slice(&flac);
+        let tags = read("flac-id3", &prefixed).unwrap();
+        assert!(tags.compilation);
+        assert_eq!(tags.title.as_deref(), Some("Title"));
+
+        // Comments cut off by the end of the file are skipped.
+        let truncated = read("flac-truncated", &flac[..flac.len() - 10]).unwrap();
+        assert_eq!(truncated.track, Some(3));
+        assert_eq!(truncated.year, None);
+        assert_eq!(read("flac-header", &flac[..20]), None);
+    }
+
+    #[test]
+    fn ogg_comments_are_read() {
+        let identification = b"\x01vorbis".to_vec();
+        let mut comments = b"\x03vorbis".to_vec();
+        comments.extend_from_slice(&vorbis(&["ALBUMARTIST=Various", "COMPILATION=1"]));
+        let mut ogg = b"OggS".to_vec();
+        ogg.extend_from_slice(&[0; 22]);
+        ogg.push(2);
+        ogg.push(identification.len() as u8);
+        ogg.push(comments.len() as u8);
+        ogg.extend_from_slice(&identification);
+        ogg.extend_from_slice(&comments);
+        let tags = read("ogg", &ogg).unwrap();
+        assert_eq!(tags.album_artist.as_deref(), Some("Various"));
+        assert!(tags.compilation);
+
+        assert_eq!(read("ogg-truncated", &ogg[..40]), None);
+    }
+
+    #[test]
+    fn text_frames_are_decoded() {
+        assert_eq!(decode(b"\x00caf\xe9").as_deref(), Some("café"));
+        assert_eq!(decode(b"\x01\xff\xfea\x00b\x00").as_deref(), Some("ab"));
+        assert_eq!(decode(b"\x01\xfe\xff\x00a\x00b").as_deref(), Some("ab"));
+        assert_eq!(decode(b"\x03one\0two").as_deref(), Some("one"));
+        assert_eq!(decode(b"\x04text"), None);
+        assert_eq!(decode(b""), None);
+    }
+
+    #[test]
+    fn syncsafe_and_resync_round_trip() {
+        assert_eq!(syncsafe(&[0x00, 0x00, 0x02, 0x01]), 257);
+        assert_eq!(syncsafe(&[0x7F, 0x7F, 0x7F, 0x7F]), (1 << 28) - 1);
+        assert_eq!(&*resync(&[0xFF, 0x00, 0xE0, 0xFF]), [0xFF, 0xE0, 0xFF]);
+        assert!(matches!(resync(&[1, 2, 3]), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn fields_are_rendered() {
+        let tags = Tags {
+            artist: Some("AC/DC".to_owned()),
+            track: Some(7),
+            ..Tags::default()
+        };
+        let mut output = String::new();
+        Field::Artist.render(&tags, &mut output);
+        Field::Track(2).render(&tags, &mut output);
+        Field::Album.render(&tags, &mut output);
+        Field::Disc(2).render(&tags, &mut output);
+        assert_eq!(output, "AC_DC07");
+    }
+}
@@ -0,0 +1,492 @@
+use super::case::Case;
+use super::counter::Counter;
+use super::date::DateFormat;
+use super::error::Error;
+#[cfg(feature = "exif")]
+use super::exif::Field as ExifField;
+use super::recipe::{Fields, Value};
+use super::subject::Subject;
+#[cfg(feature = "audiotags")]
+use super::tags::Field as TagField;
+use crate::rename::{self, RenameQueue};
+use crate::walk_dir::DirEntry;
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A template rendering destination names from directory entries.
+///
+/// A template is literal text interspersed with tokens in braces:
+///
+/// - `{name}`: the file name without its extension;
+/// - `{ext}`: the extension without the leading dot, or nothing;
+/// - `{parent}`: the name of the parent directory;
+/// - `{counter}` or `{counter:OPTIONS}`: a number incremented by every
+///   rendering, configured by a comma-separated list of options:
+///   - `N` or `width=N`: pads the number with zeros to `N` digits;
+///   - `start=N`: the first number, defaulting to 1;
+///   - `step=N`: the increment, defaulting to 1;
+///   - `reset`: counts each directory separately;
+///   - `style=STYLE`: writes numbers in `decimal`, the default, as letters in
+///     `lower-alpha` or `upper-alpha` (`a` to `z`, then `aa`), or as roman
+///     numerals in `lower-roman` or `upper-roman`. Numbers a style cannot
+///     write, such as 0, or roman numerals above 3999, are written in
+///     decimal, which is the only style padded to the width;
+/// - `{date}` or `{date:FORMAT}`: the current UTC date, formatted with a
+///   `strftime`-like format defaulting to `%Y-%m-%d`;
+/// - `{mtime}` or `{mtime:FORMAT}`: the last modification time of the file,
+///   formatted like `{date}`;
+/// - `{ctime}` or `{ctime:FORMAT}`: the creation time of the file, or its
+///   modification time where creation times are not recorded, formatted
+///   like `{date}`;
+/// - `{hash}`, `{hash:ALGORITHM}` or `{hash:ALGORITHM:N}`: the digest of the
+///   contents of the file in lowercase hexadecimal, truncated to `N`
+///   digits. The only algorithm is `sha256`, which is the default;
+/// - `{size}` or `{size:human}`: the size of the file in bytes, or in binary
+///   units such as `1.5KiB` or `340MiB`;
+/// - `{kind}`: the type of the file, which is `file`, `dir`, `symlink` or
+///   `other`.
+///
+/// Times, sizes and types of files are read from the cached metadata of
+/// entries, without following symbolic links, and render as nothing if the
+/// metadata cannot be read.
+///
+/// With the `exif` feature, the metadata of JPEG, PNG, WebP and TIFF-based
+/// raw images is available through these tokens too:
+///
+/// - `{exif.datetime}` or `{exif.datetime:FORMAT}`: the time the photo was
+///   taken, as recorded by the camera, formatted like `{date}`;
+/// - `{exif.make}`: the maker of the camera;
+/// - `{exif.camera}`: the model of the camera, such as `X-T5`;
+/// - `{exif.lens}`: the model of the lens;
+/// - `{exif.iso}`: the ISO speed.
+///
+/// With the `audiotags` feature, the ID3, FLAC and Vorbis tags of MP3,
+/// FLAC, Ogg Vorbis and Opus files are available through these tokens:
+///
+/// - `{tag.artist}`, `{tag.album_artist}`, `{tag.album}` and `{tag.title}`;
+/// - `{tag.track}` or `{tag.track:N}`: the track number, padded with zeros
+///   to `N` digits;
+/// - `{tag.disc}` or `{tag.disc:N}`: the disc number, padded likewise;
+/// - `{tag.year}`: the year of release.
+///
+/// Missing fields render as nothing, and `/` or `\` in them as `_`.
+///
+/// Any token may be followed by `|` and the name of a [`Case`] to convert
+/// it to, as in `{name|snake}`: `lower`, `upper`, `title`, `snake`, `kebab`
+/// or `camel`.
+///
+/// Literal braces are written `{{` and `}}`.
+///
+/// The rendered text is joined to the directory containing the entry, so it
+/// may move the entry into a subdirectory, or anywhere else if absolute.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::rules::Template;
+/// use mofu::walk_dir::walk_dir;
+///
+/// let mut template = Template::new("{parent}_{counter:03,reset}.{ext}").unwrap();
+/// let mut queue = template.queue(walk_dir("scans", 1).unwrap()).unwrap();
+/// queue.rename().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Template {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Name,
+    Ext,
+    Parent,
+    Counter(Counter),
+    Date(Clock, DateFormat),
+    Size {
+        human: bool,
+    },
+    Kind,
+    /// A SHA-256 digest, truncated to a number of hexadecimal digits.
+    Hash(usize),
+    #[cfg(feature = "exif")]
+    Exif(ExifField),
+    #[cfg(feature = "audiotags")]
+    Tag(TagField),
+    Cased(Box<Segment>, Case),
+}
+
+/// Where the time of a date token comes from.
+#[derive(Debug, Clone, Copy)]
+enum Clock {
+    Now,
+    Modified,
+    Created,
+}
+
+impl Template {
+    /// Parses a template.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidTemplate`] if the template is malformed or
+    /// contains an unknown token.
+    pub fn new(template: &str) -> Result<Self, Error> {
+        let error = |offset: usize, message: &str| Error::InvalidTemplate {
+            template: template.to_owned(),
+            offset,
+            message: message.to_owned(),
+        };
+
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut offset = 0;
+        while let Some(char) = template[offset..].chars().next() {
+            let rest = &template[offset..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push(char);
+                offset += 2;
+                continue;
+            }
+            if char == '}' {
+                return Err(error(offset, "unmatched `}`"));
+            }
+            if char != '{' {
+                literal.push(char);
+                offset += char.len_utf8();
+                continue;
+            }
+
+            let end = rest
+                .find('}')
+                .ok_or_else(|| error(offset, "unclosed token"))?;
+            let token = &rest[1..end];
+            let (token, case) = match token.rsplit_once('|') {
+                Some((token, name)) => match Case::from_name(name) {
+                    Some(case) => (token, Some(case)),
+                    None => return Err(error(offset + 1 + token.len() + 1, "unknown case")),
+                },
+                None => (token, None),
+            };
+            let (name, argument) = match token.split_once(':') {
+                Some((name, argument)) => (name, Some(argument)),
+                None => (token, None),
+            };
+            let argument_offset = offset + 1 + name.len() + 1;
+            let segment = match (name, argument) {
+                ("name", None) => Segment::Name,
+                ("ext", None) => Segment::Ext,
+                ("parent", None) => Segment::Parent,
+                ("counter", None) => Segment::Counter(Counter::default()),
+                ("date" | "mtime" | "ctime", format) => {
+                    let clock = match name {
+                        "mtime" => Clock::Modified,
+                        "ctime" => Clock::Created,
+                        _ => Clock::Now,
+                    };
+                    match format.map_or(Ok(DateFormat::default()), DateFormat::parse) {
+                        Ok(format) => Segment::Date(clock, format),
+                        Err((at, message)) => return Err(error(argument_offset + at, message)),
+                    }
+                }
+                ("size", None) => Segment::Size { human: false },
+                ("size", Some("human")) => Segment::Size { human: true },
+                ("size", Some(_)) => return Err(error(argument_offset, "expected `human`")),
+                ("kind", None) => Segment::Kind,
+                ("hash", None) => Segment::Hash(64),
+                ("hash", Some(options)) => {
+                    let (algorithm, digits) = match options.split_once(':') {
+                        Some((algorithm, digits)) => (algorithm, Some(digits)),
+                        None => (options, None),
+                    };
+                    if algorithm != "sha256" {
+                        return Err(error(argument_offset, "unsupported algorithm"));
+                    }
+                    match digits.map(str::parse) {
+                        None => Segment::Hash(64),
+                        Some(Ok(digits @ 1..=64)) => Segment::Hash(digits),
+                        Some(_) => {
+                            let digits_offset = argument_offset + algorithm.len() + 1;
+                            return Err(error(digits_offset, "expected 1 to 64 digits"));
+                        }
+                    }
+                }
+                ("counter", Some(options)) => match Counter::parse(options) {
+                    Ok(counter) => Segment::Counter(counter),
+                    Err((at, message)) => return Err(error(argument_offset + at, message)),
+                },
+                #[cfg(feature = "exif")]
+                ("exif.datetime", format) => {
+                    match format.map_or(Ok(DateFormat::default()), DateFormat::parse) {
+                        Ok(format) => Segment::Exif(ExifField::DateTime(format)),
+                        Err((at, message)) => return Err(error(argument_offset + at, message)),
+                    }
+                }
+                #[cfg(feature = "exif")]
+                ("exif.make", None) => Segment::Exif(ExifField::Make),
+                #[cfg(feature = "exif")]
+                ("exif.camera", None) => Segment::Exif(ExifField::Camera),
+                #[cfg(feature = "exif")]
+                ("exif.lens", None) => Segment::Exif(ExifField::Lens),
+                #[cfg(feature = "exif")]
+                ("exif.iso", None) => Segment::Exif(ExifField::Iso),
+                #[cfg(feature = "exif")]
+                ("exif.make" | "exif.camera" | "exif.lens" | "exif.iso", Some(_)) => {
+                    return Err(error(argument_offset, "unexpected argument"));
+                }
+                #[cfg(feature = "audiotags")]
+                ("tag.artist", None) => Segment::Tag(TagField::Artist),
+                #[cfg(feature = "audiotags")]
+                ("tag.album_artist", None) => Segment::Tag(TagField::AlbumArtist),
+                #[cfg(feature = "audiotags")]
+                ("tag.album", None) => Segment::Tag(TagField::Album),
+                #[cfg(feature = "audiotags")]
+                ("tag.title", None) => Segment::Tag(TagField::Title),
+                #[cfg(feature = "audiotags")]
+                ("tag.year", None) => Segment::Tag(TagField::Year),
+                #[cfg(feature = "audiotags")]
+                ("tag.track" | "tag.disc", width) => {
+                    let width = match width.map(str::parse) {
+                        Some(Ok(width)) => width,
+                        Some(Err(_)) => return Err(error(argument_offset, "invalid width")),
+                        None => 0,
+                    };
+                    match name {
+                        "tag.track" => Segment::Tag(TagField::Track(width)),
+                        _ => Segment::Tag(TagField::Disc(width)),
+                    }
+                }
+                #[cfg(feature = "audiotags")]
+                (
+                    "tag.artist" | "tag.album_artist" | "tag.album" | "tag.title" | "tag.year",
+                    Some(_),
+                ) => {
+                    return Err(error(argument_offset, "unexpected argument"));
+                }
+                #[cfg(not(feature = "audiotags"))]
+                (name, _) if name.starts_with("tag.") => {
+                    return Err(error(offset + 1, "requires the `audiotags` feature"));
+                }
+                #[cfg(not(feature = "exif"))]
+                (name, _) if name.starts_with("exif.") => {
+                    return Err(error(offset + 1, "requires the `exif` feature"));
+                }
+                ("name" | "ext" | "parent" | "kind", Some(_)) => {
+                    return Err(error(argument_offset, "unexpected argument"));
+                }
+                _ => return Err(error(offset + 1, "unknown token")),
+            };
+            let segment = match case {
+                Some(case) => Segment::Cased(Box::new(segment), case),
+                None => segment,
+            };
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(mem::take(&mut literal)));
+            }
+            segments.push(segment);
+            offset += end + 1;
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self {
+            source: template.to_owned(),
+            segments,
+        })
+    }
+
+    /// Returns the template this was parsed from.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Renders the destination path for an entry.
+    ///
+    /// Counters are advanced, so rendering the same entry twice may give
+    /// different results.
+    pub fn render(&mut self, entry: &DirEntry) -> PathBuf {
+        self.render_subject(&Subject::from_entry(entry))
+    }
+
+    /// Renders the destination path for the file at `path`, reading its
+    /// metadata if needed.
+    ///
+    /// Counters are advanced, as with [`Template::render`].
+    pub fn render_path(&mut self, path: &Path) -> PathBuf {
+        self.render_subject(&Subject::from_path(path))
+    }
+
+    pub(super) fn render_subject(&mut self, subject: &Subject) -> PathBuf {
+        let now = SystemTime::now();
+        let mut output = OsString::new();
+        for segment in &mut self.segments {
+            segment.render(subject, now, &mut output);
+        }
+        let directory = subject.path().parent().unwrap_or(Path::new(""));
+        directory.join(output)
+    }
+
+    /// Renders every entry, returning the mappings of those that change.
+    pub fn mappings<I>(&mut self, entries: I) -> Vec<(PathBuf, PathBuf)>
+    where
+        I: IntoIterator<Item = DirEntry>,
+    {
+        entries
+            .into_iter()
+            .filter_map(|entry| {
+                let dst = self.render(&entry);
+                (dst != entry.path()).then(|| (entry.into(), dst))
+            })
+            .collect()
+    }
+
+    /// Renders every entry, and plans the resulting renames.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mappings conflict, as described in
+    /// [`RenameQueue::new`].
+    pub fn queue<I>(&mut self, entries: I) -> Result<RenameQueue, rename::Error>
+    where
+        I: IntoIterator<Item = DirEntry>,
+    {
+        RenameQueue::new(self.mappings(entries))
+    }
+}
+
+impl Segment {
+    fn render(&mut self, subject: &Subject, now: SystemTime, output: &mut OsString) {
+        let path = subject.path();
+        match self {
+            Self::Literal(literal) => output.push(literal),
+            Self::Name => {
+                if let Some(stem) = path.file_stem() {
+                    output.push(stem);
+                }
+            }
+            Self::Ext => {
+                if let Some(ext) = path.extension() {
+                    output.push(ext);
+                }
+            }
+            Self::Parent => {
+                if let Some(parent) = path.parent().and_then(|parent| parent.file_name()) {
+                    output.push(parent);
+                }
+            }
+            Self::Counter(counter) => {
+                let mut text = String::new();
+                counter.render(path.parent().unwrap_or(Path::new("")), &mut text);
+                output.push(text);
+            }
+            Self::Date(clock, format) => {
+                let time = match clock {
+                    Clock::Now => Some(now),
+                    Clock::Modified => subject.modified(),
+                    Clock::Created => subject.created(),
+                };
+                if let Some(time) = time {
+                    let mut text = String::new();
+                    format.render(time, &mut text);
+                    output.push(text);
+                }
+            }
+            Self::Size { human } => {
+                if let Some(metadata) = subject.metadata() {
+                    output.push(if *human {
+                        human_size(metadata.len())
+                    } else {
+                        metadata.len().to_string()
+                    });
+                }
+            }
+            Self::Kind => {
+                if let Some(metadata) = subject.metadata() {
+                    let file_type = metadata.file_type();
+                    output.push(if file_type.is_symlink() {
+                        "symlink"
+                    } else if file_type.is_dir() {
+                        "dir"
+                    } else if file_type.is_file() {
+                        "file"
+                    } else {
+                        "other"
+                    });
+                }
+            }
+            Self::Hash(digits) => {
+                if let Some(digest) = subject.sha256() {
+                    let mut text = String::with_capacity(64);
+                    for byte in digest {
+                        // Writing into a `String` never fails.
+                        let _ = write!(text, "{byte:02x}");
+                    }
+                    text.truncate(*digits);
+                    output.push(text);
+                }
+            }
+            #[cfg(feature = "exif")]
+            Self::Exif(field) => {
+                if let Some(exif) = subject.exif() {
+                    let mut text = String::new();
+                    field.render(exif, &mut text);
+                    output.push(text);
+                }
+            }
+            #[cfg(feature = "audiotags")]
+            Self::Tag(field) => {
+                if let Some(tags) = subject.tags() {
+                    let mut text = String::new();
+                    field.render(tags, &mut text);
+                    output.push(text);
+                }
+            }
+            Self::Cased(segment, case) => {
+                let mut text = OsString::new();
+                segment.render(subject, now, &mut text);
+                // Names that are not valid UTF-8 are left as they are.
+                match text.to_str() {
+                    Some(text) => output.push(case.convert(text)),
+                    None => output.push(text),
+                }
+            }
+        }
+    }
+}
+
+impl Template {
+    pub(super) fn to_fields(&self) -> Vec<(String, Value)> {
+        vec![("template".to_owned(), Value::str(&self.source))]
+    }
+
+    pub(super) fn from_fields(fields: &mut Fields) -> Result<Self, String> {
+        let template = fields.require("template")?.into_str()?;
+        Self::new(&template).map_err(|error| error.to_string().trim_end().to_owned())
+    }
+}
+
+/// Formats a size in binary units, with one decimal below 10.
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes}B");
+    }
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{value:.1}{}", UNITS[unit])
+    } else {
+        format!("{value:.0}{}", UNITS[unit])
+    }
+}
@@ -0,0 +1,33 @@
+use std::path::{Path, PathBuf};
+
+/// A function from paths to new paths.
+///
+/// Transforms take `&mut self` so that they may keep state across paths,
+/// as counters do. Closures taking a path and returning an optional new path
+/// are transforms too.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rules::{Case, CaseRule, Transform};
+/// use std::path::Path;
+///
+/// let mut rule = CaseRule::new(Case::Upper);
+/// let dst = Transform::apply(&mut rule, Path::new("a.txt")).unwrap();
+/// assert_eq!(dst, Path::new("A.txt"));
+/// ```
+pub trait Transform {
+    /// Applies the transform to a path, returning the new path, or [`None`]
+    /// if the path is left unchanged.
+    fn apply(&mut self, path: &Path) -> Option<PathBuf>;
+}
+
+impl<F> Transform for F
+where
+    F: FnMut(&Path) -> Option<PathBuf>,
+{
+    #[inline]
+    fn apply(&mut self, path: &Path) -> Option<PathBuf> {
+        self(path)
+    }
+}
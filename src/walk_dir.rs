@@ -1,10 +1,53 @@
 //! Utilities for directory traversal.
 
+pub use self::by_directory::ByDirectory;
+pub use self::cursor::Cursor;
+pub use self::list::{FileKind, ListDir, ListEntry, ListWalk};
+pub use self::stats::Stats;
+#[cfg(feature = "stream")]
+pub use self::stream::WalkStream;
+pub use self::tree::DirTree;
+pub use self::usage::{DiskUsage, disk_usage};
+#[cfg(feature = "watch")]
+pub use self::watch::{Event, Watcher};
+
+use self::archive::{Archive, Member};
+use self::cursor::Level;
+#[cfg(feature = "ignore")]
+use self::ignore::Ignore;
+use crate::rename::SplitMix64;
+use crate::rules;
+#[cfg(feature = "globset")]
+use crate::rules::glob_matches;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::ffi::OsStr;
+use std::fmt;
 use std::fs;
-use std::fs::{Metadata, ReadDir};
-use std::io::{Error, ErrorKind, Result};
+use std::fs::{FileType, Metadata, ReadDir};
+use std::io::{self, ErrorKind};
+use std::mem;
 use std::num::NonZero;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use std::vec;
+
+mod archive;
+mod by_directory;
+mod cursor;
+#[cfg(feature = "ignore")]
+mod ignore;
+mod list;
+mod stats;
+#[cfg(feature = "stream")]
+mod stream;
+mod tree;
+mod usage;
+#[cfg(feature = "watch")]
+mod watch;
 
 /// Returns an iterator that recursively traverses the specified directory.
 ///
@@ -26,7 +69,7 @@ use std::path::{Path, PathBuf};
 /// - The provided `path` doesn't exist.
 /// - The process lacks permissions to view the contents.
 /// - The `path` points at a non-directory file.
-pub fn walk_dir<P>(path: P, max_depth: usize) -> Result<impl Iterator<Item = DirEntry>>
+pub fn walk_dir<P>(path: P, max_depth: usize) -> io::Result<impl Iterator<Item = DirEntry>>
 where
     P: AsRef<Path>,
 {
@@ -39,6 +82,10 @@ where
 
 /// An iterator that recursively traverses the specified directory.
 ///
+/// On Windows, directories are opened through extended-length paths, as in
+/// `\\?\C:\...`, so trees deeper than `MAX_PATH` can be traversed, while
+/// the paths of entries keep the form the root was given in.
+///
 /// # Examples
 ///
 /// ```
@@ -54,10 +101,105 @@ where
 ///     println!("{}", entry.path().display());
 /// }
 /// ```
-#[derive(Debug)]
 pub struct WalkDir {
+    root: Arc<Path>,
+    /// The roots left to traverse after the current one.
+    roots: VecDeque<PathBuf>,
+    /// Whether the traversal of the current root is prepared.
+    entered: bool,
+    /// Whether the current root was yielded or held back before resuming.
+    resumed: bool,
+    /// Whether the current root is not a directory, and is yielded by
+    /// itself.
+    file_root: bool,
+    /// Whether the current root is yet to be read once entered, and if so,
+    /// whether it may be a non-directory file.
+    deferred: Option<bool>,
     stack: Vec<StackItem>,
+    min_depth: usize,
     max_depth: Option<NonZero<usize>>,
+    include_root: bool,
+    follow_symlinks: bool,
+    /// Whether to follow junctions, or [`None`] to follow them along with
+    /// symbolic links.
+    #[cfg(windows)]
+    follow_junctions: Option<bool>,
+    #[cfg(windows)]
+    skip_junctions: bool,
+    skip_hidden: bool,
+    contents_first: bool,
+    kind: Option<Kind>,
+    extensions: Option<Vec<rules::Predicate>>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_since: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    #[cfg(unix)]
+    uid: Option<u32>,
+    #[cfg(unix)]
+    gid: Option<u32>,
+    /// The mask of permission bits to check, and the bits expected.
+    #[cfg(unix)]
+    mode: Option<(u32, u32)>,
+    same_file_system: bool,
+    walk_archives: bool,
+    /// The device of the current root, only tracked when staying on its
+    /// file system.
+    device: Option<u64>,
+    /// The statistics of the entries yielded, only tracked when collecting
+    /// them.
+    stats: Option<Stats>,
+    error_policy: ErrorPolicy,
+    /// The errors collected, as told by [`ErrorPolicy::Collect`].
+    errors: Vec<WalkError>,
+    /// Whether the traversal ended at an error, as told by
+    /// [`ErrorPolicy::Abort`].
+    aborted: bool,
+    max_open: Option<NonZero<usize>>,
+    max_entries: Option<usize>,
+    /// The number of entries yielded, only counted when limited.
+    yielded: usize,
+    /// The probability of yielding each entry, and the generator deciding
+    /// it.
+    sample: Option<(f64, SplitMix64)>,
+    /// The other paths of the files with more than one hard link yielded,
+    /// only tracked when yielding each of them once.
+    hard_links: Option<HashMap<(u64, u64), Vec<PathBuf>>>,
+    compare: Option<Box<Compare>>,
+    deterministic: bool,
+    predicates: Vec<Box<Predicate>>,
+    #[cfg(feature = "globset")]
+    include: Vec<String>,
+    #[cfg(feature = "globset")]
+    exclude: Vec<String>,
+    #[cfg(feature = "ignore")]
+    ignore_files: bool,
+    #[cfg(feature = "ignore")]
+    global: Option<Ignore>,
+}
+
+/// A comparator ordering the entries of each directory.
+type Compare = dyn FnMut(&DirEntry, &DirEntry) -> Ordering + Send + Sync;
+
+/// A predicate deciding whether to yield an entry and descend into it.
+type Predicate = dyn FnMut(&DirEntry) -> bool + Send + Sync;
+
+/// How [`WalkDir`] handles errors, as set by [`WalkDir::error_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ErrorPolicy {
+    /// Yields each error, and carries on.
+    #[default]
+    Yield,
+
+    /// Yields the first error, and ends the traversal.
+    Abort,
+
+    /// Skips errors without yielding them.
+    Skip,
+
+    /// Skips errors without yielding them, collecting them instead, to be
+    /// retrieved by [`WalkDir::errors`] or [`WalkDir::take_errors`].
+    Collect,
 }
 
 impl WalkDir {
@@ -70,17 +212,317 @@ impl WalkDir {
     ///
     /// - The provided `path` doesn't exist.
     /// - The process lacks permissions to view the contents.
-    /// - The `path` points at a non-directory file.
-    pub fn new<P>(path: P) -> Result<Self>
+    /// - The `path` points at a non-directory file, which can be yielded by
+    ///   itself with [`WalkDir::new_or_file`] instead.
+    pub fn new<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let root: Arc<Path> = Arc::from(path.as_ref());
+        let stack = vec![StackItem::root(&root, fs::read_dir(extended(&root))?)];
+        Ok(Self::with_stack(root, stack))
+    }
+
+    /// Creates a new [`WalkDir`] like [`WalkDir::new`], except that `path`
+    /// is not read until the first call to [`Iterator::next`], so it never
+    /// fails here.
+    ///
+    /// Errors reading `path` are yielded as the first item, at depth 0,
+    /// after which the traversal ends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let paths = ["src", "tests", "missing"]
+    ///     .into_iter()
+    ///     .flat_map(WalkDir::new_lazy)
+    ///     .filter_map(Result::ok)
+    ///     .map(|entry| entry.path().to_path_buf());
+    /// ```
+    pub fn new_lazy<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let root: Arc<Path> = Arc::from(path.as_ref());
+        let mut walk_dir = Self::with_stack(root, Vec::new());
+        walk_dir.deferred = Some(false);
+        walk_dir
+    }
+
+    /// Creates a new [`WalkDir`] like [`WalkDir::new`], except that a `path`
+    /// pointing at a non-directory file is yielded by itself, so paths can
+    /// be walked alike whatever they point at.
+    ///
+    /// The file is yielded as the root, at depth 0, regardless of
+    /// [`WalkDir::include_root`]. It is still subject to filters on the
+    /// entries yielded, such as [`WalkDir::min_depth`] or
+    /// [`WalkDir::dirs_only`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` cannot be read, as described in
+    /// [`WalkDir::new`], except for pointing at a non-directory file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mofu::walk_dir::WalkDir;
+    /// use std::env;
+    ///
+    /// for path in env::args_os().skip(1) {
+    ///     for entry in WalkDir::new_or_file(path).unwrap().filter_map(Result::ok) {
+    ///         println!("{}", entry.path().display());
+    ///     }
+    /// }
+    /// ```
+    pub fn new_or_file<P>(path: P) -> io::Result<Self>
     where
         P: AsRef<Path>,
     {
-        let depth = unsafe { NonZero::new_unchecked(1) };
-        let iter = fs::read_dir(path)?;
-        let item = StackItem { depth, iter };
-        let stack = vec![item];
+        let root: Arc<Path> = Arc::from(path.as_ref());
+        match fs::read_dir(extended(&root)) {
+            Ok(iter) => {
+                let stack = vec![StackItem::root(&root, iter)];
+                Ok(Self::with_stack(root, stack))
+            }
+            Err(error) => match fs::metadata(extended(&root)) {
+                Ok(metadata) if !metadata.is_dir() => {
+                    let mut walk_dir = Self::with_stack(root, Vec::new());
+                    walk_dir.file_root = true;
+                    Ok(walk_dir)
+                }
+                _ => Err(error),
+            },
+        }
+    }
+
+    /// Creates a new [`WalkDir`] traversing `root` from `stack`, with the
+    /// default options.
+    fn with_stack(root: Arc<Path>, stack: Vec<StackItem>) -> Self {
+        let roots = VecDeque::new();
+        let entered = false;
+        let resumed = false;
+        let file_root = false;
+        let deferred = None;
+        let min_depth = 0;
         let max_depth = None;
-        Ok(Self { stack, max_depth })
+        let include_root = false;
+        let follow_symlinks = false;
+        #[cfg(windows)]
+        let follow_junctions = None;
+        #[cfg(windows)]
+        let skip_junctions = false;
+        let skip_hidden = false;
+        let contents_first = false;
+        let kind = None;
+        let extensions = None;
+        let min_size = None;
+        let max_size = None;
+        let modified_since = None;
+        let modified_before = None;
+        #[cfg(unix)]
+        let uid = None;
+        #[cfg(unix)]
+        let gid = None;
+        #[cfg(unix)]
+        let mode = None;
+        let same_file_system = false;
+        let walk_archives = false;
+        let device = None;
+        let stats = None;
+        let error_policy = ErrorPolicy::default();
+        let errors = Vec::new();
+        let aborted = false;
+        let max_open = None;
+        let max_entries = None;
+        let yielded = 0;
+        let sample = None;
+        let hard_links = None;
+        let compare = None;
+        let deterministic = false;
+        let predicates = Vec::new();
+        #[cfg(feature = "globset")]
+        let include = Vec::new();
+        #[cfg(feature = "globset")]
+        let exclude = Vec::new();
+        #[cfg(feature = "ignore")]
+        let ignore_files = false;
+        #[cfg(feature = "ignore")]
+        let global = None;
+        Self {
+            root,
+            roots,
+            entered,
+            resumed,
+            file_root,
+            deferred,
+            stack,
+            min_depth,
+            max_depth,
+            include_root,
+            follow_symlinks,
+            #[cfg(windows)]
+            follow_junctions,
+            #[cfg(windows)]
+            skip_junctions,
+            skip_hidden,
+            contents_first,
+            kind,
+            extensions,
+            min_size,
+            max_size,
+            modified_since,
+            modified_before,
+            #[cfg(unix)]
+            uid,
+            #[cfg(unix)]
+            gid,
+            #[cfg(unix)]
+            mode,
+            same_file_system,
+            walk_archives,
+            device,
+            stats,
+            error_policy,
+            errors,
+            aborted,
+            max_open,
+            max_entries,
+            yielded,
+            sample,
+            hard_links,
+            compare,
+            deterministic,
+            predicates,
+            #[cfg(feature = "globset")]
+            include,
+            #[cfg(feature = "globset")]
+            exclude,
+            #[cfg(feature = "ignore")]
+            ignore_files,
+            #[cfg(feature = "ignore")]
+            global,
+        }
+    }
+
+    /// Creates a new [`WalkDir`] traversing each of `paths` in turn.
+    ///
+    /// Depths are counted from the root each entry is yielded from, which
+    /// is told by [`DirEntry::root`]. Roots found within other roots, as
+    /// well as duplicate ones, are left out, so no entry is yielded twice,
+    /// although symbolic links may still lead to the same files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of `paths` cannot be resolved, or if the first
+    /// one left cannot be read, as described in [`WalkDir::new`]. Errors
+    /// reading the others are yielded once they are reached.
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidInput`] if `paths` is
+    /// empty.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// // `photos/2024` is within `photos`, so it is left out.
+    /// let iter = WalkDir::new_many(["photos", "scans", "photos/2024"]).unwrap();
+    /// for entry in iter.filter_map(Result::ok) {
+    ///     println!("{}: {}", entry.root().display(), entry.relative_path().display());
+    /// }
+    /// ```
+    pub fn new_many<I, P>(paths: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut roots = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            roots.push((path.to_path_buf(), fs::canonicalize(extended(path))?));
+        }
+        let canonical: Vec<PathBuf> = roots
+            .iter()
+            .map(|(_, canonical)| canonical.clone())
+            .collect();
+        let mut roots: VecDeque<PathBuf> = roots
+            .into_iter()
+            .enumerate()
+            .filter(|(index, (_, path))| {
+                // Among duplicates, the first one is kept.
+                !canonical.iter().enumerate().any(|(other, ancestor)| {
+                    other != *index
+                        && path.starts_with(ancestor)
+                        && (path != ancestor || other < *index)
+                })
+            })
+            .map(|(_, (path, _))| path)
+            .collect();
+        let Some(first) = roots.pop_front() else {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "no roots to traverse",
+            ));
+        };
+        let mut walk_dir = Self::new(first)?;
+        walk_dir.roots = roots;
+        Ok(walk_dir)
+    }
+
+    /// Creates a new [`WalkDir`] resuming the traversal recorded by `cursor`,
+    /// as returned by [`WalkDir::cursor`].
+    ///
+    /// Options are not recorded, so they should be set as they were before,
+    /// or entries may be yielded differently. Entries created since the
+    /// cursor was taken are not yielded, and those removed since are
+    /// yielded as errors once reached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let mut iter = WalkDir::new(".").unwrap().sort_by_file_name();
+    /// let first: Vec<_> = iter.by_ref().take(3).collect();
+    /// // The cursor can be serialized, and deserialized after a crash.
+    /// let cursor = iter.cursor();
+    /// let rest = WalkDir::resume(cursor).sort_by_file_name();
+    /// for entry in first.into_iter().chain(rest).filter_map(Result::ok) {
+    ///     println!("{}", entry.path().display());
+    /// }
+    /// ```
+    pub fn resume(cursor: Cursor) -> Self {
+        let root: Arc<Path> = Arc::from(cursor.root.as_path());
+        let stack = cursor
+            .levels
+            .into_iter()
+            .enumerate()
+            .map(|(index, level)| StackItem::resumed(&root, index, level))
+            .collect();
+        let mut walk_dir = Self::with_stack(root, stack);
+        walk_dir.roots = cursor.roots.into();
+        // Roots not entered yet may not have been read, or may not be
+        // directories at all.
+        if !cursor.started && walk_dir.stack.is_empty() {
+            walk_dir.deferred = Some(true);
+        }
+        walk_dir.resumed = cursor.started;
+        walk_dir
+    }
+
+    /// Sets the minimum depth of yielded entries, where top-level entries
+    /// are at depth 1.
+    ///
+    /// Shallower entries are still traversed, but are not yielded, while
+    /// errors are yielded regardless of depth.
+    ///
+    /// Defaults to 0.
+    pub fn min_depth(mut self, min_depth: usize) -> Self {
+        self.min_depth = min_depth;
+        self
     }
 
     /// Sets the maximum depth for traversal.
@@ -88,107 +530,2610 @@ impl WalkDir {
         self.max_depth = max_depth;
         self
     }
-}
 
-impl Iterator for WalkDir {
-    type Item = Result<DirEntry>;
+    /// Sets whether to yield the root itself, at depth 0, before its
+    /// contents, or after them with [`WalkDir::contents_first`] set.
+    ///
+    /// The root is subject to the filters on yielded entries, such as
+    /// [`WalkDir::min_depth`] and [`WalkDir::files_only`], but is always
+    /// descended into.
+    ///
+    /// Defaults to `false`.
+    pub fn include_root(mut self, include_root: bool) -> Self {
+        self.include_root = include_root;
+        self
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let (depth, entry) = loop {
-            let item = self.stack.last_mut()?;
-            match item.iter.next() {
-                None => self.stack.pop(),
-                Some(Err(error)) => return Some(Err(error)),
-                Some(Ok(entry)) => break (item.depth, entry),
-            };
-        };
+    /// Sets whether to follow symbolic links.
+    ///
+    /// When set, symbolic links to directories are descended into, and the
+    /// metadata of entries follows symbolic links, except for broken ones.
+    /// A symbolic link to a directory containing it is reported as an error
+    /// wrapping a [`LoopError`], instead of being descended into forever.
+    ///
+    /// Defaults to `false`.
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
 
-        let entry = match DirEntry::try_from(entry) {
-            Err(error) => return Some(Err(error)),
-            Ok(entry) => entry,
-        };
+    /// Sets whether to follow NTFS junctions and mount points, regardless
+    /// of [`WalkDir::follow_symlinks`], which they are otherwise followed
+    /// along with.
+    ///
+    /// Junctions are followed as described in [`WalkDir::follow_symlinks`].
+    /// Telling them apart from symbolic links takes reading their reparse
+    /// tags, which only happens when the two are followed differently.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// // Junctions such as `Application Data` lead back into the profile.
+    /// let iter = WalkDir::new(r"C:\Users\me\AppData")
+    ///     .unwrap()
+    ///     .follow_symlinks(true)
+    ///     .follow_junctions(false);
+    /// ```
+    #[cfg(windows)]
+    pub fn follow_junctions(mut self, follow_junctions: bool) -> Self {
+        self.follow_junctions = Some(follow_junctions);
+        self
+    }
 
-        if entry.metadata.is_dir() && self.max_depth.is_none_or(|max_depth| depth < max_depth) {
-            match fs::read_dir(entry.path()) {
-                // Yes, this branch is still reachable.
-                Err(error) if error.kind() == ErrorKind::NotADirectory => (),
-                Err(error) => return Some(Err(error)),
-                Ok(iter) => {
-                    // Will not overflow because `depth < max_depth`.
-                    let depth = unsafe { NonZero::new_unchecked(depth.get() + 1) };
-                    let item = StackItem { depth, iter };
-                    self.stack.push(item);
-                }
-            }
-        }
+    /// Sets whether to skip NTFS junctions and mount points, neither
+    /// yielding nor descending into them.
+    ///
+    /// This reads the reparse tag of every entry that is a reparse point.
+    ///
+    /// Defaults to `false`.
+    #[cfg(windows)]
+    pub fn skip_junctions(mut self, skip_junctions: bool) -> Self {
+        self.skip_junctions = skip_junctions;
+        self
+    }
 
-        Some(Ok(entry))
+    /// Sets whether to skip hidden entries, which are those whose names
+    /// start with a dot on Unix, and those with the hidden attribute on
+    /// Windows.
+    ///
+    /// Hidden directories are not descended into.
+    ///
+    /// Defaults to `false`.
+    pub fn skip_hidden(mut self, skip_hidden: bool) -> Self {
+        self.skip_hidden = skip_hidden;
+        self
     }
-}
 
-/// A directory entry returned by [`WalkDir`].
-///
-/// Each entry provides a path along with cached metadata to help avoid
-/// redundant system calls. However, due to possible concurrent file access,
-/// the cached metadata may degrade in validity over time.
-///
-/// Note that the metadata does not follow symlinks.
-#[derive(Debug)]
-pub struct DirEntry {
-    path: PathBuf,
-    metadata: Metadata,
-}
+    /// Sets whether to yield directories after their contents.
+    ///
+    /// This suits operations from the bottom up, such as removing empty
+    /// directories. Directories that are not descended into are yielded
+    /// right away.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mofu::walk_dir::WalkDir;
+    /// use std::fs;
+    ///
+    /// let iter = WalkDir::new("downloads")
+    ///     .unwrap()
+    ///     .contents_first(true)
+    ///     .filter_map(Result::ok);
+    /// for entry in iter {
+    ///     if entry.metadata().is_dir() {
+    ///         // Fails unless the directory is empty.
+    ///         let _ = fs::remove_dir(entry.path());
+    ///     }
+    /// }
+    /// ```
+    pub fn contents_first(mut self, contents_first: bool) -> Self {
+        self.contents_first = contents_first;
+        self
+    }
 
-impl DirEntry {
-    /// Returns the path.
-    #[inline]
-    pub fn path(&self) -> &Path {
-        &self.path
+    /// Yields regular files only, while still descending into directories.
+    ///
+    /// This overrides [`WalkDir::dirs_only`], [`WalkDir::symlinks_only`]
+    /// and [`WalkDir::broken_symlinks_only`].
+    pub fn files_only(mut self) -> Self {
+        self.kind = Some(Kind::File);
+        self
     }
 
-    /// Returns the cached metadata.
+    /// Yields directories only.
     ///
-    /// Due to possible concurrent file access, the cached metadata may degrade in
-    /// validity over time.
+    /// This overrides [`WalkDir::files_only`], [`WalkDir::symlinks_only`]
+    /// and [`WalkDir::broken_symlinks_only`].
+    pub fn dirs_only(mut self) -> Self {
+        self.kind = Some(Kind::Dir);
+        self
+    }
+
+    /// Yields symbolic links only, while still descending into directories.
     ///
-    /// Note that the metadata does not follow symbolic links.
-    #[inline]
-    pub fn metadata(&self) -> &Metadata {
-        &self.metadata
+    /// With [`WalkDir::follow_symlinks`] set, only broken symbolic links
+    /// are yielded, as others are followed. This overrides
+    /// [`WalkDir::files_only`], [`WalkDir::dirs_only`] and
+    /// [`WalkDir::broken_symlinks_only`].
+    pub fn symlinks_only(mut self) -> Self {
+        self.kind = Some(Kind::Symlink);
+        self
     }
-}
 
-impl TryFrom<fs::DirEntry> for DirEntry {
-    type Error = Error;
+    /// Yields symbolic links whose targets do not exist only, as told by
+    /// [`DirEntry::is_broken_symlink`], while still descending into
+    /// directories.
+    ///
+    /// This overrides [`WalkDir::files_only`], [`WalkDir::dirs_only`] and
+    /// [`WalkDir::symlinks_only`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mofu::walk_dir::WalkDir;
+    /// use std::fs;
+    ///
+    /// let iter = WalkDir::new("project")
+    ///     .unwrap()
+    ///     .broken_symlinks_only()
+    ///     .filter_map(Result::ok);
+    /// for entry in iter {
+    ///     fs::remove_file(entry.path()).unwrap();
+    /// }
+    /// ```
+    pub fn broken_symlinks_only(mut self) -> Self {
+        self.kind = Some(Kind::BrokenSymlink);
+        self
+    }
 
-    #[inline]
-    fn try_from(value: fs::DirEntry) -> Result<Self> {
-        let path = value.path();
-        let metadata = value.metadata()?;
-        Ok(Self { path, metadata })
+    /// Yields regular files with any of the `extensions` only, while still
+    /// descending into directories.
+    ///
+    /// Extensions are compared ignoring ASCII case, with or without a
+    /// leading dot, and may span several dots, as in `tar.gz`. See
+    /// [`Predicate::Extension`] for details.
+    ///
+    /// [`Predicate::Extension`]: crate::rules::Predicate::Extension
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .with_extensions(["jpg", "jpeg", "png"]);
+    /// ```
+    pub fn with_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let extensions = extensions
+            .into_iter()
+            .map(|extension| {
+                let extension = extension.as_ref().trim_start_matches('.');
+                rules::Predicate::Extension(extension.to_owned())
+            })
+            .collect();
+        self.extensions = Some(extensions);
+        self
     }
-}
 
-impl TryFrom<PathBuf> for DirEntry {
-    type Error = Error;
+    /// Yields regular files of at least `size` bytes only, while still
+    /// descending into directories.
+    ///
+    /// The size is taken from the cached metadata.
+    pub fn min_size(mut self, size: u64) -> Self {
+        self.min_size = Some(size);
+        self
+    }
 
-    #[inline]
-    fn try_from(value: PathBuf) -> Result<Self> {
-        let path = value;
-        let metadata = path.symlink_metadata()?;
-        Ok(Self { path, metadata })
+    /// Yields regular files of at most `size` bytes only, while still
+    /// descending into directories.
+    ///
+    /// The size is taken from the cached metadata.
+    pub fn max_size(mut self, size: u64) -> Self {
+        self.max_size = Some(size);
+        self
     }
-}
 
-impl From<DirEntry> for PathBuf {
-    #[inline]
-    fn from(value: DirEntry) -> Self {
-        value.path
+    /// Yields entries last modified at or after `time` only, while still
+    /// descending into directories.
+    ///
+    /// The time is taken from the cached metadata, and entries whose time
+    /// is not available are not yielded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// // Large files untouched for a year.
+    /// let year_ago = SystemTime::now() - Duration::from_secs(365 * 24 * 60 * 60);
+    /// let iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .min_size(100 * 1024 * 1024)
+    ///     .modified_before(year_ago);
+    /// ```
+    pub fn modified_since(mut self, time: SystemTime) -> Self {
+        self.modified_since = Some(time);
+        self
     }
-}
 
-#[derive(Debug)]
-struct StackItem {
-    depth: NonZero<usize>,
-    iter: ReadDir,
+    /// Yields entries last modified before `time` only, while still
+    /// descending into directories.
+    ///
+    /// The time is taken as described in [`WalkDir::modified_since`].
+    pub fn modified_before(mut self, time: SystemTime) -> Self {
+        self.modified_before = Some(time);
+        self
+    }
+
+    /// Yields entries owned by the user `uid` only, while still descending
+    /// into directories.
+    ///
+    /// The owner is taken from the cached metadata.
+    #[cfg(unix)]
+    pub fn uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Yields entries owned by the group `gid` only, while still descending
+    /// into directories.
+    ///
+    /// The group is taken from the cached metadata.
+    #[cfg(unix)]
+    pub fn gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Yields entries whose permission bits within `mask` are exactly
+    /// `bits` only, while still descending into directories.
+    ///
+    /// The permissions are taken from the cached metadata. Bits of `bits`
+    /// outside `mask` are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// // World-writable files.
+    /// let iter = WalkDir::new(".").unwrap().files_only().mode(0o002, 0o002);
+    ///
+    /// // Files that are not executable by anyone.
+    /// let iter = WalkDir::new(".").unwrap().files_only().mode(0o111, 0);
+    /// ```
+    #[cfg(unix)]
+    pub fn mode(mut self, mask: u32, bits: u32) -> Self {
+        self.mode = Some((mask, bits & mask));
+        self
+    }
+
+    /// Sets whether to stay on the file system of the root.
+    ///
+    /// When set, directories on other file systems, such as mount points,
+    /// are yielded but not descended into. File systems are told apart by
+    /// device numbers on Unix, and by volume serial numbers on Windows,
+    /// where each directory is opened to read it. This has no effect on
+    /// other platforms.
+    ///
+    /// Defaults to `false`.
+    pub fn same_file_system(mut self, same_file_system: bool) -> Self {
+        self.same_file_system = same_file_system;
+        self
+    }
+
+    /// Sets whether to walk into zip and tar archives, including
+    /// gzip-compressed ones, yielding their entries as virtual ones after
+    /// the archives themselves, without extracting them.
+    ///
+    /// Archives are told by the extensions `.zip`, `.tar`, `.tar.gz` and
+    /// `.tgz`, and their entries are placed under their paths, so the entry
+    /// `docs/a.txt` of `backup.zip` is yielded as `backup.zip/docs/a.txt`,
+    /// and is told by [`DirEntry::is_virtual`]. Archives within archives are
+    /// not walked into. Errors reading an archive are yielded after it.
+    ///
+    /// Archives are read as by [`ZipFileSystem`] and [`TarFileSystem`], so
+    /// gzip-compressed ones are decompressed by the `gzip` program.
+    ///
+    /// [`ZipFileSystem`]: crate::rename::ZipFileSystem
+    /// [`TarFileSystem`]: crate::rename::TarFileSystem
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .walk_archives(true)
+    ///     .files_only()
+    ///     .filter_map(Result::ok)
+    ///     .filter(|entry| entry.is_virtual());
+    /// for entry in iter {
+    ///     println!("{} ({} bytes)", entry.path().display(), entry.size());
+    /// }
+    /// ```
+    pub fn walk_archives(mut self, walk_archives: bool) -> Self {
+        self.walk_archives = walk_archives;
+        self
+    }
+
+    /// Sets whether to collect statistics of the entries yielded, which
+    /// are returned by [`WalkDir::stats`].
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let mut iter = WalkDir::new(".").unwrap().collect_stats(true);
+    /// for entry in iter.by_ref().filter_map(Result::ok) {
+    ///     // ...
+    /// }
+    /// let stats = iter.stats().unwrap();
+    /// println!("{} files, {} bytes", stats.files(), stats.bytes());
+    /// ```
+    pub fn collect_stats(mut self, collect_stats: bool) -> Self {
+        self.stats = collect_stats.then(Stats::default);
+        self
+    }
+
+    /// Sets how errors are handled.
+    ///
+    /// Defaults to [`ErrorPolicy::Yield`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::{ErrorPolicy, WalkDir};
+    ///
+    /// let mut iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .error_policy(ErrorPolicy::Collect);
+    /// for entry in iter.by_ref() {
+    ///     // Never fails.
+    ///     let entry = entry.unwrap();
+    /// }
+    /// for error in iter.errors() {
+    ///     eprintln!("{error}");
+    /// }
+    /// ```
+    pub fn error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    /// Sets the maximum number of directories kept open at once, or
+    /// [`None`] for no limit, so deep traversals do not run out of file
+    /// descriptors.
+    ///
+    /// Once the limit is reached, the remaining entries of the shallowest
+    /// directory still open are read into memory before another one is
+    /// opened, trading memory for handles. Each traversal is bounded on its
+    /// own, so traversals run in parallel should share the limit among
+    /// them.
+    ///
+    /// Defaults to [`None`].
+    pub fn max_open(mut self, max_open: Option<NonZero<usize>>) -> Self {
+        self.max_open = max_open;
+        self
+    }
+
+    /// Sets the maximum number of entries to yield, after which the
+    /// traversal ends.
+    ///
+    /// Errors are yielded regardless, and do not count towards the limit.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Yields about one in every `every` entries, chosen at random by a
+    /// generator seeded with `seed`, for previewing a representative subset
+    /// of a large tree.
+    ///
+    /// Entries left out are still descended into, and errors are yielded
+    /// regardless. The same seed chooses the same entries as long as the
+    /// tree and the order of traversal are unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    /// use std::num::NonZero;
+    ///
+    /// let every = NonZero::new(100).unwrap();
+    /// let iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .sort_by_file_name()
+    ///     .sample(every, 42)
+    ///     .max_entries(20)
+    ///     .filter_map(Result::ok);
+    /// for entry in iter {
+    ///     println!("{}", entry.path().display());
+    /// }
+    /// ```
+    pub fn sample(mut self, every: NonZero<u64>, seed: u64) -> Self {
+        let probability = 1.0 / every.get() as f64;
+        self.sample = Some((probability, SplitMix64::new(seed)));
+        self
+    }
+
+    /// Sets whether to yield files with more than one hard link only once,
+    /// by the first path reached, so they are not counted or hashed twice.
+    ///
+    /// The other paths are skipped, and recorded to be returned by
+    /// [`WalkDir::hard_links`]. Files are told apart by
+    /// [`DirEntry::file_id`], so each file is opened to read it on Windows,
+    /// and this has no effect on platforms other than Unix and Windows.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let mut iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .files_only()
+    ///     .dedup_hard_links(true);
+    /// let entries: Vec<_> = iter.by_ref().filter_map(Result::ok).collect();
+    /// let bytes: u64 = entries.iter().map(|entry| entry.metadata().len()).sum();
+    /// for entry in &entries {
+    ///     for path in iter.hard_links(entry) {
+    ///         println!("{} is also {}", entry.path().display(), path.display());
+    ///     }
+    /// }
+    /// ```
+    pub fn dedup_hard_links(mut self, dedup_hard_links: bool) -> Self {
+        self.hard_links = dedup_hard_links.then(HashMap::new);
+        self
+    }
+
+    /// Sets a comparator ordering the entries of each directory.
+    ///
+    /// The entries of a directory are all read and sorted before any of
+    /// them is yielded, and subdirectories are still descended into right
+    /// after being yielded. Errors reading a directory are yielded before
+    /// its entries.
+    ///
+    /// By default, entries are yielded in the order the file system
+    /// returns them, which is unspecified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// // Largest files first.
+    /// let iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .sort_by(|a, b| b.metadata().len().cmp(&a.metadata().len()));
+    /// ```
+    pub fn sort_by<F>(mut self, compare: F) -> Self
+    where
+        F: FnMut(&DirEntry, &DirEntry) -> Ordering + Send + Sync + 'static,
+    {
+        self.compare = Some(Box::new(compare));
+        self
+    }
+
+    /// Sets a key function ordering the entries of each directory, as
+    /// described in [`WalkDir::sort_by`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// // Oldest files first.
+    /// let iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .sort_by_key(|entry| entry.metadata().modified().ok());
+    /// ```
+    pub fn sort_by_key<K, F>(self, mut key: F) -> Self
+    where
+        K: Ord,
+        F: FnMut(&DirEntry) -> K + Send + Sync + 'static,
+    {
+        self.sort_by(move |a, b| key(a).cmp(&key(b)))
+    }
+
+    /// Orders the entries of each directory by their file names, as
+    /// described in [`WalkDir::sort_by`].
+    pub fn sort_by_file_name(self) -> Self {
+        self.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()))
+    }
+
+    /// Orders the entries of each directory by their file names in natural
+    /// order, so `img2` sorts before `img10`, as described in
+    /// [`natural_cmp`] and [`WalkDir::sort_by`].
+    ///
+    /// File names that are not valid UTF-8 are compared lossily, with ties
+    /// broken by comparing them as they are.
+    ///
+    /// [`natural_cmp`]: crate::rules::natural_cmp
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// // `scan2.png` comes before `scan10.png`.
+    /// let iter = WalkDir::new(".").unwrap().sort_by_natural_name();
+    /// ```
+    pub fn sort_by_natural_name(self) -> Self {
+        self.sort_by(|a, b| {
+            let (x, y) = (a.file_name(), b.file_name());
+            rules::natural_cmp(&x.to_string_lossy(), &y.to_string_lossy()).then_with(|| x.cmp(y))
+        })
+    }
+
+    /// Sets whether to sort the entries of each directory by file name, so
+    /// traversals are reproducible regardless of the order the operating
+    /// system reads directories in.
+    ///
+    /// Entries are read and sorted as described in [`WalkDir::sort_by`].
+    /// A comparator set by it takes precedence, with ties broken by file
+    /// name. Errors reading a directory are ordered by path.
+    ///
+    /// Defaults to `false`.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Adds a predicate deciding whether to yield each entry.
+    ///
+    /// Entries failing any predicate are skipped, and directories failing
+    /// any predicate are not descended into, so their subtrees are never
+    /// read. Predicates are checked regardless of [`WalkDir::min_depth`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .filter_entry(|entry| entry.path().file_name() != Some(".git".as_ref()))
+    ///     .filter_entry(|entry| entry.path().file_name() != Some("target".as_ref()));
+    /// ```
+    pub fn filter_entry<F>(mut self, predicate: F) -> Self
+    where
+        F: FnMut(&DirEntry) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    /// Adds a glob pattern entries must match to be yielded.
+    ///
+    /// Patterns without `/` are matched against file names, and others
+    /// against paths relative to the root, with `/` as the separator. See
+    /// [`Predicate::Glob`] for the syntax.
+    ///
+    /// If any pattern is added, only entries matching at least one of them
+    /// are yielded. Unlike [`WalkDir::exclude`], this does not prune
+    /// subtrees, as entries within a directory may match even if the
+    /// directory does not.
+    ///
+    /// [`Predicate::Glob`]: crate::rules::Predicate::Glob
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .include("*.rs")
+    ///     .include("*.toml");
+    /// ```
+    #[cfg(feature = "globset")]
+    pub fn include<S>(mut self, pattern: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Adds a glob pattern excluding the entries it matches.
+    ///
+    /// Patterns are matched as described in [`WalkDir::include`]. Entries
+    /// matching any pattern are skipped, and directories matching any
+    /// pattern are not descended into, so their subtrees are never read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .exclude("target")
+    ///     .exclude("**/node_modules");
+    /// ```
+    #[cfg(feature = "globset")]
+    pub fn exclude<S>(mut self, pattern: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Sets whether to honor ignore files, as Git and ripgrep do.
+    ///
+    /// When set, patterns are read from the `.gitignore` and `.ignore` files
+    /// of each directory traversed, as well as from the global excludes
+    /// file of Git, which is set by `core.excludesFile` in `~/.gitconfig`,
+    /// or defaults to `~/.config/git/ignore`. Ignored entries are skipped,
+    /// and ignored directories are not descended into.
+    ///
+    /// Patterns in deeper directories take precedence over shallower ones,
+    /// patterns in `.ignore` over `.gitignore`, and the global excludes
+    /// come last. Ignore files above the root are not read.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let iter = WalkDir::new(".").unwrap().ignore_files(true);
+    /// ```
+    #[cfg(feature = "ignore")]
+    pub fn ignore_files(mut self, ignore_files: bool) -> Self {
+        self.ignore_files = ignore_files;
+        self
+    }
+
+    /// Consumes the traversal, returning an asynchronous stream over its
+    /// entries, read on a thread of its own, as described in
+    /// [`WalkStream`].
+    ///
+    /// This is only available with the `stream` feature.
+    #[cfg(feature = "stream")]
+    pub fn into_stream(self) -> WalkStream {
+        WalkStream::new(self)
+    }
+
+    /// Returns `true` if `entry` is ignored by the ignore files read so
+    /// far.
+    #[cfg(feature = "ignore")]
+    fn ignored(&self, entry: &DirEntry) -> bool {
+        if !self.ignore_files {
+            return false;
+        }
+        let is_dir = entry.metadata.is_dir();
+        self.stack
+            .iter()
+            .rev()
+            .flat_map(|item| &item.ignores)
+            .chain(&self.global)
+            .find_map(|ignore| ignore.matches(&entry.path, is_dir))
+            .unwrap_or(false)
+    }
+}
+
+impl WalkDir {
+    /// Skips the rest of the directory currently being read.
+    ///
+    /// Called right after a directory is yielded, this prevents descending
+    /// into it. Otherwise, this skips the remaining entries of the
+    /// directory containing the last yielded entry, which is always the
+    /// case with [`WalkDir::contents_first`] set, where the skipped
+    /// directory is still yielded afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let mut iter = WalkDir::new(".").unwrap();
+    /// while let Some(entry) = iter.next() {
+    ///     let Ok(entry) = entry else {
+    ///         continue;
+    ///     };
+    ///     // Skip directories marked as caches.
+    ///     if entry.is_dir() && entry.path().join("CACHEDIR.TAG").exists() {
+    ///         iter.skip_current_dir();
+    ///     }
+    /// }
+    /// ```
+    pub fn skip_current_dir(&mut self) {
+        if let Some(item) = self.stack.last_mut() {
+            item.entries = Entries::Sorted(Vec::new().into_iter());
+        }
+    }
+
+    /// Returns a [`Cursor`] recording the position of the traversal, which
+    /// can be persisted and resumed from with [`WalkDir::resume`].
+    ///
+    /// The directories being read are read to the end at once, so their
+    /// entries can be recorded. This does not change what is yielded next.
+    pub fn cursor(&mut self) -> Cursor {
+        let follow = self.follow();
+        let mut levels = Vec::with_capacity(self.stack.len());
+        for item in &mut self.stack {
+            item.read_all(
+                &self.root,
+                follow,
+                self.compare.as_mut(),
+                self.deterministic,
+            );
+            let remaining = match &item.entries {
+                // Everything has just been read.
+                Entries::Unsorted(_) => Vec::new(),
+                Entries::Sorted(iter) => iter
+                    .as_slice()
+                    .iter()
+                    .filter_map(|entry| match entry {
+                        // Entries within archives cannot be read again.
+                        Ok(entry) if entry.member.is_some() => None,
+                        Ok(entry) => Some(entry.path.clone()),
+                        // Entries that failed are read again on resuming,
+                        // unlike the directory itself.
+                        Err(error) if error.path != item.path => Some(error.path.clone()),
+                        Err(_) => None,
+                    })
+                    .collect(),
+                Entries::Resumed(iter) => iter.as_slice().to_vec(),
+            };
+            levels.push(Level {
+                path: item.path.clone(),
+                remaining,
+                pending: item.pending.is_some(),
+            });
+        }
+        Cursor {
+            root: self.root.to_path_buf(),
+            roots: self.roots.iter().cloned().collect(),
+            started: self.entered || self.resumed,
+            levels,
+        }
+    }
+
+    /// Consumes the traversal, returning the tree of the entries yielded
+    /// within the current root, so it can be rendered without walking the
+    /// file system again.
+    ///
+    /// Directories left out by the filters, such as
+    /// [`WalkDir::files_only`], are still included when entries within them
+    /// are yielded, so the hierarchy is kept. Errors are skipped, and
+    /// counted in the [`Stats`] of the root. With several roots, as given
+    /// to [`WalkDir::new_many`], the traversal ends with the first one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the metadata of the root cannot be read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::{DirTree, WalkDir};
+    ///
+    /// fn render(tree: &DirTree, indent: usize) {
+    ///     for child in tree.children() {
+    ///         let name = child.entry().file_name().to_string_lossy();
+    ///         println!("{:indent$}{name} ({} files)", "", child.stats().files());
+    ///         render(child, indent + 2);
+    ///     }
+    /// }
+    ///
+    /// let tree = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .sort_by_file_name()
+    ///     .collect_tree()
+    ///     .unwrap();
+    /// render(&tree, 0);
+    /// ```
+    pub fn collect_tree(self) -> Result<DirTree, WalkError> {
+        DirTree::collect(self)
+    }
+
+    /// Consumes the traversal, returning an iterator over the paths of the
+    /// entries, for callers with no use for their metadata.
+    pub fn into_paths(self) -> impl Iterator<Item = Result<PathBuf, WalkError>> {
+        self.map(|entry| entry.map(PathBuf::from))
+    }
+
+    /// Consumes the traversal, returning an iterator over the paths of the
+    /// entries, skipping those that produce errors, as [`walk_dir`] does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    /// use std::path::PathBuf;
+    ///
+    /// let paths: Vec<PathBuf> = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .with_extensions(["rs"])
+    ///     .paths()
+    ///     .collect();
+    /// ```
+    pub fn paths(self) -> impl Iterator<Item = PathBuf> {
+        self.filter_map(Result::ok).map(PathBuf::from)
+    }
+
+    /// Consumes the traversal, returning an iterator over its entries
+    /// batched by the directory holding them, as described in
+    /// [`ByDirectory`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    ///
+    /// let iter = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .files_only()
+    ///     .sort_by_file_name()
+    ///     .by_directory()
+    ///     .filter_map(Result::ok);
+    /// for (dir, entries) in iter {
+    ///     // Number the files of each directory on their own.
+    ///     for (index, entry) in entries.iter().enumerate() {
+    ///         println!("{}: {index} {}", dir.display(), entry.path().display());
+    ///     }
+    /// }
+    /// ```
+    pub fn by_directory(self) -> ByDirectory {
+        ByDirectory::new(self)
+    }
+
+    /// Returns the statistics of the entries yielded so far, or [`None`]
+    /// unless [`WalkDir::collect_stats`] is set.
+    ///
+    /// Entries are counted once yielded, so those filtered out are not
+    /// counted, while errors are counted regardless of
+    /// [`WalkDir::error_policy`].
+    #[inline]
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    /// Returns the other paths of the file yielded as `entry`, which were
+    /// skipped as told by [`WalkDir::dedup_hard_links`].
+    ///
+    /// Only the paths reached so far are returned, so the list is complete
+    /// once the traversal ends.
+    pub fn hard_links(&self, entry: &DirEntry) -> &[PathBuf] {
+        entry
+            .file_id()
+            .and_then(|id| self.hard_links.as_ref()?.get(&id))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the errors collected so far, as told by
+    /// [`ErrorPolicy::Collect`].
+    #[inline]
+    pub fn errors(&self) -> &[WalkError] {
+        &self.errors
+    }
+
+    /// Takes the errors collected so far, as told by
+    /// [`ErrorPolicy::Collect`].
+    pub fn take_errors(&mut self) -> Vec<WalkError> {
+        mem::take(&mut self.errors)
+    }
+
+    /// Returns `true` if `entry` is another path of a file yielded before,
+    /// recording the path, as told by [`WalkDir::dedup_hard_links`].
+    fn linked_again(&mut self, entry: &DirEntry) -> bool {
+        let Some(hard_links) = &mut self.hard_links else {
+            return false;
+        };
+        if entry.metadata.is_dir() {
+            return false;
+        }
+        let Some(id) = hard_link_id(entry) else {
+            return false;
+        };
+        match hard_links.get_mut(&id) {
+            Some(paths) => {
+                paths.push(entry.path.clone());
+                true
+            }
+            None => {
+                hard_links.insert(id, Vec::new());
+                false
+            }
+        }
+    }
+
+    /// Prepares the traversal of the current root, once its entries are
+    /// being read, returning the root itself if it is to be yielded now.
+    fn enter(&mut self) -> Option<Result<DirEntry, WalkError>> {
+        self.entered = true;
+        if let Some(files) = self.deferred.take() {
+            match fs::read_dir(extended(&self.root)) {
+                Ok(iter) => self.stack.push(StackItem::root(&self.root, iter)),
+                Err(_)
+                    if files
+                        && fs::metadata(extended(&self.root))
+                            .is_ok_and(|metadata| !metadata.is_dir()) =>
+                {
+                    self.file_root = true;
+                }
+                Err(error) => {
+                    let path = self.root.to_path_buf();
+                    let depth = 0;
+                    return Some(Err(WalkError { path, depth, error }));
+                }
+            }
+        }
+        let follow = self.follow();
+        let root = &self.root;
+        // There are several directories being read after resuming.
+        for item in &mut self.stack {
+            if follow.any() {
+                // Directories being read may be reached again through
+                // symbolic links.
+                item.identity = fs::metadata(extended(&item.path))
+                    .and_then(|metadata| Identity::new(&item.path, &metadata))
+                    .ok();
+                if let Some(pending) = &mut item.pending {
+                    pending.follow(follow);
+                }
+            }
+            #[cfg(feature = "ignore")]
+            if self.ignore_files {
+                item.ignores = Ignore::read_dir(&item.path);
+            }
+        }
+        #[cfg(feature = "ignore")]
+        if self.ignore_files {
+            self.global = Ignore::read_global(root);
+        }
+        self.device = None;
+        if self.same_file_system
+            && let Ok(metadata) = fs::metadata(root)
+        {
+            self.device = device(root, &metadata);
+        }
+
+        if !(self.include_root || self.file_root) || mem::take(&mut self.resumed) {
+            return None;
+        }
+        match self.root_entry() {
+            Err(error) => Some(Err(error)),
+            Ok(entry) if self.contents_first && !self.file_root => {
+                if let Some(item) = self.stack.first_mut() {
+                    item.pending = Some(entry);
+                }
+                None
+            }
+            Ok(entry) => self.yields(&entry).then_some(Ok(entry)),
+        }
+    }
+
+    /// Moves on to the next root, returning an error if it cannot be read.
+    ///
+    /// Returns [`None`] if there are no roots left.
+    fn next_root(&mut self) -> Option<Result<(), WalkError>> {
+        let path = self.roots.pop_front()?;
+        self.root = Arc::from(path.as_path());
+        match fs::read_dir(extended(&path)) {
+            Err(error) => {
+                // There is nothing to prepare.
+                self.entered = true;
+                let depth = 0;
+                Some(Err(WalkError { path, depth, error }))
+            }
+            Ok(iter) => {
+                self.entered = false;
+                self.stack.push(StackItem::root(&self.root, iter));
+                Some(Ok(()))
+            }
+        }
+    }
+
+    /// Returns which kinds of links to follow.
+    fn follow(&self) -> Follow {
+        let symlinks = self.follow_symlinks;
+        // Junctions to skip are left as they are, so they can be told.
+        #[cfg(windows)]
+        let junctions = !self.skip_junctions && self.follow_junctions.unwrap_or(symlinks);
+        #[cfg(not(windows))]
+        let junctions = symlinks;
+        Follow {
+            symlinks,
+            junctions,
+        }
+    }
+
+    /// Reads the entry of the root, at depth 0.
+    fn root_entry(&self) -> Result<DirEntry, WalkError> {
+        path_entry(self.root.to_path_buf(), &self.root, 0, self.follow())
+    }
+
+    /// Returns `true` if `entry` passes the filters that do not prune
+    /// subtrees.
+    fn yields(&self, entry: &DirEntry) -> bool {
+        if entry.depth < self.min_depth {
+            return false;
+        }
+        #[cfg(feature = "globset")]
+        if !self.include.is_empty() && !globbed(&self.root, entry, &self.include) {
+            return false;
+        }
+        if let Some(kind) = self.kind {
+            let matches = match kind {
+                Kind::File => entry.is_file(),
+                Kind::Dir => entry.is_dir(),
+                Kind::Symlink => entry.is_symlink(),
+                Kind::BrokenSymlink => entry.is_broken_symlink(),
+            };
+            if !matches {
+                return false;
+            }
+        }
+        if let Some(extensions) = &self.extensions
+            && !(entry.is_file()
+                && extensions
+                    .iter()
+                    .any(|extension| extension.matches_entry(entry)))
+        {
+            return false;
+        }
+        if self.min_size.is_some() || self.max_size.is_some() {
+            let size = entry.size();
+            if !entry.is_file()
+                || self.min_size.is_some_and(|min_size| size < min_size)
+                || self.max_size.is_some_and(|max_size| size > max_size)
+            {
+                return false;
+            }
+        }
+        if self.modified_since.is_some() || self.modified_before.is_some() {
+            let Ok(modified) = entry.metadata.modified() else {
+                return false;
+            };
+            if self.modified_since.is_some_and(|since| modified < since)
+                || self
+                    .modified_before
+                    .is_some_and(|before| modified >= before)
+            {
+                return false;
+            }
+        }
+        #[cfg(unix)]
+        if !self.owned(entry) {
+            return false;
+        }
+        true
+    }
+
+    /// Returns `true` if `entry` passes the filters on ownership and
+    /// permissions.
+    #[cfg(unix)]
+    fn owned(&self, entry: &DirEntry) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = &entry.metadata;
+        self.uid.is_none_or(|uid| metadata.uid() == uid)
+            && self.gid.is_none_or(|gid| metadata.gid() == gid)
+            && self
+                .mode
+                .is_none_or(|(mask, bits)| metadata.mode() & mask == bits)
+    }
+
+    /// Advances the traversal by one entry.
+    fn advance(&mut self) -> Option<Result<DirEntry, WalkError>> {
+        let follow = self.follow();
+        loop {
+            let entry = loop {
+                let item = self.stack.last_mut()?;
+                let depth = item.depth.get();
+                if self.compare.is_some() || self.deterministic {
+                    item.read_all(
+                        &self.root,
+                        follow,
+                        self.compare.as_mut(),
+                        self.deterministic,
+                    );
+                }
+                let entry = match &mut item.entries {
+                    Entries::Unsorted(iter) => iter
+                        .next()
+                        .map(|entry| read_entry(entry, &self.root, &item.path, depth, follow)),
+                    Entries::Sorted(iter) => iter.next(),
+                    Entries::Resumed(iter) => iter
+                        .next()
+                        .map(|path| path_entry(path, &self.root, depth, follow)),
+                };
+                match entry {
+                    None => {
+                        // Directories held back come after their contents.
+                        let item = self.stack.pop()?;
+                        if let Some(pending) = item.pending {
+                            return Some(Ok(pending));
+                        }
+                        continue;
+                    }
+                    Some(Err(error)) => return Some(Err(error)),
+                    Some(Ok(entry)) => {
+                        if self.skip_hidden && entry.is_hidden() {
+                            continue;
+                        }
+                        #[cfg(windows)]
+                        if self.skip_junctions && entry.is_junction() {
+                            continue;
+                        }
+                        #[cfg(feature = "globset")]
+                        if globbed(&self.root, &entry, &self.exclude) {
+                            continue;
+                        }
+                        #[cfg(feature = "ignore")]
+                        if self.ignored(&entry) {
+                            continue;
+                        }
+                        if self
+                            .predicates
+                            .iter_mut()
+                            .all(|predicate| predicate(&entry))
+                        {
+                            break entry;
+                        }
+                        continue;
+                    }
+                };
+            };
+
+            if entry.metadata.is_dir()
+                && self
+                    .max_depth
+                    .is_none_or(|max_depth| entry.depth < max_depth.get())
+                && self
+                    .device
+                    .is_none_or(|root| device(&entry.path, &entry.metadata) == Some(root))
+            {
+                let mut identity = None;
+                if follow.any() {
+                    let current = match Identity::new(entry.path(), &entry.metadata) {
+                        Err(error) => return Some(Err(WalkError::new(entry, error))),
+                        Ok(current) => current,
+                    };
+                    let looped = self
+                        .stack
+                        .iter()
+                        .find(|item| item.identity.as_ref() == Some(&current));
+                    if let Some(item) = looped {
+                        let ancestor = item.path.clone();
+                        let child = entry.path.clone();
+                        let error = io::Error::other(LoopError { ancestor, child });
+                        return Some(Err(WalkError::new(entry, error)));
+                    }
+                    identity = Some(current);
+                }
+
+                self.close_excess();
+                match fs::read_dir(extended(entry.path())) {
+                    // Yes, this branch is still reachable.
+                    Err(error) if error.kind() == ErrorKind::NotADirectory => (),
+                    Err(error) => return Some(Err(WalkError::new(entry, error))),
+                    Ok(iter) => {
+                        // Will not overflow because `depth < max_depth`.
+                        let depth = unsafe { NonZero::new_unchecked(entry.depth + 1) };
+                        let entries = Entries::Unsorted(iter);
+                        let mut item = StackItem {
+                            depth,
+                            entries,
+                            path: entry.path.clone(),
+                            identity,
+                            pending: None,
+                            #[cfg(feature = "ignore")]
+                            ignores: if self.ignore_files {
+                                Ignore::read_dir(&entry.path)
+                            } else {
+                                Vec::new()
+                            },
+                        };
+                        if self.contents_first {
+                            item.pending = Some(entry);
+                            self.stack.push(item);
+                            continue;
+                        }
+                        self.stack.push(item);
+                    }
+                }
+            } else if self.walk_archives
+                && self
+                    .max_depth
+                    .is_none_or(|max_depth| entry.depth < max_depth.get())
+            {
+                let entries = match &entry.member {
+                    Some(member) if member.is_dir() => Some(Ok(member.entries())),
+                    Some(_) => None,
+                    None if entry.metadata.is_file() && Archive::is_archive(&entry.path) => {
+                        Some(Archive::open(&entry).map(|archive| archive.entries(Path::new(""))))
+                    }
+                    None => None,
+                };
+                if let Some(entries) = entries {
+                    let entries = match entries {
+                        Ok(mut entries) => {
+                            if let Some(compare) = &mut self.compare {
+                                entries.sort_by(|a, b| compare(a, b));
+                            }
+                            entries.into_iter().map(Ok).collect()
+                        }
+                        Err(error) => {
+                            let path = entry.path.clone();
+                            let depth = entry.depth;
+                            vec![Err(WalkError { path, depth, error })]
+                        }
+                    };
+                    // Will not overflow because `depth < max_depth`.
+                    let depth = unsafe { NonZero::new_unchecked(entry.depth + 1) };
+                    let mut item = StackItem {
+                        depth,
+                        entries: Entries::Sorted(entries.into_iter()),
+                        path: entry.path.clone(),
+                        identity: None,
+                        pending: None,
+                        #[cfg(feature = "ignore")]
+                        ignores: Vec::new(),
+                    };
+                    if self.contents_first {
+                        item.pending = Some(entry);
+                        self.stack.push(item);
+                        continue;
+                    }
+                    self.stack.push(item);
+                }
+            }
+
+            return Some(Ok(entry));
+        }
+    }
+
+    /// Reads the shallowest directories still open into memory, until
+    /// another one can be opened within [`WalkDir::max_open`].
+    fn close_excess(&mut self) {
+        let Some(max_open) = self.max_open else {
+            return;
+        };
+        let open = self
+            .stack
+            .iter()
+            .filter(|item| matches!(item.entries, Entries::Unsorted(_)))
+            .count();
+        let excess = (open + 1).saturating_sub(max_open.get());
+        let follow = self.follow();
+        self.stack
+            .iter_mut()
+            .filter(|item| matches!(item.entries, Entries::Unsorted(_)))
+            .take(excess)
+            .for_each(|item| {
+                item.read_all(
+                    &self.root,
+                    follow,
+                    self.compare.as_mut(),
+                    self.deterministic,
+                )
+            });
+    }
+
+    /// Returns the next entry or error to yield, across all roots.
+    fn walk(&mut self) -> Option<Result<DirEntry, WalkError>> {
+        loop {
+            if !self.entered
+                && let Some(root) = self.enter()
+            {
+                return Some(root);
+            }
+            match self.advance() {
+                None => {
+                    if let Err(error) = self.next_root()? {
+                        return Some(Err(error));
+                    }
+                }
+                Some(Ok(entry)) if !self.yields(&entry) => continue,
+                Some(result) => return Some(result),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for WalkDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalkDir")
+            .field("root", &self.root)
+            .field("roots", &self.roots)
+            .field("entered", &self.entered)
+            .field("resumed", &self.resumed)
+            .field("file_root", &self.file_root)
+            .field("deferred", &self.deferred)
+            .field("stack", &self.stack)
+            .field("min_depth", &self.min_depth)
+            .field("max_depth", &self.max_depth)
+            .field("include_root", &self.include_root)
+            .field("follow_symlinks", &self.follow_symlinks)
+            .field("skip_hidden", &self.skip_hidden)
+            .field("contents_first", &self.contents_first)
+            .field("kind", &self.kind)
+            .field("same_file_system", &self.same_file_system)
+            .field("walk_archives", &self.walk_archives)
+            .field("device", &self.device)
+            .field("stats", &self.stats)
+            .field("error_policy", &self.error_policy)
+            .field("errors", &self.errors)
+            .field("aborted", &self.aborted)
+            .field("max_open", &self.max_open)
+            .field("max_entries", &self.max_entries)
+            .field("yielded", &self.yielded)
+            .field("sample", &self.sample)
+            .field("hard_links", &self.hard_links)
+            .field("deterministic", &self.deterministic)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Iterator for WalkDir {
+    type Item = Result<DirEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.aborted
+            || self
+                .max_entries
+                .is_some_and(|max_entries| self.yielded >= max_entries)
+        {
+            return None;
+        }
+        let entry = loop {
+            let error = match self.walk()? {
+                Ok(entry) => {
+                    if self.linked_again(&entry) {
+                        continue;
+                    }
+                    if let Some((probability, generator)) = &mut self.sample
+                        && generator.next_f64() >= *probability
+                    {
+                        continue;
+                    }
+                    break entry;
+                }
+                Err(error) => error,
+            };
+            // Errors are counted even if they are not yielded.
+            if let Some(stats) = &mut self.stats {
+                stats.record_error();
+            }
+            match self.error_policy {
+                ErrorPolicy::Yield => return Some(Err(error)),
+                ErrorPolicy::Abort => {
+                    self.aborted = true;
+                    return Some(Err(error));
+                }
+                ErrorPolicy::Skip => continue,
+                ErrorPolicy::Collect => self.errors.push(error),
+            }
+        };
+        if self.max_entries.is_some() {
+            self.yielded += 1;
+        }
+        if let Some(stats) = &mut self.stats {
+            stats.record_entry(&entry);
+        }
+        Some(Ok(entry))
+    }
+}
+
+/// Returns `true` if `entry` matches any of the glob `patterns`, as
+/// described in [`WalkDir::include`].
+#[cfg(feature = "globset")]
+fn globbed(root: &Path, entry: &DirEntry, patterns: &[String]) -> bool {
+    if patterns.is_empty() {
+        return false;
+    }
+    let relative = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+    let relative = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let name = entry
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    patterns.iter().any(|pattern| {
+        let text = if pattern.contains('/') {
+            &relative
+        } else {
+            name.as_ref()
+        };
+        glob_matches(pattern, text)
+    })
+}
+
+/// A directory entry returned by [`WalkDir`].
+///
+/// Each entry provides a path along with cached metadata to help avoid
+/// redundant system calls. However, due to possible concurrent file access,
+/// the cached metadata may degrade in validity over time.
+///
+/// Note that the metadata does not follow symlinks, unless
+/// [`WalkDir::follow_symlinks`] is set.
+#[derive(Debug)]
+pub struct DirEntry {
+    path: PathBuf,
+    metadata: Metadata,
+    root: Arc<Path>,
+    depth: usize,
+    /// The entry within an archive, only for virtual entries.
+    member: Option<Member>,
+}
+
+impl DirEntry {
+    /// Returns the path.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the cached metadata.
+    ///
+    /// Due to possible concurrent file access, the cached metadata may degrade in
+    /// validity over time.
+    ///
+    /// Note that the metadata does not follow symbolic links, unless
+    /// [`WalkDir::follow_symlinks`] is set. For virtual entries, this is the
+    /// metadata of the archive holding them.
+    #[inline]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the root of the traversal the entry was yielded from, as
+    /// given to [`WalkDir::new`] or [`WalkDir::new_many`].
+    ///
+    /// This is the path itself for entries not yielded by [`WalkDir`].
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the path relative to the root of the traversal, which is
+    /// empty for the root itself.
+    ///
+    /// This is the last [`DirEntry::depth`] components of the path, so it
+    /// is empty for entries not yielded by [`WalkDir`] as well.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mofu::walk_dir::WalkDir;
+    /// use std::fs;
+    /// use std::path::Path;
+    ///
+    /// // Mirror the directory tree of `src` into `dst`.
+    /// let iter = WalkDir::new("src").unwrap().dirs_only();
+    /// for entry in iter.filter_map(Result::ok) {
+    ///     fs::create_dir_all(Path::new("dst").join(entry.relative_path())).unwrap();
+    /// }
+    /// ```
+    pub fn relative_path(&self) -> &Path {
+        let mut components = self.path.components();
+        let count = components.clone().count();
+        for _ in self.depth..count {
+            components.next();
+        }
+        components.as_path()
+    }
+
+    /// Returns the final component of the path, or the whole path if it
+    /// has none, such as `/` or `..`.
+    #[inline]
+    pub fn file_name(&self) -> &OsStr {
+        self.path
+            .file_name()
+            .unwrap_or_else(|| self.path.as_os_str())
+    }
+
+    /// Returns the extension of the file name, as described in
+    /// [`Path::extension`].
+    #[inline]
+    pub fn extension(&self) -> Option<&OsStr> {
+        self.path.extension()
+    }
+
+    /// Returns the file type from the cached metadata.
+    ///
+    /// For virtual entries, this is the file type of the archive holding
+    /// them, so [`DirEntry::is_dir`] and [`DirEntry::is_file`] should be
+    /// used instead.
+    #[inline]
+    pub fn file_type(&self) -> FileType {
+        self.metadata.file_type()
+    }
+
+    /// Returns `true` if the entry is a directory, according to the cached
+    /// metadata.
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        match &self.member {
+            Some(member) => member.is_dir(),
+            None => self.metadata.is_dir(),
+        }
+    }
+
+    /// Returns `true` if the entry is a regular file, according to the
+    /// cached metadata.
+    #[inline]
+    pub fn is_file(&self) -> bool {
+        match &self.member {
+            Some(member) => !member.is_dir(),
+            None => self.metadata.is_file(),
+        }
+    }
+
+    /// Returns the size in bytes, according to the cached metadata.
+    ///
+    /// For virtual entries, this is the uncompressed size of files, and 0
+    /// for directories.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        match &self.member {
+            Some(member) => member.len(),
+            None => self.metadata.len(),
+        }
+    }
+
+    /// Returns `true` if the entry is within an archive, as yielded with
+    /// [`WalkDir::walk_archives`].
+    #[inline]
+    pub fn is_virtual(&self) -> bool {
+        self.member.is_some()
+    }
+
+    /// Returns the path of the archive holding the entry, if it is virtual.
+    #[inline]
+    pub fn archive(&self) -> Option<&Path> {
+        self.member.as_ref().map(Member::archive)
+    }
+
+    /// Returns `true` if the entry is a symbolic link, according to the
+    /// cached metadata.
+    ///
+    /// This is always `false` for links followed by
+    /// [`WalkDir::follow_symlinks`], except for broken ones.
+    #[inline]
+    pub fn is_symlink(&self) -> bool {
+        self.metadata.is_symlink()
+    }
+
+    /// Returns `true` if the entry is a symbolic link whose target does not
+    /// exist, or cannot be reached.
+    ///
+    /// This reads the metadata of the target, unless the cached metadata
+    /// tells the entry is not a symbolic link. With
+    /// [`WalkDir::follow_symlinks`] set, symbolic links that are not broken
+    /// are followed, so they are never reported.
+    pub fn is_broken_symlink(&self) -> bool {
+        self.metadata.is_symlink() && fs::metadata(extended(&self.path)).is_err()
+    }
+
+    /// Returns the depth, where top-level entries of the traversal are at
+    /// depth 1.
+    ///
+    /// The root, as yielded with [`WalkDir::include_root`], and entries not
+    /// yielded by [`WalkDir`] are at depth 0.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the device number of the file system holding the entry,
+    /// according to the cached metadata.
+    #[cfg(unix)]
+    #[inline]
+    pub fn dev(&self) -> u64 {
+        use std::os::unix::fs::MetadataExt;
+
+        self.metadata.dev()
+    }
+
+    /// Returns the inode number of the entry, according to the cached
+    /// metadata.
+    #[cfg(unix)]
+    #[inline]
+    pub fn ino(&self) -> u64 {
+        use std::os::unix::fs::MetadataExt;
+
+        self.metadata.ino()
+    }
+
+    /// Returns what identifies the file regardless of the path it is
+    /// reached by, which is the device and inode numbers on Unix, according
+    /// to the cached metadata, and the volume serial number and file index
+    /// on Windows, which are read from the file system.
+    ///
+    /// Entries sharing an identifier are hard links to the same file, or
+    /// the same directory reached through symbolic links.
+    ///
+    /// Returns [`None`] for virtual entries, for files that cannot be opened
+    /// on Windows, and on other platforms, where the identifiers are not
+    /// available.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mofu::walk_dir::WalkDir;
+    /// use std::collections::HashSet;
+    ///
+    /// // Files with more than one hard link are only counted once.
+    /// let mut seen = HashSet::new();
+    /// let unique = WalkDir::new(".")
+    ///     .unwrap()
+    ///     .files_only()
+    ///     .filter_map(Result::ok)
+    ///     .filter(|entry| entry.file_id().is_none_or(|id| seen.insert(id)))
+    ///     .count();
+    /// ```
+    #[cfg(unix)]
+    pub fn file_id(&self) -> Option<(u64, u64)> {
+        self.member.is_none().then(|| (self.dev(), self.ino()))
+    }
+
+    /// Returns what identifies the file regardless of the path it is
+    /// reached by, as described for Unix.
+    #[cfg(windows)]
+    pub fn file_id(&self) -> Option<(u64, u64)> {
+        self.file_information().map(|info| info.id())
+    }
+
+    /// Returns [`None`], as identifiers of files are not available on this
+    /// platform.
+    #[cfg(not(any(unix, windows)))]
+    pub fn file_id(&self) -> Option<(u64, u64)> {
+        None
+    }
+
+    /// Reads the information on the file, or on the link itself unless the
+    /// cached metadata follows it, or [`None`] for virtual entries.
+    #[cfg(windows)]
+    fn file_information(&self) -> Option<FileInformation> {
+        if self.member.is_some() {
+            return None;
+        }
+        file_information(&self.path, !self.metadata.is_symlink()).ok()
+    }
+
+    /// Returns `true` if the entry is a reparse point, such as a symbolic
+    /// link or a junction, according to the cached metadata.
+    #[cfg(windows)]
+    #[inline]
+    pub fn is_reparse_point(&self) -> bool {
+        use std::os::windows::fs::MetadataExt;
+
+        const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+        self.metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0
+    }
+
+    /// Returns `true` if the entry is an NTFS junction or mount point,
+    /// which the cached metadata tells as a symbolic link.
+    ///
+    /// This reads the reparse tag of the entry, unless the cached metadata
+    /// tells it is not a reparse point.
+    #[cfg(windows)]
+    pub fn is_junction(&self) -> bool {
+        const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xa000_0003;
+        self.is_reparse_point()
+            && reparse_tag(&self.path).is_ok_and(|tag| tag == IO_REPARSE_TAG_MOUNT_POINT)
+    }
+
+    /// Replaces the cached metadata of a link to `follow` with that of its
+    /// target, leaving broken links as they are.
+    fn follow(&mut self, follow: Follow) {
+        if !self.metadata.is_symlink() {
+            return;
+        }
+        #[cfg(windows)]
+        let follows = if follow.symlinks == follow.junctions {
+            follow.symlinks
+        } else if self.is_junction() {
+            follow.junctions
+        } else {
+            follow.symlinks
+        };
+        #[cfg(not(windows))]
+        let follows = follow.symlinks;
+        if follows && let Ok(metadata) = fs::metadata(extended(&self.path)) {
+            self.metadata = metadata;
+        }
+    }
+
+    /// Returns `true` if the entry is hidden, as described in
+    /// [`WalkDir::skip_hidden`].
+    #[cfg(not(windows))]
+    fn is_hidden(&self) -> bool {
+        self.file_name().as_encoded_bytes().starts_with(b".")
+    }
+
+    /// Returns `true` if the entry is hidden, as described in
+    /// [`WalkDir::skip_hidden`].
+    #[cfg(windows)]
+    fn is_hidden(&self) -> bool {
+        use std::os::windows::fs::MetadataExt;
+
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        self.metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+    }
+}
+
+impl AsRef<Path> for DirEntry {
+    #[inline]
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl TryFrom<fs::DirEntry> for DirEntry {
+    type Error = io::Error;
+
+    #[inline]
+    fn try_from(value: fs::DirEntry) -> io::Result<Self> {
+        let path = value.path();
+        let metadata = value.metadata()?;
+        let root = Arc::from(path.as_path());
+        let depth = 0;
+        let member = None;
+        Ok(Self {
+            path,
+            metadata,
+            root,
+            depth,
+            member,
+        })
+    }
+}
+
+impl TryFrom<PathBuf> for DirEntry {
+    type Error = io::Error;
+
+    #[inline]
+    fn try_from(value: PathBuf) -> io::Result<Self> {
+        let path = value;
+        let metadata = extended(&path).symlink_metadata()?;
+        let root = Arc::from(path.as_path());
+        let depth = 0;
+        let member = None;
+        Ok(Self {
+            path,
+            metadata,
+            root,
+            depth,
+            member,
+        })
+    }
+}
+
+impl From<DirEntry> for PathBuf {
+    #[inline]
+    fn from(value: DirEntry) -> Self {
+        value.path
+    }
+}
+
+/// An error reported when following a symbolic link would loop back to a
+/// directory being traversed.
+///
+/// It is wrapped in the [`io::Error`] of a [`WalkError`], of kind
+/// [`ErrorKind::Other`], from which it can be recovered with
+/// [`io::Error::downcast`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopError {
+    ancestor: PathBuf,
+    child: PathBuf,
+}
+
+impl LoopError {
+    /// Returns the directory being traversed that the link leads back to.
+    #[inline]
+    pub fn ancestor(&self) -> &Path {
+        &self.ancestor
+    }
+
+    /// Returns the path within the ancestor that leads back to it.
+    #[inline]
+    pub fn child(&self) -> &Path {
+        &self.child
+    }
+}
+
+impl fmt::Display for LoopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "file system loop detected: {} leads back to {}",
+            self.child.display(),
+            self.ancestor.display(),
+        )
+    }
+}
+
+impl error::Error for LoopError {}
+
+/// An error yielded by [`WalkDir`], along with the path and depth where it
+/// occurred.
+///
+/// The path is that of the entry whose metadata or contents could not be
+/// read, or that of the directory being read if its entries could not be
+/// listed.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::walk_dir::WalkDir;
+/// use std::io;
+///
+/// for entry in WalkDir::new(".").unwrap() {
+///     match entry {
+///         Ok(entry) => println!("{}", entry.path().display()),
+///         Err(error) => {
+///             eprintln!("{}: {}", error.path().display(), error.io_error());
+///             let _: io::Error = error.into();
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct WalkError {
+    path: PathBuf,
+    depth: usize,
+    error: io::Error,
+}
+
+impl WalkError {
+    fn new(entry: DirEntry, error: io::Error) -> Self {
+        let path = entry.path;
+        let depth = entry.depth;
+        Self { path, depth, error }
+    }
+
+    /// Returns the path where the error occurred.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the depth of the path, as described in [`DirEntry::depth`],
+    /// where the root is at depth 0.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the underlying I/O error.
+    #[inline]
+    pub fn io_error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Returns the kind of the underlying I/O error.
+    #[inline]
+    pub fn kind(&self) -> ErrorKind {
+        self.error.kind()
+    }
+
+    /// Consumes the error, returning the underlying I/O error without the
+    /// path and depth.
+    #[inline]
+    pub fn into_io_error(self) -> io::Error {
+        self.error
+    }
+}
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl error::Error for WalkError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<WalkError> for io::Error {
+    /// Converts into an I/O error of the same kind, whose message includes
+    /// the path.
+    fn from(value: WalkError) -> Self {
+        io::Error::new(value.error.kind(), value)
+    }
+}
+
+/// Converts an entry read from the directory `dir` within `root`, whose
+/// entries are at `depth`, following it if it is a link to `follow`.
+fn read_entry(
+    entry: io::Result<fs::DirEntry>,
+    root: &Arc<Path>,
+    dir: &Path,
+    depth: usize,
+    follow: Follow,
+) -> Result<DirEntry, WalkError> {
+    let entry = entry.map_err(|error| WalkError {
+        path: dir.to_path_buf(),
+        depth: depth - 1,
+        error,
+    })?;
+    // Paths read from extended-length ones are rebuilt as they were given.
+    let path = dir.join(entry.file_name());
+    let metadata = match entry.metadata() {
+        Err(error) => return Err(WalkError { path, depth, error }),
+        Ok(metadata) => metadata,
+    };
+    let mut entry = DirEntry {
+        path,
+        metadata,
+        root: Arc::clone(root),
+        depth,
+        member: None,
+    };
+    entry.follow(follow);
+    Ok(entry)
+}
+
+/// Reads the entry at `path` within `root` at `depth`, following it if it
+/// is a link to `follow`.
+fn path_entry(
+    path: PathBuf,
+    root: &Arc<Path>,
+    depth: usize,
+    follow: Follow,
+) -> Result<DirEntry, WalkError> {
+    let metadata = match extended(&path).symlink_metadata() {
+        Err(error) => return Err(WalkError { path, depth, error }),
+        Ok(metadata) => metadata,
+    };
+    let mut entry = DirEntry {
+        path,
+        metadata,
+        root: Arc::clone(root),
+        depth,
+        member: None,
+    };
+    entry.follow(follow);
+    Ok(entry)
+}
+
+/// Returns the identifier of the file, if it has more than one hard link.
+#[cfg(unix)]
+fn hard_link_id(entry: &DirEntry) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    if entry.metadata.nlink() > 1 {
+        entry.file_id()
+    } else {
+        None
+    }
+}
+
+/// Returns the identifier of the file, if it has more than one hard link.
+#[cfg(windows)]
+fn hard_link_id(entry: &DirEntry) -> Option<(u64, u64)> {
+    let info = entry.file_information()?;
+    (info.number_of_links > 1).then(|| info.id())
+}
+
+/// Hard links are not counted on this platform.
+#[cfg(not(any(unix, windows)))]
+fn hard_link_id(_entry: &DirEntry) -> Option<(u64, u64)> {
+    None
+}
+
+/// Returns the device number of the file system holding a file.
+#[cfg(unix)]
+fn device(_path: &Path, metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.dev())
+}
+
+/// Returns the serial number of the volume holding a file, following
+/// links.
+#[cfg(windows)]
+fn device(path: &Path, _metadata: &Metadata) -> Option<u64> {
+    let info = file_information(path, true).ok()?;
+    Some(info.volume_serial_number.into())
+}
+
+/// Device numbers are not available on this platform.
+#[cfg(not(any(unix, windows)))]
+fn device(_path: &Path, _metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Returns `path` as an absolute extended-length path, prefixed with
+/// `\\?\`, so paths longer than `MAX_PATH` can be opened.
+///
+/// Paths that cannot be made absolute, or are already prefixed, are
+/// returned as they are.
+#[cfg(windows)]
+fn extended(path: &Path) -> Cow<'_, Path> {
+    use std::ffi::OsString;
+    use std::path::{Component, Prefix};
+
+    if let Some(Component::Prefix(prefix)) = path.components().next()
+        && (prefix.kind().is_verbatim() || matches!(prefix.kind(), Prefix::DeviceNS(_)))
+    {
+        return Cow::Borrowed(path);
+    }
+    let Ok(absolute) = std::path::absolute(path) else {
+        return Cow::Borrowed(path);
+    };
+    let mut extended = OsString::from(r"\\?\");
+    match absolute.as_os_str().as_encoded_bytes().strip_prefix(br"\\") {
+        // `\\server\share` becomes `\\?\UNC\server\share`.
+        Some(unc) => {
+            extended.push(r"UNC\");
+            // Safe because it is split right after ASCII characters.
+            extended.push(unsafe { OsStr::from_encoded_bytes_unchecked(unc) });
+        }
+        None => extended.push(&absolute),
+    }
+    Cow::Owned(PathBuf::from(extended))
+}
+
+/// Returns the reparse tag of the reparse point at `path`.
+#[cfg(windows)]
+fn reparse_tag(path: &Path) -> io::Result<u32> {
+    use std::ffi::c_void;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    const FILE_ATTRIBUTE_TAG_INFO: i32 = 9;
+
+    #[repr(C)]
+    struct FileAttributeTagInfo {
+        file_attributes: u32,
+        reparse_tag: u32,
+    }
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetFileInformationByHandleEx(
+            file: *mut c_void,
+            class: i32,
+            info: *mut c_void,
+            size: u32,
+        ) -> i32;
+    }
+
+    // Directories can only be opened with backup semantics, and the link
+    // itself with the reparse point flag.
+    let file = fs::OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_BACKUP_SEMANTICS)
+        .open(extended(path))?;
+    let mut info = FileAttributeTagInfo {
+        file_attributes: 0,
+        reparse_tag: 0,
+    };
+    // Safe because the handle is open, and `info` matches the class.
+    let succeeded = unsafe {
+        GetFileInformationByHandleEx(
+            file.as_raw_handle(),
+            FILE_ATTRIBUTE_TAG_INFO,
+            (&raw mut info).cast(),
+            size_of::<FileAttributeTagInfo>() as u32,
+        )
+    };
+    if succeeded == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(info.reparse_tag)
+}
+
+/// The information on a file returned by `GetFileInformationByHandle`, of
+/// which only some fields are read.
+#[cfg(windows)]
+#[allow(dead_code)]
+#[repr(C)]
+struct FileInformation {
+    file_attributes: u32,
+    creation_time: [u32; 2],
+    last_access_time: [u32; 2],
+    last_write_time: [u32; 2],
+    volume_serial_number: u32,
+    file_size_high: u32,
+    file_size_low: u32,
+    number_of_links: u32,
+    file_index_high: u32,
+    file_index_low: u32,
+}
+
+#[cfg(windows)]
+impl FileInformation {
+    /// Returns the volume serial number and the file index.
+    fn id(&self) -> (u64, u64) {
+        let index = (u64::from(self.file_index_high) << 32) | u64::from(self.file_index_low);
+        (self.volume_serial_number.into(), index)
+    }
+}
+
+/// Reads the information on the file at `path`, or on the link itself
+/// unless `follow` is set.
+#[cfg(windows)]
+fn file_information(path: &Path, follow: bool) -> io::Result<FileInformation> {
+    use std::ffi::c_void;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetFileInformationByHandle(file: *mut c_void, info: *mut FileInformation) -> i32;
+    }
+
+    // Directories can only be opened with backup semantics.
+    let mut flags = FILE_FLAG_BACKUP_SEMANTICS;
+    if !follow {
+        flags |= FILE_FLAG_OPEN_REPARSE_POINT;
+    }
+    let file = fs::OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(flags)
+        .open(extended(path))?;
+    let mut info = FileInformation {
+        file_attributes: 0,
+        creation_time: [0; 2],
+        last_access_time: [0; 2],
+        last_write_time: [0; 2],
+        volume_serial_number: 0,
+        file_size_high: 0,
+        file_size_low: 0,
+        number_of_links: 0,
+        file_index_high: 0,
+        file_index_low: 0,
+    };
+    // Safe because the handle is open, and `info` matches the structure
+    // expected.
+    let succeeded = unsafe { GetFileInformationByHandle(file.as_raw_handle(), &raw mut info) };
+    if succeeded == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(info)
+}
+
+/// Paths are not limited in length on this platform.
+#[cfg(not(windows))]
+#[inline]
+fn extended(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+/// Which kinds of links to follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Follow {
+    symlinks: bool,
+    /// Whether to follow junctions, which are only told apart from
+    /// symbolic links on Windows.
+    junctions: bool,
+}
+
+impl Follow {
+    /// Returns `true` if any kind of link is followed.
+    fn any(self) -> bool {
+        self.symlinks || self.junctions
+    }
+}
+
+/// The kind of entries to yield.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    File,
+    Dir,
+    Symlink,
+    BrokenSymlink,
+}
+
+#[derive(Debug)]
+struct StackItem {
+    depth: NonZero<usize>,
+    entries: Entries,
+    path: PathBuf,
+    /// The identity of the directory, only tracked when following symbolic
+    /// links.
+    identity: Option<Identity>,
+    /// The directory being read, held back until its contents are
+    /// yielded.
+    pending: Option<DirEntry>,
+    /// The ignore files of the directory, only read when honoring them.
+    #[cfg(feature = "ignore")]
+    ignores: Vec<Ignore>,
+}
+
+impl StackItem {
+    /// Creates the item of a root, whose entries are at depth 1.
+    fn root(path: &Path, iter: ReadDir) -> Self {
+        Self {
+            depth: NonZero::<usize>::MIN,
+            entries: Entries::Unsorted(iter),
+            path: path.to_path_buf(),
+            identity: None,
+            pending: None,
+            #[cfg(feature = "ignore")]
+            ignores: Vec::new(),
+        }
+    }
+
+    /// Creates the item of a directory recorded by a [`Cursor`] at `index`,
+    /// whose entries are at the depth after it.
+    fn resumed(root: &Arc<Path>, index: usize, level: Level) -> Self {
+        let pending = if level.pending {
+            // Links are followed once the options are known.
+            let follow = Follow {
+                symlinks: false,
+                junctions: false,
+            };
+            path_entry(level.path.clone(), root, index, follow).ok()
+        } else {
+            None
+        };
+        Self {
+            depth: NonZero::<usize>::MIN.saturating_add(index),
+            entries: Entries::Resumed(level.remaining.into_iter()),
+            path: level.path,
+            identity: None,
+            pending,
+            #[cfg(feature = "ignore")]
+            ignores: Vec::new(),
+        }
+    }
+
+    /// Reads the remaining entries at once if they are still being read
+    /// from the directory, sorting them by `compare` if set, with errors
+    /// first.
+    ///
+    /// If `deterministic` is set, entries are sorted by file name before
+    /// `compare`, and errors by path.
+    fn read_all(
+        &mut self,
+        root: &Arc<Path>,
+        follow: Follow,
+        compare: Option<&mut Box<Compare>>,
+        deterministic: bool,
+    ) {
+        let Entries::Unsorted(iter) = &mut self.entries else {
+            return;
+        };
+        let depth = self.depth.get();
+        let mut errors = Vec::new();
+        let mut entries = Vec::new();
+        for entry in iter {
+            match read_entry(entry, root, &self.path, depth, follow) {
+                Err(error) => errors.push(error),
+                Ok(entry) => entries.push(entry),
+            }
+        }
+        if deterministic {
+            errors.sort_by(|a, b| a.path.cmp(&b.path));
+            entries.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+        }
+        // Stable sorting breaks ties by file name when deterministic.
+        if let Some(compare) = compare {
+            entries.sort_by(|a, b| compare(a, b));
+        }
+        let errors = errors.into_iter().map(Err);
+        let entries = errors
+            .chain(entries.into_iter().map(Ok))
+            .collect::<Vec<_>>();
+        self.entries = Entries::Sorted(entries.into_iter());
+    }
+}
+
+/// The entries of a directory, which are read and sorted at once if a
+/// comparator is set.
+#[derive(Debug)]
+enum Entries {
+    Unsorted(ReadDir),
+    Sorted(vec::IntoIter<Result<DirEntry, WalkError>>),
+    /// The paths of the entries left when a [`Cursor`] was taken, which
+    /// are read once reached.
+    Resumed(vec::IntoIter<PathBuf>),
+}
+
+/// What identifies a directory regardless of the path it is reached by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Identity {
+    /// The device and inode numbers.
+    #[cfg(unix)]
+    Inode(u64, u64),
+    /// The canonical path, where inode numbers are not available.
+    #[cfg(not(unix))]
+    Path(PathBuf),
+}
+
+impl Identity {
+    #[cfg(unix)]
+    fn new(_path: &Path, metadata: &Metadata) -> io::Result<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        Ok(Self::Inode(metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn new(path: &Path, _metadata: &Metadata) -> io::Result<Self> {
+        fs::canonicalize(extended(path)).map(Self::Path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    /// Runs `test` on a fresh directory holding a small tree, removing it
+    /// after.
+    ///
+    /// ```text
+    /// a/
+    ///     c.txt
+    ///     d/
+    ///         e.txt
+    /// b.txt
+    /// ```
+    fn with_tree<F>(name: &str, test: F)
+    where
+        F: FnOnce(&Path),
+    {
+        let root = env::temp_dir().join(format!("mofu-walk-dir-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a/d")).unwrap();
+        fs::write(root.join("a/c.txt"), "c").unwrap();
+        fs::write(root.join("a/d/e.txt"), "e").unwrap();
+        fs::write(root.join("b.txt"), "b").unwrap();
+        test(&root);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Returns the paths of the entries yielded, relative to their roots,
+    /// with `/` as the separator.
+    fn relative<I>(iter: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = Result<DirEntry, WalkError>>,
+    {
+        iter.into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let components = entry.relative_path().components();
+                let components =
+                    components.map(|component| component.as_os_str().to_string_lossy());
+                components.collect::<Vec<_>>().join("/")
+            })
+            .collect()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn following_a_link_back_to_an_ancestor_is_a_loop_error() {
+        use std::os::unix::fs::symlink;
+
+        with_tree("loop", |root| {
+            symlink(root.join("a"), root.join("a/d/up")).unwrap();
+            let (entries, errors): (Vec<_>, Vec<_>) = WalkDir::new(root)
+                .unwrap()
+                .follow_symlinks(true)
+                .sort_by_file_name()
+                .partition(Result::is_ok);
+            assert_eq!(
+                relative(entries),
+                ["a", "a/c.txt", "a/d", "a/d/e.txt", "b.txt"],
+            );
+
+            let [Err(error)] = <[_; 1]>::try_from(errors).unwrap() else {
+                unreachable!();
+            };
+            assert_eq!(error.path(), root.join("a/d/up"));
+            assert_eq!(error.depth(), 3);
+            let error = error.into_io_error().downcast::<LoopError>().unwrap();
+            assert_eq!(error.ancestor(), root.join("a"));
+            assert_eq!(error.child(), root.join("a/d/up"));
+
+            // Links are not descended into unless followed.
+            let iter = WalkDir::new(root).unwrap().sort_by_file_name();
+            assert_eq!(
+                relative(iter),
+                ["a", "a/c.txt", "a/d", "a/d/e.txt", "a/d/up", "b.txt"],
+            );
+        });
+    }
+
+    #[test]
+    fn contents_first_yields_directories_after_their_contents() {
+        with_tree("contents-first", |root| {
+            let iter = WalkDir::new(root)
+                .unwrap()
+                .include_root(true)
+                .contents_first(true)
+                .sort_by_file_name();
+            assert_eq!(
+                relative(iter),
+                ["a/c.txt", "a/d/e.txt", "a/d", "a", "b.txt", ""],
+            );
+
+            // Skipping a directory still yields it afterwards.
+            let mut iter = WalkDir::new(root)
+                .unwrap()
+                .contents_first(true)
+                .sort_by_file_name();
+            let first = iter.next().unwrap().unwrap();
+            assert_eq!(first.path(), root.join("a/c.txt"));
+            iter.skip_current_dir();
+            assert_eq!(relative(iter), ["a", "b.txt"]);
+        });
+    }
+
+    #[test]
+    fn new_many_walks_each_root_once() {
+        with_tree("new-many", |root| {
+            fs::create_dir(root.join("f")).unwrap();
+            fs::write(root.join("f/g.txt"), "g").unwrap();
+            let roots = [
+                root.join("a/d"),
+                root.join("f"),
+                root.join("a"),
+                root.join("f"),
+            ];
+            let entries: Vec<_> = WalkDir::new_many(&roots)
+                .unwrap()
+                .sort_by_file_name()
+                .map(Result::unwrap)
+                .map(|entry| {
+                    let root = entry.root().strip_prefix(root).unwrap().to_path_buf();
+                    (root, entry.relative_path().to_path_buf(), entry.depth())
+                })
+                .collect();
+            let expected = [
+                ("f", "g.txt", 1),
+                ("a", "c.txt", 1),
+                ("a", "d", 1),
+                ("a", "d/e.txt", 2),
+            ];
+            let expected = expected.map(|(root, path, depth)| (root.into(), path.into(), depth));
+            assert_eq!(entries, expected);
+
+            let error = WalkDir::new_many::<_, &Path>([]).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+            assert!(WalkDir::new_many([root.join("missing")]).is_err());
+        });
+    }
+
+    #[test]
+    fn resuming_from_a_cursor_yields_exactly_the_rest() {
+        with_tree("cursor", |root| {
+            // Options are not recorded, so they are set again on resuming.
+            let sorted = |iter: WalkDir| iter.include_root(true).sort_by_file_name();
+            let all = relative(sorted(WalkDir::new(root).unwrap()));
+            assert_eq!(all, ["", "a", "a/c.txt", "a/d", "a/d/e.txt", "b.txt"]);
+
+            for taken in 0..=all.len() {
+                let mut iter = sorted(WalkDir::new(root).unwrap());
+                let head = relative(iter.by_ref().take(taken));
+                let rest = relative(sorted(WalkDir::resume(iter.cursor())));
+                assert_eq!([head, rest].concat(), all, "{taken}");
+            }
+
+            // Entries are yielded once whatever order they are read in.
+            let mut iter = WalkDir::new(root).unwrap().include_root(true);
+            let mut seen = relative(iter.by_ref().take(3));
+            seen.extend(relative(WalkDir::resume(iter.cursor()).include_root(true)));
+            seen.sort();
+            assert_eq!(seen, all);
+        });
+    }
+
+    #[test]
+    fn max_open_bounds_the_directories_open_at_once() {
+        with_tree("max-open", |root| {
+            fs::create_dir_all(root.join("a/d/x/y/z")).unwrap();
+            fs::write(root.join("a/d/x/y/z/w.txt"), "w").unwrap();
+
+            // Returns the entries yielded, sorted, and the most directories
+            // open at once.
+            let walk = |max_open: Option<NonZero<usize>>| {
+                let mut iter = WalkDir::new(root).unwrap().max_open(max_open);
+                let mut entries = Vec::new();
+                let mut most_open = 0;
+                while let Some(entry) = iter.next() {
+                    entries.push(entry);
+                    let open = iter
+                        .stack
+                        .iter()
+                        .filter(|item| matches!(item.entries, Entries::Unsorted(_)))
+                        .count();
+                    most_open = most_open.max(open);
+                }
+                let mut entries = relative(entries);
+                entries.sort();
+                (entries, most_open)
+            };
+
+            let (all, most_open) = walk(None);
+            assert_eq!(all.len(), 9);
+            assert_eq!(most_open, 6);
+            for max_open in 1..=3 {
+                let (entries, most_open) = walk(NonZero::new(max_open));
+                assert_eq!(entries, all);
+                assert!(most_open <= max_open, "{most_open} > {max_open}");
+            }
+        });
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn error_policies_yield_skip_or_collect_errors() {
+        use std::os::unix::fs::symlink;
+
+        with_tree("error-policy", |root| {
+            symlink(root.join("a"), root.join("a/d/up")).unwrap();
+            symlink(root, root.join("a/top")).unwrap();
+            let walk = |error_policy| {
+                WalkDir::new(root)
+                    .unwrap()
+                    .follow_symlinks(true)
+                    .sort_by_file_name()
+                    .error_policy(error_policy)
+            };
+            let entries = ["a", "a/c.txt", "a/d", "a/d/e.txt", "b.txt"];
+            let errors = [root.join("a/d/up"), root.join("a/top")];
+            let error_paths = |errors: &[WalkError]| -> Vec<PathBuf> {
+                errors
+                    .iter()
+                    .map(|error| error.path().to_path_buf())
+                    .collect()
+            };
+
+            let (ok, err): (Vec<_>, Vec<_>) = walk(ErrorPolicy::Yield).partition(Result::is_ok);
+            assert_eq!(relative(ok), entries);
+            let err: Vec<_> = err.into_iter().map(Result::unwrap_err).collect();
+            assert_eq!(error_paths(&err), errors);
+
+            let mut iter = walk(ErrorPolicy::Abort);
+            let results: Vec<_> = iter.by_ref().collect();
+            let (last, head) = results.split_last().unwrap();
+            assert_eq!(last.as_ref().unwrap_err().path(), errors[0]);
+            assert!(head.iter().all(Result::is_ok));
+            assert!(iter.next().is_none());
+
+            let mut iter = walk(ErrorPolicy::Skip);
+            assert_eq!(relative(iter.by_ref()), entries);
+            assert!(iter.errors().is_empty());
+
+            let mut iter = walk(ErrorPolicy::Collect);
+            assert_eq!(relative(iter.by_ref()), entries);
+            assert_eq!(error_paths(iter.errors()), errors);
+            assert_eq!(error_paths(&iter.take_errors()), errors);
+            assert!(iter.errors().is_empty());
+        });
+    }
 }
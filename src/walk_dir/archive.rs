@@ -0,0 +1,274 @@
+use super::DirEntry;
+use crate::rename::{TarFileSystem, ZipFileSystem};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::Metadata;
+use std::io::{self, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// The entries of an archive, as walked into by
+/// [`WalkDir::walk_archives`].
+///
+/// [`WalkDir::walk_archives`]: super::WalkDir::walk_archives
+pub(super) struct Archive {
+    /// The entry of the archive itself.
+    path: PathBuf,
+    metadata: Metadata,
+    root: Arc<Path>,
+    depth: usize,
+    /// The entries directly within each directory of the archive, ordered
+    /// by name, where the top-level ones are within the empty path.
+    children: HashMap<PathBuf, Vec<(PathBuf, Node)>>,
+}
+
+/// What is known of an entry within an archive without extracting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Node {
+    dir: bool,
+    len: u64,
+}
+
+/// An entry within an archive, which is yielded as a virtual [`DirEntry`].
+pub(super) struct Member {
+    archive: Arc<Archive>,
+    /// The path within the archive.
+    name: PathBuf,
+    node: Node,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Tar,
+}
+
+impl Archive {
+    /// Returns `true` if `path` is named as a supported archive.
+    pub(super) fn is_archive(path: &Path) -> bool {
+        Format::of(path).is_some()
+    }
+
+    /// Lists the entries of the archive yielded as `entry`.
+    pub(super) fn open(entry: &DirEntry) -> io::Result<Arc<Self>> {
+        let format = Format::of(&entry.path).ok_or(ErrorKind::InvalidInput)?;
+        let names = match format {
+            Format::Zip => ZipFileSystem::open(&entry.path)?.members(),
+            // Gzip-compressed archives are detected by their contents.
+            Format::Tar => TarFileSystem::open(&entry.path)?.members(),
+        };
+
+        // Directories may be left implied by the names of their entries.
+        let mut nodes = BTreeMap::new();
+        for (name, dir, len) in names {
+            let name: PathBuf = Path::new(&name)
+                .components()
+                .filter(|component| matches!(component, Component::Normal(_)))
+                .collect();
+            if name.as_os_str().is_empty() {
+                continue;
+            }
+            for ancestor in name.ancestors().skip(1) {
+                if ancestor.as_os_str().is_empty() {
+                    break;
+                }
+                let len = 0;
+                let dir = true;
+                nodes.insert(ancestor.to_path_buf(), Node { dir, len });
+            }
+            nodes.entry(name).or_insert(Node { dir, len });
+        }
+        let mut children: HashMap<_, Vec<_>> = HashMap::new();
+        for (name, node) in nodes {
+            let parent = name.parent().unwrap_or(Path::new("")).to_path_buf();
+            children.entry(parent).or_default().push((name, node));
+        }
+
+        Ok(Arc::new(Self {
+            path: entry.path.clone(),
+            metadata: entry.metadata.clone(),
+            root: Arc::clone(&entry.root),
+            depth: entry.depth,
+            children,
+        }))
+    }
+
+    /// Returns the entries directly within the directory `name` of the
+    /// archive, ordered by name.
+    pub(super) fn entries(self: &Arc<Self>, name: &Path) -> Vec<DirEntry> {
+        let Some(children) = self.children.get(name) else {
+            return Vec::new();
+        };
+        children
+            .iter()
+            .map(|(name, node)| DirEntry {
+                path: self.path.join(name),
+                metadata: self.metadata.clone(),
+                root: Arc::clone(&self.root),
+                depth: self.depth + name.components().count(),
+                member: Some(Member {
+                    archive: Arc::clone(self),
+                    name: name.clone(),
+                    node: *node,
+                }),
+            })
+            .collect()
+    }
+}
+
+impl Member {
+    /// Returns the virtual directory holding `entry`, unless it is held by
+    /// the archive itself.
+    pub(super) fn parent(entry: &DirEntry) -> Option<DirEntry> {
+        let member = entry.member.as_ref()?;
+        let name = member
+            .name
+            .parent()
+            .filter(|name| !name.as_os_str().is_empty())?;
+        Some(DirEntry {
+            path: entry.path.parent()?.to_path_buf(),
+            metadata: entry.metadata.clone(),
+            root: Arc::clone(&entry.root),
+            depth: entry.depth - 1,
+            member: Some(Member {
+                archive: Arc::clone(&member.archive),
+                name: name.to_path_buf(),
+                node: Node { dir: true, len: 0 },
+            }),
+        })
+    }
+
+    /// Returns the path of the archive holding the entry.
+    pub(super) fn archive(&self) -> &Path {
+        &self.archive.path
+    }
+
+    /// Returns the entries directly within the entry, if it is a directory.
+    pub(super) fn entries(&self) -> Vec<DirEntry> {
+        self.archive.entries(&self.name)
+    }
+
+    pub(super) fn is_dir(&self) -> bool {
+        self.node.dir
+    }
+
+    pub(super) fn len(&self) -> u64 {
+        self.node.len
+    }
+}
+
+impl fmt::Debug for Member {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Member")
+            .field("archive", &self.archive.path)
+            .field("name", &self.name)
+            .field("dir", &self.node.dir)
+            .field("len", &self.node.len)
+            .finish()
+    }
+}
+
+impl Format {
+    fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if [".tar", ".tar.gz", ".tgz"]
+            .iter()
+            .any(|extension| name.ends_with(extension))
+        {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::WalkDir;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::{env, fs, process};
+
+    /// Runs `test` on a fresh directory holding a small tree under `src`,
+    /// removing it after.
+    fn with_tree<F>(name: &str, test: F)
+    where
+        F: FnOnce(&Path),
+    {
+        let root = env::temp_dir().join(format!("mofu-archive-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/docs")).unwrap();
+        fs::write(root.join("src/docs/a.txt"), "alpha").unwrap();
+        fs::write(root.join("src/b.txt"), "").unwrap();
+        fs::create_dir(root.join("out")).unwrap();
+        test(&root);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    fn tar(root: &Path, args: &[&str]) {
+        let status = Command::new("tar")
+            .current_dir(root.join("src"))
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    /// Walks `root` into archives, returning the virtual entries with their
+    /// sizes and whether they are directories.
+    fn members(root: &Path) -> Vec<(PathBuf, bool, u64)> {
+        WalkDir::new(root)
+            .unwrap()
+            .walk_archives(true)
+            .sort_by_file_name()
+            .map(Result::unwrap)
+            .filter(|entry| entry.is_virtual())
+            .map(|entry| (entry.path().to_path_buf(), entry.is_dir(), entry.size()))
+            .collect()
+    }
+
+    #[test]
+    fn tar_archives_are_walked_into() {
+        with_tree("tar", |root| {
+            tar(root, &["-cf", "../out/x.tar", "docs", "b.txt"]);
+            tar(root, &["-czf", "../out/y.tgz", "."]);
+            let out = root.join("out");
+            let mut expected = Vec::new();
+            for archive in ["x.tar", "y.tgz"] {
+                let archive = out.join(archive);
+                expected.extend([
+                    (archive.join("b.txt"), false, 0),
+                    (archive.join("docs"), true, 0),
+                    (archive.join("docs/a.txt"), false, 5),
+                ]);
+            }
+            assert_eq!(members(&out), expected);
+        });
+    }
+
+    #[test]
+    fn unreadable_archives_are_yielded_as_errors() {
+        with_tree("invalid", |root| {
+            let out = root.join("out");
+            fs::write(out.join("empty.zip"), "").unwrap();
+            tar(root, &["-cf", "../out/truncated.tar", "docs"]);
+            let truncated = fs::read(out.join("truncated.tar")).unwrap();
+            fs::write(out.join("truncated.tar"), &truncated[..700]).unwrap();
+
+            let results: Vec<_> = WalkDir::new(&out)
+                .unwrap()
+                .walk_archives(true)
+                .sort_by_file_name()
+                .collect();
+            assert_eq!(results.len(), 4);
+            for (index, name) in ["empty.zip", "truncated.tar"].into_iter().enumerate() {
+                let entry = results[index * 2].as_ref().unwrap();
+                assert_eq!(entry.path(), out.join(name));
+                let error = results[index * 2 + 1].as_ref().unwrap_err();
+                assert_eq!(error.path(), out.join(name));
+            }
+        });
+    }
+}
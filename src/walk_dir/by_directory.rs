@@ -0,0 +1,69 @@
+use super::{DirEntry, WalkDir, WalkError};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+/// An iterator over the entries of a [`WalkDir`] batched by the directory
+/// holding them, as returned by [`WalkDir::by_directory`].
+///
+/// Each directory is yielded along with its entries once the traversal
+/// leaves it, so a directory comes after those within it that are walked
+/// before the rest of its entries. Directories with no entries yielded are
+/// left out. Errors are yielded as they are encountered.
+#[derive(Debug)]
+pub struct ByDirectory {
+    walk_dir: WalkDir,
+    /// The directories being read, from the shallowest, with the entries
+    /// yielded so far.
+    open: Vec<(PathBuf, Vec<DirEntry>)>,
+    /// The directories left, ready to be yielded.
+    done: VecDeque<(PathBuf, Vec<DirEntry>)>,
+}
+
+impl ByDirectory {
+    pub(super) fn new(walk_dir: WalkDir) -> Self {
+        Self {
+            walk_dir,
+            open: Vec::new(),
+            done: VecDeque::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying traversal.
+    #[inline]
+    pub fn walk_dir(&self) -> &WalkDir {
+        &self.walk_dir
+    }
+
+    /// Adds `entry` to the batch of its directory, closing those it is not
+    /// within.
+    fn push(&mut self, entry: DirEntry) {
+        let parent = entry.path.parent().unwrap_or(Path::new("")).to_path_buf();
+        while let Some((path, _)) = self.open.last()
+            && *path != parent
+            && !parent.starts_with(path)
+        {
+            self.done.extend(self.open.pop());
+        }
+        match self.open.last_mut() {
+            Some((path, entries)) if *path == parent => entries.push(entry),
+            _ => self.open.push((parent, vec![entry])),
+        }
+    }
+}
+
+impl Iterator for ByDirectory {
+    type Item = Result<(PathBuf, Vec<DirEntry>), WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(batch) = self.done.pop_front() {
+                return Some(Ok(batch));
+            }
+            match self.walk_dir.next() {
+                None => return self.open.pop().map(Ok),
+                Some(Err(error)) => return Some(Err(error)),
+                Some(Ok(entry)) => self.push(entry),
+            }
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use serde::de::{Deserialize, Deserializer, Error as DeError, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A snapshot of the position of a [`WalkDir`], which can be persisted and
+/// resumed from with [`WalkDir::resume`], such as after a crash or a
+/// reboot, in the same way as a rename journal.
+///
+/// A cursor records the paths of the entries left in each directory being
+/// read, rather than the entries themselves, so it stays valid as the tree
+/// changes: entries created afterwards are not yielded, and those removed
+/// afterwards are yielded as errors once reached.
+///
+/// # Serialization & Deserialization
+///
+/// This type implements [`Serialize`] and [`Deserialize`]. The options of
+/// the traversal are not serialized, and should be set again on resuming.
+///
+/// [`WalkDir`]: super::WalkDir
+/// [`WalkDir::resume`]: super::WalkDir::resume
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub(super) root: PathBuf,
+    /// The roots left to traverse after the current one.
+    pub(super) roots: Vec<PathBuf>,
+    /// Whether the current root was yielded or held back.
+    pub(super) started: bool,
+    /// The directories being read, from the root down.
+    pub(super) levels: Vec<Level>,
+}
+
+/// A directory being read, whose entries are at the depth after its index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Level {
+    pub(super) path: PathBuf,
+    /// The paths of the entries left.
+    pub(super) remaining: Vec<PathBuf>,
+    /// Whether the directory itself is held back until its contents are
+    /// yielded.
+    pub(super) pending: bool,
+}
+
+impl Cursor {
+    /// Returns the root being traversed.
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns `true` if there is nothing left to traverse.
+    pub fn is_finished(&self) -> bool {
+        self.levels.is_empty() && self.roots.is_empty()
+    }
+}
+
+const FIELDS: &[&str] = &["root", "roots", "started", "levels"];
+
+impl Serialize for Cursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut cursor = serializer.serialize_struct("Cursor", 4)?;
+        cursor.serialize_field("root", &self.root)?;
+        cursor.serialize_field("roots", &self.roots)?;
+        cursor.serialize_field("started", &self.started)?;
+        cursor.serialize_field("levels", &self.levels)?;
+        cursor.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Cursor", FIELDS, CursorVisitor)
+    }
+}
+
+#[derive(Debug)]
+struct CursorVisitor;
+
+impl<'de> Visitor<'de> for CursorVisitor {
+    type Value = Cursor;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("struct Cursor")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut root: Option<PathBuf> = None;
+        let mut roots: Option<Vec<PathBuf>> = None;
+        let mut started: Option<bool> = None;
+        let mut levels: Option<Vec<Level>> = None;
+
+        while let Some(key) = map.next_key()? {
+            match key {
+                Field::Root => {
+                    if root.is_some() {
+                        return Err(DeError::duplicate_field("root"));
+                    }
+                    root = Some(map.next_value()?);
+                }
+                Field::Roots => {
+                    if roots.is_some() {
+                        return Err(DeError::duplicate_field("roots"));
+                    }
+                    roots = Some(map.next_value()?);
+                }
+                Field::Started => {
+                    if started.is_some() {
+                        return Err(DeError::duplicate_field("started"));
+                    }
+                    started = Some(map.next_value()?);
+                }
+                Field::Levels => {
+                    if levels.is_some() {
+                        return Err(DeError::duplicate_field("levels"));
+                    }
+                    levels = Some(map.next_value()?);
+                }
+            }
+        }
+
+        let root = root.ok_or_else(|| DeError::missing_field("root"))?;
+        let roots = roots.ok_or_else(|| DeError::missing_field("roots"))?;
+        let started = started.ok_or_else(|| DeError::missing_field("started"))?;
+        let levels = levels.ok_or_else(|| DeError::missing_field("levels"))?;
+
+        Ok(Cursor {
+            root,
+            roots,
+            started,
+            levels,
+        })
+    }
+}
+
+#[derive(Debug)]
+enum Field {
+    Root,
+    Roots,
+    Started,
+    Levels,
+}
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(FieldVisitor)
+    }
+}
+
+#[derive(Debug)]
+struct FieldVisitor;
+
+impl Visitor<'_> for FieldVisitor {
+    type Value = Field;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("`root`, `roots`, `started` or `levels`")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: DeError,
+    {
+        match value {
+            "root" => Ok(Field::Root),
+            "roots" => Ok(Field::Roots),
+            "started" => Ok(Field::Started),
+            "levels" => Ok(Field::Levels),
+            _ => Err(DeError::unknown_field(value, FIELDS)),
+        }
+    }
+}
+
+const LEVEL_FIELDS: &[&str] = &["path", "remaining", "pending"];
+
+impl Serialize for Level {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut level = serializer.serialize_struct("Level", 3)?;
+        level.serialize_field("path", &self.path)?;
+        level.serialize_field("remaining", &self.remaining)?;
+        level.serialize_field("pending", &self.pending)?;
+        level.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Level {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Level", LEVEL_FIELDS, LevelVisitor)
+    }
+}
+
+#[derive(Debug)]
+struct LevelVisitor;
+
+impl<'de> Visitor<'de> for LevelVisitor {
+    type Value = Level;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("struct Level")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut path: Option<PathBuf> = None;
+        let mut remaining: Option<Vec<PathBuf>> = None;
+        let mut pending: Option<bool> = None;
+
+        while let Some(key) = map.next_key()? {
+            match key {
+                LevelField::Path => {
+                    if path.is_some() {
+                        return Err(DeError::duplicate_field("path"));
+                    }
+                    path = Some(map.next_value()?);
+                }
+                LevelField::Remaining => {
+                    if remaining.is_some() {
+                        return Err(DeError::duplicate_field("remaining"));
+                    }
+                    remaining = Some(map.next_value()?);
+                }
+                LevelField::Pending => {
+                    if pending.is_some() {
+                        return Err(DeError::duplicate_field("pending"));
+                    }
+                    pending = Some(map.next_value()?);
+                }
+            }
+        }
+
+        let path = path.ok_or_else(|| DeError::missing_field("path"))?;
+        let remaining = remaining.ok_or_else(|| DeError::missing_field("remaining"))?;
+        let pending = pending.ok_or_else(|| DeError::missing_field("pending"))?;
+
+        Ok(Level {
+            path,
+            remaining,
+            pending,
+        })
+    }
+}
+
+#[derive(Debug)]
+enum LevelField {
+    Path,
+    Remaining,
+    Pending,
+}
+
+impl<'de> Deserialize<'de> for LevelField {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(LevelFieldVisitor)
+    }
+}
+
+#[derive(Debug)]
+struct LevelFieldVisitor;
+
+impl Visitor<'_> for LevelFieldVisitor {
+    type Value = LevelField;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("`path`, `remaining` or `pending`")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: DeError,
+    {
+        match value {
+            "path" => Ok(LevelField::Path),
+            "remaining" => Ok(LevelField::Remaining),
+            "pending" => Ok(LevelField::Pending),
+            _ => Err(DeError::unknown_field(value, LEVEL_FIELDS)),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::rules::glob_matches;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The names of the ignore files read from each directory, by descending
+/// precedence.
+const NAMES: [&str; 2] = [".ignore", ".gitignore"];
+
+/// The patterns of an ignore file, in the format of `.gitignore`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Ignore {
+    /// The directory patterns are relative to.
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    pattern: String,
+    negated: bool,
+    directory: bool,
+    anchored: bool,
+}
+
+impl Ignore {
+    /// Parses the patterns of `text`, relative to `base`.
+    pub(super) fn parse(base: &Path, text: &str) -> Self {
+        let rules = text.lines().filter_map(Rule::parse).collect();
+        let base = base.to_path_buf();
+        Self { base, rules }
+    }
+
+    /// Reads the ignore files of the directory `base`, by descending
+    /// precedence, skipping those that cannot be read.
+    pub(super) fn read_dir(base: &Path) -> Vec<Self> {
+        NAMES
+            .iter()
+            .filter_map(|name| fs::read_to_string(base.join(name)).ok())
+            .map(|text| Self::parse(base, &text))
+            .collect()
+    }
+
+    /// Reads the global excludes file of Git, relative to `base`.
+    pub(super) fn read_global(base: &Path) -> Option<Self> {
+        let text = fs::read_to_string(global_path()?).ok()?;
+        Some(Self::parse(base, &text))
+    }
+
+    /// Returns whether the last pattern matching `path` ignores it, or
+    /// [`None`] if no pattern matches.
+    pub(super) fn matches(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let name = relative.rsplit('/').next().unwrap_or_default();
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.directory)
+                    && if rule.anchored {
+                        glob_matches(&rule.pattern, &relative)
+                    } else {
+                        glob_matches(&rule.pattern, name)
+                    }
+            })
+            .map(|rule| !rule.negated)
+    }
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        if line.starts_with('#') {
+            return None;
+        }
+        // Trailing spaces are trimmed unless escaped.
+        let mut pattern = line.trim_end_matches('\r');
+        while pattern.ends_with(' ') && !pattern.ends_with("\\ ") {
+            pattern = &pattern[..pattern.len() - 1];
+        }
+        let negated = pattern.starts_with('!');
+        if negated {
+            pattern = &pattern[1..];
+        }
+        let directory = pattern.ends_with('/');
+        if directory {
+            pattern = &pattern[..pattern.len() - 1];
+        }
+        let anchored = pattern.contains('/');
+        if anchored {
+            pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        }
+        if pattern.is_empty() {
+            return None;
+        }
+        let pattern = pattern.to_owned();
+        Some(Self {
+            pattern,
+            negated,
+            directory,
+            anchored,
+        })
+    }
+}
+
+/// Returns the path of the global excludes file, which is set by
+/// `core.excludesFile` in the global configuration of Git, or defaults to
+/// `git/ignore` within the configuration directory.
+fn global_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from);
+    if let Some(home) = &home
+        && let Ok(config) = fs::read_to_string(home.join(".gitconfig"))
+        && let Some(path) = excludes_file(&config)
+    {
+        return match path.strip_prefix("~/") {
+            Some(path) => Some(home.join(path)),
+            None => Some(PathBuf::from(path)),
+        };
+    }
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| home.map(|home| home.join(".config")))?;
+    Some(config.join("git").join("ignore"))
+}
+
+/// Returns the value of `core.excludesFile` in a configuration of Git.
+fn excludes_file(config: &str) -> Option<&str> {
+    let mut core = false;
+    let mut path = None;
+    for line in config.lines().map(str::trim) {
+        if let Some(section) = line.strip_prefix('[') {
+            let section = section.split(']').next().unwrap_or_default();
+            core = section.trim().eq_ignore_ascii_case("core");
+        } else if core
+            && let Some((key, value)) = line.split_once('=')
+            && key.trim().eq_ignore_ascii_case("excludesfile")
+        {
+            // Later values override earlier ones.
+            path = Some(value.trim().trim_matches('"'));
+        }
+    }
+    path
+}
@@ -0,0 +1,504 @@
+use super::WalkError;
+use crate::rename::{MemoryFileSystem, OsFileSystem};
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::num::NonZero;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+/// A trait abstracting the directory listing needed by [`ListWalk`].
+///
+/// Implementing this trait allows [`ListWalk`] to run against alternative
+/// backends, such as [`MemoryFileSystem`], so code built on it can be
+/// exercised without fixtures on disk. [`WalkDir`] does not go through
+/// this trait, and always reads the file system of the operating system.
+///
+/// [`WalkDir`]: super::WalkDir
+pub trait ListDir {
+    /// Returns the paths of the direct children of a directory, following
+    /// symlinks, in any order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path does not exist or is not a directory.
+    fn read_dir(&mut self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Returns the kind of the entity at `path`, without following
+    /// symlinks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path does not exist.
+    fn file_kind(&mut self, path: &Path) -> io::Result<FileKind>;
+
+    /// Returns the size of the file at `path` in bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path does not exist or is not a file.
+    fn len(&mut self, path: &Path) -> io::Result<u64>;
+}
+
+impl<L> ListDir for &mut L
+where
+    L: ListDir + ?Sized,
+{
+    #[inline]
+    fn read_dir(&mut self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        (**self).read_dir(path)
+    }
+
+    #[inline]
+    fn file_kind(&mut self, path: &Path) -> io::Result<FileKind> {
+        (**self).file_kind(path)
+    }
+
+    #[inline]
+    fn len(&mut self, path: &Path) -> io::Result<u64> {
+        (**self).len(path)
+    }
+}
+
+impl<L> ListDir for Box<L>
+where
+    L: ListDir + ?Sized,
+{
+    #[inline]
+    fn read_dir(&mut self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        (**self).read_dir(path)
+    }
+
+    #[inline]
+    fn file_kind(&mut self, path: &Path) -> io::Result<FileKind> {
+        (**self).file_kind(path)
+    }
+
+    #[inline]
+    fn len(&mut self, path: &Path) -> io::Result<u64> {
+        (**self).len(path)
+    }
+}
+
+impl ListDir for OsFileSystem {
+    fn read_dir(&mut self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    fn file_kind(&mut self, path: &Path) -> io::Result<FileKind> {
+        let file_type = path.symlink_metadata()?.file_type();
+        Ok(if file_type.is_symlink() {
+            FileKind::Symlink
+        } else if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        })
+    }
+
+    #[inline]
+    fn len(&mut self, path: &Path) -> io::Result<u64> {
+        fs::metadata(path).map(|metadata| metadata.len())
+    }
+}
+
+impl ListDir for MemoryFileSystem {
+    #[inline]
+    fn read_dir(&mut self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        MemoryFileSystem::read_dir(self, path)
+    }
+
+    fn file_kind(&mut self, path: &Path) -> io::Result<FileKind> {
+        if self.is_symlink(path) {
+            Ok(FileKind::Symlink)
+        } else if self.is_dir(path) {
+            Ok(FileKind::Dir)
+        } else if self.is_file(path) {
+            Ok(FileKind::File)
+        } else {
+            Err(io::Error::from(ErrorKind::NotFound))
+        }
+    }
+
+    #[inline]
+    fn len(&mut self, path: &Path) -> io::Result<u64> {
+        self.read(path).map(|contents| contents.len() as u64)
+    }
+}
+
+/// The kind of an entity listed by a [`ListDir`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    /// A regular file.
+    File,
+
+    /// A directory.
+    Dir,
+
+    /// A symlink, which is never followed.
+    Symlink,
+
+    /// Anything else, such as sockets and devices.
+    Other,
+}
+
+/// An iterator recursively traversing a directory through a [`ListDir`]
+/// backend, in the order of file names.
+///
+/// This is a minimal walker, not a backend-agnostic [`WalkDir`]: it yields
+/// entries without metadata of the operating system, does not follow
+/// symlinks, and only supports depth limits and entry filters. Sorting
+/// options, ignore files, hard link deduplication, archives and the other
+/// options of [`WalkDir`] are not available here. The root is not read
+/// until the first call to [`Iterator::next`], and errors reading it are
+/// yielded as the first item.
+///
+/// [`WalkDir`]: super::WalkDir
+///
+/// # Examples
+///
+/// ```
+/// use mofu::rename::MemoryFileSystem;
+/// use mofu::walk_dir::ListWalk;
+/// use std::path::PathBuf;
+///
+/// let mut fs = MemoryFileSystem::new();
+/// fs.write("/photos/b.jpg", "b").unwrap();
+/// fs.write("/photos/2024/a.jpg", "a").unwrap();
+///
+/// let paths: Vec<PathBuf> = ListWalk::new(fs, "/photos")
+///     .filter_map(Result::ok)
+///     .map(|entry| entry.path().to_path_buf())
+///     .collect();
+/// assert_eq!(
+///     paths,
+///     [
+///         PathBuf::from("/photos/2024"),
+///         PathBuf::from("/photos/2024/a.jpg"),
+///         PathBuf::from("/photos/b.jpg"),
+///     ],
+/// );
+/// ```
+pub struct ListWalk<L> {
+    lister: L,
+    /// The root, until it is read.
+    root: Option<PathBuf>,
+    /// The entries left in each directory being read, with their depth.
+    stack: Vec<(NonZero<usize>, vec::IntoIter<PathBuf>)>,
+    min_depth: usize,
+    max_depth: Option<NonZero<usize>>,
+    predicates: Vec<Box<Predicate>>,
+}
+
+/// A predicate deciding whether to yield an entry and descend into it.
+type Predicate = dyn FnMut(&ListEntry) -> bool + Send + Sync;
+
+/// An entry yielded by [`ListWalk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListEntry {
+    path: PathBuf,
+    kind: FileKind,
+    len: u64,
+    depth: usize,
+}
+
+impl<L> ListWalk<L>
+where
+    L: ListDir,
+{
+    /// Creates a new [`ListWalk`] traversing `root` through `lister`.
+    pub fn new<P>(lister: L, root: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            lister,
+            root: Some(root.as_ref().to_path_buf()),
+            stack: Vec::new(),
+            min_depth: 0,
+            max_depth: None,
+            predicates: Vec::new(),
+        }
+    }
+
+    /// Sets the minimum depth of yielded entries, as described in
+    /// [`WalkDir::min_depth`].
+    ///
+    /// [`WalkDir::min_depth`]: super::WalkDir::min_depth
+    pub fn min_depth(mut self, min_depth: usize) -> Self {
+        self.min_depth = min_depth;
+        self
+    }
+
+    /// Sets the maximum depth for traversal.
+    pub fn max_depth(mut self, max_depth: Option<NonZero<usize>>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Adds a predicate deciding whether to yield each entry, as described
+    /// in [`WalkDir::filter_entry`].
+    ///
+    /// [`WalkDir::filter_entry`]: super::WalkDir::filter_entry
+    pub fn filter_entry<F>(mut self, predicate: F) -> Self
+    where
+        F: FnMut(&ListEntry) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    /// Returns a reference to the backend.
+    #[inline]
+    pub fn lister(&self) -> &L {
+        &self.lister
+    }
+
+    /// Consumes the iterator, returning the backend.
+    #[inline]
+    pub fn into_lister(self) -> L {
+        self.lister
+    }
+
+    /// Reads the entries of the directory at `path`, sorted by file name.
+    fn read_dir(&mut self, path: &Path) -> io::Result<vec::IntoIter<PathBuf>> {
+        let mut paths = self.lister.read_dir(path)?;
+        paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+        Ok(paths.into_iter())
+    }
+}
+
+impl<L> Iterator for ListWalk<L>
+where
+    L: ListDir,
+{
+    type Item = Result<ListEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(path) = self.root.take() {
+            match self.read_dir(&path) {
+                Err(error) => {
+                    let depth = 0;
+                    return Some(Err(WalkError { path, depth, error }));
+                }
+                Ok(iter) => self.stack.push((NonZero::<usize>::MIN, iter)),
+            }
+        }
+        loop {
+            let (depth, iter) = self.stack.last_mut()?;
+            let depth = *depth;
+            let Some(path) = iter.next() else {
+                self.stack.pop();
+                continue;
+            };
+            let kind = match self.lister.file_kind(&path) {
+                Err(error) => {
+                    let depth = depth.get();
+                    return Some(Err(WalkError { path, depth, error }));
+                }
+                Ok(kind) => kind,
+            };
+            let len = match kind {
+                FileKind::File => match self.lister.len(&path) {
+                    Err(error) => {
+                        let depth = depth.get();
+                        return Some(Err(WalkError { path, depth, error }));
+                    }
+                    Ok(len) => len,
+                },
+                _ => 0,
+            };
+            let entry = ListEntry {
+                path,
+                kind,
+                len,
+                depth: depth.get(),
+            };
+            if !self
+                .predicates
+                .iter_mut()
+                .all(|predicate| predicate(&entry))
+            {
+                continue;
+            }
+
+            if entry.kind == FileKind::Dir
+                && self.max_depth.is_none_or(|max_depth| depth < max_depth)
+            {
+                match self.read_dir(&entry.path) {
+                    Err(error) => {
+                        let path = entry.path;
+                        let depth = entry.depth;
+                        return Some(Err(WalkError { path, depth, error }));
+                    }
+                    Ok(iter) => self.stack.push((depth.saturating_add(1), iter)),
+                }
+            }
+
+            if entry.depth < self.min_depth {
+                continue;
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
+
+impl<L> fmt::Debug for ListWalk<L>
+where
+    L: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ListWalk")
+            .field("lister", &self.lister)
+            .field("root", &self.root)
+            .field("stack", &self.stack)
+            .field("min_depth", &self.min_depth)
+            .field("max_depth", &self.max_depth)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ListEntry {
+    /// Returns the path.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the file name, or the path itself if it has none.
+    pub fn file_name(&self) -> &OsStr {
+        self.path
+            .file_name()
+            .unwrap_or_else(|| self.path.as_os_str())
+    }
+
+    /// Returns the kind, without following symlinks.
+    #[inline]
+    pub fn kind(&self) -> FileKind {
+        self.kind
+    }
+
+    /// Returns the size in bytes, which is 0 for anything but files.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the size is 0.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the entry is a directory.
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::Dir
+    }
+
+    /// Returns `true` if the entry is a file.
+    #[inline]
+    pub fn is_file(&self) -> bool {
+        self.kind == FileKind::File
+    }
+
+    /// Returns `true` if the entry is a symlink.
+    #[inline]
+    pub fn is_symlink(&self) -> bool {
+        self.kind == FileKind::Symlink
+    }
+
+    /// Returns the depth, where top-level entries of the traversal are at
+    /// depth 1.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+impl AsRef<Path> for ListEntry {
+    #[inline]
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> MemoryFileSystem {
+        let mut fs = MemoryFileSystem::new();
+        fs.write("/root/b.txt", "bb").unwrap();
+        fs.write("/root/a/c.txt", "c").unwrap();
+        fs.write("/root/a/d/e.txt", "").unwrap();
+        fs.symlink("/root/b.txt", "/root/link").unwrap();
+        fs
+    }
+
+    fn paths(walk: ListWalk<MemoryFileSystem>) -> Vec<PathBuf> {
+        walk.map(|entry| entry.unwrap().path().to_path_buf())
+            .collect()
+    }
+
+    #[test]
+    fn walk_is_sorted_by_file_name() {
+        let paths = paths(ListWalk::new(fixture(), "/root"));
+        assert_eq!(
+            paths,
+            [
+                "/root/a",
+                "/root/a/c.txt",
+                "/root/a/d",
+                "/root/a/d/e.txt",
+                "/root/b.txt",
+                "/root/link",
+            ]
+            .map(PathBuf::from),
+        );
+    }
+
+    #[test]
+    fn entries_report_kind_len_and_depth() {
+        let entries: Vec<ListEntry> = ListWalk::new(fixture(), "/root")
+            .map(Result::unwrap)
+            .collect();
+        let b = entries.iter().find(|entry| entry.file_name() == "b.txt");
+        let b = b.unwrap();
+        assert!(b.is_file());
+        assert_eq!((b.len(), b.depth()), (2, 1));
+        let e = entries.iter().find(|entry| entry.file_name() == "e.txt");
+        assert!(e.unwrap().is_empty());
+        assert_eq!(e.unwrap().depth(), 3);
+        let link = entries.iter().find(|entry| entry.file_name() == "link");
+        assert!(link.unwrap().is_symlink());
+    }
+
+    #[test]
+    fn depth_limits_and_filters_prune_the_walk() {
+        let walk = ListWalk::new(fixture(), "/root")
+            .min_depth(2)
+            .max_depth(NonZero::new(2));
+        assert_eq!(
+            paths(walk),
+            ["/root/a/c.txt", "/root/a/d"].map(PathBuf::from)
+        );
+
+        let walk = ListWalk::new(fixture(), "/root").filter_entry(|entry| entry.file_name() != "d");
+        assert!(!paths(walk).contains(&PathBuf::from("/root/a/d/e.txt")));
+    }
+
+    #[test]
+    fn missing_root_is_yielded_as_an_error() {
+        let mut walk = ListWalk::new(MemoryFileSystem::new(), "/missing");
+        let error = walk.next().unwrap().unwrap_err();
+        assert_eq!(error.path(), Path::new("/missing"));
+        assert_eq!(error.depth(), 0);
+        assert!(walk.next().is_none());
+    }
+}
@@ -0,0 +1,90 @@
+use super::DirEntry;
+
+/// Statistics of the entries yielded and the errors encountered by a
+/// [`WalkDir`], as returned by [`WalkDir::stats`].
+///
+/// [`WalkDir`]: super::WalkDir
+/// [`WalkDir::stats`]: super::WalkDir::stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Stats {
+    dirs: u64,
+    files: u64,
+    symlinks: u64,
+    others: u64,
+    errors: u64,
+    bytes: u64,
+}
+
+impl Stats {
+    /// Returns the number of directories yielded.
+    #[inline]
+    pub fn dirs(&self) -> u64 {
+        self.dirs
+    }
+
+    /// Returns the number of regular files yielded.
+    #[inline]
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+
+    /// Returns the number of symbolic links yielded, which excludes those
+    /// followed.
+    #[inline]
+    pub fn symlinks(&self) -> u64 {
+        self.symlinks
+    }
+
+    /// Returns the number of other entries yielded, such as sockets and
+    /// devices.
+    #[inline]
+    pub fn others(&self) -> u64 {
+        self.others
+    }
+
+    /// Returns the number of errors encountered, including those skipped
+    /// or collected as told by [`ErrorPolicy`].
+    ///
+    /// [`ErrorPolicy`]: super::ErrorPolicy
+    #[inline]
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Returns the total size in bytes of the regular files yielded.
+    #[inline]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Returns the number of entries yielded and errors encountered.
+    pub fn total(&self) -> u64 {
+        self.dirs + self.files + self.symlinks + self.others + self.errors
+    }
+
+    pub(super) fn record_entry(&mut self, entry: &DirEntry) {
+        if entry.is_dir() {
+            self.dirs += 1;
+        } else if entry.is_file() {
+            self.files += 1;
+            self.bytes += entry.size();
+        } else if entry.is_symlink() {
+            self.symlinks += 1;
+        } else {
+            self.others += 1;
+        }
+    }
+
+    pub(super) fn record_error(&mut self) {
+        self.errors += 1;
+    }
+
+    pub(super) fn merge(&mut self, other: &Stats) {
+        self.dirs += other.dirs;
+        self.files += other.files;
+        self.symlinks += other.symlinks;
+        self.others += other.others;
+        self.errors += other.errors;
+        self.bytes += other.bytes;
+    }
+}
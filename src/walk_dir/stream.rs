@@ -0,0 +1,165 @@
+use super::{DirEntry, WalkDir, WalkError};
+use std::fmt;
+use std::future;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+/// The number of entries read ahead of the consumer.
+const CAPACITY: usize = 256;
+
+/// An asynchronous stream over the entries of a [`WalkDir`], as returned by
+/// [`WalkDir::into_stream`].
+///
+/// The traversal runs on a thread of its own, which reads ahead of the
+/// consumer by a bounded number of entries, and stops once the stream is
+/// dropped. Polling never blocks, and wakes the task once entries are
+/// ready, so this works with any executor, without depending on one.
+///
+/// This does not go through `tokio::fs`, since no file system offers
+/// asynchronous directory listing to build on: `tokio::fs::read_dir` runs
+/// each read on the blocking pool of the runtime anyway, handing entries
+/// over one at a time. A thread for each stream does the same work with
+/// fewer handovers, keeps the options of [`WalkDir`] available as they
+/// are, and spares callers from `spawn_blocking` all the same.
+///
+/// [`WalkStream::poll_next`] has the signature of `Stream::poll_next` from
+/// the `futures` crate, so it can be adapted with `futures::stream::poll_fn`.
+///
+/// This is only available with the `stream` feature.
+///
+/// # Examples
+///
+/// ```
+/// use mofu::walk_dir::WalkDir;
+///
+/// async fn count_files() -> usize {
+///     let mut stream = WalkDir::new(".").unwrap().into_stream();
+///     let mut count = 0;
+///     while let Some(entry) = stream.next().await {
+///         if entry.is_ok_and(|entry| entry.metadata().is_file()) {
+///             count += 1;
+///         }
+///     }
+///     count
+/// }
+/// ```
+pub struct WalkStream {
+    receiver: Receiver<Result<DirEntry, WalkError>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl WalkStream {
+    pub(super) fn new(walk_dir: WalkDir) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(CAPACITY);
+        let waker = Arc::new(Mutex::new(None::<Waker>));
+        let shared = Arc::clone(&waker);
+        thread::spawn(move || walk(walk_dir, sender, shared));
+        Self { receiver, waker }
+    }
+
+    /// Attempts to pull out the next entry, registering the current task
+    /// for wakeup if none is ready yet.
+    ///
+    /// Returns `Poll::Ready(None)` once the traversal is exhausted.
+    pub fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<DirEntry, WalkError>>> {
+        match self.receiver.try_recv() {
+            Ok(entry) => return Poll::Ready(Some(entry)),
+            Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+            Err(TryRecvError::Empty) => {}
+        }
+        *self.waker.lock().unwrap_or_else(|error| error.into_inner()) = Some(cx.waker().clone());
+        // An entry sent before the waker was registered would never wake
+        // the task.
+        match self.receiver.try_recv() {
+            Ok(entry) => Poll::Ready(Some(entry)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+            Err(TryRecvError::Empty) => Poll::Pending,
+        }
+    }
+
+    /// Returns the next entry, or [`None`] once the traversal is exhausted.
+    pub async fn next(&mut self) -> Option<Result<DirEntry, WalkError>> {
+        future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+impl fmt::Debug for WalkStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalkStream").finish_non_exhaustive()
+    }
+}
+
+/// Runs the traversal, sending its entries until the stream is dropped.
+fn walk(
+    walk_dir: WalkDir,
+    sender: SyncSender<Result<DirEntry, WalkError>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+) {
+    let wake = || {
+        let waker = waker
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    };
+    for entry in walk_dir {
+        if sender.send(entry).is_err() {
+            return;
+        }
+        wake();
+    }
+    // Disconnecting ends the stream.
+    drop(sender);
+    wake();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::task::Wake;
+    use std::thread::Thread;
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Polls the stream to completion on the current thread, parking it
+    /// until woken.
+    fn collect(mut stream: WalkStream) -> Vec<Result<DirEntry, WalkError>> {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut entries = Vec::new();
+        loop {
+            match Pin::new(&mut stream).poll_next(&mut cx) {
+                Poll::Ready(Some(entry)) => entries.push(entry),
+                Poll::Ready(None) => return entries,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn stream_yields_the_same_entries_as_the_iterator() {
+        let walk_dir = || WalkDir::new("src").unwrap().sort_by_file_name();
+        let expected: Vec<PathBuf> = walk_dir().map(|entry| entry.unwrap().into()).collect();
+        let streamed: Vec<PathBuf> = collect(walk_dir().into_stream())
+            .into_iter()
+            .map(|entry| entry.unwrap().into())
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(streamed, expected);
+    }
+}
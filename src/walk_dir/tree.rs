@@ -0,0 +1,137 @@
+use super::{DirEntry, Member, Stats, WalkDir, WalkError, path_entry};
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+/// An in-memory tree of the entries yielded by a [`WalkDir`], as returned
+/// by [`WalkDir::collect_tree`].
+///
+/// Each node holds its entry, with the metadata cached during the
+/// traversal, its children, and the statistics of the entries below it, so
+/// the hierarchy can be rendered or inspected any number of times without
+/// walking or querying the file system again.
+#[derive(Debug)]
+pub struct DirTree {
+    entry: DirEntry,
+    children: Vec<DirTree>,
+    stats: Stats,
+}
+
+impl DirTree {
+    pub(super) fn collect(mut walk_dir: WalkDir) -> Result<Self, WalkError> {
+        let root = walk_dir.root_entry()?;
+        let follow = walk_dir.follow();
+        // The errors encountered, counted at the root.
+        let mut errors = Stats::default();
+        let mut known = HashSet::new();
+        let mut children: HashMap<PathBuf, Vec<DirEntry>> = HashMap::new();
+        for entry in walk_dir.by_ref() {
+            let mut entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => {
+                    errors.record_error();
+                    continue;
+                }
+            };
+            if entry.root != root.root {
+                break;
+            }
+            if entry.depth == 0 || !known.insert(entry.path.clone()) {
+                continue;
+            }
+            // Directories left out by the filters are read on their own,
+            // so the entries within them keep their place.
+            while let Some(parent) = entry.path.parent().map(Path::to_path_buf) {
+                let depth = entry.depth - 1;
+                let missing = depth > 0 && known.insert(parent.clone());
+                let virtual_parent = Member::parent(&entry);
+                children.entry(parent.clone()).or_default().push(entry);
+                if !missing {
+                    break;
+                }
+                if let Some(parent) = virtual_parent {
+                    entry = parent;
+                    continue;
+                }
+                entry = match path_entry(parent, &root.root, depth, follow) {
+                    Ok(entry) => entry,
+                    Err(_) => {
+                        errors.record_error();
+                        break;
+                    }
+                };
+            }
+        }
+        let mut tree = Self::build(root, &mut children);
+        tree.stats.merge(&errors);
+        Ok(tree)
+    }
+
+    fn build(entry: DirEntry, children: &mut HashMap<PathBuf, Vec<DirEntry>>) -> Self {
+        let mut stats = Stats::default();
+        let children: Vec<_> = children
+            .remove(&entry.path)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child| {
+                let child = Self::build(child, children);
+                stats.record_entry(&child.entry);
+                stats.merge(&child.stats);
+                child
+            })
+            .collect();
+        Self {
+            entry,
+            children,
+            stats,
+        }
+    }
+
+    /// Returns the entry.
+    #[inline]
+    pub fn entry(&self) -> &DirEntry {
+        &self.entry
+    }
+
+    /// Returns the path.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.entry.path()
+    }
+
+    /// Returns the cached metadata.
+    #[inline]
+    pub fn metadata(&self) -> &Metadata {
+        self.entry.metadata()
+    }
+
+    /// Returns the entries directly below, in the order they were yielded.
+    #[inline]
+    pub fn children(&self) -> &[DirTree] {
+        &self.children
+    }
+
+    /// Returns the statistics of the entries below at any depth, where
+    /// those of the root also count the errors encountered.
+    #[inline]
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Returns the entries below at any depth, in depth-first order, where
+    /// each directory comes before its entries.
+    pub fn descendants(&self) -> impl Iterator<Item = &DirTree> {
+        let mut stack: Vec<&DirTree> = self.children.iter().rev().collect();
+        std::iter::from_fn(move || {
+            let tree = stack.pop()?;
+            stack.extend(tree.children.iter().rev());
+            Some(tree)
+        })
+    }
+
+    /// Consumes the tree, returning its entry.
+    #[inline]
+    pub fn into_entry(self) -> DirEntry {
+        self.entry
+    }
+}
@@ -0,0 +1,129 @@
+use super::WalkDir;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+/// Computes the disk usage of `path`, returning a tree of its entries, each
+/// annotated with its size, in the manner of `du`.
+///
+/// Sizes are the apparent sizes of files, and the cumulative ones of
+/// directories. Files with more than one hard link are counted once, by the
+/// first path in the order of file names, as described in
+/// [`WalkDir::dedup_hard_links`], and symbolic links are not followed,
+/// counting as 0 bytes. Entries that produce errors are skipped.
+///
+/// # Errors
+///
+/// Returns an error if `path` cannot be read, as described in
+/// [`WalkDir::new`].
+///
+/// # Examples
+///
+/// ```
+/// use mofu::walk_dir::disk_usage;
+///
+/// let usage = disk_usage(".").unwrap();
+/// println!("{} bytes in total", usage.size());
+/// // Largest first.
+/// for child in usage.children().iter().take(10) {
+///     println!("{:>12}  {}", child.size(), child.path().display());
+/// }
+/// ```
+pub fn disk_usage<P>(path: P) -> io::Result<DiskUsage>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let iter = WalkDir::new(path)?
+        .contents_first(true)
+        .sort_by_file_name()
+        .dedup_hard_links(true)
+        .filter_map(Result::ok);
+    // The entries found at each depth whose parent is yet to be yielded.
+    let mut levels: Vec<Vec<DiskUsage>> = vec![Vec::new()];
+    for entry in iter {
+        let depth = entry.depth();
+        if levels.len() <= depth {
+            levels.resize_with(depth + 1, Vec::new);
+        }
+        let usage = if entry.is_dir() {
+            let children = levels.get_mut(depth + 1).map(mem::take).unwrap_or_default();
+            DiskUsage::dir(entry.into(), children)
+        } else {
+            let size = if entry.is_file() {
+                entry.metadata().len()
+            } else {
+                0
+            };
+            DiskUsage {
+                path: entry.into(),
+                size,
+                dir: false,
+                children: Vec::new(),
+            }
+        };
+        levels[depth].push(usage);
+    }
+    let children = levels.get_mut(1).map(mem::take).unwrap_or_default();
+    Ok(DiskUsage::dir(path.to_path_buf(), children))
+}
+
+/// An entry of the tree returned by [`disk_usage`], annotated with its
+/// size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskUsage {
+    path: PathBuf,
+    size: u64,
+    dir: bool,
+    children: Vec<DiskUsage>,
+}
+
+impl DiskUsage {
+    /// Creates a directory holding `children`, ordering them by size.
+    fn dir(path: PathBuf, mut children: Vec<DiskUsage>) -> Self {
+        children.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        let size = children.iter().map(|child| child.size).sum();
+        Self {
+            path,
+            size,
+            dir: true,
+            children,
+        }
+    }
+
+    /// Returns the path.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the size in bytes, which is cumulative for directories.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns `true` if the entry is a directory.
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        self.dir
+    }
+
+    /// Returns the entries of the directory, largest first, and then by
+    /// path, which is empty for anything but directories.
+    #[inline]
+    pub fn children(&self) -> &[DiskUsage] {
+        &self.children
+    }
+
+    /// Returns the entries within the directory at any depth, in
+    /// depth-first order, where each directory comes before its entries.
+    pub fn descendants(&self) -> impl Iterator<Item = &DiskUsage> {
+        let mut stack: Vec<&DiskUsage> = self.children.iter().rev().collect();
+        std::iter::from_fn(move || {
+            let usage = stack.pop()?;
+            stack.extend(usage.children.iter().rev());
+            Some(usage)
+        })
+    }
+}
@@ -0,0 +1,412 @@
+use super::{DirEntry, WalkDir};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// A watcher of a tree, which walks it once, and then streams the changes
+/// made to it, so tools can operate continuously on hot folders.
+///
+/// Changes are found by walking the tree again at a fixed interval, and
+/// comparing what is found with the last walk, which works on any platform
+/// and file system, including network ones, at the cost of latency. Files
+/// removed and created between two walks with the same
+/// [`DirEntry::file_id`], size and modification time are reported as
+/// renamed, so renames are only told apart on Unix and Windows.
+///
+/// This does not subscribe to notifications of the operating system, such
+/// as inotify or FSEvents, so changes undone between two walks go unseen,
+/// and several changes to an entry between two walks are reported as one.
+///
+/// As an iterator, this blocks until changes are found, and never ends.
+/// Errors building a traversal are yielded, while those within it are
+/// skipped.
+///
+/// This is only available with the `watch` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mofu::walk_dir::{Event, Watcher};
+///
+/// for event in Watcher::new("inbox").filter_map(Result::ok) {
+///     match event {
+///         Event::Found(path) | Event::Create(path) => {
+///             println!("new: {}", path.display());
+///         }
+///         Event::Rename { from, to } => {
+///             println!("{} -> {}", from.display(), to.display());
+///         }
+///         _ => {}
+///     }
+/// }
+/// ```
+pub struct Watcher {
+    walk_dir: Box<dyn FnMut() -> io::Result<WalkDir> + Send>,
+    interval: Duration,
+    /// The state of each entry found by the last walk, or [`None`] before
+    /// the initial one.
+    snapshot: Option<HashMap<PathBuf, State>>,
+    events: VecDeque<Event>,
+}
+
+/// A change reported by a [`Watcher`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Event {
+    /// An entry found by the initial walk.
+    Found(PathBuf),
+
+    /// An entry created.
+    Create(PathBuf),
+
+    /// A file whose size or modification time changed.
+    Modify(PathBuf),
+
+    /// An entry removed.
+    Remove(PathBuf),
+
+    /// An entry moved from one path to another. Entries within a directory
+    /// moved are not reported on their own.
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+/// What tells apart the versions of an entry between walks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+    dir: bool,
+    len: u64,
+    modified: Option<SystemTime>,
+    id: Option<(u64, u64)>,
+}
+
+impl Watcher {
+    /// Creates a new [`Watcher`] of the tree at `path`, as traversed by
+    /// [`WalkDir::new`].
+    pub fn new<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        Self::with_walk_dir(move || WalkDir::new(&path))
+    }
+
+    /// Creates a new [`Watcher`] building each traversal with `walk_dir`,
+    /// so the options of [`WalkDir`], such as filters, apply to what is
+    /// watched.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mofu::walk_dir::{WalkDir, Watcher};
+    ///
+    /// let watcher = Watcher::with_walk_dir(|| {
+    ///     Ok(WalkDir::new("inbox")?
+    ///         .files_only()
+    ///         .with_extensions(["jpg"]))
+    /// });
+    /// ```
+    pub fn with_walk_dir<F>(walk_dir: F) -> Self
+    where
+        F: FnMut() -> io::Result<WalkDir> + Send + 'static,
+    {
+        Self {
+            walk_dir: Box::new(walk_dir),
+            interval: Duration::from_secs(1),
+            snapshot: None,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Sets the time waited between walks.
+    ///
+    /// Defaults to 1 second.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Walks the tree once without waiting, returning the changes since the
+    /// last walk, or the entries found if this is the initial one.
+    ///
+    /// Events not yet yielded as an iterator are returned first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the traversal cannot be built.
+    pub fn poll(&mut self) -> io::Result<Vec<Event>> {
+        self.scan()?;
+        Ok(self.events.drain(..).collect())
+    }
+
+    /// Walks the tree, queueing the changes since the last walk.
+    fn scan(&mut self) -> io::Result<()> {
+        let mut snapshot = HashMap::new();
+        for entry in (self.walk_dir)()?.filter_map(Result::ok) {
+            let state = State::new(&entry);
+            snapshot.insert(PathBuf::from(entry), state);
+        }
+        let Some(last) = self.snapshot.take() else {
+            let mut found: Vec<_> = snapshot.keys().cloned().collect();
+            found.sort();
+            self.events.extend(found.into_iter().map(Event::Found));
+            self.snapshot = Some(snapshot);
+            return Ok(());
+        };
+        self.diff(&last, &snapshot);
+        self.snapshot = Some(snapshot);
+        Ok(())
+    }
+
+    /// Queues the changes from the walk `last` to the walk `current`.
+    fn diff(&mut self, last: &HashMap<PathBuf, State>, current: &HashMap<PathBuf, State>) {
+        let mut removed: Vec<_> = last
+            .iter()
+            .filter(|(path, _)| !current.contains_key(*path))
+            .collect();
+        let mut created: Vec<_> = current
+            .iter()
+            .filter(|(path, _)| !last.contains_key(*path))
+            .collect();
+        let mut modified: Vec<_> = current
+            .iter()
+            .filter(|(path, state)| {
+                last.get(*path)
+                    .is_some_and(|last| !state.dir && last != *state)
+            })
+            .map(|(path, _)| path)
+            .collect();
+        removed.sort_by(|a, b| a.0.cmp(b.0));
+        created.sort_by(|a, b| a.0.cmp(b.0));
+        modified.sort();
+
+        // Files removed and created with the same identifier, size and
+        // modification time are renamed. Hard links share all of these, so
+        // each state is kept with every path removed, matching them with
+        // the paths created in order.
+        let mut sources: HashMap<State, VecDeque<&PathBuf>> = HashMap::new();
+        for (path, state) in &removed {
+            if state.id.is_some() {
+                sources.entry(**state).or_default().push_back(*path);
+            }
+        }
+        let mut renames = Vec::new();
+        let mut moved_from = HashSet::new();
+        created.retain(|(to, state)| {
+            let Some(from) = sources.get_mut(*state).and_then(VecDeque::pop_front) else {
+                return true;
+            };
+            moved_from.insert(from);
+            renames.push((from.clone(), (*to).clone()));
+            false
+        });
+        removed.retain(|(path, _)| !moved_from.contains(path));
+        renames.sort();
+
+        let mut moved: Vec<(&Path, &Path)> = Vec::new();
+        for (from, to) in &renames {
+            let within = moved.iter().any(|(dir_from, dir_to)| {
+                from.strip_prefix(dir_from)
+                    .is_ok_and(|rest| to.strip_prefix(dir_to) == Ok(rest))
+            });
+            if !within {
+                self.events.push_back(Event::Rename {
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+                if current.get(to).is_some_and(|state| state.dir) {
+                    moved.push((from, to));
+                }
+            }
+        }
+        let removed = removed
+            .into_iter()
+            .map(|(path, _)| Event::Remove(path.clone()));
+        let created = created
+            .into_iter()
+            .map(|(path, _)| Event::Create(path.clone()));
+        let modified = modified.into_iter().cloned().map(Event::Modify);
+        self.events.extend(removed);
+        self.events.extend(created);
+        self.events.extend(modified);
+    }
+}
+
+impl Iterator for Watcher {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.snapshot.is_some() {
+                thread::sleep(self.interval);
+            }
+            if let Err(error) = self.scan() {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Watcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watcher")
+            .field("interval", &self.interval)
+            .field("events", &self.events)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Event {
+    /// Returns the path the event is about, which is the destination for
+    /// renames.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Found(path) | Self::Create(path) | Self::Modify(path) | Self::Remove(path) => {
+                path
+            }
+            Self::Rename { to, .. } => to,
+        }
+    }
+}
+
+impl State {
+    fn new(entry: &DirEntry) -> Self {
+        let metadata = entry.metadata();
+        Self {
+            dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            id: entry.file_id(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    fn state(len: u64, id: Option<u64>) -> State {
+        State {
+            dir: false,
+            len,
+            modified: Some(SystemTime::UNIX_EPOCH),
+            id: id.map(|id| (1, id)),
+        }
+    }
+
+    fn diff(last: &[(&str, State)], current: &[(&str, State)]) -> Vec<Event> {
+        let snapshot = |entries: &[(&str, State)]| {
+            entries
+                .iter()
+                .map(|(path, state)| (PathBuf::from(path), *state))
+                .collect()
+        };
+        let mut watcher = Watcher::new("unused");
+        watcher.diff(&snapshot(last), &snapshot(current));
+        watcher.events.into()
+    }
+
+    fn rename(from: &str, to: &str) -> Event {
+        Event::Rename {
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+        }
+    }
+
+    #[test]
+    fn unchanged_walks_yield_nothing() {
+        let entries = [("a", state(1, Some(1))), ("b", state(2, None))];
+        assert_eq!(diff(&entries, &entries), []);
+        assert_eq!(diff(&[], &[]), []);
+    }
+
+    #[test]
+    fn changes_are_classified() {
+        let last = [
+            ("kept", state(1, Some(1))),
+            ("gone", state(2, Some(2))),
+            ("old", state(3, Some(3))),
+        ];
+        let current = [
+            ("kept", state(4, Some(1))),
+            ("new", state(3, Some(3))),
+            ("fresh", state(5, Some(5))),
+        ];
+        assert_eq!(
+            diff(&last, &current),
+            [
+                rename("old", "new"),
+                Event::Remove(PathBuf::from("gone")),
+                Event::Create(PathBuf::from("fresh")),
+                Event::Modify(PathBuf::from("kept")),
+            ],
+        );
+    }
+
+    #[test]
+    fn entries_without_identifiers_are_never_renamed() {
+        let last = [("old", state(3, None))];
+        let current = [("new", state(3, None))];
+        assert_eq!(
+            diff(&last, &current),
+            [
+                Event::Remove(PathBuf::from("old")),
+                Event::Create(PathBuf::from("new")),
+            ],
+        );
+    }
+
+    #[test]
+    fn removed_hard_links_are_not_lost() {
+        let link = state(3, Some(7));
+        let last = [("a", link), ("b", link), ("c", link)];
+        let current = [("d", link)];
+        assert_eq!(
+            diff(&last, &current),
+            [
+                rename("a", "d"),
+                Event::Remove(PathBuf::from("b")),
+                Event::Remove(PathBuf::from("c")),
+            ],
+        );
+
+        let current = [("d", link), ("e", link)];
+        assert_eq!(
+            diff(&last, &current),
+            [
+                rename("a", "d"),
+                rename("b", "e"),
+                Event::Remove(PathBuf::from("c")),
+            ],
+        );
+    }
+
+    #[test]
+    fn poll_reports_the_initial_walk_then_changes() {
+        let root = env::temp_dir().join(format!("mofu-watch-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.txt"), "a").unwrap();
+
+        let mut watcher = Watcher::new(&root);
+        assert_eq!(watcher.poll().unwrap(), [Event::Found(root.join("a.txt"))]);
+        assert_eq!(watcher.poll().unwrap(), []);
+        fs::write(root.join("b.txt"), "b").unwrap();
+        fs::remove_file(root.join("a.txt")).unwrap();
+        assert_eq!(
+            watcher.poll().unwrap(),
+            [
+                Event::Remove(root.join("a.txt")),
+                Event::Create(root.join("b.txt")),
+            ],
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+        assert!(watcher.poll().is_err());
+    }
+}